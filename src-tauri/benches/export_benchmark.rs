@@ -13,13 +13,21 @@
 ///   cargo run --release --bin export_benchmark -- --files 500 --size 10000
 
 use cortex_lib::db::Database;
-use cortex_lib::export::{BundleBuilder, ExportConfig, RakeExporter, RakeExportConfig, RakeExportMode};
+use cortex_lib::export::{
+    BundleBuilder, ChunkStrategy, ExportConfig, LineEnding, RakeExporter, RakeExportConfig, RakeExportMode,
+};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use tempfile::TempDir;
 
+/// How often the memory tracker samples resident set size
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
 #[derive(Debug)]
 struct BenchmarkResult {
     scenario: String,
@@ -49,6 +57,85 @@ impl BenchmarkResult {
     }
 }
 
+/// Tracks peak resident set size (RSS) while a scenario runs by sampling
+/// on a background thread
+struct MemoryTracker {
+    peak_kb: Arc<AtomicU64>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MemoryTracker {
+    /// Start sampling immediately
+    fn start() -> Self {
+        let peak_kb = Arc::new(AtomicU64::new(current_rss_kb().unwrap_or(0)));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let peak_kb = Arc::clone(&peak_kb);
+            let running = Arc::clone(&running);
+            std::thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    if let Some(rss) = current_rss_kb() {
+                        peak_kb.fetch_max(rss, Ordering::Relaxed);
+                    }
+                    std::thread::sleep(MEMORY_SAMPLE_INTERVAL);
+                }
+            })
+        };
+
+        Self {
+            peak_kb,
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop sampling and return the peak RSS in MB (0.0 if unsupported)
+    fn stop(mut self) -> f64 {
+        // One last sample so very short scenarios are still covered
+        if let Some(rss) = current_rss_kb() {
+            self.peak_kb.fetch_max(rss, Ordering::Relaxed);
+        }
+
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+
+        self.peak_kb.load(Ordering::Relaxed) as f64 / 1024.0
+    }
+}
+
+/// Whether `current_rss_kb` is implemented for this platform
+const MEMORY_TRACKING_SUPPORTED: bool = cfg!(any(target_os = "linux", target_os = "macos"));
+
+/// Current resident set size in KB (Linux: /proc/self/status)
+#[cfg(target_os = "linux")]
+fn current_rss_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmRSS:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse().ok())
+}
+
+/// Current resident set size in KB (macOS: `ps`, which reports KB)
+#[cfg(target_os = "macos")]
+fn current_rss_kb() -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn current_rss_kb() -> Option<u64> {
+    None
+}
+
 /// Generate synthetic test dataset
 fn generate_test_dataset(dir: &Path, file_count: usize, avg_size_bytes: usize) -> std::io::Result<u64> {
     let file_types = vec![
@@ -201,14 +288,17 @@ async fn benchmark_vscode_export(
         include_prompts: true,
         project_name: Some("BenchmarkProject".to_string()),
         custom_context: None,
+        ..ExportConfig::default()
     };
 
     let bundler = BundleBuilder::new(db.clone());
 
-    // Measure time
+    // Measure time and memory
+    let memory = MemoryTracker::start();
     let start = Instant::now();
     let result = bundler.create_bundle(&config).await?;
     let duration = start.elapsed();
+    let peak_memory_mb = memory.stop();
 
     // Calculate metrics
     let output_size = fs::metadata(&result.context_file)
@@ -223,7 +313,7 @@ async fn benchmark_vscode_export(
         file_count,
         total_size_bytes,
         duration,
-        peak_memory_mb: 0.0, // TODO: Implement memory tracking
+        peak_memory_mb,
         output_size_bytes: output_size,
         files_per_second,
         mb_per_second,
@@ -246,14 +336,19 @@ async fn benchmark_rake_export(
         output_path: output_dir.join("rake_export.json").to_string_lossy().to_string(),
         include_embeddings: false,
         export_mode: RakeExportMode::Full,
+        summaries_only: false,
+        chunk_strategy: ChunkStrategy::default(),
+        line_ending: LineEnding::default(),
     };
 
     let exporter = RakeExporter::new(db.clone());
 
-    // Measure time
+    // Measure time and memory
+    let memory = MemoryTracker::start();
     let start = Instant::now();
-    let output_path = exporter.export_to_file(&config).await?;
+    let output_path = exporter.export_to_file(&config, None).await?;
     let duration = start.elapsed();
+    let peak_memory_mb = memory.stop();
 
     // Calculate metrics
     let output_size = fs::metadata(&output_path)
@@ -268,7 +363,7 @@ async fn benchmark_rake_export(
         file_count,
         total_size_bytes,
         duration,
-        peak_memory_mb: 0.0,
+        peak_memory_mb,
         output_size_bytes: output_size,
         files_per_second,
         mb_per_second,
@@ -380,6 +475,17 @@ async fn main() -> cortex_lib::error::Result<()> {
     }
     println!();
 
+    // Memory tracking sanity check
+    if MEMORY_TRACKING_SUPPORTED {
+        for result in &results {
+            assert!(
+                result.peak_memory_mb > 0.0,
+                "Peak memory was not recorded for {}",
+                result.scenario
+            );
+        }
+    }
+
     // Performance assessment
     let vscode_time = results[0].duration.as_secs_f64();
     let passes_minimum = file_count <= 100 || vscode_time < 10.0;
@@ -49,6 +49,7 @@ fn search_with_filters(
                 filename: row.get(2)?,
                 snippet: row.get(3)?,
                 score: row.get(4)?,
+                is_deleted: false,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -270,6 +271,7 @@ async fn main() {
                     filename: row.get(2)?,
                     snippet: row.get(3)?,
                     score: row.get(4)?,
+                    is_deleted: false,
                 })
             })
             .unwrap()
@@ -0,0 +1,166 @@
+//! Embedding Space Diagnostics
+//!
+//! Summarizes how spread out the stored embeddings are, to help pick
+//! similarity thresholds and to spot a degenerate space where every file
+//! looks alike. Nearest neighbors are computed for a sample of files against
+//! the whole set, so cost grows with `sample * n` rather than `n²`.
+
+use crate::ai::cosine_similarity;
+use crate::db::EMBEDDING_DIM;
+use serde::{Deserialize, Serialize};
+
+/// Nearest-neighbor similarity at or above which two files count as near
+/// duplicates
+pub const NEAR_DUPLICATE_SIMILARITY: f32 = 0.98;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingDiagnostics {
+    pub total_embeddings: usize,
+    /// Files whose nearest neighbors were computed
+    pub sampled: usize,
+    /// Vectors without `EMBEDDING_DIM` components, left out of the statistics
+    pub wrong_dimension: usize,
+    /// Zero or non-finite vectors, left out of the statistics
+    pub degenerate: usize,
+    /// Average similarity between sampled files and all others
+    pub mean_pairwise_similarity: Option<f32>,
+    pub mean_nearest_neighbor_similarity: Option<f32>,
+    pub median_nearest_neighbor_similarity: Option<f32>,
+    /// Share of sampled files with a neighbor at or above
+    /// [`NEAR_DUPLICATE_SIMILARITY`]
+    pub near_duplicate_fraction: Option<f32>,
+}
+
+fn is_degenerate(vector: &[f32]) -> bool {
+    let norm_sq: f32 = vector.iter().map(|x| x * x).sum();
+    !norm_sq.is_finite() || norm_sq == 0.0
+}
+
+/// Diagnose the embedding space from up to `sample_size` evenly spaced files
+///
+/// The statistics are `None` when fewer than two usable vectors exist.
+pub fn diagnose_embeddings(embeddings: &[(i64, Vec<f32>)], sample_size: usize) -> EmbeddingDiagnostics {
+    let wrong_dimension = embeddings.iter().filter(|(_, v)| v.len() != EMBEDDING_DIM).count();
+    let degenerate = embeddings
+        .iter()
+        .filter(|(_, v)| v.len() == EMBEDDING_DIM && is_degenerate(v))
+        .count();
+
+    let usable: Vec<&[f32]> = embeddings
+        .iter()
+        .map(|(_, v)| v.as_slice())
+        .filter(|v| v.len() == EMBEDDING_DIM && !is_degenerate(v))
+        .collect();
+
+    let mut diagnostics = EmbeddingDiagnostics {
+        total_embeddings: embeddings.len(),
+        sampled: 0,
+        wrong_dimension,
+        degenerate,
+        mean_pairwise_similarity: None,
+        mean_nearest_neighbor_similarity: None,
+        median_nearest_neighbor_similarity: None,
+        near_duplicate_fraction: None,
+    };
+
+    if usable.len() < 2 || sample_size == 0 {
+        return diagnostics;
+    }
+
+    // Evenly spaced so the sample covers the whole set deterministically
+    let sampled = sample_size.min(usable.len());
+    let step = usable.len() as f64 / sampled as f64;
+
+    let mut nearest = Vec::with_capacity(sampled);
+    let mut pairwise_sum = 0.0f64;
+    let mut pairwise_count = 0usize;
+
+    for k in 0..sampled {
+        let i = (k as f64 * step) as usize;
+
+        let mut best = f32::MIN;
+        for (j, other) in usable.iter().enumerate() {
+            if j == i {
+                continue;
+            }
+            let similarity = cosine_similarity(usable[i], other);
+            best = best.max(similarity);
+            pairwise_sum += similarity as f64;
+            pairwise_count += 1;
+        }
+        nearest.push(best);
+    }
+
+    nearest.sort_by(|a, b| a.total_cmp(b));
+    let median = if nearest.len().is_multiple_of(2) {
+        (nearest[nearest.len() / 2 - 1] + nearest[nearest.len() / 2]) / 2.0
+    } else {
+        nearest[nearest.len() / 2]
+    };
+    let near_duplicates = nearest.iter().filter(|&&s| s >= NEAR_DUPLICATE_SIMILARITY).count();
+
+    diagnostics.sampled = sampled;
+    diagnostics.mean_pairwise_similarity = Some((pairwise_sum / pairwise_count as f64) as f32);
+    diagnostics.mean_nearest_neighbor_similarity =
+        Some(nearest.iter().sum::<f32>() / sampled as f32);
+    diagnostics.median_nearest_neighbor_similarity = Some(median);
+    diagnostics.near_duplicate_fraction = Some(near_duplicates as f32 / sampled as f32);
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit vector along `axis`, tilted slightly toward the last component
+    fn vector(axis: usize, tilt: f32) -> Vec<f32> {
+        let mut v = vec![0.0f32; EMBEDDING_DIM];
+        v[axis] = 1.0;
+        v[EMBEDDING_DIM - 1] = tilt;
+        v
+    }
+
+    #[test]
+    fn test_diagnostics_over_clustered_set() {
+        // Three orthogonal clusters of two; cluster 0 is an exact duplicate pair
+        let mut embeddings = vec![
+            (1, vector(0, 0.0)),
+            (2, vector(0, 0.0)),
+            (3, vector(1, 0.0)),
+            (4, vector(1, 0.5)),
+            (5, vector(2, 0.0)),
+            (6, vector(2, 0.5)),
+        ];
+        embeddings.push((7, vec![0.0; EMBEDDING_DIM]));
+        embeddings.push((8, vec![1.0; 3]));
+
+        let d = diagnose_embeddings(&embeddings, 100);
+        assert_eq!(d.total_embeddings, 8);
+        assert_eq!(d.sampled, 6);
+        assert_eq!(d.degenerate, 1);
+        assert_eq!(d.wrong_dimension, 1);
+
+        // Neighbors within a cluster: 1.0 for the duplicates, ~0.894 otherwise
+        assert!((d.median_nearest_neighbor_similarity.unwrap() - 0.894).abs() < 0.01);
+        assert!((d.near_duplicate_fraction.unwrap() - 2.0 / 6.0).abs() < 1e-6);
+        assert!(d.mean_pairwise_similarity.unwrap() < 0.5);
+
+        // Sampling still compares against the whole set
+        let sampled = diagnose_embeddings(&embeddings, 3);
+        assert_eq!(sampled.sampled, 3);
+        assert!(sampled.mean_nearest_neighbor_similarity.unwrap() > 0.85);
+    }
+
+    #[test]
+    fn test_degenerate_space_and_too_few_vectors() {
+        let all_alike: Vec<(i64, Vec<f32>)> = (0..10).map(|id| (id, vector(0, 0.01))).collect();
+        let d = diagnose_embeddings(&all_alike, 5);
+        assert_eq!(d.near_duplicate_fraction, Some(1.0));
+        assert!(d.mean_pairwise_similarity.unwrap() > 0.99);
+
+        let d = diagnose_embeddings(&[(1, vector(0, 0.0))], 5);
+        assert_eq!(d.sampled, 0);
+        assert!(d.mean_pairwise_similarity.is_none());
+    }
+}
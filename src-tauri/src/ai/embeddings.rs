@@ -3,17 +3,91 @@
 //! Provides semantic embeddings for file content using ONNX Runtime.
 //! Uses the `all-MiniLM-L6-v2` model for fast, offline embeddings.
 
+use crate::ai::model_downloader::get_models_dir;
 use anyhow::{Context, Result};
 use ndarray::{Array2, Array3, Axis};
 use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
 use ort::value::Value;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokenizers::Tokenizer;
 
+/// Rough characters-per-token ratio used to size chunks to the model window
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How a document longer than the model window is turned into one vector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingStrategy {
+    /// Embed only the first `max_length` tokens (everything else is ignored)
+    FirstChunk,
+    /// Embed window-sized chunks and average them
+    MeanOfChunks,
+}
+
+/// Words of a file's text embedded by the summary sources when it has no
+/// stored summary
+const SUMMARY_FALLBACK_WORDS: usize = 200;
+
+/// Which representation of a file is embedded
+///
+/// The cheaper sources trade quality for speed on large corpora.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingSource {
+    /// The extracted text, subject to `max_embed_chars`
+    #[default]
+    FullText,
+    /// The stored summary, or the first words of the text without one
+    Summary,
+    /// The filename followed by the summary
+    TitlePlusSummary,
+}
+
+impl EmbeddingSource {
+    /// The text to embed for a file, or `None` if it has none
+    pub fn document_text(&self, filename: &str, text: Option<&str>, summary: Option<&str>) -> Option<String> {
+        let summary = || {
+            summary
+                .filter(|summary| !summary.trim().is_empty())
+                .map(str::to_string)
+                .or_else(|| {
+                    text.map(|text| {
+                        text.split_whitespace()
+                            .take(SUMMARY_FALLBACK_WORDS)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                })
+        };
+
+        match self {
+            EmbeddingSource::FullText => text.map(str::to_string),
+            EmbeddingSource::Summary => summary(),
+            EmbeddingSource::TitlePlusSummary => match summary() {
+                Some(summary) => Some(format!("{}\n{}", filename, summary)),
+                None => Some(filename.to_string()),
+            },
+        }
+    }
+
+    /// `model_version` labelled with this source, so embeddings of a cheaper
+    /// representation are never taken for full-text ones
+    ///
+    /// Full-text embeddings keep the bare model version.
+    pub fn model_version(&self, model_version: &str) -> String {
+        match self {
+            EmbeddingSource::FullText => model_version.to_string(),
+            EmbeddingSource::Summary => format!("{}+summary", model_version),
+            EmbeddingSource::TitlePlusSummary => format!("{}+title_summary", model_version),
+        }
+    }
+}
+
 /// Configuration for the embedding service
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EmbeddingConfig {
     /// Path to the ONNX model file
     pub model_path: PathBuf,
@@ -23,21 +97,115 @@ pub struct EmbeddingConfig {
     pub max_length: usize,
     /// Batch size for processing multiple texts
     pub batch_size: usize,
+    /// How documents are embedded by `embed_document`
+    pub strategy: EmbeddingStrategy,
+    /// Cap on the characters of a document considered for embedding,
+    /// independent of `max_length`; bounds the chunk count for huge files
+    pub max_embed_chars: usize,
+    /// Documents estimated above this many tokens are not embedded at all
+    pub max_document_tokens: usize,
+    /// Documents with more lines than this are not embedded at all
+    pub max_document_lines: usize,
 }
 
 impl Default for EmbeddingConfig {
     fn default() -> Self {
-        let model_dir = dirs::home_dir()
-            .expect("Failed to get home directory")
-            .join(".cortex")
-            .join("models")
-            .join("all-MiniLM-L6-v2");
+        let model_dir = get_models_dir().expect("Failed to get home directory");
 
         Self {
             model_path: model_dir.join("model.onnx"),
             tokenizer_path: model_dir.join("tokenizer.json"),
             max_length: 128,
             batch_size: 32,
+            strategy: EmbeddingStrategy::FirstChunk,
+            max_embed_chars: 100_000,
+            max_document_tokens: 500_000,
+            max_document_lines: 50_000,
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// Character count above which a document exceeds `max_document_tokens`
+    pub fn max_document_chars(&self) -> usize {
+        self.max_document_tokens.saturating_mul(CHARS_PER_TOKEN)
+    }
+
+    /// Whether `text` is too large to be worth embedding (e.g. generated data)
+    ///
+    /// Such files stay in the full-text index; only embedding skips them.
+    pub fn exceeds_document_limits(&self, text: &str) -> bool {
+        text.matches('\n').count() + 1 > self.max_document_lines
+            || text.chars().count() > self.max_document_chars()
+    }
+}
+
+/// User-chosen chunking parameters, applied over the default config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkConfig {
+    /// Tokens per chunk, which is also the model's sequence length
+    pub chunk_tokens: usize,
+    pub strategy: EmbeddingStrategy,
+    /// Overrides `max_embed_chars` when set
+    #[serde(default)]
+    pub max_embed_chars: Option<usize>,
+    /// What is embedded for each file; chosen per call rather than loaded
+    /// into the model, so switching it never reloads the service
+    #[serde(default)]
+    pub source: EmbeddingSource,
+}
+
+impl ChunkConfig {
+    /// Largest sequence length the model accepts
+    pub const MAX_CHUNK_TOKENS: usize = 512;
+
+    /// Why these parameters can't be used, if they can't
+    pub fn validation_error(&self) -> Option<String> {
+        (self.chunk_tokens == 0 || self.chunk_tokens > Self::MAX_CHUNK_TOKENS).then(|| {
+            format!(
+                "Chunk size must be between 1 and {} tokens, got {}",
+                Self::MAX_CHUNK_TOKENS,
+                self.chunk_tokens
+            )
+        })
+    }
+
+    /// `config` with these chunking parameters (`source` isn't part of it)
+    pub fn apply(&self, config: EmbeddingConfig) -> EmbeddingConfig {
+        EmbeddingConfig {
+            max_length: self.chunk_tokens,
+            strategy: self.strategy,
+            max_embed_chars: self.max_embed_chars.unwrap_or(config.max_embed_chars),
+            ..config
+        }
+    }
+}
+
+/// Embed a whole document under `config`'s strategy with `embed_batch`,
+/// returning the embedding and how many chunks were evaluated
+///
+/// Text beyond `max_embed_chars` is dropped (with a warning), so a huge
+/// file costs at most `max_embed_chars / (max_length * 4)` chunk
+/// evaluations under `MeanOfChunks`.
+pub fn embed_document_chunks<F>(
+    config: &EmbeddingConfig,
+    text: &str,
+    mut embed_batch: F,
+) -> Result<(Vec<f32>, usize)>
+where
+    F: FnMut(&[&str]) -> Result<Vec<Vec<f32>>>,
+{
+    let text = truncate_for_embedding(text, config.max_embed_chars);
+
+    match config.strategy {
+        EmbeddingStrategy::FirstChunk => {
+            let embedding = embed_batch(&[text])?.into_iter().next().unwrap();
+            Ok((embedding, 1))
+        }
+        EmbeddingStrategy::MeanOfChunks => {
+            let chunks = chunk_text(text, config.max_length * CHARS_PER_TOKEN);
+            let embedding = mean_of_chunks(&chunks, config.batch_size, embed_batch)?;
+            Ok((embedding, chunks.len().max(1)))
         }
     }
 }
@@ -81,6 +249,11 @@ impl EmbeddingService {
         })
     }
 
+    /// Configuration the service was loaded with
+    pub fn config(&self) -> &EmbeddingConfig {
+        &self.config
+    }
+
     /// Generate embedding for a single text
     ///
     /// # Arguments
@@ -102,6 +275,14 @@ impl EmbeddingService {
         Ok(embeddings.into_iter().next().unwrap())
     }
 
+    /// Generate one embedding for a whole document using the configured
+    /// [`EmbeddingStrategy`] (see [`embed_document_chunks`])
+    pub fn embed_document(&mut self, text: &str) -> Result<Vec<f32>> {
+        let config = self.config.clone();
+        embed_document_chunks(&config, text, |batch| self.embed_batch(batch))
+            .map(|(embedding, _)| embedding)
+    }
+
     /// Generate embeddings for multiple texts (batch processing)
     ///
     /// # Arguments
@@ -190,26 +371,218 @@ impl EmbeddingService {
                 .expect("Failed to calculate mean")
                 .to_vec();
 
-            // Normalize the embedding (L2 normalization)
-            let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-            let normalized: Vec<f32> = embedding.iter().map(|x| x / norm).collect();
+            let mut embedding = embedding;
+            l2_normalize(&mut embedding);
 
-            embeddings.push(normalized);
+            embeddings.push(embedding);
         }
 
         Ok(embeddings)
     }
 
+    /// The tokens the model would see for `text`, `[CLS]` and `[SEP]`
+    /// included
+    ///
+    /// Unlike [`embed`](Self::embed), nothing is truncated to `max_length`,
+    /// so the count can be compared against it.
+    pub fn tokenize(&self, text: &str) -> Result<Vec<String>> {
+        let mut tokenizer = (*self.tokenizer).clone();
+        tokenizer
+            .with_truncation(None)
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer: {:?}", e))?;
+
+        let encoding = tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize: {:?}", e))?;
+        Ok(encoding.get_tokens().to_vec())
+    }
+
     /// Get the embedding dimension (should be 384 for all-MiniLM-L6-v2)
     pub fn dimension(&self) -> usize {
         384
     }
 }
 
+/// Cut `text` to at most `max_chars` characters, warning when anything is dropped
+fn truncate_for_embedding(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            log::warn!(
+                "Embedding text truncated to {} of {} characters",
+                max_chars,
+                text.chars().count()
+            );
+            &text[..end]
+        }
+        None => text,
+    }
+}
+
+/// Split `text` into chunks of at most `chunk_chars` characters, breaking at
+/// whitespace where possible
+fn chunk_text(text: &str, chunk_chars: usize) -> Vec<&str> {
+    let chunk_chars = chunk_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let end = match rest.char_indices().nth(chunk_chars) {
+            None => rest.len(),
+            Some((limit, _)) => match rest[..limit].rfind(char::is_whitespace) {
+                Some(space) if space > 0 => space,
+                _ => limit,
+            },
+        };
+
+        chunks.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    chunks
+}
+
+/// Embed `chunks` in batches and return their L2-normalized mean
+fn mean_of_chunks<F>(chunks: &[&str], batch_size: usize, mut embed_batch: F) -> Result<Vec<f32>>
+where
+    F: FnMut(&[&str]) -> Result<Vec<Vec<f32>>>,
+{
+    if chunks.is_empty() {
+        return embed_batch(&[""]).map(|mut e| e.remove(0));
+    }
+
+    let mut sum: Vec<f32> = Vec::new();
+    for batch in chunks.chunks(batch_size.max(1)) {
+        for embedding in embed_batch(batch)? {
+            if sum.is_empty() {
+                sum = vec![0.0; embedding.len()];
+            }
+            for (total, value) in sum.iter_mut().zip(&embedding) {
+                *total += value;
+            }
+        }
+    }
+
+    l2_normalize(&mut sum);
+
+    Ok(sum)
+}
+
+/// Scale `vector` to unit length in place
+///
+/// Degenerate input (e.g. an all-zero hidden state) has no direction, so a
+/// zero or non-finite norm leaves a zero vector rather than dividing into NaNs.
+fn l2_normalize(vector: &mut [f32]) {
+    let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm == 0.0 || !norm.is_finite() {
+        log::warn!("Embedding has zero or non-finite norm; using a zero vector");
+        vector.iter_mut().for_each(|x| *x = 0.0);
+        return;
+    }
+
+    vector.iter_mut().for_each(|x| *x /= norm);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_large_document_bounded_chunk_evaluations() {
+        let max_embed_chars = 10_000;
+        let chunk_chars = 128 * CHARS_PER_TOKEN;
+
+        // ~5MB of text
+        let huge = "lorem ipsum dolor sit amet ".repeat(200_000);
+        let text = truncate_for_embedding(&huge, max_embed_chars);
+        assert_eq!(text.chars().count(), max_embed_chars);
+
+        let chunks = chunk_text(text, chunk_chars);
+        let mut evaluated = 0;
+        let embedding = mean_of_chunks(&chunks, 32, |batch| {
+            evaluated += batch.len();
+            Ok(batch.iter().map(|_| vec![1.0, 0.0, 0.0]).collect())
+        })
+        .unwrap();
+
+        // Word-boundary breaks may shorten chunks slightly, never by half
+        let bound = 2 * max_embed_chars.div_ceil(chunk_chars);
+        assert!(evaluated > 0 && evaluated <= bound, "{} chunk evaluations", evaluated);
+        assert_eq!(embedding, vec![1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_chunk_text_respects_size_and_char_boundaries() {
+        let text = "héllo wörld ".repeat(50);
+        let chunks = chunk_text(&text, 20);
+
+        assert!(chunks.iter().all(|c| c.chars().count() <= 20 && !c.is_empty()));
+        assert_eq!(chunks.concat().replace(' ', ""), text.replace(' ', ""));
+        assert!(chunk_text("   ", 20).is_empty());
+    }
+
+    #[test]
+    fn test_document_limits() {
+        let config = EmbeddingConfig {
+            max_document_tokens: 10,
+            max_document_lines: 3,
+            ..EmbeddingConfig::default()
+        };
+
+        assert!(!config.exceeds_document_limits("one\ntwo\nthree"));
+        assert!(config.exceeds_document_limits("one\ntwo\nthree\nfour"));
+        assert!(!config.exceeds_document_limits(&"x".repeat(40)));
+        assert!(config.exceeds_document_limits(&"x".repeat(41)));
+    }
+
+    #[test]
+    fn test_l2_normalize_zero_and_nan_vectors() {
+        let mut unit = vec![3.0, 4.0];
+        l2_normalize(&mut unit);
+        assert_eq!(unit, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0; 4];
+        l2_normalize(&mut zero);
+        assert_eq!(zero, vec![0.0; 4]);
+
+        let mut poisoned = vec![f32::NAN, 1.0, f32::INFINITY];
+        l2_normalize(&mut poisoned);
+        assert_eq!(poisoned, vec![0.0; 3]);
+    }
+
+    #[test]
+    fn test_mean_of_zero_embeddings_stays_finite() {
+        let mean = mean_of_chunks(&["", "   "], 8, |batch| Ok(vec![vec![0.0; 3]; batch.len()])).unwrap();
+        assert_eq!(mean, vec![0.0; 3]);
+    }
+
+    #[test]
+    #[ignore] // Requires model files to be downloaded
+    fn test_embed_empty_and_whitespace_text() {
+        let config = EmbeddingConfig::default();
+        let mut service = EmbeddingService::new(config).expect("Failed to load model");
+
+        for text in ["", "   ", "\n\t"] {
+            let embedding = service.embed_document(text).unwrap();
+            assert_eq!(embedding.len(), 384);
+            assert!(embedding.iter().all(|x| x.is_finite()));
+        }
+    }
+
+    #[test]
+    #[ignore] // Requires model files to be downloaded
+    fn test_tokenize_known_strings() {
+        let service = EmbeddingService::new(EmbeddingConfig::default()).unwrap();
+
+        assert_eq!(service.tokenize("").unwrap(), vec!["[CLS]", "[SEP]"]);
+        assert_eq!(service.tokenize("Hello world").unwrap(), vec!["[CLS]", "hello", "world", "[SEP]"]);
+        assert_eq!(service.tokenize("The quick brown fox jumps over the lazy dog.").unwrap().len(), 12);
+
+        // Past max_length nothing is cut off
+        let long = "word ".repeat(1_000);
+        assert_eq!(service.tokenize(&long).unwrap().len(), 1_002);
+    }
+
     #[test]
     #[ignore] // Requires model files to be downloaded
     fn test_embed_single_text() {
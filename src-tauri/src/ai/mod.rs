@@ -9,9 +9,12 @@
 //! ## Architecture
 //!
 //! - `embeddings.rs` - ONNX-based embedding generation
-//! - `similarity.rs` - Cosine similarity and semantic search
+//! - `similarity.rs` - Cosine similarity, semantic search and similarity matrices
+//! - `diagnostics.rs` - Nearest-neighbor statistics of the embedding space
+//! - `service.rs` - Load-once cache for the embedding service
+//! - `snippets.rs` - Best-matching sentence for semantic results
 //! - `clustering.rs` - K-means clustering for collections
-//! - `tagging.rs` - Auto-tagging logic
+//! - `tagging.rs` - TF-IDF keyword extraction for auto-tagging
 //!
 //! ## Privacy & Performance
 //!
@@ -21,11 +24,27 @@
 //! - Target: >10 files/second embedding generation
 //! - Target: <500ms semantic search for 10K files
 
+pub mod diagnostics;
 pub mod embeddings;
 pub mod model_downloader;
+pub mod service;
 pub mod similarity;
+pub mod snippets;
+pub mod tagging;
 
 // Re-exports
-pub use embeddings::{EmbeddingService, EmbeddingConfig};
-pub use model_downloader::{download_model, ensure_model_downloaded, is_model_downloaded};
-pub use similarity::{cosine_similarity, find_top_k, semantic_search};
+pub use diagnostics::{diagnose_embeddings, EmbeddingDiagnostics};
+pub use embeddings::{
+    embed_document_chunks, ChunkConfig, EmbeddingConfig, EmbeddingService, EmbeddingSource, EmbeddingStrategy,
+};
+pub use model_downloader::{
+    download_model, ensure_model_downloaded, gc_models, is_model_downloaded, list_downloaded_models,
+    remove_model, DownloadConfig, DownloadedModel, ModelDownloadError,
+};
+pub use service::LazyService;
+pub use similarity::{
+    cosine_similarity, find_top_k, find_top_k_with_stats, semantic_search, similarity_matrix,
+    write_similarity_csv, TopKStats,
+};
+pub use snippets::{best_matching_sentence, MAX_SNIPPET_SENTENCES};
+pub use tagging::{extract_keywords, Keyword};
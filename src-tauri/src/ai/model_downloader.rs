@@ -1,28 +1,47 @@
 //! Model Downloader Utility
 //!
-//! Downloads the all-MiniLM-L6-v2 ONNX model and tokenizer from Hugging Face.
+//! Downloads the all-MiniLM-L6-v2 ONNX model and tokenizer from Hugging Face,
+//! or from mirrors of it. Transient failures are retried with exponential
+//! backoff before moving on to the next mirror.
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The embedding model in use, which is also its directory name
+pub const MODEL_NAME: &str = "all-MiniLM-L6-v2";
 
 /// URLs for model files on Hugging Face
 const MODEL_REPO: &str = "sentence-transformers/all-MiniLM-L6-v2";
 const HF_BASE_URL: &str = "https://huggingface.co";
 
+/// Comma-separated base URLs to download from instead of Hugging Face, each
+/// serving the same `/<repo>/resolve/main/<file>` layout
+const MIRRORS_ENV_VAR: &str = "CORTEX_MODEL_MIRRORS";
+
+/// File marking a model directory as downloaded by Cortex, so garbage
+/// collection never touches models the user put there
+const DOWNLOAD_MARKER: &str = ".cortex-download";
+
 /// Model files to download
 const MODEL_FILES: &[(&str, &str)] = &[
     ("model.onnx", "onnx/model.onnx"),
     ("tokenizer.json", "tokenizer.json"),
 ];
 
-/// Get the path to the models directory
-pub fn get_models_dir() -> Result<PathBuf> {
+/// Directory holding one subdirectory per downloaded model
+pub fn get_models_root() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Failed to get home directory")?;
-    let models_dir = home.join(".cortex").join("models").join("all-MiniLM-L6-v2");
+    Ok(home.join(".cortex").join("models"))
+}
 
-    Ok(models_dir)
+/// Get the path to the models directory
+pub fn get_models_dir() -> Result<PathBuf> {
+    Ok(get_models_root()?.join(MODEL_NAME))
 }
 
 /// Check if model files are already downloaded
@@ -39,16 +58,221 @@ pub fn is_model_downloaded() -> Result<bool> {
     Ok(true)
 }
 
+/// A model directory under the models root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadedModel {
+    pub name: String,
+    pub size_bytes: u64,
+    /// The model embeddings are currently generated with
+    pub active: bool,
+    /// Downloaded by Cortex, so [`gc_models`] may remove it
+    pub downloaded: bool,
+}
+
+/// Every model downloaded under `~/.cortex/models`
+pub fn list_downloaded_models() -> Result<Vec<DownloadedModel>> {
+    list_models_in(&get_models_root()?, MODEL_NAME)
+}
+
+/// Delete a downloaded model other than the active one, returning the
+/// bytes freed
+pub fn remove_model(name: &str) -> Result<u64> {
+    remove_model_in(&get_models_root()?, name, MODEL_NAME)
+}
+
+/// Delete every model Cortex downloaded except the active one
+pub fn gc_models() -> Result<Vec<DownloadedModel>> {
+    gc_models_in(&get_models_root()?, MODEL_NAME)
+}
+
+/// The model directories in `root`, sorted by name
+pub fn list_models_in(root: &Path, active: &str) -> Result<Vec<DownloadedModel>> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut models = Vec::new();
+    for entry in fs::read_dir(root).context("Failed to read models directory")? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        models.push(DownloadedModel {
+            active: name == active,
+            size_bytes: dir_size(&entry.path())?,
+            downloaded: entry.path().join(DOWNLOAD_MARKER).is_file(),
+            name,
+        });
+    }
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+/// Delete the model `name` from `root` unless it is `active`
+pub fn remove_model_in(root: &Path, name: &str, active: &str) -> Result<u64> {
+    // Only a plain directory name, so nothing outside `root` can be removed
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        anyhow::bail!("Invalid model name: {:?}", name);
+    }
+    if name == active {
+        anyhow::bail!("Cannot remove {}: it is the active embedding model", name);
+    }
+
+    let dir = root.join(name);
+    if !dir.is_dir() {
+        anyhow::bail!("Model {} is not downloaded", name);
+    }
+
+    let size = dir_size(&dir)?;
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove model {}", name))?;
+    log::info!("Removed model {} ({} bytes)", name, size);
+
+    Ok(size)
+}
+
+/// Delete every model Cortex downloaded into `root` but `active`,
+/// returning those removed
+///
+/// Directories without the download marker were put there some other way
+/// and are left alone; [`remove_model_in`] removes them by name.
+pub fn gc_models_in(root: &Path, active: &str) -> Result<Vec<DownloadedModel>> {
+    let mut removed = Vec::new();
+    for model in list_models_in(root, active)? {
+        if model.downloaded && !model.active {
+            remove_model_in(root, &model.name, active)?;
+            removed.push(model);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Total size of the files under `path`
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Where and how persistently to download the model from
+#[derive(Debug, Clone)]
+pub struct DownloadConfig {
+    /// Base URLs tried in order until one serves the file
+    pub mirrors: Vec<String>,
+    /// Extra attempts per mirror after a transient failure
+    pub max_retries: u32,
+    /// Wait before the first retry; doubled for each one after
+    pub initial_backoff: Duration,
+    /// Per-request timeout
+    pub timeout: Duration,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            mirrors: vec![HF_BASE_URL.to_string()],
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl DownloadConfig {
+    /// The defaults, with mirrors from `CORTEX_MODEL_MIRRORS` if it is set
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Ok(value) = std::env::var(MIRRORS_ENV_VAR) {
+            let mirrors: Vec<String> = value
+                .split(',')
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty())
+                .collect();
+            if !mirrors.is_empty() {
+                config.mirrors = mirrors;
+            }
+        }
+
+        config
+    }
+}
+
+/// Why a model file couldn't be downloaded from any mirror
+#[derive(Debug)]
+pub enum ModelDownloadError {
+    /// No mirror could be reached at all, e.g. no network connection
+    Offline { file: String, failures: Vec<String> },
+    /// Every mirror answered, but none has the file
+    NotFound { file: String, urls: Vec<String> },
+    /// Some other mix of failures, one entry per mirror
+    AllMirrorsFailed { file: String, failures: Vec<String> },
+}
+
+impl fmt::Display for ModelDownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offline { file, failures } => write!(
+                f,
+                "Could not download {}: no mirror was reachable, check the network connection ({})",
+                file,
+                failures.join("; ")
+            ),
+            Self::NotFound { file, urls } => write!(
+                f,
+                "Could not download {}: not found on any mirror ({})",
+                file,
+                urls.join(", ")
+            ),
+            Self::AllMirrorsFailed { file, failures } => write!(
+                f,
+                "Could not download {} from any mirror: {}",
+                file,
+                failures.join("; ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelDownloadError {}
+
+/// Outcome of one mirror, after any retries
+enum MirrorFailure {
+    Unreachable(String),
+    NotFound,
+    Failed(String),
+}
+
 /// Download all required model files
 pub fn download_model() -> Result<()> {
-    let models_dir = get_models_dir()?;
+    download_model_to(&get_models_dir()?, &DownloadConfig::from_env())
+}
 
+/// Download any model files missing from `models_dir`, per `config`
+pub fn download_model_to(models_dir: &Path, config: &DownloadConfig) -> Result<()> {
     // Create models directory if it doesn't exist
-    fs::create_dir_all(&models_dir)
+    fs::create_dir_all(models_dir)
         .context("Failed to create models directory")?;
 
     log::info!("Downloading all-MiniLM-L6-v2 model to {:?}", models_dir);
 
+    let client = reqwest::blocking::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .context("Failed to create HTTP client")?;
+
     for (filename, repo_path) in MODEL_FILES {
         let file_path = models_dir.join(filename);
 
@@ -58,22 +282,7 @@ pub fn download_model() -> Result<()> {
             continue;
         }
 
-        // Construct download URL
-        let url = format!("{}/{}/resolve/main/{}", HF_BASE_URL, MODEL_REPO, repo_path);
-
-        log::info!("Downloading {} from {}", filename, url);
-
-        // Download file
-        let response = reqwest::blocking::get(&url)
-            .context(format!("Failed to download {}", filename))?;
-
-        if !response.status().is_success() {
-            anyhow::bail!("Failed to download {}: HTTP {}", filename, response.status());
-        }
-
-        // Save to file
-        let bytes = response.bytes()
-            .context(format!("Failed to read response for {}", filename))?;
+        let bytes = download_file(&client, config, filename, repo_path)?;
 
         let mut file = File::create(&file_path)
             .context(format!("Failed to create file {}", filename))?;
@@ -84,11 +293,113 @@ pub fn download_model() -> Result<()> {
         log::info!("Downloaded {} ({} bytes)", filename, bytes.len());
     }
 
+    // Marked once complete, also for files an earlier version downloaded
+    File::create(models_dir.join(DOWNLOAD_MARKER)).context("Failed to mark the model as downloaded")?;
+
     log::info!("Model download complete!");
 
     Ok(())
 }
 
+/// Fetch one file from the first mirror that serves it
+fn download_file(
+    client: &reqwest::blocking::Client,
+    config: &DownloadConfig,
+    filename: &str,
+    repo_path: &str,
+) -> std::result::Result<Vec<u8>, ModelDownloadError> {
+    let mut failures = Vec::new();
+
+    for mirror in &config.mirrors {
+        let url = format!("{}/{}/resolve/main/{}", mirror.trim_end_matches('/'), MODEL_REPO, repo_path);
+
+        log::info!("Downloading {} from {}", filename, url);
+
+        match fetch_with_retries(client, config, &url) {
+            Ok(bytes) => return Ok(bytes),
+            Err(failure) => {
+                log::warn!("Mirror {} failed for {}", mirror, filename);
+                failures.push((url, failure));
+            }
+        }
+    }
+
+    let file = filename.to_string();
+    let describe = |(url, failure): &(String, MirrorFailure)| match failure {
+        MirrorFailure::Unreachable(reason) | MirrorFailure::Failed(reason) => {
+            format!("{}: {}", url, reason)
+        }
+        MirrorFailure::NotFound => format!("{}: not found", url),
+    };
+
+    if failures.iter().all(|(_, f)| matches!(f, MirrorFailure::Unreachable(_))) {
+        Err(ModelDownloadError::Offline {
+            file,
+            failures: failures.iter().map(describe).collect(),
+        })
+    } else if failures.iter().all(|(_, f)| matches!(f, MirrorFailure::NotFound)) {
+        Err(ModelDownloadError::NotFound {
+            file,
+            urls: failures.into_iter().map(|(url, _)| url).collect(),
+        })
+    } else {
+        Err(ModelDownloadError::AllMirrorsFailed {
+            file,
+            failures: failures.iter().map(describe).collect(),
+        })
+    }
+}
+
+/// GET `url`, retrying connection errors, timeouts and 408/429/5xx
+/// responses with exponential backoff
+fn fetch_with_retries(
+    client: &reqwest::blocking::Client,
+    config: &DownloadConfig,
+    url: &str,
+) -> std::result::Result<Vec<u8>, MirrorFailure> {
+    let mut attempt = 0;
+
+    loop {
+        let failure = match client.get(url).send() {
+            Ok(response) if response.status().is_success() => match response.bytes() {
+                Ok(bytes) => return Ok(bytes.to_vec()),
+                Err(e) => MirrorFailure::Unreachable(format!("failed to read response: {}", e)),
+            },
+            Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                return Err(MirrorFailure::NotFound);
+            }
+            Ok(response) => {
+                let status = response.status();
+                let transient = status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                let failure = MirrorFailure::Failed(format!("HTTP {}", status));
+                if !transient {
+                    return Err(failure);
+                }
+                failure
+            }
+            Err(e) if e.is_connect() || e.is_timeout() => MirrorFailure::Unreachable(e.to_string()),
+            Err(e) => return Err(MirrorFailure::Failed(e.to_string())),
+        };
+
+        if attempt >= config.max_retries {
+            return Err(failure);
+        }
+
+        let backoff = config.initial_backoff * 2u32.pow(attempt);
+        attempt += 1;
+        log::warn!(
+            "Transient failure downloading {}, retrying in {:?} (attempt {} of {})",
+            url,
+            backoff,
+            attempt,
+            config.max_retries
+        );
+        std::thread::sleep(backoff);
+    }
+}
+
 /// Ensure model is downloaded, download if not
 pub fn ensure_model_downloaded() -> Result<()> {
     if !is_model_downloaded()? {
@@ -113,6 +424,134 @@ mod tests {
         assert!(dir.to_str().unwrap().contains("all-MiniLM-L6-v2"));
     }
 
+    #[test]
+    fn test_gc_removes_all_but_the_active_model() {
+        let root = tempfile::TempDir::new().unwrap();
+        for (name, size) in [("all-MiniLM-L6-v2", 10), ("old-model", 300), ("older-model", 20), ("my-model", 5)] {
+            let dir = root.path().join(name).join("onnx");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("model.onnx"), vec![0u8; size]).unwrap();
+            if name != "my-model" {
+                fs::write(root.path().join(name).join(DOWNLOAD_MARKER), "").unwrap();
+            }
+        }
+        fs::write(root.path().join("notes.txt"), "not a model").unwrap();
+
+        let models = list_models_in(root.path(), "all-MiniLM-L6-v2").unwrap();
+        let names: Vec<_> =
+            models.iter().map(|m| (m.name.as_str(), m.size_bytes, m.active, m.downloaded)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("all-MiniLM-L6-v2", 10, true, true),
+                ("my-model", 5, false, false),
+                ("old-model", 300, false, true),
+                ("older-model", 20, false, true),
+            ]
+        );
+
+        // The active model and anything outside the root are refused
+        assert!(remove_model_in(root.path(), "all-MiniLM-L6-v2", "all-MiniLM-L6-v2").is_err());
+        assert!(remove_model_in(root.path(), "..", "all-MiniLM-L6-v2").is_err());
+        assert!(remove_model_in(root.path(), "missing", "all-MiniLM-L6-v2").is_err());
+
+        let removed = gc_models_in(root.path(), "all-MiniLM-L6-v2").unwrap();
+        assert_eq!(removed.iter().map(|m| m.size_bytes).sum::<u64>(), 320);
+        assert!(root.path().join("all-MiniLM-L6-v2/onnx/model.onnx").exists());
+        assert!(!root.path().join("old-model").exists());
+        assert!(!root.path().join("older-model").exists());
+        assert!(root.path().join("notes.txt").exists());
+
+        // A model the user placed there goes only when asked for by name
+        assert!(root.path().join("my-model").exists());
+        assert_eq!(remove_model_in(root.path(), "my-model", "all-MiniLM-L6-v2").unwrap(), 5);
+    }
+
+    fn test_config(mirrors: Vec<String>) -> DownloadConfig {
+        DownloadConfig {
+            mirrors,
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn file_url(name: &str) -> String {
+        let repo_path = MODEL_FILES.iter().find(|(f, _)| *f == name).unwrap().1;
+        format!("/{}/resolve/main/{}", MODEL_REPO, repo_path)
+    }
+
+    #[test]
+    fn test_transient_failure_succeeds_on_retry() {
+        let mut server = mockito::Server::new();
+        let flaky = server
+            .mock("GET", file_url("model.onnx").as_str())
+            .with_status(503)
+            .expect(1)
+            .create();
+        let model = server
+            .mock("GET", file_url("model.onnx").as_str())
+            .with_body("onnx bytes")
+            .expect(1)
+            .create();
+        let tokenizer = server
+            .mock("GET", file_url("tokenizer.json").as_str())
+            .with_body("{}")
+            .create();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        download_model_to(temp_dir.path(), &test_config(vec![server.url()])).unwrap();
+
+        flaky.assert();
+        model.assert();
+        tokenizer.assert();
+        assert_eq!(fs::read(temp_dir.path().join("model.onnx")).unwrap(), b"onnx bytes");
+        assert!(temp_dir.path().join("tokenizer.json").exists());
+    }
+
+    #[test]
+    fn test_all_mirrors_failing_is_reported_per_mirror() {
+        let mut missing = mockito::Server::new();
+        missing.mock("GET", mockito::Matcher::Any).with_status(404).create();
+        let mut broken = mockito::Server::new();
+        let retried = broken
+            .mock("GET", mockito::Matcher::Any)
+            .with_status(500)
+            .expect(3)
+            .create();
+
+        // Nothing listens on a port that was just released
+        let unreachable = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let download = |mirrors: Vec<String>| {
+            download_model_to(temp_dir.path(), &test_config(mirrors))
+                .unwrap_err()
+                .downcast::<ModelDownloadError>()
+                .unwrap()
+        };
+
+        match download(vec![missing.url(), broken.url(), unreachable.clone()]) {
+            ModelDownloadError::AllMirrorsFailed { file, failures } => {
+                assert_eq!(file, "model.onnx");
+                assert_eq!(failures.len(), 3);
+                assert!(failures[0].starts_with(&missing.url()) && failures[0].ends_with("not found"));
+                assert!(failures[1].contains("HTTP 500"));
+                assert!(failures[2].starts_with(&unreachable));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        // The first try and two retries
+        retried.assert();
+
+        assert!(matches!(download(vec![missing.url()]), ModelDownloadError::NotFound { .. }));
+        assert!(matches!(download(vec![unreachable]), ModelDownloadError::Offline { .. }));
+        assert!(!temp_dir.path().join("model.onnx").exists());
+    }
+
     #[test]
     #[ignore] // Requires network access
     fn test_download_model() {
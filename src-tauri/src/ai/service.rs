@@ -0,0 +1,149 @@
+//! Shared Model Services
+//!
+//! Loading the ONNX session and tokenizer takes hundreds of milliseconds to
+//! seconds, so the embedding service is loaded once and kept on `AppState`
+//! instead of being rebuilt by every command.
+
+use std::sync::Mutex;
+
+/// A service that is loaded on first use and reused afterwards
+///
+/// Calls are serialized by an internal mutex. A failed load is not cached,
+/// so the next call tries again (e.g. once the model has been downloaded).
+pub struct LazyService<T> {
+    inner: Mutex<Option<T>>,
+}
+
+impl<T> LazyService<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(None),
+        }
+    }
+
+    /// Whether the service has been loaded
+    pub fn is_loaded(&self) -> bool {
+        self.inner.lock().unwrap().is_some()
+    }
+
+    /// Run `f` with the service, calling `load` first if it isn't loaded yet
+    pub fn with<R, E>(
+        &self,
+        load: impl FnOnce() -> Result<T, E>,
+        f: impl FnOnce(&mut T) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let mut guard = self.inner.lock().unwrap();
+
+        if guard.is_none() {
+            *guard = Some(load()?);
+        }
+
+        f(guard.as_mut().unwrap())
+    }
+
+    /// Like [`with`](Self::with), but reloads first when `is_stale` rejects
+    /// the loaded service (e.g. because its configuration changed)
+    pub fn with_reload_if<R, E>(
+        &self,
+        is_stale: impl FnOnce(&T) -> bool,
+        load: impl FnOnce() -> Result<T, E>,
+        f: impl FnOnce(&mut T) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let mut guard = self.inner.lock().unwrap();
+
+        if guard.as_ref().is_some_and(is_stale) {
+            *guard = None;
+        }
+
+        if guard.is_none() {
+            *guard = Some(load()?);
+        }
+
+        f(guard.as_mut().unwrap())
+    }
+
+    /// Drop the loaded service so the next call loads it again
+    pub fn invalidate(&self) {
+        *self.inner.lock().unwrap() = None;
+    }
+}
+
+impl<T> Default for LazyService<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_loaded_once_and_reused() {
+        let service: LazyService<Vec<&str>> = LazyService::new();
+        let mut loads = 0;
+
+        for call in ["first", "second", "third"] {
+            service
+                .with(
+                    || {
+                        loads += 1;
+                        Ok::<_, String>(Vec::new())
+                    },
+                    |calls| {
+                        calls.push(call);
+                        Ok(())
+                    },
+                )
+                .unwrap();
+        }
+
+        assert_eq!(loads, 1);
+        assert!(service.is_loaded());
+
+        // State persists between calls, so it's the same instance
+        let seen = service.with(|| unreachable!(), |calls| Ok::<_, String>(calls.clone())).unwrap();
+        assert_eq!(seen, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_failed_load_is_retried() {
+        let service: LazyService<u32> = LazyService::new();
+
+        let err = service.with(|| Err("model missing"), |_| Ok(())).unwrap_err();
+        assert_eq!(err, "model missing");
+        assert!(!service.is_loaded());
+
+        let value = service.with(|| Ok(7), |v| Ok::<_, &str>(*v)).unwrap();
+        assert_eq!(value, 7);
+    }
+
+    #[test]
+    fn test_reload_when_config_changes() {
+        // The loaded "service" is just the config it was built from
+        let service: LazyService<u32> = LazyService::new();
+        let mut loads = 0;
+
+        let mut call = |config: u32| {
+            service
+                .with_reload_if(
+                    |loaded| *loaded != config,
+                    || {
+                        loads += 1;
+                        Ok::<_, String>(config)
+                    },
+                    |loaded| Ok(*loaded),
+                )
+                .unwrap()
+        };
+
+        assert_eq!(call(1), 1);
+        assert_eq!(call(1), 1);
+        assert_eq!(call(2), 2);
+        assert_eq!(call(2), 2);
+        assert_eq!(loads, 2);
+
+        service.invalidate();
+        assert!(!service.is_loaded());
+    }
+}
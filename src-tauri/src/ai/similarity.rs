@@ -31,6 +31,12 @@ use anyhow::Result;
 /// assert!((similarity - 1.0).abs() < 0.001);
 /// ```
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    defined_similarity(a, b).unwrap_or(0.0)
+}
+
+/// Cosine similarity, or `None` when it is undefined: a zero-length vector
+/// or NaN/infinite components
+fn defined_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
     assert_eq!(a.len(), b.len(), "Vectors must have the same length");
 
     let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
@@ -38,10 +44,11 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     let norm_b: f32 = b.iter().map(|y| y * y).sum::<f32>().sqrt();
 
     if norm_a == 0.0 || norm_b == 0.0 {
-        return 0.0;
+        return None;
     }
 
-    dot / (norm_a * norm_b)
+    let similarity = dot / (norm_a * norm_b);
+    similarity.is_finite().then_some(similarity)
 }
 
 /// Find top-k most similar vectors to a query vector
@@ -55,7 +62,8 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
 ///
 /// # Returns
 ///
-/// Vector of (id, score) pairs sorted by score descending
+/// Vector of (id, score) pairs sorted by score descending. Candidates whose
+/// similarity is undefined (zero or NaN vectors) never match.
 ///
 /// # Example
 ///
@@ -76,11 +84,41 @@ pub fn find_top_k<T: Clone>(
     top_k: usize,
     threshold: f32,
 ) -> Vec<(T, f32)> {
+    find_top_k_with_stats(query, candidates, top_k, threshold).0
+}
+
+/// How a [`find_top_k_with_stats`] call's candidates fared against the
+/// threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TopKStats {
+    /// Candidates with a defined similarity to the query
+    pub candidates_scored: usize,
+    /// Candidates at or above the threshold, before truncating to `top_k`
+    pub passed_threshold: usize,
+    /// Highest similarity seen, whether or not it cleared the threshold
+    pub max_score: Option<f32>,
+}
+
+/// [`find_top_k`], also reporting how many candidates were scored and
+/// cleared the threshold
+pub fn find_top_k_with_stats<T: Clone>(
+    query: &[f32],
+    candidates: &[(T, Vec<f32>)],
+    top_k: usize,
+    threshold: f32,
+) -> (Vec<(T, f32)>, TopKStats) {
+    let mut stats = TopKStats::default();
+
     let mut scored: Vec<(T, f32)> = candidates
         .iter()
-        .map(|(id, vec)| (id.clone(), cosine_similarity(query, vec)))
+        .filter_map(|(id, vec)| defined_similarity(query, vec).map(|score| (id.clone(), score)))
+        .inspect(|(_, score)| {
+            stats.candidates_scored += 1;
+            stats.max_score = Some(stats.max_score.map_or(*score, |max| max.max(*score)));
+        })
         .filter(|(_, score)| *score >= threshold)
         .collect();
+    stats.passed_threshold = scored.len();
 
     // Sort by score descending
     scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
@@ -88,7 +126,53 @@ pub fn find_top_k<T: Clone>(
     // Take top K
     scored.truncate(top_k);
 
-    scored
+    (scored, stats)
+}
+
+/// Pairwise cosine similarities of `vectors`, as a symmetric matrix
+///
+/// The diagonal is exactly 1.0, except for vectors whose similarity is
+/// undefined (zero or NaN), whose whole row is 0.0 as in
+/// [`cosine_similarity`].
+pub fn similarity_matrix(vectors: &[&[f32]]) -> Vec<Vec<f32>> {
+    let n = vectors.len();
+    let mut matrix = vec![vec![0.0f32; n]; n];
+
+    for i in 0..n {
+        if defined_similarity(vectors[i], vectors[i]).is_some() {
+            matrix[i][i] = 1.0;
+        }
+        for j in (i + 1)..n {
+            let similarity = cosine_similarity(vectors[i], vectors[j]);
+            matrix[i][j] = similarity;
+            matrix[j][i] = similarity;
+        }
+    }
+
+    matrix
+}
+
+/// Write `matrix` as CSV with `ids` labelling both the rows and columns
+pub fn write_similarity_csv<W: std::io::Write>(
+    out: &mut W,
+    ids: &[i64],
+    matrix: &[Vec<f32>],
+) -> std::io::Result<()> {
+    write!(out, "file_id")?;
+    for id in ids {
+        write!(out, ",{}", id)?;
+    }
+    writeln!(out)?;
+
+    for (id, row) in ids.iter().zip(matrix) {
+        write!(out, "{}", id)?;
+        for similarity in row {
+            write!(out, ",{:.6}", similarity)?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
 }
 
 /// Semantic search function (placeholder - to be integrated with database)
@@ -170,4 +254,24 @@ mod tests {
         assert_eq!(results.len(), 2);
         assert!(results.iter().all(|(_, score)| *score >= 0.7));
     }
+
+    #[test]
+    fn test_degenerate_vectors_never_match() {
+        let query = vec![1.0, 0.0, 0.0];
+        let candidates = vec![
+            (1, vec![0.0, 0.0, 0.0]),
+            (2, vec![f32::NAN, 0.0, 0.0]),
+            (3, vec![1.0, 0.0, 0.0]),
+        ];
+
+        assert_eq!(cosine_similarity(&query, &candidates[1].1), 0.0);
+
+        // Even a permissive threshold only returns the real match
+        let results = find_top_k(&query, &candidates, 10, -1.0);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, 3);
+
+        // A zero query matches nothing
+        assert!(find_top_k(&[0.0, 0.0, 0.0], &candidates, 10, -1.0).is_empty());
+    }
 }
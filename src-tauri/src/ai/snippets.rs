@@ -0,0 +1,100 @@
+//! Semantic Snippets
+//!
+//! Files only have one document-level embedding, so a semantic match can't
+//! point at the passage that caused it. Instead, the file's sentences are
+//! embedded on demand and the one closest to the query is shown.
+
+use crate::ai::similarity::cosine_similarity;
+use crate::error::Result;
+use crate::text::split_sentences;
+
+/// Most sentences embedded per file, to bound the cost of a snippet
+pub const MAX_SNIPPET_SENTENCES: usize = 12;
+
+/// Sentences longer than this are cut before embedding and display
+const MAX_SENTENCE_CHARS: usize = 400;
+
+/// The sentence of `text` most similar to `query_embedding`
+///
+/// Only the first `max_sentences` sentences are considered, embedded in a
+/// single `embed_batch` call. Returns `None` for text without sentences.
+pub fn best_matching_sentence<F>(
+    text: &str,
+    query_embedding: &[f32],
+    max_sentences: usize,
+    embed_batch: F,
+) -> Result<Option<String>>
+where
+    F: FnOnce(&[&str]) -> Result<Vec<Vec<f32>>>,
+{
+    let sentences: Vec<&str> = split_sentences(text)
+        .into_iter()
+        .take(max_sentences)
+        .map(|sentence| match sentence.char_indices().nth(MAX_SENTENCE_CHARS) {
+            Some((end, _)) => &sentence[..end],
+            None => sentence,
+        })
+        .collect();
+
+    if sentences.is_empty() {
+        return Ok(None);
+    }
+
+    let embeddings = embed_batch(&sentences)?;
+
+    let best = sentences
+        .iter()
+        .zip(&embeddings)
+        .map(|(sentence, embedding)| (sentence, cosine_similarity(query_embedding, embedding)))
+        .fold(None, |best: Option<(&&str, f32)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        });
+
+    Ok(best.map(|(sentence, _)| sentence.split_whitespace().collect::<Vec<_>>().join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One dimension per topic word, so sentences sharing the query's topic
+    /// score highest
+    fn embed(text: &str) -> Vec<f32> {
+        ["engine", "garden", "invoice"]
+            .iter()
+            .map(|topic| if text.to_lowercase().contains(topic) { 1.0 } else { 0.0 })
+            .chain(std::iter::once(0.1))
+            .collect()
+    }
+
+    #[test]
+    fn test_snippet_is_most_relevant_sentence() {
+        let text = "The invoice was paid in March. We planted tomatoes in the garden. \
+                    Afterwards the engine   needed new spark plugs! Nothing else happened.";
+
+        let snippet = best_matching_sentence(text, &embed("engine trouble"), 10, |sentences| {
+            assert_eq!(sentences.len(), 4);
+            Ok(sentences.iter().map(|s| embed(s)).collect())
+        })
+        .unwrap();
+
+        assert_eq!(snippet.as_deref(), Some("Afterwards the engine needed new spark plugs!"));
+    }
+
+    #[test]
+    fn test_sentence_cap_and_empty_text() {
+        let text = "First garden note. Second garden note. The engine note.";
+
+        // The engine sentence is past the cap, so it can't be chosen
+        let snippet = best_matching_sentence(text, &embed("engine"), 2, |sentences| {
+            assert_eq!(sentences.len(), 2);
+            Ok(sentences.iter().map(|s| embed(s)).collect())
+        })
+        .unwrap();
+        assert_eq!(snippet.as_deref(), Some("First garden note."));
+
+        let none = best_matching_sentence("   ", &embed("engine"), 10, |_| unreachable!()).unwrap();
+        assert!(none.is_none());
+    }
+}
@@ -0,0 +1,156 @@
+//! Keyword Extraction
+//!
+//! Ranks a file's terms by TF-IDF: how often a term appears in the file,
+//! weighted by how rare it is across the indexed corpus. Document
+//! frequencies come from the FTS index, so nothing needs precomputing.
+
+use crate::db::operations::get_file_content;
+use crate::error::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Only the most frequent terms of a file are scored against the corpus
+const MAX_CANDIDATE_TERMS: usize = 64;
+
+/// Shortest term considered a keyword
+const MIN_TERM_LEN: usize = 3;
+
+/// Common English words that never make useful keywords
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "again", "all", "also", "and", "any", "are", "because", "been", "before",
+    "being", "between", "both", "but", "can", "could", "did", "does", "doing", "down", "each",
+    "few", "for", "from", "further", "had", "has", "have", "having", "her", "here", "hers",
+    "him", "his", "how", "into", "its", "itself", "just", "more", "most", "not", "now", "off",
+    "once", "only", "other", "our", "ours", "out", "over", "own", "same", "she", "should",
+    "some", "such", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+    "this", "those", "through", "too", "under", "until", "very", "was", "were", "what", "when",
+    "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyword {
+    pub term: String,
+    pub score: f32,
+}
+
+/// Lowercase word tokens worth considering as keywords
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_LEN)
+        .filter(|word| !word.chars().all(|c| c.is_numeric()))
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+/// Number of indexed documents whose content matches `term`
+fn document_frequency(conn: &Connection, term: &str) -> Result<i64> {
+    // Tokens are alphanumeric, so quoting makes them safe FTS phrases
+    let query = format!("content : \"{}\"", term);
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM files_fts WHERE files_fts MATCH ?1",
+        params![query],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// The `limit` most distinctive terms of a file, highest score first
+///
+/// Returns nothing for files without text content.
+pub fn extract_keywords(conn: &Connection, file_id: i64, limit: usize) -> Result<Vec<Keyword>> {
+    let text = match get_file_content(conn, file_id)?.and_then(|c| c.text_content) {
+        Some(text) => text,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut term_counts: HashMap<String, usize> = HashMap::new();
+    let mut total_terms = 0;
+    for term in tokenize(&text) {
+        *term_counts.entry(term).or_insert(0) += 1;
+        total_terms += 1;
+    }
+
+    if total_terms == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut candidates: Vec<(String, usize)> = term_counts.into_iter().collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    candidates.truncate(MAX_CANDIDATE_TERMS);
+
+    let total_docs: i64 = conn.query_row("SELECT COUNT(*) FROM files_fts", [], |row| row.get(0))?;
+
+    let mut keywords = Vec::with_capacity(candidates.len());
+    for (term, count) in candidates {
+        let df = document_frequency(conn, &term)?;
+        let tf = count as f32 / total_terms as f32;
+        // Smoothed so terms in every document still score above zero
+        let idf = ((total_docs + 1) as f32 / (df + 1) as f32).ln() + 1.0;
+
+        keywords.push(Keyword {
+            term,
+            score: tf * idf,
+        });
+    }
+
+    keywords.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.term.cmp(&b.term))
+    });
+    keywords.truncate(limit);
+
+    Ok(keywords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_tables, insert_file, upsert_file_content};
+
+    fn add_doc(conn: &Connection, name: &str, text: &str) -> i64 {
+        let id = insert_file(conn, &format!("/test/{}", name), name, "md", text.len() as i64,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(conn, id, Some(text), None).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_rust_file_keywords_beat_filler() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let rust = add_doc(&conn, "rust.md",
+            "Project notes: the Rust borrow checker. Rust ownership and Rust lifetimes \
+             make the project notes about Rust easier. The project notes continue.");
+        add_doc(&conn, "garden.md", "Project notes on the garden and the tomato beds.");
+        add_doc(&conn, "travel.md", "Project notes for the trip and the train schedule.");
+
+        let keywords = extract_keywords(&conn, rust, 3).unwrap();
+        let terms: Vec<&str> = keywords.iter().map(|k| k.term.as_str()).collect();
+
+        assert_eq!(terms[0], "rust");
+        assert!(!terms.contains(&"the"));
+
+        // "project" appears as often as "rust" but in every document
+        let all = extract_keywords(&conn, rust, 50).unwrap();
+        let score = |term: &str| all.iter().find(|k| k.term == term).unwrap().score;
+        assert!(score("rust") > score("project"));
+        assert!(score("rust") > score("notes"));
+    }
+
+    #[test]
+    fn test_no_content_no_keywords() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let empty = insert_file(&conn, "/test/empty.md", "empty.md", "md", 0,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        assert!(extract_keywords(&conn, empty, 5).unwrap().is_empty());
+
+        let filler = add_doc(&conn, "filler.md", "the and with 42 of");
+        assert!(extract_keywords(&conn, filler, 5).unwrap().is_empty());
+    }
+}
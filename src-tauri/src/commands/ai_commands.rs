@@ -2,27 +2,72 @@
 //!
 //! Provides Tauri commands for AI features:
 //! - Embedding generation
-//! - Semantic search
+//! - Semantic search, with best-sentence snippets
 //! - Similarity finding
+//! - Related search suggestions
+//! - Single-file embedding refresh
+//! - Per-file keywords
+//! - Embedding space diagnostics
+//! - Similarity matrix export
+//! - Full re-embed under new chunking
+//! - Model warm-up
+//! - Token counting
+//! - Embedding throughput benchmark
 
-use crate::ai::{ensure_model_downloaded, is_model_downloaded, EmbeddingConfig, EmbeddingService};
-use crate::db::operations::{
-    count_embeddings, get_all_embeddings, get_embedding, get_file_content,
-    get_files_by_ids, get_files_without_embeddings, upsert_embedding,
+use crate::ai::{
+    cosine_similarity, diagnose_embeddings, embed_document_chunks, ensure_model_downloaded, extract_keywords,
+    is_model_downloaded, best_matching_sentence, similarity_matrix, write_similarity_csv, ChunkConfig,
+    DownloadedModel, EmbeddingConfig, EmbeddingDiagnostics, EmbeddingService, EmbeddingSource, Keyword,
+    LazyService, MAX_SNIPPET_SENTENCES,
 };
+use crate::db::config::{get_config, set_config};
+use crate::db::embeddings::{
+    count_embeddings, count_files_over_embedding_limits, delete_embedding, get_all_embeddings, get_embedding,
+    get_files_without_embeddings, get_live_embeddings, list_orphaned_embeddings, upsert_embedding,
+};
+use crate::db::operations::{duplicate_paths, get_file_by_id, get_file_content, get_files_by_ids, list_files};
+use crate::db::search_history::{get_history_with_embeddings, set_history_embedding};
+use crate::db::{Database, File};
 use crate::error::{CortexError, Result};
+use crate::export::PathValidator;
+use crate::indexer::ProfileRoots;
 use crate::state::AppState;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tauri::State;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
 
 const MODEL_VERSION: &str = "all-MiniLM-L6-v2";
 
+/// Only the top semantic results get a snippet, since each one embeds up to
+/// [`MAX_SNIPPET_SENTENCES`] of the file's sentences
+const SEMANTIC_SNIPPET_RESULTS: usize = 5;
+
+/// Past searches less similar than this are not suggested
+const RELATED_SEARCH_THRESHOLD: f32 = 0.5;
+
+/// Files [`reembed_files`] embeds between taking the database lock
+const REEMBED_BATCH_FILES: usize = 32;
+
+/// Files [`benchmark_embeddings`] embeds when no sample size is given, and
+/// the most it will embed
+const DEFAULT_BENCHMARK_SAMPLE: usize = 20;
+const MAX_BENCHMARK_SAMPLE: usize = 500;
+
+/// Most files a similarity matrix export may cover; the CSV grows with the
+/// square of this (about 40 MB at the cap)
+pub const MAX_SIMILARITY_MATRIX_FILES: usize = 2000;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmbeddingStatus {
     pub total_files: i64,
     pub files_with_embeddings: i64,
     pub files_without_embeddings: i64,
+    /// Files left unembedded because they exceed the configured size limits
+    pub files_skipped: i64,
     pub model_downloaded: bool,
     pub model_version: String,
 }
@@ -34,6 +79,214 @@ pub struct SemanticSearchResult {
     pub filename: String,
     pub file_type: String,
     pub similarity_score: f32,
+    /// The sentence most similar to the query, for the top results
+    pub snippet: Option<String>,
+    /// Live files with the same content, which share this one's embedding
+    #[serde(default)]
+    pub duplicate_paths: Vec<String>,
+}
+
+/// Semantic search results, with how the scored files fared against the
+/// threshold
+///
+/// When fewer than `limit` results come back, comparing `passed_threshold`
+/// with `candidates_scored` and `max_score` tells "nothing relevant" apart
+/// from "threshold too high".
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SemanticSearchResponse {
+    pub results: Vec<SemanticSearchResult>,
+    /// Embedded files compared against the query
+    pub candidates_scored: usize,
+    /// Files at or above the threshold, which may exceed `limit`
+    pub passed_threshold: usize,
+    /// Best similarity of any scored file, above the threshold or not
+    pub max_score: Option<f32>,
+    /// Embeddings whose file no longer exists, which can't match;
+    /// `cleanup_orphans` or `repair_index` removes them
+    pub orphaned_embeddings: Vec<i64>,
+    pub limit: usize,
+    pub threshold: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimilarityMatrixExport {
+    pub output_path: String,
+    /// Files in the matrix, in row/column order
+    pub file_ids: Vec<i64>,
+    /// Requested files left out because they have no embedding
+    pub missing_embeddings: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileComparison {
+    pub file_id_a: i64,
+    pub file_id_b: i64,
+    /// Cosine similarity of the two files' embeddings
+    pub similarity: f32,
+    /// Versions the embeddings were made with; differing ones (e.g. a
+    /// summary against full text) make the score less meaningful
+    pub model_version_a: String,
+    pub model_version_b: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReembedProgressEvent {
+    pub total_files: usize,
+    /// Files processed so far, embedded or skipped
+    pub processed_files: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReembedSummary {
+    pub total_files: usize,
+    pub embedded_files: usize,
+    /// Chunks evaluated across all files under the new chunking
+    pub chunks_embedded: usize,
+    /// Files without content, over the size limits, or whose embedding failed
+    pub skipped_files: usize,
+    /// Stopped by [`cancel_reembed`]; the remaining files keep their
+    /// previous embeddings
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelatedSearch {
+    pub query: String,
+    pub similarity_score: f32,
+    pub search_count: i64,
+}
+
+/// Download (if needed) and load the embedding model
+fn load_embedding_service(config: EmbeddingConfig) -> Result<EmbeddingService> {
+    ensure_model_downloaded().map_err(|e| CortexError::ModelNotReady {
+        reason: format!("download failed: {}", e),
+    })?;
+
+    EmbeddingService::new(config).map_err(|e| CortexError::ModelNotReady {
+        reason: format!("failed to load: {}", e),
+    })
+}
+
+/// The embedding config under the chunking [`reembed_all`] last applied
+///
+/// Every embedding is made under this config, so query and document
+/// vectors stay comparable after a re-embed.
+pub fn configured_embedding_config(conn: &Connection) -> Result<EmbeddingConfig> {
+    let config = EmbeddingConfig::default();
+    Ok(match get_config(conn)?.embedding_chunks {
+        Some(chunks) => chunks.apply(config),
+        None => config,
+    })
+}
+
+/// What [`reembed_all`] last embedded, which embedding commands and
+/// searches default to
+pub fn configured_embedding_source(conn: &Connection) -> Result<EmbeddingSource> {
+    Ok(get_config(conn)?.embedding_chunks.map(|chunks| chunks.source).unwrap_or_default())
+}
+
+/// Run `f` with the shared embedding service
+///
+/// The model is loaded on first use, under the chunking stored in `db`, and
+/// reused by later calls; it is only reloaded if that config has changed
+/// since.
+pub fn with_embedding_service<R>(
+    service: &LazyService<EmbeddingService>,
+    db: &Mutex<Database>,
+    f: impl FnOnce(&mut EmbeddingService) -> Result<R>,
+) -> Result<R> {
+    let config = configured_embedding_config(db.lock().unwrap().get_connection())?;
+    with_embedding_config(service, config, f)
+}
+
+/// [`with_embedding_service`] with the service loaded under `config`
+pub fn with_embedding_config<R>(
+    service: &LazyService<EmbeddingService>,
+    config: EmbeddingConfig,
+    f: impl FnOnce(&mut EmbeddingService) -> Result<R>,
+) -> Result<R> {
+    service.with_reload_if(
+        |loaded| loaded.config() != &config,
+        || load_embedding_service(config.clone()),
+        f,
+    )
+}
+
+/// Load the embedding model into `service` if it isn't loaded yet
+pub fn warmup_embedding_service(service: &LazyService<EmbeddingService>, db: &Mutex<Database>) -> Result<()> {
+    with_embedding_service(service, db, |_| Ok(()))
+}
+
+/// Load the embedding model ahead of the first AI command
+///
+/// Safe to call repeatedly; only the first call does any work.
+#[tauri::command]
+pub async fn warmup_embeddings(state: State<'_, Arc<AppState>>) -> Result<()> {
+    warmup_embedding_service(&state.embedding_service, &state.db)
+}
+
+/// Exact token count of `text` under the embedding model's tokenizer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenCount {
+    /// Includes the `[CLS]` and `[SEP]` tokens the model adds
+    pub token_count: usize,
+    /// The tokens themselves, when requested
+    pub tokens: Option<Vec<String>>,
+}
+
+/// Count `text`'s tokens with the embedding model's tokenizer, optionally
+/// returning the tokens
+///
+/// Uses the loaded model, loading it if needed; fails with `ModelNotReady`
+/// rather than downloading it.
+#[tauri::command]
+pub async fn count_tokens(
+    text: String,
+    include_tokens: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<TokenCount> {
+    if !is_model_downloaded().unwrap_or(false) {
+        return Err(CortexError::ModelNotReady {
+            reason: "the embedding model hasn't been downloaded".to_string(),
+        });
+    }
+
+    let tokens = with_embedding_service(&state.embedding_service, &state.db, |service| {
+        service.tokenize(&text).map_err(|e| CortexError::Internal {
+            message: e.to_string(),
+        })
+    })?;
+
+    Ok(TokenCount {
+        token_count: tokens.len(),
+        tokens: include_tokens.unwrap_or(false).then_some(tokens),
+    })
+}
+
+/// Models downloaded under `~/.cortex/models`, with their sizes
+#[tauri::command]
+pub async fn list_downloaded_models() -> Result<Vec<DownloadedModel>> {
+    crate::ai::list_downloaded_models().map_err(model_files_error)
+}
+
+/// Delete a downloaded model, returning the bytes freed; the active model
+/// can't be removed
+#[tauri::command]
+pub async fn remove_model(name: String) -> Result<u64> {
+    crate::ai::remove_model(&name).map_err(model_files_error)
+}
+
+/// Delete every model Cortex downloaded except the active one, e.g. after
+/// switching models; models placed there by hand are kept
+#[tauri::command]
+pub async fn gc_models() -> Result<Vec<DownloadedModel>> {
+    crate::ai::gc_models().map_err(model_files_error)
+}
+
+fn model_files_error(e: anyhow::Error) -> CortexError {
+    CortexError::Internal {
+        message: format!("{:#}", e),
+    }
 }
 
 /// Get embedding generation status
@@ -48,8 +301,14 @@ pub async fn get_embedding_status(state: State<'_, Arc<AppState>>) -> Result<Emb
         |row| row.get(0),
     )?;
 
+    let config = configured_embedding_config(conn)?;
     let files_with_embeddings = count_embeddings(conn)?;
-    let files_without_embeddings = total_files - files_with_embeddings;
+    let files_skipped = count_files_over_embedding_limits(
+        conn,
+        config.max_document_chars(),
+        config.max_document_lines,
+    )?;
+    let files_without_embeddings = total_files - files_with_embeddings - files_skipped;
 
     let model_downloaded = is_model_downloaded().unwrap_or(false);
 
@@ -57,126 +316,427 @@ pub async fn get_embedding_status(state: State<'_, Arc<AppState>>) -> Result<Emb
         total_files,
         files_with_embeddings,
         files_without_embeddings,
+        files_skipped,
         model_downloaded,
         model_version: MODEL_VERSION.to_string(),
     })
 }
 
 /// Generate embeddings for specific file IDs
+///
+/// `source` picks what is embedded (the configured source by default); the
+/// stored model version records it.
 #[tauri::command]
 pub async fn generate_embeddings(
     state: State<'_, Arc<AppState>>,
     file_ids: Vec<i64>,
+    source: Option<EmbeddingSource>,
 ) -> Result<usize> {
-    // Ensure model is downloaded
-    ensure_model_downloaded()
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to download model: {}", e),
-        })?;
+    let source = match source {
+        Some(source) => source,
+        None => configured_embedding_source(state.db.lock().unwrap().get_connection())?,
+    };
 
-    // Create embedding service
-    let config = EmbeddingConfig::default();
-    let mut service = EmbeddingService::new(config)
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to initialize embedding service: {}", e),
-        })?;
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let db = state.db.lock().unwrap();
+        let conn = db.get_connection();
 
-    let db = state.db.lock().unwrap();
-    let conn = db.get_connection();
+        let mut generated_count = 0;
 
-    let mut generated_count = 0;
+        for file_id in file_ids {
+            let Ok(file) = get_file_by_id(conn, file_id) else {
+                continue;
+            };
+
+            if let Some(text) = embedding_text(conn, &file, source)? {
+                if service.config().exceeds_document_limits(&text) {
+                    log::warn!("Skipping embedding for file {}: content exceeds size limits", file_id);
+                    continue;
+                }
 
-    for file_id in file_ids {
-        // Get file content
-        if let Some(content) = get_file_content(conn, file_id)? {
-            if let Some(ref text) = content.text_content {
                 // Generate embedding
                 let embedding = service
-                    .embed(text)
+                    .embed_document(&text)
                     .map_err(|e| CortexError::Internal {
                         message: format!("Failed to generate embedding for file {}: {}", file_id, e),
                     })?;
 
                 // Store in database
-                upsert_embedding(conn, file_id, &embedding, MODEL_VERSION)?;
+                upsert_embedding(conn, file_id, &embedding, &source.model_version(MODEL_VERSION))?;
                 generated_count += 1;
             }
         }
-    }
 
-    Ok(generated_count)
+        Ok(generated_count)
+    })
 }
 
 /// Generate embeddings for all files without embeddings (batch processing)
+///
+/// Files indexed under a profile with `embed` turned off are left out.
 #[tauri::command]
 pub async fn generate_all_embeddings(
     state: State<'_, Arc<AppState>>,
     batch_size: Option<usize>,
+    source: Option<EmbeddingSource>,
 ) -> Result<usize> {
     let batch_size = batch_size.unwrap_or(100);
+    let source = match source {
+        Some(source) => source,
+        None => configured_embedding_source(state.db.lock().unwrap().get_connection())?,
+    };
 
-    // Ensure model is downloaded
-    ensure_model_downloaded()
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to download model: {}", e),
-        })?;
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let db = state.db.lock().unwrap();
+        let conn = db.get_connection();
+        let profile_roots = ProfileRoots::load(conn)?;
 
-    // Create embedding service
-    let config = EmbeddingConfig::default();
-    let mut service = EmbeddingService::new(config)
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to initialize embedding service: {}", e),
-        })?;
+        let mut total_generated = 0;
+        // Files left without an embedding come back first in every batch
+        let mut skipped = 0;
 
-    let db = state.db.lock().unwrap();
-    let conn = db.get_connection();
+        loop {
+            // Get files without embeddings
+            let files = get_files_without_embeddings(conn, skipped + batch_size)?;
 
-    let mut total_generated = 0;
+            if files.len() <= skipped {
+                break;
+            }
 
-    loop {
-        // Get files without embeddings
-        let files = get_files_without_embeddings(conn, batch_size)?;
+            for file in files.into_iter().skip(skipped) {
+                if !profile_roots.embeds(Path::new(&file.path)) {
+                    skipped += 1;
+                    continue;
+                }
 
-        if files.is_empty() {
-            break;
+                let Some(text) = embedding_text(conn, &file, source)? else {
+                    skipped += 1;
+                    continue;
+                };
+                if service.config().exceeds_document_limits(&text) {
+                    log::warn!("Skipping embedding for file {}: content exceeds size limits", file.id);
+                    skipped += 1;
+                    continue;
+                }
+
+                // Generate embedding
+                match service.embed_document(&text) {
+                    Ok(embedding) => {
+                        // Store in database
+                        upsert_embedding(conn, file.id, &embedding, &source.model_version(MODEL_VERSION))?;
+                        total_generated += 1;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to generate embedding for file {}: {}", file.id, e);
+                        skipped += 1;
+                    }
+                }
+            }
         }
 
-        for file in files {
-            // Get file content
-            if let Some(content) = get_file_content(conn, file.id)? {
-                if let Some(ref text) = content.text_content {
-                    // Generate embedding
-                    match service.embed(text) {
-                        Ok(embedding) => {
-                            // Store in database
-                            upsert_embedding(conn, file.id, &embedding, MODEL_VERSION)?;
-                            total_generated += 1;
+        Ok(total_generated)
+    })
+}
+
+/// The text `source` embeds for `file`, or `None` if it has nothing to embed
+fn embedding_text(conn: &Connection, file: &File, source: EmbeddingSource) -> Result<Option<String>> {
+    Ok(get_file_content(conn, file.id)?.and_then(|content| {
+        source.document_text(&file.filename, content.text_content.as_deref(), content.summary.as_deref())
+    }))
+}
+
+/// Regenerate every embedding under new chunking parameters, which become
+/// the config all later embeddings use
+///
+/// Emits `embeddings:progress` as files are processed and stops early when
+/// [`cancel_reembed`] is called. Existing embeddings are left untouched if
+/// the model can't be loaded.
+#[tauri::command]
+pub async fn reembed_all(
+    state: State<'_, Arc<AppState>>,
+    app: AppHandle,
+    chunk_config: ChunkConfig,
+) -> Result<ReembedSummary> {
+    if let Some(message) = chunk_config.validation_error() {
+        return Err(CortexError::InvalidInput { message });
+    }
+
+    state.stop_embedding.store(false, Ordering::SeqCst);
+    let config = chunk_config.apply(EmbeddingConfig::default());
+
+    with_embedding_config(&state.embedding_service, config.clone(), |service| {
+        // Stored once the model loads, so a failed load changes nothing
+        {
+            let db = state.db.lock().unwrap();
+            let conn = db.get_connection();
+            let mut cortex_config = get_config(conn)?;
+            cortex_config.embedding_chunks = Some(chunk_config);
+            set_config(conn, &cortex_config)?;
+        }
+
+        reembed_files(
+            &state.db,
+            &config,
+            chunk_config.source,
+            |batch| service.embed_batch(batch),
+            &state.stop_embedding,
+            |processed_files, total_files| {
+                let _ = app.emit(
+                    "embeddings:progress",
+                    ReembedProgressEvent {
+                        total_files,
+                        processed_files,
+                    },
+                );
+            },
+        )
+    })
+}
+
+/// Stop a running [`reembed_all`] after the file in progress
+#[tauri::command]
+pub async fn cancel_reembed(state: State<'_, Arc<AppState>>) -> Result<()> {
+    state.stop_embedding.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Re-embed every live file's `source` text under `config` (see
+/// [`reembed_all`])
+///
+/// Files are embedded [`REEMBED_BATCH_FILES`] at a time with `db` unlocked,
+/// and each batch's embeddings replace the old ones in one transaction, so
+/// searches keep working throughout and a cancelled run leaves the
+/// remaining files with their previous embeddings. `on_progress` receives
+/// the processed and total file counts.
+pub fn reembed_files<F, P>(
+    db: &Mutex<Database>,
+    config: &EmbeddingConfig,
+    source: EmbeddingSource,
+    mut embed_batch: F,
+    cancel: &AtomicBool,
+    mut on_progress: P,
+) -> Result<ReembedSummary>
+where
+    F: FnMut(&[&str]) -> anyhow::Result<Vec<Vec<f32>>>,
+    P: FnMut(usize, usize),
+{
+    let files = list_files(db.lock().unwrap().get_connection(), i64::MAX as usize, 0)?;
+    let model_version = source.model_version(MODEL_VERSION);
+    let mut summary = ReembedSummary {
+        total_files: files.len(),
+        embedded_files: 0,
+        chunks_embedded: 0,
+        skipped_files: 0,
+        cancelled: false,
+    };
+
+    let mut processed = 0;
+    for batch in files.chunks(REEMBED_BATCH_FILES) {
+        let texts = {
+            let db = db.lock().unwrap();
+            batch
+                .iter()
+                .map(|file| embedding_text(db.get_connection(), file, source))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        // `None` drops the file's stale embedding
+        let mut embedded = Vec::with_capacity(batch.len());
+        for (file, text) in batch.iter().zip(texts) {
+            if cancel.load(Ordering::SeqCst) {
+                summary.cancelled = true;
+                break;
+            }
+
+            let embedding = match text {
+                Some(text) if !config.exceeds_document_limits(&text) => {
+                    match embed_document_chunks(config, &text, &mut embed_batch) {
+                        Ok((embedding, chunks)) => {
+                            summary.embedded_files += 1;
+                            summary.chunks_embedded += chunks;
+                            Some(embedding)
                         }
                         Err(e) => {
-                            log::warn!("Failed to generate embedding for file {}: {}", file.id, e);
+                            log::warn!("Failed to re-embed file {}: {}", file.id, e);
+                            None
                         }
                     }
                 }
+                _ => None,
+            };
+            if embedding.is_none() {
+                summary.skipped_files += 1;
             }
+            embedded.push((file.id, embedding));
         }
 
-        // If we got fewer files than batch size, we're done
-        if total_generated < batch_size {
+        {
+            let db = db.lock().unwrap();
+            let tx = db.get_connection().unchecked_transaction()?;
+            for (file_id, embedding) in &embedded {
+                match embedding {
+                    Some(embedding) => upsert_embedding(&tx, *file_id, embedding, &model_version)?,
+                    None => delete_embedding(&tx, *file_id)?,
+                }
+            }
+            tx.commit()?;
+        }
+
+        processed += embedded.len();
+        on_progress(processed, files.len());
+
+        if summary.cancelled {
+            log::info!("Re-embedding cancelled after {} of {} files", processed, files.len());
             break;
         }
     }
 
-    Ok(total_generated)
+    Ok(summary)
+}
+
+/// Measured embedding speed on this machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingBenchmark {
+    /// Files embedded for the measurement
+    pub files_embedded: usize,
+    /// Chunks those files were split into
+    pub chunks_embedded: usize,
+    /// Time spent embedding, excluding model loading and database reads
+    pub elapsed_ms: f64,
+    pub files_per_second: f64,
+    pub ms_per_file: f64,
+}
+
+impl EmbeddingBenchmark {
+    /// Throughput of `files_embedded` files embedded in `elapsed`
+    pub fn from_measurement(files_embedded: usize, chunks_embedded: usize, elapsed: Duration) -> Self {
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        let (files_per_second, ms_per_file) = if files_embedded == 0 || elapsed_ms == 0.0 {
+            (0.0, 0.0)
+        } else {
+            (files_embedded as f64 / elapsed.as_secs_f64(), elapsed_ms / files_embedded as f64)
+        };
+
+        Self {
+            files_embedded,
+            chunks_embedded,
+            elapsed_ms,
+            files_per_second,
+            ms_per_file,
+        }
+    }
+}
+
+/// Embed a sample of indexed files and report the throughput, without
+/// storing the embeddings
+///
+/// `sample_size` defaults to 20 files and is capped at 500. Fails with
+/// `ModelNotReady` rather than downloading the model, so the download isn't
+/// measured; loading it isn't either.
+#[tauri::command]
+pub async fn benchmark_embeddings(
+    sample_size: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<EmbeddingBenchmark> {
+    if !is_model_downloaded().unwrap_or(false) {
+        return Err(CortexError::ModelNotReady {
+            reason: "the embedding model hasn't been downloaded".to_string(),
+        });
+    }
+
+    let sample_size = sample_size.unwrap_or(DEFAULT_BENCHMARK_SAMPLE).clamp(1, MAX_BENCHMARK_SAMPLE);
+
+    // Read the sample up front so the database isn't locked while embedding
+    let samples = {
+        let db = state.db.lock().unwrap();
+        let conn = db.get_connection();
+        let config = configured_embedding_config(conn)?;
+        let source = configured_embedding_source(conn)?;
+        benchmark_samples(conn, &config, source, sample_size)?
+    };
+
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let config = service.config().clone();
+        benchmark_embedding_samples(&config, &samples, |batch| service.embed_batch(batch))
+    })
+}
+
+/// Up to `sample_size` indexed files' ids and texts for
+/// [`benchmark_embedding_samples`]
+///
+/// Files with nothing to embed or over the size limits are skipped.
+pub fn benchmark_samples(
+    conn: &Connection,
+    config: &EmbeddingConfig,
+    source: EmbeddingSource,
+    sample_size: usize,
+) -> Result<Vec<(i64, String)>> {
+    let mut samples = Vec::with_capacity(sample_size);
+    let mut offset = 0;
+
+    while samples.len() < sample_size {
+        let files = list_files(conn, sample_size, offset)?;
+        if files.is_empty() {
+            break;
+        }
+        offset += files.len();
+
+        for file in &files {
+            if samples.len() == sample_size {
+                break;
+            }
+            let Some(text) = embedding_text(conn, file, source)? else {
+                continue;
+            };
+            if config.exceeds_document_limits(&text) {
+                continue;
+            }
+            samples.push((file.id, text));
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Time embedding `samples` with `embed_batch` (see [`benchmark_embeddings`])
+pub fn benchmark_embedding_samples<F>(
+    config: &EmbeddingConfig,
+    samples: &[(i64, String)],
+    mut embed_batch: F,
+) -> Result<EmbeddingBenchmark>
+where
+    F: FnMut(&[&str]) -> anyhow::Result<Vec<Vec<f32>>>,
+{
+    let mut chunks_embedded = 0;
+    let mut elapsed = Duration::ZERO;
+
+    for (file_id, text) in samples {
+        let started = Instant::now();
+        let (_, chunks) = embed_document_chunks(config, text, &mut embed_batch).map_err(|e| {
+            CortexError::Internal {
+                message: format!("Failed to embed file {}: {}", file_id, e),
+            }
+        })?;
+        elapsed += started.elapsed();
+
+        chunks_embedded += chunks;
+    }
+
+    Ok(EmbeddingBenchmark::from_measurement(samples.len(), chunks_embedded, elapsed))
 }
 
 /// Semantic search using embeddings
+///
+/// Only files embedded from `source` (the configured source by default)
+/// are compared.
 #[tauri::command]
 pub async fn semantic_search(
     state: State<'_, Arc<AppState>>,
     query: String,
     limit: Option<usize>,
     threshold: Option<f32>,
-) -> Result<Vec<SemanticSearchResult>> {
+    source: Option<EmbeddingSource>,
+) -> Result<SemanticSearchResponse> {
     let limit = limit.unwrap_or(50);
     let threshold = threshold.unwrap_or(0.7);
 
@@ -186,58 +746,293 @@ pub async fn semantic_search(
         });
     }
 
-    // Ensure model is downloaded
-    ensure_model_downloaded()
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to download model: {}", e),
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let query_embedding = service.embed(&query).map_err(|e| CortexError::Internal {
+            message: format!("Failed to generate query embedding: {}", e),
         })?;
 
-    // Create embedding service
-    let config = EmbeddingConfig::default();
-    let mut service = EmbeddingService::new(config)
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to initialize embedding service: {}", e),
-        })?;
+        let (mut response, texts) = {
+            let db = state.db.lock().unwrap();
+            let conn = db.get_connection();
+            let source = match source {
+                Some(source) => source,
+                None => configured_embedding_source(conn)?,
+            };
 
-    // Generate query embedding
-    let query_embedding = service
-        .embed(&query)
-        .map_err(|e| CortexError::Internal {
-            message: format!("Failed to generate query embedding: {}", e),
-        })?;
+            let model_version = source.model_version(MODEL_VERSION);
+            let response = run_semantic_search(conn, &query, &model_version, limit, threshold, |_| {
+                Ok(query_embedding.clone())
+            })?;
+            let texts = snippet_texts(conn, &response.results);
+            (response, texts)
+        }; // db is dropped here
 
-    let db = state.db.lock().unwrap();
-    let conn = db.get_connection();
+        attach_semantic_snippets(&mut response.results, texts, &query_embedding, |sentences| {
+            service.embed_batch(sentences).map_err(|e| CortexError::Internal {
+                message: format!("Failed to embed snippet sentences: {}", e),
+            })
+        });
+
+        Ok(response)
+    })
+}
 
-    // Get all file embeddings
-    let file_embeddings = get_all_embeddings(conn)?;
+/// Stored text of the top [`SEMANTIC_SNIPPET_RESULTS`] results, read up
+/// front so snippets are embedded without holding the database lock
+///
+/// Files without text, or whose text can't be read, get `None`.
+pub fn snippet_texts(conn: &Connection, results: &[SemanticSearchResult]) -> Vec<Option<String>> {
+    results
+        .iter()
+        .take(SEMANTIC_SNIPPET_RESULTS)
+        .map(|result| match get_file_content(conn, result.file_id) {
+            Ok(content) => content.and_then(|c| c.text_content),
+            Err(e) => {
+                log::warn!("No semantic snippet for {}: {}", result.path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Fill in each result's snippet from its text in `texts` (see
+/// [`snippet_texts`])
+///
+/// A snippet that can't be computed is left empty rather than failing the
+/// search.
+pub fn attach_semantic_snippets<F>(
+    results: &mut [SemanticSearchResult],
+    texts: Vec<Option<String>>,
+    query_embedding: &[f32],
+    mut embed_batch: F,
+) where
+    F: FnMut(&[&str]) -> Result<Vec<Vec<f32>>>,
+{
+    for (result, text) in results.iter_mut().zip(texts) {
+        let Some(text) = text else {
+            continue;
+        };
+        match best_matching_sentence(&text, query_embedding, MAX_SNIPPET_SENTENCES, &mut embed_batch) {
+            Ok(snippet) => result.snippet = snippet,
+            Err(e) => log::warn!("No semantic snippet for {}: {}", result.path, e),
+        }
+    }
+}
+
+/// Rank files embedded under `model_version` by similarity to `query`,
+/// embedded via `embed`
+///
+/// Logs at debug level when fewer than `limit` files clear `threshold`; the
+/// response carries the same counts for the UI.
+pub fn run_semantic_search<F>(
+    conn: &Connection,
+    query: &str,
+    model_version: &str,
+    limit: usize,
+    threshold: f32,
+    embed: F,
+) -> Result<SemanticSearchResponse>
+where
+    F: FnOnce(&str) -> Result<Vec<f32>>,
+{
+    // Generate query embedding
+    let query_embedding = embed(query)?;
+
+    // Only files that can be results compete for the top `limit`, and only
+    // vectors of the same source are comparable
+    let file_embeddings = get_live_embeddings(conn, model_version)?;
 
     // Calculate similarities
-    use crate::ai::similarity::find_top_k;
-    let scored_files = find_top_k(&query_embedding, &file_embeddings, limit, threshold);
+    use crate::ai::similarity::find_top_k_with_stats;
+    let (scored_files, stats) =
+        find_top_k_with_stats(&query_embedding, &file_embeddings, limit, threshold);
+
+    if stats.passed_threshold < limit {
+        log::debug!(
+            "Semantic search: only {} of {} scored files cleared threshold {} (limit {}, best score {:?})",
+            stats.passed_threshold,
+            stats.candidates_scored,
+            threshold,
+            limit,
+            stats.max_score
+        );
+    }
+
+    let mut results = resolve_scored_files(conn, scored_files)?;
+
+    // Sort by similarity descending
+    results.sort_by(|a, b| {
+        b.similarity_score
+            .partial_cmp(&a.similarity_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
 
-    // Get file details
+    Ok(SemanticSearchResponse {
+        results,
+        candidates_scored: stats.candidates_scored,
+        passed_threshold: stats.passed_threshold,
+        max_score: stats.max_score,
+        orphaned_embeddings: orphaned_embeddings(conn)?,
+        limit,
+        threshold,
+    })
+}
+
+/// Results for `scored_files`, which come from [`get_live_embeddings`]
+fn resolve_scored_files(conn: &Connection, scored_files: Vec<(i64, f32)>) -> Result<Vec<SemanticSearchResult>> {
     let file_ids: Vec<i64> = scored_files.iter().map(|(id, _)| *id).collect();
     let files = get_files_by_ids(conn, &file_ids)?;
 
-    // Create result with scores
-    let mut results: Vec<SemanticSearchResult> = scored_files
-        .into_iter()
-        .filter_map(|(file_id, score)| {
-            files.iter().find(|f| f.id == file_id).map(|file| SemanticSearchResult {
-                file_id: file.id,
-                path: file.path.clone(),
-                filename: file.filename.clone(),
-                file_type: file.file_type.clone(),
-                similarity_score: score,
-            })
+    let mut results = Vec::with_capacity(scored_files.len());
+    for (file_id, score) in scored_files {
+        let Some(file) = files.iter().find(|f| f.id == file_id) else {
+            continue;
+        };
+        results.push(SemanticSearchResult {
+            file_id: file.id,
+            path: file.path.clone(),
+            filename: file.filename.clone(),
+            file_type: file.file_type.clone(),
+            similarity_score: score,
+            snippet: None,
+            duplicate_paths: duplicate_paths(conn, file.id)?,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Embeddings left behind by removed files, logged for the maintenance
+/// commands to clean up; searches only report them
+fn orphaned_embeddings(conn: &Connection) -> Result<Vec<i64>> {
+    let orphaned = list_orphaned_embeddings(conn)?;
+    if !orphaned.is_empty() {
+        log::warn!(
+            "{} embeddings have no file; run cleanup_orphans or repair_index to remove them",
+            orphaned.len()
+        );
+    }
+    Ok(orphaned)
+}
+
+/// Nearest-neighbor statistics of the stored embeddings, for tuning
+/// similarity thresholds
+///
+/// Up to `sample_size` files (default 500) are compared against all others.
+#[tauri::command]
+pub async fn embedding_diagnostics(
+    state: State<'_, Arc<AppState>>,
+    sample_size: Option<usize>,
+) -> Result<EmbeddingDiagnostics> {
+    let db = state.db.lock().unwrap();
+    let embeddings = get_all_embeddings(db.get_connection())?;
+
+    Ok(diagnose_embeddings(&embeddings, sample_size.unwrap_or(500).min(5000)))
+}
+
+/// Write the pairwise cosine similarities of `file_ids` to `output_path` as
+/// CSV, for analysis outside the app
+///
+/// Rows and columns are labelled with file ids. At most
+/// [`MAX_SIMILARITY_MATRIX_FILES`] files are accepted.
+#[tauri::command]
+pub async fn export_similarity_matrix(
+    state: State<'_, Arc<AppState>>,
+    file_ids: Vec<i64>,
+    output_path: String,
+) -> Result<SimilarityMatrixExport> {
+    let output_path = PathValidator::validate_export_path(&output_path)?;
+
+    let db = state.db.lock().unwrap();
+    write_similarity_matrix(db.get_connection(), &file_ids, &output_path)
+}
+
+/// Compute and write the matrix for [`export_similarity_matrix`]
+pub fn write_similarity_matrix(
+    conn: &Connection,
+    file_ids: &[i64],
+    output_path: &Path,
+) -> Result<SimilarityMatrixExport> {
+    let mut unique_ids = file_ids.to_vec();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+
+    if unique_ids.len() > MAX_SIMILARITY_MATRIX_FILES {
+        return Err(CortexError::InvalidInput {
+            message: format!(
+                "Similarity matrix is limited to {} files, got {}",
+                MAX_SIMILARITY_MATRIX_FILES,
+                unique_ids.len()
+            ),
+        });
+    }
+
+    let mut ids = Vec::with_capacity(unique_ids.len());
+    let mut vectors = Vec::with_capacity(unique_ids.len());
+    let mut missing_embeddings = Vec::new();
+    for file_id in unique_ids {
+        match get_embedding(conn, file_id)? {
+            Some(embedding) => {
+                ids.push(file_id);
+                vectors.push(embedding.embedding);
+            }
+            None => missing_embeddings.push(file_id),
+        }
+    }
+
+    let slices: Vec<&[f32]> = vectors.iter().map(|v| v.as_slice()).collect();
+    let matrix = similarity_matrix(&slices);
+
+    let io_error = |e: std::io::Error| CortexError::Internal {
+        message: format!("Failed to write {}: {}", output_path.display(), e),
+    };
+    let mut out = std::io::BufWriter::new(std::fs::File::create(output_path).map_err(io_error)?);
+    write_similarity_csv(&mut out, &ids, &matrix).map_err(io_error)?;
+    std::io::Write::flush(&mut out).map_err(io_error)?;
+
+    Ok(SimilarityMatrixExport {
+        output_path: output_path.display().to_string(),
+        file_ids: ids,
+        missing_embeddings,
+    })
+}
+
+/// How similar two files are, by the cosine similarity of their embeddings
+#[tauri::command]
+pub async fn compare_files(
+    state: State<'_, Arc<AppState>>,
+    file_id_a: i64,
+    file_id_b: i64,
+) -> Result<FileComparison> {
+    let db = state.db.lock().unwrap();
+    compare_file_embeddings(db.get_connection(), file_id_a, file_id_b)
+}
+
+/// Compare two files' stored embeddings (see [`compare_files`])
+///
+/// Fails with `FileNotFound` for an unknown id, and with an error naming
+/// the file if either has no embedding yet.
+pub fn compare_file_embeddings(conn: &Connection, file_id_a: i64, file_id_b: i64) -> Result<FileComparison> {
+    let load = |file_id: i64| {
+        get_file_by_id(conn, file_id).map_err(|_| CortexError::FileNotFound {
+            path: format!("file id {}", file_id),
+        })?;
+
+        get_embedding(conn, file_id)?.ok_or_else(|| CortexError::InvalidInput {
+            message: format!("No embedding found for file {}; generate embeddings first", file_id),
         })
-        .collect();
+    };
 
-    // Sort by similarity descending
-    results.sort_by(|a, b| b.similarity_score.partial_cmp(&a.similarity_score).unwrap());
+    let a = load(file_id_a)?;
+    let b = load(file_id_b)?;
 
-    Ok(results)
+    Ok(FileComparison {
+        file_id_a,
+        file_id_b,
+        similarity: cosine_similarity(&a.embedding, &b.embedding),
+        model_version_a: a.model_version,
+        model_version_b: b.model_version,
+    })
 }
 
 /// Find similar files to a given file
@@ -247,7 +1042,7 @@ pub async fn find_similar_files(
     file_id: i64,
     limit: Option<usize>,
     threshold: Option<f32>,
-) -> Result<Vec<SemanticSearchResult>> {
+) -> Result<SemanticSearchResponse> {
     let limit = limit.unwrap_or(10);
     let threshold = threshold.unwrap_or(0.7);
 
@@ -260,8 +1055,8 @@ pub async fn find_similar_files(
             message: format!("No embedding found for file {}", file_id),
         })?;
 
-    // Get all other file embeddings
-    let all_embeddings = get_all_embeddings(conn)?;
+    // Get all other live file embeddings of the same source
+    let all_embeddings = get_live_embeddings(conn, &reference_embedding.model_version)?;
 
     // Filter out the reference file itself
     let other_embeddings: Vec<(i64, Vec<f32>)> = all_embeddings
@@ -270,26 +1065,759 @@ pub async fn find_similar_files(
         .collect();
 
     // Find top K similar files
-    use crate::ai::similarity::find_top_k;
-    let scored_files = find_top_k(&reference_embedding.embedding, &other_embeddings, limit, threshold);
+    use crate::ai::similarity::find_top_k_with_stats;
+    let (scored_files, stats) =
+        find_top_k_with_stats(&reference_embedding.embedding, &other_embeddings, limit, threshold);
 
-    // Get file details
-    let file_ids: Vec<i64> = scored_files.iter().map(|(id, _)| *id).collect();
-    let files = get_files_by_ids(conn, &file_ids)?;
+    let results = resolve_scored_files(conn, scored_files)?;
 
-    // Create results
-    let results: Vec<SemanticSearchResult> = scored_files
-        .into_iter()
-        .filter_map(|(file_id, score)| {
-            files.iter().find(|f| f.id == file_id).map(|file| SemanticSearchResult {
-                file_id: file.id,
-                path: file.path.clone(),
-                filename: file.filename.clone(),
-                file_type: file.file_type.clone(),
-                similarity_score: score,
+    Ok(SemanticSearchResponse {
+        results,
+        candidates_scored: stats.candidates_scored,
+        passed_threshold: stats.passed_threshold,
+        max_score: stats.max_score,
+        orphaned_embeddings: orphaned_embeddings(conn)?,
+        limit,
+        threshold,
+    })
+}
+
+/// Suggest past searches semantically related to `query`
+#[tauri::command]
+pub async fn suggest_related_searches(
+    state: State<'_, Arc<AppState>>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<RelatedSearch>> {
+    let limit = limit.unwrap_or(5);
+
+    if query.trim().is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query,
+            reason: "Query cannot be empty".to_string(),
+        });
+    }
+
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let db = state.db.lock().unwrap();
+        let conn = db.get_connection();
+
+        find_related_searches(conn, &query, limit, MODEL_VERSION, |text| {
+            service.embed(text).map_err(|e| CortexError::Internal {
+                message: format!("Failed to generate query embedding: {}", e),
+            })
+        })
+    })
+}
+
+/// Most distinctive terms of a file, scored by TF-IDF against the corpus
+///
+/// Computed on demand; does not depend on any stored tags.
+#[tauri::command]
+pub async fn get_file_keywords(
+    state: State<'_, Arc<AppState>>,
+    file_id: i64,
+    limit: Option<usize>,
+) -> Result<Vec<Keyword>> {
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    get_file_by_id(conn, file_id).map_err(|_| CortexError::FileNotFound {
+        path: format!("file id {}", file_id),
+    })?;
+
+    extract_keywords(conn, file_id, limit.unwrap_or(10))
+}
+
+/// Re-embed a single file's current content, e.g. after it was reindexed
+///
+/// Returns false when the file has no text to embed.
+#[tauri::command]
+pub async fn regenerate_embedding(state: State<'_, Arc<AppState>>, file_id: i64) -> Result<bool> {
+    // Fail on a bad id before paying for model start-up
+    {
+        let db = state.db.lock().unwrap();
+        get_file_by_id(db.get_connection(), file_id).map_err(|_| CortexError::FileNotFound {
+            path: format!("file id {}", file_id),
+        })?;
+    }
+
+    with_embedding_service(&state.embedding_service, &state.db, |service| {
+        let db = state.db.lock().unwrap();
+        let conn = db.get_connection();
+
+        let config = service.config().clone();
+        let source = configured_embedding_source(conn)?;
+        regenerate_file_embedding(conn, file_id, MODEL_VERSION, &config, source, |text| {
+            service.embed_document(text).map_err(|e| CortexError::Internal {
+                message: format!("Failed to generate embedding for file {}: {}", file_id, e),
             })
         })
+    })
+}
+
+/// Replace a file's embedding with one computed from its current content
+///
+/// A file whose content is now empty, or too large to embed under `config`,
+/// has any stale embedding removed and returns false.
+pub fn regenerate_file_embedding<F>(
+    conn: &Connection,
+    file_id: i64,
+    model_version: &str,
+    config: &EmbeddingConfig,
+    source: EmbeddingSource,
+    embed: F,
+) -> Result<bool>
+where
+    F: FnOnce(&str) -> Result<Vec<f32>>,
+{
+    let file = get_file_by_id(conn, file_id)?;
+    let text = embedding_text(conn, &file, source)?
+        .filter(|text| !text.trim().is_empty())
+        .filter(|text| {
+            let oversized = config.exceeds_document_limits(text);
+            if oversized {
+                log::warn!("Skipping embedding for file {}: content exceeds size limits", file_id);
+            }
+            !oversized
+        });
+
+    let text = match text {
+        Some(text) => text,
+        None => {
+            delete_embedding(conn, file_id)?;
+            return Ok(false);
+        }
+    };
+
+    let embedding = embed(&text)?;
+    upsert_embedding(conn, file_id, &embedding, &source.model_version(model_version))?;
+
+    Ok(true)
+}
+
+/// Rank search history by embedding similarity to `query`
+///
+/// History entries without a cached embedding for `model_version` are
+/// embedded once via `embed` and cached. The query itself is never suggested.
+pub fn find_related_searches<F>(
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+    model_version: &str,
+    mut embed: F,
+) -> Result<Vec<RelatedSearch>>
+where
+    F: FnMut(&str) -> Result<Vec<f32>>,
+{
+    let query = query.trim();
+    let query_embedding = embed(query)?;
+
+    let mut candidates = Vec::new();
+    for (entry, cached) in get_history_with_embeddings(conn, model_version)? {
+        if entry.query.eq_ignore_ascii_case(query) {
+            continue;
+        }
+
+        let embedding = match cached {
+            Some(embedding) => embedding,
+            None => {
+                let embedding = embed(&entry.query)?;
+                set_history_embedding(conn, entry.id, &embedding, model_version)?;
+                embedding
+            }
+        };
+
+        candidates.push(((entry.query, entry.search_count), embedding));
+    }
+
+    use crate::ai::similarity::find_top_k;
+    let related = find_top_k(&query_embedding, &candidates, limit, RELATED_SEARCH_THRESHOLD)
+        .into_iter()
+        .map(|((query, search_count), score)| RelatedSearch {
+            query,
+            similarity_score: score,
+            search_count,
+        })
         .collect();
 
-    Ok(results)
+    Ok(related)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::search_history::record_search;
+    use crate::db::{create_tables, EMBEDDING_DIM};
+
+    /// Toy embedder: vectors by topic, so "automobile" is close to "car
+    /// repair" even though "cartography" shares more letters with it
+    fn topic_embedding(text: &str) -> Result<Vec<f32>> {
+        let mut vector = vec![0.0f32; EMBEDDING_DIM];
+        let has_word = |words: &[&str]| text.split_whitespace().any(|w| words.contains(&w));
+        let topic = if has_word(&["car", "automobile", "engine"]) {
+            0
+        } else if has_word(&["map", "cartography"]) {
+            1
+        } else {
+            2
+        };
+        vector[topic] = 1.0;
+        vector[3] = 0.1;
+        Ok(vector)
+    }
+
+    #[test]
+    fn test_related_searches_prefer_semantic_match() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        record_search(&conn, "cartography basics").unwrap();
+        record_search(&conn, "car repair manual").unwrap();
+
+        let related = find_related_searches(&conn, "automobile", 5, "toy", topic_embedding).unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].query, "car repair manual");
+    }
+
+    #[test]
+    fn test_related_searches_cache_history_embeddings() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        record_search(&conn, "engine tuning").unwrap();
+        record_search(&conn, "automobile").unwrap();
+
+        let mut calls = Vec::new();
+        let mut counting = |text: &str| {
+            calls.push(text.to_string());
+            topic_embedding(text)
+        };
+
+        find_related_searches(&conn, "automobile", 5, "toy", &mut counting).unwrap();
+        find_related_searches(&conn, "automobile", 5, "toy", &mut counting).unwrap();
+
+        // History embedded once; the current query every call; the query
+        // itself is skipped from history
+        assert_eq!(calls, vec!["automobile", "engine tuning", "automobile"]);
+    }
+
+    #[test]
+    fn test_regenerate_embedding_tracks_content_changes() {
+        use crate::db::{insert_file, upsert_file_content};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let file_id = insert_file(&conn, "/test/notes.txt", "notes.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, file_id, Some("map of the coast"), None).unwrap();
+        let config = EmbeddingConfig::default();
+
+        assert!(regenerate_file_embedding(&conn, file_id, "toy", &config, EmbeddingSource::FullText, topic_embedding).unwrap());
+        let before = get_embedding(&conn, file_id).unwrap().unwrap().embedding;
+
+        upsert_file_content(&conn, file_id, Some("engine rebuild log"), None).unwrap();
+        assert!(regenerate_file_embedding(&conn, file_id, "toy", &config, EmbeddingSource::FullText, topic_embedding).unwrap());
+        let after = get_embedding(&conn, file_id).unwrap().unwrap().embedding;
+
+        assert_ne!(before, after);
+        assert_eq!(after, topic_embedding("engine rebuild log").unwrap());
+
+        // Empty content is skipped and the stale vector dropped
+        upsert_file_content(&conn, file_id, Some("   "), None).unwrap();
+        assert!(!regenerate_file_embedding(&conn, file_id, "toy", &config, EmbeddingSource::FullText, |_| unreachable!()).unwrap());
+        assert!(get_embedding(&conn, file_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_repeated_searches_load_embedder_once() {
+        use crate::db::{insert_file, upsert_embedding};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        for (name, text) in [("cars.txt", "car engine"), ("maps.txt", "map cartography")] {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &topic_embedding(text).unwrap(), "toy").unwrap();
+        }
+
+        // Stands in for the ONNX-backed service held on AppState
+        struct ToyEmbedder;
+        let service: LazyService<ToyEmbedder> = LazyService::new();
+        let mut loads = 0;
+
+        for query in ["automobile", "engine", "map"] {
+            let results = service
+                .with(
+                    || {
+                        loads += 1;
+                        Ok(ToyEmbedder)
+                    },
+                    |_embedder| run_semantic_search(&conn, query, "toy", 10, 0.5, topic_embedding),
+                )
+                .unwrap()
+                .results;
+            assert_eq!(results.len(), 1);
+        }
+
+        assert_eq!(loads, 1);
+    }
+
+    #[test]
+    fn test_zero_embeddings_do_not_poison_search() {
+        use crate::db::{insert_file, upsert_embedding};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        for (name, embedding) in [
+            ("cars.txt", topic_embedding("car").unwrap()),
+            ("blank.txt", vec![0.0f32; EMBEDDING_DIM]),
+        ] {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &embedding, "toy").unwrap();
+        }
+
+        let results = run_semantic_search(&conn, "engine", "toy", 10, 0.0, topic_embedding).unwrap().results;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].filename, "cars.txt");
+        assert!(results.iter().all(|r| r.similarity_score.is_finite()));
+
+        // An empty query embeds to a zero vector and matches nothing
+        let empty = run_semantic_search(&conn, "", "toy", 10, 0.0, |_| Ok(vec![0.0f32; EMBEDDING_DIM])).unwrap();
+        assert!(empty.results.is_empty());
+        assert_eq!(empty.candidates_scored, 0);
+        assert_eq!(empty.max_score, None);
+    }
+
+    #[test]
+    fn test_semantic_search_reports_scored_and_passed_counts() {
+        use crate::db::{insert_file, upsert_embedding};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        for (name, embedding) in [
+            ("cars.txt", topic_embedding("car").unwrap()),
+            ("maps.txt", topic_embedding("map").unwrap()),
+            ("misc.txt", topic_embedding("other").unwrap()),
+            ("blank.txt", vec![0.0f32; EMBEDDING_DIM]),
+        ] {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &embedding, "toy").unwrap();
+        }
+
+        // The zero vector can't be scored, so only three files count
+        let response = run_semantic_search(&conn, "engine", "toy", 10, 0.5, topic_embedding).unwrap();
+        assert_eq!(response.results.len(), 1);
+        assert_eq!(response.candidates_scored, 3);
+        assert_eq!(response.passed_threshold, 1);
+        assert!((response.max_score.unwrap() - 1.0).abs() < 1e-6);
+
+        // Nothing clears an unreachable threshold, but the best score shows
+        // how far off it was
+        let response = run_semantic_search(&conn, "engine", "toy", 10, 1.5, topic_embedding).unwrap();
+        assert!(response.results.is_empty());
+        assert_eq!(response.candidates_scored, 3);
+        assert_eq!(response.passed_threshold, 0);
+        assert!((response.max_score.unwrap() - 1.0).abs() < 1e-6);
+
+        // Files past the limit still count as passing
+        let response = run_semantic_search(&conn, "engine", "toy", 1, 0.0, topic_embedding).unwrap();
+        assert_eq!(response.results.len(), 1);
+        assert_eq!(response.passed_threshold, 3);
+        assert_eq!(response.limit, 1);
+    }
+
+    #[test]
+    fn test_semantic_search_skips_deleted_files_without_deleting() {
+        use crate::db::{cleanup_orphans, insert_file, mark_file_deleted, upsert_embedding};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let mut ids = Vec::new();
+        for (name, topic) in [("live.txt", "bicycle"), ("trashed.txt", "car"), ("removed.txt", "car")] {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &topic_embedding(topic).unwrap(), "toy").unwrap();
+            ids.push(id);
+        }
+        let (live, trashed, removed) = (ids[0], ids[1], ids[2]);
+
+        // Soft-deleted since it was embedded, and removed outright by a
+        // connection without foreign-key enforcement
+        mark_file_deleted(&conn, trashed).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute("DELETE FROM files WHERE id = ?1", [removed]).unwrap();
+
+        // The closer dead files don't take the only slot
+        let response = run_semantic_search(&conn, "engine", "toy", 1, 0.0, topic_embedding).unwrap();
+        let result_ids: Vec<i64> = response.results.iter().map(|r| r.file_id).collect();
+        assert_eq!(result_ids, vec![live]);
+        assert_eq!(response.candidates_scored, 1);
+        assert_eq!(response.orphaned_embeddings, vec![removed]);
+
+        // Searching changes nothing; cleanup removes only the dangling one
+        assert!(get_embedding(&conn, trashed).unwrap().is_some());
+        assert!(get_embedding(&conn, removed).unwrap().is_some());
+        assert_eq!(cleanup_orphans(&conn).unwrap().orphaned_embeddings, 1);
+        assert!(get_embedding(&conn, trashed).unwrap().is_some());
+        let response = run_semantic_search(&conn, "engine", "toy", 1, 0.0, topic_embedding).unwrap();
+        assert!(response.orphaned_embeddings.is_empty());
+    }
+
+    #[test]
+    fn test_semantic_search_compares_only_embeddings_of_one_source() {
+        use crate::db::{insert_file, upsert_embedding};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let add = |name: &str, source: EmbeddingSource| {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &topic_embedding("car").unwrap(), &source.model_version(MODEL_VERSION))
+                .unwrap();
+            id
+        };
+        let full_text = add("engine.txt", EmbeddingSource::FullText);
+        let summary = add("garage.txt", EmbeddingSource::Summary);
+
+        for (source, expected) in [(EmbeddingSource::FullText, full_text), (EmbeddingSource::Summary, summary)] {
+            let model_version = source.model_version(MODEL_VERSION);
+            let response = run_semantic_search(&conn, "engine", &model_version, 10, 0.0, topic_embedding).unwrap();
+            let result_ids: Vec<i64> = response.results.iter().map(|r| r.file_id).collect();
+            assert_eq!(result_ids, vec![expected]);
+            assert_eq!(response.candidates_scored, 1);
+        }
+    }
+
+    #[test]
+    fn test_oversized_file_skipped_for_embedding_but_searchable() {
+        use crate::db::{insert_file, search_files_fts, upsert_file_content};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let config = EmbeddingConfig {
+            max_document_tokens: 1_000,
+            ..EmbeddingConfig::default()
+        };
+
+        // ~10k tokens of generated data
+        let generated = "zygomorphic fixture 0123456789 ".repeat(1_300);
+        let big = insert_file(&conn, "/test/generated.csv", "generated.csv", "csv", generated.len() as i64,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, big, Some(&generated), None).unwrap();
+
+        let small = insert_file(&conn, "/test/notes.txt", "notes.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, small, Some("engine notes"), None).unwrap();
+
+        assert!(!regenerate_file_embedding(&conn, big, "toy", &config, EmbeddingSource::FullText, |_| unreachable!()).unwrap());
+        assert!(regenerate_file_embedding(&conn, small, "toy", &config, EmbeddingSource::FullText, topic_embedding).unwrap());
+        assert!(get_embedding(&conn, big).unwrap().is_none());
+
+        let skipped = count_files_over_embedding_limits(
+            &conn,
+            config.max_document_chars(),
+            config.max_document_lines,
+        )
+        .unwrap();
+        assert_eq!(skipped, 1);
+
+        let hits = search_files_fts(&conn, "zygomorphic", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].file_id, big);
+    }
+
+    #[test]
+    fn test_semantic_results_get_best_sentence_snippet() {
+        use crate::db::{insert_file, upsert_embedding, upsert_file_content};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let text = "Notes from the trip. We got lost without a map. \
+                    Later the car engine overheated. Dinner was late.";
+        let id = insert_file(&conn, "/test/trip.txt", "trip.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, id, Some(text), None).unwrap();
+        upsert_embedding(&conn, id, &topic_embedding("car").unwrap(), "toy").unwrap();
+
+        let query_embedding = topic_embedding("automobile").unwrap();
+        let mut results = run_semantic_search(&conn, "automobile", "toy", 10, 0.5, |_| Ok(query_embedding.clone()))
+            .unwrap()
+            .results;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].snippet.is_none());
+
+        let texts = snippet_texts(&conn, &results);
+        attach_semantic_snippets(&mut results, texts, &query_embedding, |sentences| {
+            sentences.iter().map(|s| topic_embedding(&s.to_lowercase())).collect()
+        });
+        assert_eq!(results[0].snippet.as_deref(), Some("Later the car engine overheated."));
+    }
+
+    #[test]
+    fn test_similarity_matrix_symmetric_with_unit_diagonal() {
+        use crate::db::insert_file;
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let mut file_ids = Vec::new();
+        for (i, text) in ["car engine", "automobile", "map", "soup"].iter().enumerate() {
+            let id = insert_file(&conn, &format!("/test/{}.txt", i), &format!("{}.txt", i), "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_embedding(&conn, id, &topic_embedding(text).unwrap(), MODEL_VERSION).unwrap();
+            file_ids.push(id);
+        }
+        let unembedded = insert_file(&conn, "/test/none.txt", "none.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+
+        let output = temp_dir.path().join("matrix.csv");
+        let mut requested = file_ids.clone();
+        requested.push(unembedded);
+        let export = write_similarity_matrix(&conn, &requested, &output).unwrap();
+        assert_eq!(export.file_ids, file_ids);
+        assert_eq!(export.missing_embeddings, vec![unembedded]);
+
+        let csv = std::fs::read_to_string(&output).unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<i64> = lines.next().unwrap().split(',').skip(1).map(|v| v.parse().unwrap()).collect();
+        assert_eq!(header, file_ids);
+
+        let matrix: Vec<Vec<f32>> = lines
+            .map(|line| line.split(',').skip(1).map(|v| v.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(matrix.len(), file_ids.len());
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row[i], 1.0);
+            for (j, similarity) in row.iter().enumerate() {
+                assert_eq!(*similarity, matrix[j][i]);
+            }
+        }
+        // Same topic is closer than different topics
+        assert!(matrix[0][1] > matrix[0][2]);
+
+        let too_many: Vec<i64> = (1..=MAX_SIMILARITY_MATRIX_FILES as i64 + 1).collect();
+        assert!(matches!(
+            write_similarity_matrix(&conn, &too_many, &output),
+            Err(CortexError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_benchmark_throughput_from_duration_and_sample() {
+        use crate::db::{insert_file, upsert_file_content};
+
+        let measured = EmbeddingBenchmark::from_measurement(8, 12, Duration::from_millis(2000));
+        assert_eq!(measured.elapsed_ms, 2000.0);
+        assert_eq!(measured.files_per_second, 4.0);
+        assert_eq!(measured.ms_per_file, 250.0);
+        assert_eq!(EmbeddingBenchmark::from_measurement(0, 0, Duration::ZERO).files_per_second, 0.0);
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        for i in 0..5 {
+            let id = insert_file(&conn, &format!("/test/{}.txt", i), &format!("{}.txt", i), "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(&conn, id, Some("car engine repair notes"), None).unwrap();
+        }
+
+        // Each file takes at least 5ms to embed
+        let slow_batch = |batch: &[&str]| -> anyhow::Result<Vec<Vec<f32>>> {
+            std::thread::sleep(Duration::from_millis(5));
+            Ok(batch.iter().map(|text| topic_embedding(text).unwrap()).collect())
+        };
+        let config = EmbeddingConfig::default();
+        let samples = benchmark_samples(&conn, &config, EmbeddingSource::FullText, 3).unwrap();
+        assert_eq!(samples.len(), 3);
+        let benchmark = benchmark_embedding_samples(&config, &samples, slow_batch).unwrap();
+
+        assert_eq!(benchmark.files_embedded, 3);
+        assert!(benchmark.ms_per_file >= 5.0);
+        assert!((benchmark.files_per_second * benchmark.elapsed_ms / 1000.0 - 3.0).abs() < 1e-9);
+        assert_eq!(count_embeddings(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reembed_with_smaller_chunks_embeds_more_chunks() {
+        use crate::ai::EmbeddingStrategy;
+        use crate::db::{insert_file, upsert_file_content};
+
+        let db = Mutex::new(Database::open_in_memory().unwrap());
+        let embeddings = || count_embeddings(db.lock().unwrap().get_connection()).unwrap();
+
+        {
+            let db = db.lock().unwrap();
+            let conn = db.get_connection();
+            for (i, text) in ["car engine repair notes ".repeat(100), "map".to_string()].iter().enumerate() {
+                let id = insert_file(conn, &format!("/test/{}.txt", i), &format!("{}.txt", i), "txt", 10,
+                    "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+                upsert_file_content(conn, id, Some(text), None).unwrap();
+            }
+            let empty = insert_file(conn, "/test/empty.txt", "empty.txt", "txt", 0,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, empty, None, None).unwrap();
+        }
+
+        let toy_batch = |batch: &[&str]| -> anyhow::Result<Vec<Vec<f32>>> {
+            Ok(batch.iter().map(|text| topic_embedding(text).unwrap()).collect())
+        };
+        let chunks = |chunk_tokens: usize| ChunkConfig {
+            chunk_tokens,
+            strategy: EmbeddingStrategy::MeanOfChunks,
+            max_embed_chars: None,
+            source: EmbeddingSource::FullText,
+        };
+        let reembed = |chunk_tokens: usize| {
+            let config = chunks(chunk_tokens).apply(EmbeddingConfig::default());
+            let mut progress = Vec::new();
+            let summary = reembed_files(&db, &config, EmbeddingSource::FullText, toy_batch, &AtomicBool::new(false), |done, total| {
+                progress.push((done, total))
+            })
+            .unwrap();
+            (summary, progress)
+        };
+
+        let (large, progress) = reembed(128);
+        assert_eq!(large.total_files, 3);
+        assert_eq!(large.embedded_files, 2);
+        assert_eq!(large.skipped_files, 1);
+        assert_eq!(progress.last(), Some(&(3, 3)));
+        assert_eq!(embeddings(), 2);
+
+        let (small, _) = reembed(16);
+        assert_eq!(small.embedded_files, 2);
+        assert!(small.chunks_embedded > large.chunks_embedded);
+        assert_eq!(embeddings(), 2);
+
+        // Cancelled before the first file: the previous embeddings survive
+        let config = EmbeddingConfig::default();
+        let summary = reembed_files(&db, &config, EmbeddingSource::FullText, toy_batch, &AtomicBool::new(true), |_, _| {}).unwrap();
+        assert!(summary.cancelled);
+        assert_eq!(summary.embedded_files, 0);
+        assert_eq!(embeddings(), 2);
+
+        // The database isn't locked while a file is embedded
+        reembed_files(&db, &config, EmbeddingSource::FullText, |batch: &[&str]| {
+            assert!(db.try_lock().is_ok());
+            toy_batch(batch)
+        }, &AtomicBool::new(false), |_, _| {})
+        .unwrap();
+
+        // Stored chunking becomes the config every later embedding uses
+        let db = db.lock().unwrap();
+        let conn = db.get_connection();
+        assert_eq!(configured_embedding_config(conn).unwrap(), EmbeddingConfig::default());
+        let mut cortex_config = get_config(conn).unwrap();
+        cortex_config.embedding_chunks = Some(chunks(16));
+        set_config(conn, &cortex_config).unwrap();
+        assert_eq!(configured_embedding_config(conn).unwrap(), chunks(16).apply(EmbeddingConfig::default()));
+
+        cortex_config.embedding_chunks = Some(chunks(0));
+        assert!(matches!(set_config(conn, &cortex_config), Err(CortexError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn test_summary_source_embeddings_are_labelled() {
+        use crate::db::{insert_file, upsert_file_content};
+
+        let db = Mutex::new(Database::open_in_memory().unwrap());
+        let (summarized, unsummarized) = {
+            let db = db.lock().unwrap();
+            let conn = db.get_connection();
+            let add = |name: &str, text: &str, summary: Option<&str>| {
+                let id = insert_file(conn, &format!("/test/{}", name), name, "txt", 10,
+                    "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+                upsert_file_content(conn, id, Some(text), summary).unwrap();
+                id
+            };
+            (
+                add("engine.txt", &"map legend ".repeat(500), Some("car engine overview")),
+                add("atlas.txt", &format!("map {}", "filler ".repeat(1000)), None),
+            )
+        };
+
+        let mut embedded = Vec::new();
+        let summary = reembed_files(&db, &EmbeddingConfig::default(), EmbeddingSource::Summary, |batch: &[&str]| {
+            embedded.extend(batch.iter().map(|text| text.to_string()));
+            Ok(batch.iter().map(|text| topic_embedding(text).unwrap()).collect())
+        }, &AtomicBool::new(false), |_, _| {})
+        .unwrap();
+        assert_eq!(summary.embedded_files, 2);
+
+        // The stored summary is embedded; without one, only the opening words
+        assert!(embedded.contains(&"car engine overview".to_string()));
+        assert!(embedded.iter().all(|text| text.split_whitespace().count() <= 200));
+
+        {
+            let db = db.lock().unwrap();
+            let conn = db.get_connection();
+            for id in [summarized, unsummarized] {
+                let stored = get_embedding(conn, id).unwrap().unwrap();
+                assert_eq!(stored.model_version, "all-MiniLM-L6-v2+summary");
+            }
+            let stored = get_embedding(conn, summarized).unwrap().unwrap();
+            assert_eq!(stored.embedding, topic_embedding("car engine overview").unwrap());
+        }
+
+        // Full-text embeddings keep the bare model version
+        reembed_files(&db, &EmbeddingConfig::default(), EmbeddingSource::FullText, |batch: &[&str]| {
+            Ok(batch.iter().map(|text| topic_embedding(text).unwrap()).collect())
+        }, &AtomicBool::new(false), |_, _| {})
+        .unwrap();
+        let db = db.lock().unwrap();
+        assert_eq!(get_embedding(db.get_connection(), summarized).unwrap().unwrap().model_version, MODEL_VERSION);
+
+        assert_eq!(
+            EmbeddingSource::TitlePlusSummary.document_text("engine.txt", None, Some("car engine overview")),
+            Some("engine.txt\ncar engine overview".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compare_files_scores_related_content_higher() {
+        use crate::db::insert_file;
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let add = |name: &str, text: Option<&str>| {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            if let Some(text) = text {
+                upsert_embedding(&conn, id, &topic_embedding(text).unwrap(), MODEL_VERSION).unwrap();
+            }
+            id
+        };
+        let engine = add("engine.txt", Some("car engine repair"));
+        let engine_copy = add("engine-copy.txt", Some("car engine repair"));
+        let atlas = add("atlas.txt", Some("map cartography"));
+        let unembedded = add("notes.txt", None);
+
+        let identical = compare_file_embeddings(&conn, engine, engine_copy).unwrap();
+        assert!((identical.similarity - 1.0).abs() < 1e-5);
+        assert_eq!(identical.model_version_a, MODEL_VERSION);
+
+        let unrelated = compare_file_embeddings(&conn, engine, atlas).unwrap();
+        assert!(unrelated.similarity < 0.1);
+
+        match compare_file_embeddings(&conn, engine, unembedded) {
+            Err(CortexError::InvalidInput { message }) => assert!(message.contains(&unembedded.to_string())),
+            other => panic!("expected a missing embedding error, got {:?}", other),
+        }
+        assert!(matches!(
+            compare_file_embeddings(&conn, 9999, engine),
+            Err(CortexError::FileNotFound { .. })
+        ));
+    }
 }
@@ -0,0 +1,26 @@
+use crate::state::AppState;
+use tauri::State;
+
+/// Bookmark a file; false if it already was
+#[tauri::command]
+pub async fn add_bookmark(file_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::add_bookmark(conn, file_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Undo [`add_bookmark`]
+#[tauri::command]
+pub async fn remove_bookmark(file_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::remove_bookmark(conn, file_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Bookmarked files with current metadata, most recent first
+#[tauri::command]
+pub async fn list_bookmarks(state: State<'_, AppState>) -> Result<Vec<crate::db::File>, String> {
+    state.db_pool.read(crate::db::list_bookmarks).map_err(|e| e.to_string())
+}
@@ -0,0 +1,53 @@
+use crate::db::Collection;
+use crate::state::AppState;
+use tauri::State;
+
+/// Create an empty collection
+#[tauri::command]
+pub async fn create_collection(name: String, state: State<'_, AppState>) -> Result<Collection, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::create_collection(conn, &name))
+        .map_err(|e| e.to_string())
+}
+
+/// Delete a collection, leaving its files indexed; false if it didn't exist
+#[tauri::command]
+pub async fn delete_collection(collection_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::delete_collection(conn, collection_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Every collection with its file count
+#[tauri::command]
+pub async fn list_collections(state: State<'_, AppState>) -> Result<Vec<Collection>, String> {
+    state.db_pool.read(crate::db::list_collections).map_err(|e| e.to_string())
+}
+
+/// Add files to a collection; returns how many weren't in it yet
+#[tauri::command]
+pub async fn add_files_to_collection(
+    collection_id: i64,
+    file_ids: Vec<i64>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::add_files_to_collection(conn, collection_id, &file_ids))
+        .map_err(|e| e.to_string())
+}
+
+/// Remove files from a collection; returns how many were in it
+#[tauri::command]
+pub async fn remove_files_from_collection(
+    collection_id: i64,
+    file_ids: Vec<i64>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::remove_files_from_collection(conn, collection_id, &file_ids))
+        .map_err(|e| e.to_string())
+}
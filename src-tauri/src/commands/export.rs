@@ -1,12 +1,22 @@
 use crate::export::{
-    BundleBuilder, ExportConfig, ExportPreview, ExportResult, ExportStatsInfo,
-    RakeExportConfig, RakeExportMode, RakeExportMetadata, RakeExporter,
+    BundleBuilder, ChunkStrategy, ContextOptions, ExportConfig, ExportPreview, ExportResult, ExportStatsInfo,
+    LineEnding, PathValidator, PromptBuilder, PromptVariable, template_variables, RakeConfigProblem, RakeExportConfig, RakeExportMode, RakeExportMetadata, RakeExportProgress, RakeExporter,
 };
+use crate::db::{collection_exists, CustomPromptTemplate};
 use crate::error::{CortexError, Result};
 use crate::state::AppState;
-use tauri::State;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Emitter, State};
+
+const INVALID_MODE_REASON: &str = "Invalid export mode. Use 'full', 'incremental', or 'collection'";
 
 /// Export VS Code Claude context bundle
+///
+/// Fails with `CortexError::Cancelled`, writing nothing, if
+/// [`cancel_export`] is called while the bundle is being built.
 #[tauri::command]
 pub async fn export_vscode_context(
     collection_id: Option<String>,
@@ -15,6 +25,10 @@ pub async fn export_vscode_context(
     output_path: String,
     project_name: Option<String>,
     custom_context: Option<String>,
+    context_options: Option<ContextOptions>,
+    summaries_only: Option<bool>,
+    line_ending: Option<LineEnding>,
+    changed_since: Option<DateTime<Utc>>,
     state: State<'_, AppState>,
 ) -> Result<ExportResult> {
     let config = ExportConfig {
@@ -24,10 +38,16 @@ pub async fn export_vscode_context(
         include_prompts,
         project_name,
         custom_context,
+        context: context_options.unwrap_or_default(),
+        summaries_only: summaries_only.unwrap_or(false),
+        line_ending: line_ending.unwrap_or_default(),
+        changed_since,
     };
 
     // Clone the database Arc to move into the blocking task
     let db_arc = state.db.clone();
+    state.stop_export.store(false, Ordering::SeqCst);
+    let stop_export = state.stop_export.clone();
 
     // Run export in blocking task since Database is not Send
     let result = tokio::task::spawn_blocking(move || {
@@ -35,7 +55,7 @@ pub async fn export_vscode_context(
         let db = db_guard.clone();
         drop(db_guard);
 
-        let bundler = BundleBuilder::new(db);
+        let bundler = BundleBuilder::new(db).with_cancel_flag(stop_export);
         // Call the sync version since we're in a blocking context
         tokio::runtime::Handle::current().block_on(bundler.create_bundle(&config))
     })
@@ -48,6 +68,10 @@ pub async fn export_vscode_context(
 }
 
 /// Export Rake-compatible package
+///
+/// Emits `rake_export:progress` events as files are converted. Fails with
+/// `CortexError::Cancelled`, writing nothing, if [`cancel_export`] is called
+/// before the package is written.
 #[tauri::command]
 pub async fn export_rake_package(
     collection_id: Option<String>,
@@ -55,8 +79,14 @@ pub async fn export_rake_package(
     output_path: String,
     include_embeddings: bool,
     export_mode: String, // "full", "incremental", or "collection"
+    summaries_only: Option<bool>,
+    chunk_strategy: Option<ChunkStrategy>,
+    line_ending: Option<LineEnding>,
+    max_output_bytes: Option<u64>,
+    changed_since: Option<DateTime<Utc>>,
+    app: AppHandle,
     state: State<'_, AppState>,
-) -> Result<String> {
+) -> Result<Vec<String>> {
     // Validate tenant_id
     if tenant_id.trim().is_empty() {
         return Err(CortexError::InvalidQuery {
@@ -66,14 +96,12 @@ pub async fn export_rake_package(
     }
 
     // Parse export mode
-    let mode = match export_mode.to_lowercase().as_str() {
-        "full" => RakeExportMode::Full,
-        "incremental" => RakeExportMode::Incremental,
-        "collection" => RakeExportMode::Collection,
-        _ => {
+    let mode = match RakeExportMode::parse(&export_mode) {
+        Some(mode) => mode,
+        None => {
             return Err(CortexError::InvalidQuery {
                 query: export_mode,
-                reason: "Invalid export mode. Use 'full', 'incremental', or 'collection'".to_string(),
+                reason: INVALID_MODE_REASON.to_string(),
             });
         }
     };
@@ -84,26 +112,119 @@ pub async fn export_rake_package(
         output_path,
         include_embeddings,
         export_mode: mode,
+        summaries_only: summaries_only.unwrap_or(false),
+        chunk_strategy: chunk_strategy.unwrap_or_default(),
+        line_ending: line_ending.unwrap_or_default(),
+        max_output_bytes,
+        changed_since,
     };
 
     // Clone the database Arc to move into the blocking task
     let db_arc = state.db.clone();
+    state.stop_export.store(false, Ordering::SeqCst);
+    let stop_export = state.stop_export.clone();
 
     // Run export in blocking task since Database is not Send
-    let file_path = tokio::task::spawn_blocking(move || {
+    let file_paths = tokio::task::spawn_blocking(move || {
         let db_guard = db_arc.lock().unwrap();
         let db = db_guard.clone();
         drop(db_guard);
 
-        let exporter = RakeExporter::new(db);
-        tokio::runtime::Handle::current().block_on(exporter.export_to_file(&config))
+        // Emit on the first and last file and every 10th in between
+        let emit_progress = move |progress: RakeExportProgress| {
+            let processed = progress.files_processed;
+            if processed == 1 || processed == progress.total_files || processed.is_multiple_of(10) {
+                let _ = app.emit("rake_export:progress", progress);
+            }
+        };
+
+        let exporter = RakeExporter::new(db).with_cancel_flag(stop_export);
+        tokio::runtime::Handle::current()
+            .block_on(exporter.export_to_file(&config, Some(&emit_progress)))
     })
     .await
     .map_err(|e| CortexError::Internal {
         message: format!("Export task failed: {}", e),
     })??;
 
-    Ok(file_path)
+    Ok(file_paths)
+}
+
+/// Ask running exports to stop after the file they're on
+#[tauri::command]
+pub async fn cancel_export(state: State<'_, AppState>) -> Result<()> {
+    state.stop_export.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Check a Rake export config without exporting
+///
+/// Returns every problem found rather than stopping at the first; an empty
+/// list means the export can run. Nothing is written.
+#[tauri::command]
+pub async fn validate_rake_config(
+    collection_id: Option<String>,
+    tenant_id: String,
+    output_path: String,
+    export_mode: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<RakeConfigProblem>> {
+    state.db_pool.read(|conn| {
+        rake_config_problems(conn, collection_id.as_deref(), &tenant_id, &output_path, &export_mode)
+    })
+}
+
+/// Problems with a Rake export config (see [`validate_rake_config`])
+///
+/// In collection mode the collection ID must name an existing collection.
+pub fn rake_config_problems(
+    conn: &Connection,
+    collection_id: Option<&str>,
+    tenant_id: &str,
+    output_path: &str,
+    export_mode: &str,
+) -> Result<Vec<RakeConfigProblem>> {
+    let mut problems = Vec::new();
+    let mut problem = |field: &str, message: String| {
+        problems.push(RakeConfigProblem {
+            field: field.to_string(),
+            message,
+        })
+    };
+
+    if tenant_id.trim().is_empty() {
+        problem("tenant_id", "Tenant ID cannot be empty".to_string());
+    }
+
+    match RakeExportMode::parse(export_mode) {
+        None => problem("export_mode", INVALID_MODE_REASON.to_string()),
+        Some(RakeExportMode::Collection) => match collection_id.map(str::trim).filter(|id| !id.is_empty()) {
+            None => problem("collection_id", "Collection mode requires a collection ID".to_string()),
+            Some(id) => match id.parse::<i64>() {
+                Ok(parsed) if collection_exists(conn, parsed)? => {}
+                Ok(_) => problem("collection_id", format!("Unknown collection: {}", id)),
+                Err(_) => problem("collection_id", format!("Collection ID must be a number: {}", id)),
+            },
+        },
+        Some(_) => {}
+    }
+
+    match PathValidator::validate_export_path(output_path) {
+        Ok(path) => match path.parent() {
+            Some(parent) if !parent.is_dir() => problem(
+                "output_path",
+                format!("Output directory does not exist: {}", parent.display()),
+            ),
+            Some(parent) if parent.metadata().is_ok_and(|m| m.permissions().readonly()) => problem(
+                "output_path",
+                format!("Output directory is read-only: {}", parent.display()),
+            ),
+            _ => {}
+        },
+        Err(e) => problem("output_path", e.to_string()),
+    }
+
+    Ok(problems)
 }
 
 /// Get preview of what will be exported
@@ -137,7 +258,7 @@ pub async fn get_export_preview(
 
     // Check embeddings
     let embedded_file_count = if include_embeddings {
-        crate::db::operations::count_embeddings(conn)? as usize
+        crate::db::embeddings::count_embeddings(conn)? as usize
     } else {
         0
     };
@@ -162,15 +283,12 @@ pub async fn get_rake_export_preview(
     tenant_id: String,
     include_embeddings: bool,
     export_mode: String,
+    summaries_only: Option<bool>,
+    chunk_strategy: Option<ChunkStrategy>,
     state: State<'_, AppState>,
 ) -> Result<RakeExportMetadata> {
     // Parse export mode
-    let mode = match export_mode.to_lowercase().as_str() {
-        "full" => RakeExportMode::Full,
-        "incremental" => RakeExportMode::Incremental,
-        "collection" => RakeExportMode::Collection,
-        _ => RakeExportMode::Full,
-    };
+    let mode = RakeExportMode::parse(&export_mode).unwrap_or(RakeExportMode::Full);
 
     let config = RakeExportConfig {
         collection_id,
@@ -178,6 +296,11 @@ pub async fn get_rake_export_preview(
         output_path: String::new(), // Not needed for preview
         include_embeddings,
         export_mode: mode,
+        summaries_only: summaries_only.unwrap_or(false),
+        chunk_strategy: chunk_strategy.unwrap_or_default(),
+        line_ending: LineEnding::default(),
+        max_output_bytes: None,
+        changed_since: None,
     };
 
     // Clone database from Arc<Mutex<Database>> and perform sync operation
@@ -193,11 +316,55 @@ pub async fn get_rake_export_preview(
     Ok(preview)
 }
 
-/// List available prompt templates
+/// List available prompt templates, built-in ones first
+#[tauri::command]
+pub async fn list_prompt_templates(state: State<'_, AppState>) -> Result<Vec<PromptTemplateInfo>> {
+    let db_guard = state.db.lock().unwrap();
+    all_prompt_templates(db_guard.get_connection())
+}
+
+fn all_prompt_templates(conn: &rusqlite::Connection) -> Result<Vec<PromptTemplateInfo>> {
+    let mut templates = builtin_prompt_templates();
+    templates.extend(crate::db::list_prompt_templates(conn)?.into_iter().map(PromptTemplateInfo::from));
+    Ok(templates)
+}
+
+/// Create a user-defined prompt template
+///
+/// `body` marks each variable as its upper-cased key in braces, e.g.
+/// `{VERSION}` for the key `version`.
 #[tauri::command]
-pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
-    // Return predefined templates
-    Ok(vec![
+pub async fn create_prompt_template(
+    name: String,
+    description: Option<String>,
+    icon: Option<String>,
+    body: String,
+    variables: Vec<PromptVariable>,
+    state: State<'_, AppState>,
+) -> Result<PromptTemplateInfo> {
+    let db_guard = state.db.lock().unwrap();
+    let template = crate::db::create_prompt_template(
+        db_guard.get_connection(),
+        &name,
+        &description.unwrap_or_default(),
+        &icon.unwrap_or_else(|| "📝".to_string()),
+        &body,
+        &variables,
+    )?;
+
+    Ok(template.into())
+}
+
+/// Delete a user-defined prompt template; returns false if there was none
+/// with that id
+#[tauri::command]
+pub async fn delete_prompt_template(template_id: String, state: State<'_, AppState>) -> Result<bool> {
+    let db_guard = state.db.lock().unwrap();
+    crate::db::delete_prompt_template(db_guard.get_connection(), &template_id)
+}
+
+fn builtin_prompt_templates() -> Vec<PromptTemplateInfo> {
+    vec![
         PromptTemplateInfo {
             id: "add_feature".to_string(),
             name: "Add Feature".to_string(),
@@ -205,6 +372,7 @@ pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
             description: "Guided prompt for implementing new features following project patterns".to_string(),
             icon: "✨".to_string(),
             category: "feature".to_string(),
+            variables: template_variables("add_feature").unwrap_or_default(),
         },
         PromptTemplateInfo {
             id: "fix_bug".to_string(),
@@ -213,6 +381,7 @@ pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
             description: "Systematic approach to identifying and fixing bugs".to_string(),
             icon: "🐛".to_string(),
             category: "bug_fix".to_string(),
+            variables: template_variables("fix_bug").unwrap_or_default(),
         },
         PromptTemplateInfo {
             id: "refactor".to_string(),
@@ -221,6 +390,7 @@ pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
             description: "Refactor code while maintaining functionality".to_string(),
             icon: "♻️".to_string(),
             category: "refactor".to_string(),
+            variables: template_variables("refactor").unwrap_or_default(),
         },
         PromptTemplateInfo {
             id: "add_tests".to_string(),
@@ -229,6 +399,7 @@ pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
             description: "Add comprehensive unit and integration tests".to_string(),
             icon: "🧪".to_string(),
             category: "testing".to_string(),
+            variables: template_variables("add_tests").unwrap_or_default(),
         },
         PromptTemplateInfo {
             id: "documentation".to_string(),
@@ -237,8 +408,36 @@ pub async fn list_prompt_templates() -> Result<Vec<PromptTemplateInfo>> {
             description: "Create or update project documentation".to_string(),
             icon: "📚".to_string(),
             category: "documentation".to_string(),
+            variables: template_variables("documentation").unwrap_or_default(),
         },
-    ])
+    ]
+}
+
+/// Render a prompt template with the given variable values, for previewing
+/// it before export
+///
+/// Fails with `CortexError::InvalidQuery` for an unknown template or a
+/// missing required variable.
+#[tauri::command]
+pub async fn render_prompt_template(
+    template_id: String,
+    variables: HashMap<String, String>,
+    state: State<'_, AppState>,
+) -> Result<String> {
+    let db_arc = state.db.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let db_guard = db_arc.lock().unwrap();
+        let db = db_guard.clone();
+        drop(db_guard);
+
+        let builder = PromptBuilder::new(db);
+        tokio::runtime::Handle::current().block_on(builder.render_template(&template_id, &variables))
+    })
+    .await
+    .map_err(|e| CortexError::Internal {
+        message: format!("Render task failed: {}", e),
+    })?
 }
 
 /// Prompt template info (simplified version for IPC)
@@ -250,6 +449,21 @@ pub struct PromptTemplateInfo {
     pub description: String,
     pub icon: String,
     pub category: String,
+    pub variables: Vec<PromptVariable>,
+}
+
+impl From<CustomPromptTemplate> for PromptTemplateInfo {
+    fn from(template: CustomPromptTemplate) -> Self {
+        Self {
+            id: template.id,
+            short_description: template.description.lines().next().unwrap_or_default().to_string(),
+            name: template.name,
+            description: template.description,
+            icon: template.icon,
+            category: "custom".to_string(),
+            variables: template.variables,
+        }
+    }
 }
 
 /// Get database statistics for export preview
@@ -260,7 +474,7 @@ pub async fn get_export_stats(state: State<'_, AppState>) -> Result<ExportStatsI
     let conn = db_guard.get_connection();
 
     let (total_files, indexed_files, total_size) = crate::db::operations::get_db_stats(conn)?;
-    let embedding_count = crate::db::operations::count_embeddings(conn)?;
+    let embedding_count = crate::db::embeddings::count_embeddings(conn)?;
 
     Ok(ExportStatsInfo {
         total_files: total_files as usize,
@@ -278,11 +492,72 @@ mod tests {
     #[test]
     fn test_prompt_templates_list() {
         // Test that we have at least the 5 core templates
-        let templates = tokio_test::block_on(list_prompt_templates()).unwrap();
+        let templates = builtin_prompt_templates();
         assert_eq!(templates.len(), 5);
 
         let feature_template = templates.iter().find(|t| t.id == "add_feature");
         assert!(feature_template.is_some());
         assert_eq!(feature_template.unwrap().name, "Add Feature");
     }
+
+    #[test]
+    fn test_custom_prompt_templates_listed_after_builtins() {
+        let db = crate::db::Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let created = crate::db::create_prompt_template(conn, "Release notes", "Summarize a release\nin detail",
+            "📝", "Notes for {VERSION}", &[]).unwrap();
+
+        let templates = all_prompt_templates(conn).unwrap();
+        assert_eq!(templates.len(), 6);
+        assert_eq!(templates[5].id, created.id);
+        assert_eq!(templates[5].short_description, "Summarize a release");
+        assert_eq!(templates[5].category, "custom");
+    }
+
+    #[test]
+    fn test_rake_config_problems_all_reported() {
+        let db = crate::db::Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let good_path = temp_dir.path().join("export.json");
+        let good_path = good_path.to_str().unwrap();
+
+        assert!(rake_config_problems(conn, None, "tenant", good_path, "full").unwrap().is_empty());
+
+        let fields = |problems: Result<Vec<RakeConfigProblem>>| -> Vec<String> {
+            problems.unwrap().into_iter().map(|p| p.field).collect()
+        };
+
+        assert_eq!(fields(rake_config_problems(conn, None, "  ", good_path, "full")), vec!["tenant_id"]);
+        assert_eq!(fields(rake_config_problems(conn, None, "tenant", good_path, "everything")), vec!["export_mode"]);
+        assert_eq!(
+            fields(rake_config_problems(conn, None, "tenant", "../../etc/export.json", "full")),
+            vec!["output_path"]
+        );
+        assert_eq!(fields(rake_config_problems(conn, None, "tenant", good_path, "collection")), vec!["collection_id"]);
+
+        // Not just the first problem
+        let all = rake_config_problems(conn, None, "", "../escape.json", "bogus");
+        assert_eq!(fields(all), vec!["tenant_id", "export_mode", "output_path"]);
+    }
+
+    #[test]
+    fn test_rake_config_collection_must_exist() {
+        let db = crate::db::Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let good_path = temp_dir.path().join("export.json");
+        let good_path = good_path.to_str().unwrap();
+
+        let collection = crate::db::create_collection(conn, "Client work").unwrap();
+        let id = collection.id.to_string();
+        assert!(rake_config_problems(conn, Some(&id), "tenant", good_path, "collection").unwrap().is_empty());
+
+        for unknown in [(collection.id + 1).to_string(), "client-work".to_string()] {
+            let problems = rake_config_problems(conn, Some(&unknown), "tenant", good_path, "collection").unwrap();
+            assert_eq!(problems.len(), 1);
+            assert_eq!(problems[0].field, "collection_id");
+            assert!(problems[0].message.contains(&unknown));
+        }
+    }
 }
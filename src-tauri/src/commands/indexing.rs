@@ -1,10 +1,27 @@
-use crate::db::{insert_file, upsert_file_content};
+use crate::db::{
+    export_index_archive, find_content_by_hash, find_content_owner, find_moved_file,
+    get_file_by_id, get_file_by_path, get_file_links, get_files_without_embeddings, import_index_archive,
+    insert_file, link_duplicate, list_deleted_file_ids, list_files_by_type,
+    list_files_without_content, list_files_without_hash, mark_file_deleted, move_file,
+    purge_in_batches, record_profile_roots, release_duplicates, set_code_split, set_content_extractor,
+    set_content_metadata, set_file_hash, set_file_links, unlink_duplicate, update_file, upsert_file_content,
+    DatabasePool, File, IndexArchiveSummary, OrphanReport, PurgeSummary, RepairReport,
+    PURGE_BATCH_SIZE,
+};
 use crate::error::CortexError;
-use crate::indexer::{ContentExtractor, FileScanner};
+use crate::export::PathValidator;
+use crate::indexer::{
+    hash_file, plan_roots, split_comments, CodeLanguage, ContentExtractor, ExtractOptions, ExtractedContent,
+    ExtractionWarning, FileScanner, IndexEstimate, IndexJob, IndexTarget, IndexThroughput,
+    PlannedRoots, ProfileRoots, SupportedFormat, WarningSummary, METADATA_TITLE,
+};
+use crate::paths::{normalize_roots, to_long_path};
 use crate::state::AppState;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use tauri::{AppHandle, Emitter, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +48,8 @@ pub struct IndexCompleteEvent {
     pub indexed_files: usize,
     pub errors: Vec<String>,
     pub duration_secs: f64,
+    /// Extraction warnings, e.g. lossy decoding, counted by kind
+    pub warnings: WarningSummary,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -39,18 +58,123 @@ pub struct IndexErrorEvent {
     pub error: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexSummary {
+    pub file_type: String,
+    pub reindexed_files: usize,
+    /// Files no longer on disk, soft-deleted during the reindex
+    pub removed_files: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewlyEligibleSummary {
+    /// Eligible files found on disk, indexed or not
+    pub scanned_files: usize,
+    /// Files that weren't in the index and now are
+    pub indexed_files: usize,
+    pub errors: Vec<String>,
+}
+
+/// What the extractor produced for one file, without indexing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionPreview {
+    pub path: String,
+    /// Extractor that handled the file, e.g. `markdown` or `pdf`
+    pub extractor: String,
+    pub file_type: String,
+    pub size: u64,
+    pub modified_at: Option<String>,
+    pub text: String,
+    pub word_count: usize,
+    pub char_count: usize,
+    pub summary: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashBackfillSummary {
+    pub hashed_files: usize,
+    /// Files that could not be read (e.g. no longer on disk); left unhashed
+    pub skipped_files: usize,
+    pub errors: Vec<String>,
+}
+
 /// Start indexing one or more directories
+///
+/// With `quick` set, every file's metadata is stored first so files are
+/// listable right away, and content extraction follows in the same background
+/// task; search results fill in as extraction progresses. With `incremental`
+/// set, files unchanged since they were last indexed are skipped.
 #[tauri::command]
 pub async fn start_indexing(
-    paths: Vec<String>,
+    paths: Vec<IndexTarget>,
+    quick: Option<bool>,
+    incremental: Option<bool>,
     app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    // Resolve profiles up front so an unknown one fails the call itself
+    let profiles = state
+        .db_pool
+        .read(crate::db::get_config)
+        .map_err(|e| e.to_string())?
+        .indexing_profiles;
+    let planned = plan_roots(&paths, &profiles).map_err(|e| e.to_string())?;
+
+    spawn_indexing(planned, quick.unwrap_or(false), incremental.unwrap_or(false), app, &state)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok("Indexing started in background".to_string())
+}
+
+/// Roots to re-index on launch, or `None` if auto-indexing is off or has
+/// no roots configured
+pub fn auto_index_plan(conn: &Connection) -> Result<Option<PlannedRoots>, CortexError> {
+    let config = crate::db::get_config(conn)?;
+    if !config.auto_index_on_startup || config.auto_index_roots.is_empty() {
+        return Ok(None);
+    }
+
+    plan_roots(&config.auto_index_roots, &config.indexing_profiles).map(Some)
+}
+
+/// Start the launch-time re-index configured by `auto_index_on_startup`
+///
+/// Runs incrementally in the background like [`start_indexing`], emitting
+/// the same events. Returns whether a run was started.
+pub async fn start_auto_index(app: AppHandle, state: &AppState) -> Result<bool, CortexError> {
+    let Some(planned) = state.db_pool.read(auto_index_plan)? else {
+        return Ok(false);
+    };
+
+    for (path_str, e) in &planned.unresolved {
+        log::warn!("Auto-index root is unavailable: {} ({})", path_str, e);
+    }
+
+    spawn_indexing(planned, false, true, app, state).await?;
+    Ok(true)
+}
+
+/// Run [`run_indexing_pipeline`] in a background task, emitting
+/// `indexing:complete` when it finishes
+async fn spawn_indexing(
+    planned: PlannedRoots,
+    quick: bool,
+    incremental: bool,
+    app: AppHandle,
+    state: &AppState,
+) -> Result<(), CortexError> {
     // Check if already indexing
     {
         let indexing_active = state.indexing_active.read().await;
         if *indexing_active {
-            return Err(CortexError::IndexingInProgress.to_string());
+            return Err(CortexError::IndexingInProgress);
         }
     }
 
@@ -58,21 +182,33 @@ pub async fn start_indexing(
     state.reset_indexing_state().await;
     *state.indexing_active.write().await = true;
 
-    log::info!("Starting indexing for {} paths", paths.len());
+    log::info!("Starting indexing for {} roots", planned.roots.len());
 
     // Spawn background task for indexing
     let db = state.db.clone();
+    let db_pool = state.db_pool.clone();
     let indexing_active = state.indexing_active.clone();
     let indexing_progress = state.indexing_progress.clone();
     let indexing_errors = state.indexing_errors.clone();
     let stop_indexing = state.stop_indexing.clone();
+    let stop_embedding = state.stop_embedding.clone();
+    let stop_purge = state.stop_purge.clone();
+    let stop_export = state.stop_export.clone();
+    let embedding_service = state.embedding_service.clone();
+    let watcher = state.watcher.clone();
 
     let state_clone = Arc::new(crate::state::AppState {
         db,
+        db_pool,
         indexing_active,
         indexing_progress,
         indexing_errors,
         stop_indexing,
+        stop_embedding,
+        stop_purge,
+        stop_export,
+        embedding_service,
+        watcher,
     });
     let app_clone = app.clone();
 
@@ -84,7 +220,7 @@ pub async fn start_indexing(
         let state_for_emit = state_clone.clone();
 
         // Run the indexing pipeline
-        match run_indexing_pipeline(paths, app_clone, state_clone).await {
+        match run_indexing_pipeline(planned, quick, incremental, app_clone, state_clone).await {
             Ok(stats) => {
                 let duration = start_time.elapsed();
                 log::info!(
@@ -92,6 +228,12 @@ pub async fn start_indexing(
                     stats.indexed_files,
                     duration.as_secs_f64()
                 );
+                if stats.warnings.files_with_warnings > 0 {
+                    log::info!(
+                        "{} files were indexed with extraction warnings",
+                        stats.warnings.files_with_warnings
+                    );
+                }
 
                 // Emit completion event
                 let errors = state_for_emit.indexing_errors.read().await.clone();
@@ -102,6 +244,7 @@ pub async fn start_indexing(
                         indexed_files: stats.indexed_files,
                         errors,
                         duration_secs: duration.as_secs_f64(),
+                        warnings: stats.warnings,
                     },
                 );
             }
@@ -119,7 +262,43 @@ pub async fn start_indexing(
         *state_for_emit.indexing_active.write().await = false;
     });
 
-    Ok("Indexing started in background".to_string())
+    Ok(())
+}
+
+/// Estimate how long indexing `paths` would take
+///
+/// Only walks the directories and reads file metadata, so it is fast even
+/// for large trees. Paths that don't exist are skipped; each root is scanned
+/// with its target's profile.
+#[tauri::command]
+pub async fn estimate_indexing(
+    paths: Vec<IndexTarget>,
+    state: State<'_, AppState>,
+) -> Result<IndexEstimate, String> {
+    let profiles = state
+        .db_pool
+        .read(crate::db::get_config)
+        .map_err(|e| e.to_string())?
+        .indexing_profiles;
+
+    tokio::task::spawn_blocking(move || {
+        let planned = plan_roots(&paths, &profiles).map_err(|e| e.to_string())?;
+        let mut all_jobs = Vec::new();
+
+        for (path_str, e) in &planned.unresolved {
+            log::warn!("Skipping missing path in estimate: {} ({})", path_str, e);
+        }
+
+        for root in &planned.roots {
+            // Only the jobs are needed, so skip the progress counting pass
+            let scanner = root.scanner(FileScanner::new().with_progress_count(false));
+            all_jobs.extend(scanner.scan_directory(&root.path).map_err(|e| e.to_string())?);
+        }
+
+        Ok(IndexEstimate::from_jobs(&all_jobs, IndexThroughput::DEFAULT))
+    })
+    .await
+    .map_err(|e| format!("Estimate task failed: {}", e))?
 }
 
 /// Stop ongoing indexing operation
@@ -164,196 +343,2291 @@ pub async fn get_index_status(state: State<'_, AppState>) -> Result<IndexStatus,
     })
 }
 
-// Private helper types
-struct IndexingStats {
-    total_files: usize,
-    indexed_files: usize,
+/// Re-extract and re-index every indexed file of one type from disk
+///
+/// Useful after an extractor improves. Files that no longer exist are
+/// soft-deleted instead of re-indexed.
+#[tauri::command]
+pub async fn reindex_by_type(
+    file_type: String,
+    state: State<'_, AppState>,
+) -> Result<ReindexSummary, String> {
+    let file_type = file_type.trim().trim_start_matches('.').to_string();
+    if file_type.is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: file_type,
+            reason: "File type cannot be empty".to_string(),
+        }
+        .to_string());
+    }
+
+    // Each file is written separately, so keep other indexing runs out
+    // meanwhile
+    {
+        let mut indexing_active = state.indexing_active.write().await;
+        if *indexing_active {
+            return Err(CortexError::IndexingInProgress.to_string());
+        }
+        *indexing_active = true;
+    }
+
+    log::info!("Reindexing all .{} files", file_type);
+
+    let result = reindex_files_by_type(&state.db_pool, &file_type);
+    *state.indexing_active.write().await = false;
+    let summary = result.map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Reindex of .{} complete: {} reindexed, {} removed, {} errors",
+        file_type,
+        summary.reindexed_files,
+        summary.removed_files,
+        summary.errors.len()
+    );
+
+    Ok(summary)
 }
 
-/// Run the complete indexing pipeline
-async fn run_indexing_pipeline(
-    paths: Vec<String>,
-    app: AppHandle,
-    state: Arc<AppState>,
-) -> Result<IndexingStats, CortexError> {
-    let scanner = FileScanner::new();
-    let mut all_jobs = Vec::new();
+/// Reindex all non-deleted files of `file_type` (see [`reindex_by_type`])
+///
+/// Files are read and extracted on the calling thread; only storing each
+/// one goes through `db`'s writer.
+pub fn reindex_files_by_type(
+    db: &DatabasePool,
+    file_type: &str,
+) -> Result<ReindexSummary, CortexError> {
+    let (files, profile_roots) =
+        db.read(|conn| Ok((list_files_by_type(conn, file_type)?, ProfileRoots::load(conn)?)))?;
 
-    // Step 1: Scan all directories
-    log::info!("Scanning {} directories...", paths.len());
-    for path_str in paths {
-        let path = PathBuf::from(&path_str);
+    let mut summary = ReindexSummary {
+        file_type: file_type.to_string(),
+        reindexed_files: 0,
+        removed_files: 0,
+        errors: Vec::new(),
+    };
 
-        if !path.exists() {
-            let error = format!("Path does not exist: {}", path_str);
-            log::warn!("{}", error);
-            state.indexing_errors.write().await.push(error);
-            continue;
-        }
+    for file in files {
+        let path = PathBuf::from(&file.path);
 
-        match scanner.scan_directory(&path) {
-            Ok(jobs) => {
-                log::info!("Found {} files in {}", jobs.len(), path_str);
-                all_jobs.extend(jobs);
+        let metadata = match std::fs::metadata(&path) {
+            Ok(m) if m.is_file() => m,
+            _ => {
+                log::info!("File no longer exists, marking deleted: {}", file.path);
+                let file_id = file.id;
+                db.write(move |conn| mark_file_deleted(conn, file_id))?;
+                summary.removed_files += 1;
+                continue;
             }
+        };
+
+        let options = profile_roots.extract_options(&path);
+        let extracted = match ContentExtractor::extract_with_options(&to_long_path(&path), options) {
+            Ok(content) => content,
             Err(e) => {
-                let error = format!("Failed to scan {}: {}", path_str, e);
+                let error = format!("Failed to extract {}: {}", file.path, e);
                 log::warn!("{}", error);
-                state.indexing_errors.write().await.push(error);
+                summary.errors.push(error);
+                continue;
             }
-        }
+        };
 
-        // Check for stop signal
-        if *state.stop_indexing.read().await {
-            log::info!("Indexing stopped during scan phase");
-            return Ok(IndexingStats {
-                total_files: all_jobs.len(),
-                indexed_files: 0,
-            });
+        let modified_at = metadata
+            .modified()
+            .map(|t| DateTime::<Utc>::from(t).to_rfc3339())
+            .unwrap_or_else(|_| Utc::now().to_rfc3339());
+        let hash = hash_for_index(&path);
+        let content_key = ContentExtractor::content_key(&path);
+
+        let size = metadata.len();
+        let stored = db.write(move |conn| {
+            store_hashed_file(conn, &path, size, &modified_at, hash, &content_key, &extracted)
+        });
+        match stored {
+            Ok(_) => summary.reindexed_files += 1,
+            Err(e) => {
+                let error = format!("Failed to reindex {}: {}", file.path, e);
+                log::warn!("{}", error);
+                summary.errors.push(error);
+            }
         }
     }
 
-    let total_files = all_jobs.len();
-    log::info!("Total files to index: {}", total_files);
+    Ok(summary)
+}
 
-    // Update progress
+/// Index files under `paths` that are eligible now but not yet indexed
+///
+/// Meant for after a config change such as raising `max_file_size`: files
+/// the old limit skipped are picked up without re-processing the ones
+/// already in the index.
+#[tauri::command]
+pub async fn index_newly_eligible(
+    paths: Vec<String>,
+    max_file_size: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<NewlyEligibleSummary, String> {
+    // Each file is written separately, so keep other indexing runs out
+    // meanwhile
     {
-        let mut progress = state.indexing_progress.write().await;
-        *progress = Some(crate::indexer::ScanProgress {
-            total_files,
-            current_file: 0,
-        });
+        let mut indexing_active = state.indexing_active.write().await;
+        if *indexing_active {
+            return Err(CortexError::IndexingInProgress.to_string());
+        }
+        *indexing_active = true;
     }
 
-    // Step 2: Extract and index each file
-    let mut indexed_count = 0;
+    let mut scanner = FileScanner::new().with_progress_count(false);
+    if let Some(size) = max_file_size {
+        scanner = scanner.with_max_file_size(size);
+    }
 
-    for (idx, job) in all_jobs.iter().enumerate() {
-        // Check for stop signal
-        if *state.stop_indexing.read().await {
-            log::info!("Indexing stopped at file {}/{}", idx + 1, total_files);
-            break;
-        }
+    let result = index_new_files(&state.db_pool, &scanner, &paths);
+    *state.indexing_active.write().await = false;
+    let summary = result.map_err(|e| e.to_string())?;
 
-        let current_file = idx + 1;
+    log::info!(
+        "Indexed {} newly eligible files ({} scanned, {} errors)",
+        summary.indexed_files,
+        summary.scanned_files,
+        summary.errors.len()
+    );
 
-        // Update progress
-        {
-            let mut progress = state.indexing_progress.write().await;
-            *progress = Some(crate::indexer::ScanProgress {
-                total_files,
-                current_file,
-            });
-        }
+    Ok(summary)
+}
 
-        // Emit progress event every 10 files or on first/last file
-        if idx == 0 || idx == total_files - 1 || idx % 10 == 0 {
-            let _ = app.emit(
-                "indexing:progress",
-                IndexProgressEvent {
-                    total_files,
-                    indexed_files: indexed_count,
-                    current_file: job.path.display().to_string(),
-                    progress_percentage: (current_file as f64 / total_files as f64) * 100.0,
-                },
-            );
+/// `jobs` for files that are new, restored, or changed since they were last
+/// indexed
+///
+/// A file counts as unchanged if its size matches the index and it hasn't
+/// been modified since `last_indexed`.
+pub fn changed_jobs(conn: &Connection, jobs: Vec<IndexJob>) -> Result<Vec<IndexJob>, CortexError> {
+    let mut changed = Vec::with_capacity(jobs.len());
+
+    for job in jobs {
+        let unchanged = get_file_by_path(conn, &job.path.to_string_lossy())?.is_some_and(|file| {
+            !file.is_deleted
+                && file.size == job.size as i64
+                && DateTime::parse_from_rfc3339(&file.last_indexed)
+                    .is_ok_and(|indexed| DateTime::<Utc>::from(job.modified) <= indexed)
+        });
+
+        if !unchanged {
+            changed.push(job);
         }
+    }
 
-        // Extract content
-        let extracted = match ContentExtractor::extract(&job.path) {
-            Ok(content) => content,
-            Err(e) => {
-                let error = format!("Failed to extract {}: {}", job.path.display(), e);
-                log::warn!("{}", error);
-                state.indexing_errors.write().await.push(error.clone());
+    Ok(changed)
+}
 
-                // Emit error event
-                let _ = app.emit(
-                    "indexing:error",
-                    IndexErrorEvent {
-                        file_path: job.path.display().to_string(),
-                        error: e.to_string(),
-                    },
-                );
+/// Scan `paths` with `scanner` and index only files with no row yet (see
+/// [`index_newly_eligible`])
+///
+/// Overlapping roots are collapsed first, so each file is scanned once.
+/// Scanning, hashing and extraction run on the calling thread; `db` is only
+/// used for each file's lookups and its insert.
+pub fn index_new_files(
+    db: &DatabasePool,
+    scanner: &FileScanner,
+    paths: &[String],
+) -> Result<NewlyEligibleSummary, CortexError> {
+    let mut summary = NewlyEligibleSummary {
+        scanned_files: 0,
+        indexed_files: 0,
+        errors: Vec::new(),
+    };
+
+    let normalized = normalize_roots(paths);
+    for (path_str, e) in normalized.unresolved {
+        let error = format!("Failed to resolve {}: {}", path_str, e);
+        log::warn!("{}", error);
+        summary.errors.push(error);
+    }
 
+    let profile_roots = db.read(ProfileRoots::load)?;
+    for root in &normalized.roots {
+        let jobs = match scanner.scan_directory(root) {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                let error = format!("Failed to scan {}: {}", root.display(), e);
+                log::warn!("{}", error);
+                summary.errors.push(error);
                 continue;
             }
         };
+        summary.scanned_files += jobs.len();
 
-        // Log any extraction warnings
-        for warning in &extracted.warnings {
-            log::debug!("Extraction warning for {}: {}", job.path.display(), warning);
-        }
-
-        // Prepare database data
-        let now = Utc::now().to_rfc3339();
-        let file_type = job
-            .path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("unknown");
-
-        let filename = job
-            .path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-
-        let root_path = job
-            .path
-            .parent()
-            .and_then(|p| p.to_str())
-            .unwrap_or("");
-
-        // Acquire database lock only for the insert operation
-        // Scope the lock to avoid holding it across await points
-        let insert_result = {
-            let db = state.db.lock().unwrap();
-            let conn = db.get_connection();
+        for job in jobs {
+            let path = job.path.to_string_lossy();
+            if db.read(|conn| get_file_by_path(conn, &path))?.is_some() {
+                continue;
+            }
 
-            match insert_file(
-                conn,
-                &job.path.to_string_lossy(),
-                filename,
-                file_type,
-                job.size as i64,
-                &now,
-                &now,
-                None,
-                root_path,
-            ) {
-                Ok(file_id) => {
-                    // Insert content
-                    match upsert_file_content(
-                        conn,
-                        file_id,
-                        Some(&extracted.text),
-                        extracted.summary.as_deref(),
-                    ) {
-                        Ok(_) => Ok(()),
-                        Err(e) => Err(format!("Failed to insert content for {}: {}", filename, e)),
-                    }
+            let options = profile_roots.extract_options(&job.path);
+            match index_new_file(db, &job, &normalized.roots, options) {
+                Ok(_) => summary.indexed_files += 1,
+                Err(e) => {
+                    let error = format!("Failed to index {}: {}", job.path.display(), e);
+                    log::warn!("{}", error);
+                    summary.errors.push(error);
                 }
-                Err(e) => Err(format!("Failed to insert file {}: {}", filename, e)),
             }
-        }; // db and conn are dropped here
+        }
+    }
 
-        // Handle result with async operations outside the database lock
-        match insert_result {
-            Ok(()) => {
-                indexed_count += 1;
-            }
-            Err(error) => {
-                log::warn!("{}", error);
-                state.indexing_errors.write().await.push(error);
-            }
+    Ok(summary)
+}
+
+/// Index one file found by [`index_new_files`], using `db` only for the
+/// move check, the cache lookup and the insert
+fn index_new_file(
+    db: &DatabasePool,
+    job: &IndexJob,
+    scanned_roots: &[PathBuf],
+    options: ExtractOptions,
+) -> Result<i64, CortexError> {
+    let hash = hash_for_index(&job.path);
+    let content_key = ContentExtractor::content_key(&job.path);
+
+    // A file that moved here keeps its row; nothing to extract
+    let moved = {
+        let (path, hash, scanned_roots) = (job.path.clone(), hash.clone(), scanned_roots.to_vec());
+        db.write(move |conn| reconcile_move(conn, &path, hash.as_deref(), &scanned_roots))?
+    };
+    if let Some(file_id) = moved {
+        return Ok(file_id);
+    }
+
+    let extracted = match db.read(|conn| cached_extraction(conn, &content_key, hash.as_deref()))? {
+        Some(content) => content,
+        None => ContentExtractor::extract_with_options(&to_long_path(&job.path), options)?,
+    };
+
+    let path = job.path.clone();
+    let size = job.size;
+    let modified_at = DateTime::<Utc>::from(job.modified).to_rfc3339();
+    db.write(move |conn| store_hashed_file(conn, &path, size, &modified_at, hash, &content_key, &extracted))
+}
+
+/// Write the whole index (files, content, tags and embeddings) to a
+/// portable archive at `path`
+#[tauri::command]
+pub async fn export_index(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<IndexArchiveSummary, String> {
+    let path = PathValidator::validate_export_path(&path).map_err(|e| e.to_string())?;
+
+    let file = std::fs::File::create(&path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+    let summary = state
+        .db_pool
+        .read(|conn| export_index_archive(conn, std::io::BufWriter::new(file)))
+        .map_err(|e| e.to_string())?;
+
+    log::info!("Exported {} files to {}", summary.files, path.display());
+    Ok(summary)
+}
+
+/// Restore an archive written by [`export_index`] into an empty index
+#[tauri::command]
+pub async fn import_index(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<IndexArchiveSummary, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    let file = std::fs::File::open(&path).map_err(|_| {
+        CortexError::FileNotFound { path: path.clone() }.to_string()
+    })?;
+
+    let summary = state
+        .db_pool
+        .write(move |conn| import_index_archive(conn, std::io::BufReader::new(file)))
+        .map_err(|e| e.to_string())?;
+
+    log::info!("Imported {} files from {}", summary.files, path);
+    Ok(summary)
+}
+
+/// File formats Cortex can index, grouped by the extractor that reads them
+#[tauri::command]
+pub async fn get_supported_formats() -> Result<Vec<SupportedFormat>, String> {
+    Ok(ContentExtractor::supported_formats())
+}
+
+/// Run the extractor on `path` and return its output without indexing it
+///
+/// A debugging aid for files whose content looks wrong in search.
+#[tauri::command]
+pub async fn preview_extraction(path: String) -> Result<ExtractionPreview, String> {
+    preview_file_extraction(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Extract `path` for [`preview_extraction`]
+pub fn preview_file_extraction(path: &Path) -> Result<ExtractionPreview, CortexError> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) if m.is_file() => m,
+        _ => {
+            return Err(CortexError::FileNotFound {
+                path: path.display().to_string(),
+            })
         }
+    };
+
+    if !crate::indexer::is_supported_path(path) {
+        return Err(CortexError::InvalidPath {
+            path: path.display().to_string(),
+            reason: "Unsupported file type".to_string(),
+        });
     }
 
-    Ok(IndexingStats {
-        total_files,
-        indexed_files: indexed_count,
+    let extracted = ContentExtractor::extract(&to_long_path(path))?;
+
+    Ok(ExtractionPreview {
+        path: path.display().to_string(),
+        extractor: ContentExtractor::extractor_name(path),
+        file_type: path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("unknown")
+            .to_lowercase(),
+        size: metadata.len(),
+        modified_at: metadata
+            .modified()
+            .ok()
+            .map(|t| DateTime::<Utc>::from(t).to_rfc3339()),
+        char_count: extracted.text.chars().count(),
+        text: extracted.text,
+        word_count: extracted.word_count,
+        summary: extracted.summary,
+        warnings: extracted.warnings.into_iter().map(|w| w.message).collect(),
     })
 }
 
-use std::sync::Arc;
+/// Count content and FTS rows left without a parent file
+#[tauri::command]
+pub async fn find_orphans(state: State<'_, AppState>) -> Result<OrphanReport, String> {
+    state
+        .db_pool
+        .read(crate::db::find_orphans)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete content and FTS rows left without a parent file
+#[tauri::command]
+pub async fn cleanup_orphans(state: State<'_, AppState>) -> Result<OrphanReport, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    let report = state
+        .db_pool
+        .write(crate::db::cleanup_orphans)
+        .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Orphan cleanup removed {} content rows and {} FTS rows",
+        report.orphaned_content,
+        report.orphaned_fts
+    );
+
+    Ok(report)
+}
+
+/// Bring the search index back in line with the stored content
+///
+/// Rebuilds a corrupt FTS index, deletes orphaned content, FTS rows and
+/// embeddings, and re-indexes content whose FTS row is missing or outdated.
+/// Returns what was found and fixed.
+#[tauri::command]
+pub async fn repair_index(state: State<'_, AppState>) -> Result<RepairReport, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    let report = state
+        .db_pool
+        .write(crate::db::repair_index)
+        .map_err(|e| e.to_string())?;
+
+    if report.repaired_anything() {
+        log::warn!("Index repair fixed inconsistencies: {:?}", report);
+    } else {
+        log::info!("Index repair found nothing to fix");
+    }
+
+    Ok(report)
+}
+
+/// Run SQLite's full integrity check on the index, returning the problems
+/// it found (empty when healthy)
+///
+/// Startup only runs the quick check; this one is slower but also verifies
+/// that every index matches its table.
+#[tauri::command]
+pub async fn check_integrity(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let problems = state
+        .db_pool
+        .read(crate::db::check_integrity)
+        .map_err(|e| e.to_string())?;
+
+    if problems.is_empty() {
+        log::info!("Integrity check found no problems");
+    } else {
+        log::warn!("Integrity check found {} problems: {:?}", problems.len(), problems);
+    }
+
+    Ok(problems)
+}
+
+/// Delete many files at once, e.g. everything under a removed directory
+///
+/// Soft deletes unless `hard` is set. Runs in a single transaction, so either
+/// all files are deleted or none are. Returns the number of files affected.
+#[tauri::command]
+pub async fn delete_files(
+    file_ids: Vec<i64>,
+    hard: bool,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    let deleted = state
+        .db_pool
+        .write(move |conn| crate::db::delete_files(conn, &file_ids, hard))
+        .map_err(|e| e.to_string())?;
+
+    log::info!("Deleted {} files ({})", deleted, if hard { "hard" } else { "soft" });
+
+    Ok(deleted)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeProgressEvent {
+    pub total_files: usize,
+    pub processed_files: usize,
+}
+
+/// Permanently delete `file_ids`, or everything in the trash if omitted, in
+/// batches of [`PURGE_BATCH_SIZE`]
+///
+/// Unlike [`delete_files`], each batch is its own transaction queued on the
+/// database writer, so other writes and searches keep running during a large
+/// purge. Emits `purge:progress` after each batch and stops early when
+/// [`cancel_purge`] is called.
+#[tauri::command]
+pub async fn purge_files(
+    file_ids: Option<Vec<i64>>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<PurgeSummary, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    // The batches block on the database writer, so they run off the async
+    // runtime
+    let db_pool = state.db_pool.clone();
+    let stop_purge = Arc::clone(&state.stop_purge);
+    stop_purge.store(false, Ordering::SeqCst);
+
+    let summary = tokio::task::spawn_blocking(move || {
+        let file_ids = match file_ids {
+            Some(ids) => ids,
+            None => db_pool.read(list_deleted_file_ids)?,
+        };
+
+        purge_in_batches(
+            &file_ids,
+            PURGE_BATCH_SIZE,
+            &stop_purge,
+            |batch| {
+                let batch = batch.to_vec();
+                db_pool.write(move |conn| crate::db::delete_files(conn, &batch, true))
+            },
+            |processed_files, total_files| {
+                let _ = app.emit(
+                    "purge:progress",
+                    PurgeProgressEvent {
+                        total_files,
+                        processed_files,
+                    },
+                );
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Purge task failed: {}", e))?
+    .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Purged {} of {} files{}",
+        summary.deleted_files,
+        summary.total_files,
+        if summary.cancelled { " (cancelled)" } else { "" }
+    );
+
+    Ok(summary)
+}
+
+/// Stop a running [`purge_files`] after the batch in progress
+#[tauri::command]
+pub async fn cancel_purge(state: State<'_, AppState>) -> Result<(), String> {
+    state.stop_purge.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Compute content hashes for indexed files that don't have one yet
+///
+/// Rows indexed before hashing was added have `hash = NULL`; this fills them
+/// in so incremental indexing and duplicate detection can rely on the column.
+#[tauri::command]
+pub async fn backfill_hashes(state: State<'_, AppState>) -> Result<HashBackfillSummary, String> {
+    if *state.indexing_active.read().await {
+        return Err(CortexError::IndexingInProgress.to_string());
+    }
+
+    let db = state.db.lock().unwrap();
+    let summary = backfill_file_hashes(db.get_connection()).map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Hash backfill complete: {} hashed, {} skipped",
+        summary.hashed_files,
+        summary.skipped_files
+    );
+
+    Ok(summary)
+}
+
+/// Indexed files whose content hasn't been extracted yet
+///
+/// These are left behind by a quick index that was stopped before its
+/// extraction pass finished.
+#[tauri::command]
+pub async fn get_files_missing_content(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<File>, String> {
+    let limit = Some(limit.unwrap_or(100).min(10_000));
+    state
+        .db_pool
+        .read(|conn| list_files_without_content(conn, limit))
+        .map_err(|e| e.to_string())
+}
+
+/// Indexed files that don't have an embedding yet, most recently modified first
+///
+/// Duplicates are left out since they read their original's embedding.
+#[tauri::command]
+pub async fn get_files_missing_embeddings(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<File>, String> {
+    let limit = limit.unwrap_or(100).min(10_000);
+    state
+        .db_pool
+        .read(|conn| get_files_without_embeddings(conn, limit))
+        .map_err(|e| e.to_string())
+}
+
+/// Hash every non-deleted file lacking a hash (see [`backfill_hashes`])
+pub fn backfill_file_hashes(conn: &Connection) -> Result<HashBackfillSummary, CortexError> {
+    let files = list_files_without_hash(conn)?;
+
+    let mut summary = HashBackfillSummary {
+        hashed_files: 0,
+        skipped_files: 0,
+        errors: Vec::new(),
+    };
+
+    for file in files {
+        match hash_file(&to_long_path(Path::new(&file.path))) {
+            Ok(hash) => {
+                set_file_hash(conn, file.id, &hash)?;
+                summary.hashed_files += 1;
+            }
+            Err(e) => {
+                log::warn!("{}", e);
+                summary.errors.push(e.to_string());
+                summary.skipped_files += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Write one extracted file's metadata and content to the database
+///
+/// Inserts a new row, or refreshes the existing row for the same path, so it
+/// serves both first-time indexing and re-indexing. The file's bytes are
+/// hashed with blake3 and stored alongside; a hashing failure is logged and
+/// leaves the hash empty rather than failing the whole file.
+///
+/// If another live file already holds identical content (same hash), this
+/// file is linked to it as a duplicate instead of storing a second copy.
+pub fn store_indexed_file(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+    extracted: &ExtractedContent,
+) -> Result<i64, CortexError> {
+    let hash = hash_for_index(path);
+    let content_key = ContentExtractor::content_key(path);
+    store_hashed_file(conn, path, size, modified_at, hash, &content_key, extracted)
+}
+
+/// Hash a file's bytes for indexing, logging (not failing) on error
+fn hash_for_index(path: &Path) -> Option<String> {
+    match hash_file(&to_long_path(path)) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            log::warn!("{}", e);
+            None
+        }
+    }
+}
+
+/// Treat an unindexed `path` whose bytes match a file gone from its old
+/// path as that file having moved, rather than as a new file
+///
+/// Saves re-extracting and re-embedding the content, and keeps the file's
+/// tags and bookmarks. Returns the moved file's id, or `None` if `path` is
+/// already indexed or no missing file matches (see [`find_moved_file`] for
+/// how `scanned_roots` limits the candidates).
+fn reconcile_move(
+    conn: &Connection,
+    path: &Path,
+    hash: Option<&str>,
+    scanned_roots: &[PathBuf],
+) -> Result<Option<i64>, CortexError> {
+    let Some(hash) = hash else {
+        return Ok(None);
+    };
+
+    let path_str = path.to_string_lossy();
+    if get_file_by_path(conn, &path_str)?.is_some() {
+        return Ok(None);
+    }
+
+    let Some(moved) = find_moved_file(conn, hash, scanned_roots)? else {
+        return Ok(None);
+    };
+
+    log::info!("{} was moved to {}", moved.path, path.display());
+    move_file(conn, moved.id, &path_str)?;
+    Ok(Some(moved.id))
+}
+
+/// Content previously extracted from identical bytes, if any
+///
+/// Lets unchanged and duplicate files skip extraction, which is expensive for
+/// PDF and DOCX. The cache is the stored content itself, keyed by file hash
+/// and by `content_key`, the extractor that would handle the file now (see
+/// `ExtractorRegistry::content_key`), so content from an older or different
+/// extractor is extracted again.
+fn cached_extraction(
+    conn: &Connection,
+    content_key: &str,
+    hash: Option<&str>,
+) -> Result<Option<ExtractedContent>, CortexError> {
+    let Some(hash) = hash else {
+        return Ok(None);
+    };
+
+    let Some(content) = find_content_by_hash(conn, hash, content_key)? else {
+        return Ok(None);
+    };
+
+    let text = content.text_content.unwrap_or_default();
+    Ok(Some(ExtractedContent {
+        word_count: content
+            .word_count
+            .map(|count| count as usize)
+            .unwrap_or_else(|| text.split_whitespace().count()),
+        summary: content.summary,
+        text,
+        warnings: Vec::new(),
+        links: get_file_links(conn, content.file_id)?,
+        metadata: content.metadata,
+    }))
+}
+
+/// Index a file, reusing cached extraction results when its bytes are
+/// unchanged or already indexed elsewhere; `extract` runs only on a miss
+///
+/// `scanned_roots` are the roots being indexed, under which a missing file
+/// with the same bytes is taken to have moved to `path`.
+pub fn index_file_cached<F>(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+    scanned_roots: &[PathBuf],
+    extract: F,
+) -> Result<i64, CortexError>
+where
+    F: FnOnce(&Path) -> Result<ExtractedContent, CortexError>,
+{
+    let content_key = ContentExtractor::content_key(path);
+    index_file_keyed(conn, path, size, modified_at, scanned_roots, &content_key, extract)
+}
+
+/// [`index_file_cached`] for content that `extract` stores under `content_key`
+fn index_file_keyed<F>(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+    scanned_roots: &[PathBuf],
+    content_key: &str,
+    extract: F,
+) -> Result<i64, CortexError>
+where
+    F: FnOnce(&Path) -> Result<ExtractedContent, CortexError>,
+{
+    let hash = hash_for_index(path);
+
+    if let Some(file_id) = reconcile_move(conn, path, hash.as_deref(), scanned_roots)? {
+        return Ok(file_id);
+    }
+
+    let extracted = match cached_extraction(conn, content_key, hash.as_deref())? {
+        Some(content) => {
+            log::debug!("Reusing extracted content for {}", path.display());
+            content
+        }
+        None => extract(&to_long_path(path))?,
+    };
+
+    store_hashed_file(conn, path, size, modified_at, hash, content_key, &extracted)
+}
+
+/// [`store_indexed_file`] with the hash and content key already computed
+fn store_hashed_file(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+    hash: Option<String>,
+    content_key: &str,
+    extracted: &ExtractedContent,
+) -> Result<i64, CortexError> {
+    // Content is about to change: duplicates keep the old copy
+    if let Some(existing) = get_file_by_path(conn, &path.to_string_lossy())? {
+        if existing.hash != hash {
+            release_duplicates(conn, existing.id)?;
+        }
+    }
+
+    let file_id = upsert_file_metadata(conn, path, size, modified_at, hash.as_deref())?;
+    // Duplicates get their own copy, so searches by link list every file
+    set_file_links(conn, file_id, &extracted.links)?;
+
+    if let Some(hash) = hash.as_deref() {
+        if let Some(owner_id) = find_content_owner(conn, hash, file_id)? {
+            log::debug!("{} duplicates file {}, sharing its content", path.display(), owner_id);
+            link_duplicate(conn, file_id, owner_id)?;
+            return Ok(file_id);
+        }
+    }
+
+    unlink_duplicate(conn, file_id)?;
+    upsert_file_content(conn, file_id, Some(&extracted.text), stored_summary(extracted))?;
+    set_content_extractor(conn, file_id, content_key)?;
+    set_content_metadata(conn, file_id, &extracted.metadata)?;
+
+    let code_split = CodeLanguage::from_path(path)
+        .map(|language| split_comments(&extracted.text, language));
+    set_code_split(conn, file_id, code_split.as_ref())?;
+
+    Ok(file_id)
+}
+
+/// Summary to store for extracted content: the document's own title when
+/// it records one (e.g. a PDF's), which says more than its first line
+fn stored_summary(extracted: &ExtractedContent) -> Option<&str> {
+    extracted
+        .metadata
+        .get(METADATA_TITLE)
+        .map(String::as_str)
+        .or(extracted.summary.as_deref())
+}
+
+/// Write a file's metadata without extracting content (quick index mode)
+///
+/// The file is listable immediately but not content-searchable until
+/// [`extract_deferred_file`] fills in its content.
+pub fn store_file_metadata(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+) -> Result<i64, CortexError> {
+    upsert_file_metadata(conn, path, size, modified_at, None)
+}
+
+/// A file quick mode stored metadata for, whose content is extracted in
+/// its second pass
+#[derive(Debug, Clone)]
+pub struct DeferredFile {
+    pub file_id: i64,
+    pub path: String,
+    /// Computed by the first pass for files new to the index
+    pub hash: Option<String>,
+}
+
+/// Result of quick mode's first pass (see [`store_quick_metadata`])
+#[derive(Debug, Default)]
+pub struct QuickMetadata {
+    /// Files whose content still has to be extracted
+    pub deferred: Vec<DeferredFile>,
+    /// Files found moved to a job's path, which keep their content
+    pub moved: usize,
+    pub errors: Vec<String>,
+}
+
+/// Quick mode's first pass: make every job listable without extracting it
+///
+/// Jobs at paths new to the index are hashed first, on the calling thread,
+/// so a file that moved there keeps its row and content (see
+/// [`reconcile_move`]) instead of waiting to be extracted again. The rest
+/// are written in one transaction.
+pub fn store_quick_metadata(
+    db: &DatabasePool,
+    jobs: &[IndexJob],
+    scanned_roots: &[PathBuf],
+    now: &str,
+) -> Result<QuickMetadata, CortexError> {
+    let unindexed = db.read(|conn| {
+        jobs.iter()
+            .map(|job| Ok(get_file_by_path(conn, &job.path.to_string_lossy())?.is_none()))
+            .collect::<Result<Vec<bool>, CortexError>>()
+    })?;
+
+    let hashes: Vec<Option<String>> = jobs
+        .iter()
+        .zip(unindexed)
+        .map(|(job, unindexed)| unindexed.then(|| hash_for_index(&job.path)).flatten())
+        .collect();
+
+    let (jobs, scanned_roots, now) = (jobs.to_vec(), scanned_roots.to_vec(), now.to_string());
+    db.write(move |conn| store_quick_rows(conn, &jobs, hashes, &scanned_roots, &now))
+}
+
+/// Write [`store_quick_metadata`]'s rows, `hashes` being the jobs' hashes
+fn store_quick_rows(
+    conn: &Connection,
+    jobs: &[IndexJob],
+    hashes: Vec<Option<String>>,
+    scanned_roots: &[PathBuf],
+    now: &str,
+) -> Result<QuickMetadata, CortexError> {
+    let tx = conn.unchecked_transaction()?;
+    let mut metadata = QuickMetadata::default();
+
+    for (job, hash) in jobs.iter().zip(hashes) {
+        let stored = reconcile_move(&tx, &job.path, hash.as_deref(), scanned_roots).and_then(|moved| match moved {
+            Some(_) => Ok(None),
+            None => upsert_file_metadata(&tx, &job.path, job.size, now, hash.as_deref()).map(Some),
+        });
+
+        match stored {
+            Ok(Some(file_id)) => metadata.deferred.push(DeferredFile {
+                file_id,
+                path: job.path.display().to_string(),
+                hash,
+            }),
+            Ok(None) => metadata.moved += 1,
+            Err(e) => metadata
+                .errors
+                .push(format!("Failed to index {}: {}", job.path.display(), e)),
+        }
+    }
+
+    tx.commit()?;
+    Ok(metadata)
+}
+
+/// Extract and store content for a file stored by [`store_file_metadata`]
+/// or [`store_quick_metadata`], re-extracting any content it already has
+///
+/// The file is hashed unless `hash` is given, so unchanged and duplicate
+/// bytes reuse their earlier extraction. `db` is only used to read the row
+/// and the cache and to store the result; hashing and extraction run on the
+/// calling thread, so searches and other writes aren't held up meanwhile.
+/// Returns the extraction's warnings, which are empty when cached content
+/// was reused.
+pub fn extract_deferred_file(
+    db: &DatabasePool,
+    file_id: i64,
+    hash: Option<String>,
+    options: ExtractOptions,
+) -> Result<Vec<ExtractionWarning>, CortexError> {
+    let file = db.read(|conn| get_file_by_id(conn, file_id))?;
+    let path = PathBuf::from(&file.path);
+    let hash = hash.or_else(|| hash_for_index(&path));
+    let content_key = ContentExtractor::content_key(&path);
+
+    let cached = db.read(|conn| cached_extraction(conn, &content_key, hash.as_deref()))?;
+
+    let mut warnings = Vec::new();
+    let extracted = match cached {
+        Some(content) => content,
+        None => {
+            let extracted = ContentExtractor::extract_with_options(&to_long_path(&path), options)?;
+            warnings = extracted.warnings.clone();
+            extracted
+        }
+    };
+
+    db.write(move |conn| {
+        store_hashed_file(conn, &path, file.size as u64, &file.modified_at, hash, &content_key, &extracted)
+    })?;
+    Ok(warnings)
+}
+
+/// Insert a file row, or refresh the existing row for the same path
+fn upsert_file_metadata(
+    conn: &Connection,
+    path: &Path,
+    size: u64,
+    modified_at: &str,
+    hash: Option<&str>,
+) -> Result<i64, CortexError> {
+    let path_str = path.to_string_lossy();
+
+    let file_id = match get_file_by_path(conn, &path_str)? {
+        Some(existing) => {
+            update_file(
+                conn,
+                existing.id,
+                Some(size as i64),
+                Some(modified_at),
+                hash,
+            )?;
+            // Seen on disk again, so no longer deleted
+            if existing.is_deleted {
+                conn.execute("UPDATE files SET is_deleted = 0 WHERE id = ?1", [existing.id])?;
+            }
+            existing.id
+        }
+        None => {
+            let file_type = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("unknown");
+
+            let filename = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+
+            let root_path = path
+                .parent()
+                .and_then(|p| p.to_str())
+                .unwrap_or("");
+
+            let now = Utc::now().to_rfc3339();
+
+            insert_file(
+                conn,
+                &path_str,
+                filename,
+                file_type,
+                size as i64,
+                &now,
+                modified_at,
+                hash,
+                root_path,
+            )?
+        }
+    };
+
+    Ok(file_id)
+}
+
+// Private helper types
+struct IndexingStats {
+    total_files: usize,
+    indexed_files: usize,
+    warnings: WarningSummary,
+}
+
+/// Run the complete indexing pipeline
+async fn run_indexing_pipeline(
+    planned: PlannedRoots,
+    quick: bool,
+    incremental: bool,
+    app: AppHandle,
+    state: Arc<AppState>,
+) -> Result<IndexingStats, CortexError> {
+    let mut all_jobs = Vec::new();
+
+    // Step 1: Scan all directories, each file once even if roots overlap
+    for (path_str, e) in planned.unresolved {
+        let error = format!("Path does not exist: {} ({})", path_str, e);
+        log::warn!("{}", error);
+        state.indexing_errors.write().await.push(error);
+    }
+
+    // Later extraction and embedding of these files keep their profile
+    let profile_roots = {
+        let roots = planned.roots.clone();
+        state.db_pool.write(move |conn| record_profile_roots(conn, &roots))?;
+        let base = state.db_pool.read(crate::db::get_config)?.extraction;
+        ProfileRoots::from_planned(&planned.roots, base)
+    };
+
+    log::info!("Scanning {} directories...", planned.roots.len());
+    let scanned_roots: Vec<PathBuf> = planned.roots.iter().map(|root| root.path.clone()).collect();
+    for root in &planned.roots {
+        let path = &root.path;
+        // Progress is tracked per job below, so the scanner's own count is unused
+        let scanner = root.scanner(FileScanner::new().with_progress_count(false));
+        match scanner.scan_directory(path) {
+            Ok(jobs) => {
+                log::info!("Found {} files in {}", jobs.len(), path.display());
+                all_jobs.extend(jobs);
+            }
+            Err(e) => {
+                let error = format!("Failed to scan {}: {}", path.display(), e);
+                log::warn!("{}", error);
+                state.indexing_errors.write().await.push(error);
+            }
+        }
+
+        // Check for stop signal
+        if *state.stop_indexing.read().await {
+            log::info!("Indexing stopped during scan phase");
+            return Ok(IndexingStats {
+                total_files: all_jobs.len(),
+                indexed_files: 0,
+                warnings: WarningSummary::default(),
+            });
+        }
+    }
+
+    if incremental {
+        let scanned = all_jobs.len();
+        all_jobs = state.db_pool.read(|conn| changed_jobs(conn, all_jobs))?;
+        log::info!("{} of {} files changed since last indexed", all_jobs.len(), scanned);
+    }
+
+    let total_files = all_jobs.len();
+    log::info!("Total files to index: {}", total_files);
+
+    // Update progress
+    {
+        let mut progress = state.indexing_progress.write().await;
+        *progress = Some(crate::indexer::ScanProgress {
+            total_files,
+            current_file: 0,
+        });
+    }
+
+    if quick {
+        return run_quick_indexing(all_jobs, scanned_roots, profile_roots, app, state).await;
+    }
+
+    // Step 2: Extract and index each file
+    let mut indexed_count = 0;
+    let mut warnings = WarningSummary::default();
+
+    for (idx, job) in all_jobs.iter().enumerate() {
+        // Check for stop signal
+        if *state.stop_indexing.read().await {
+            log::info!("Indexing stopped at file {}/{}", idx + 1, total_files);
+            break;
+        }
+
+        let current_file = idx + 1;
+
+        // Update progress
+        {
+            let mut progress = state.indexing_progress.write().await;
+            *progress = Some(crate::indexer::ScanProgress {
+                total_files,
+                current_file,
+            });
+        }
+
+        // Emit progress event every 10 files or on first/last file
+        if idx == 0 || idx == total_files - 1 || idx % 10 == 0 {
+            let _ = app.emit(
+                "indexing:progress",
+                IndexProgressEvent {
+                    total_files,
+                    indexed_files: indexed_count,
+                    current_file: job.path.display().to_string(),
+                    progress_percentage: (current_file as f64 / total_files as f64) * 100.0,
+                },
+            );
+        }
+
+        // A moved file keeps its row; nothing to extract
+        let hash = hash_for_index(&job.path);
+        let moved = {
+            let (path, hash, scanned_roots) = (job.path.clone(), hash.clone(), scanned_roots.clone());
+            state
+                .db_pool
+                .write(move |conn| reconcile_move(conn, &path, hash.as_deref(), &scanned_roots))
+        };
+
+        match moved {
+            Ok(Some(_)) => {
+                indexed_count += 1;
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Move detection failed for {}: {}", job.path.display(), e),
+        }
+
+        // Unchanged or duplicate bytes reuse their earlier extraction
+        let content_key = ContentExtractor::content_key(&job.path);
+        let cached = state
+            .db_pool
+            .read(|conn| cached_extraction(conn, &content_key, hash.as_deref()));
+
+        let cached = cached.unwrap_or_else(|e| {
+            log::warn!("Extraction cache lookup failed for {}: {}", job.path.display(), e);
+            None
+        });
+
+        // Extract content (extended-length form for paths past MAX_PATH)
+        let extracted = match cached {
+            Some(content) => Ok(content),
+            None => ContentExtractor::extract_with_options(
+                &to_long_path(&job.path),
+                profile_roots.extract_options(&job.path),
+            ),
+        };
+
+        let extracted = match extracted {
+            Ok(content) => content,
+            Err(e) => {
+                let error = format!("Failed to extract {}: {}", job.path.display(), e);
+                log::warn!("{}", error);
+                state.indexing_errors.write().await.push(error.clone());
+
+                // Emit error event
+                let _ = app.emit(
+                    "indexing:error",
+                    IndexErrorEvent {
+                        file_path: job.path.display().to_string(),
+                        error: e.to_string(),
+                    },
+                );
+
+                continue;
+            }
+        };
+
+        // Log any extraction warnings
+        for warning in &extracted.warnings {
+            log::debug!("Extraction warning for {}: {}", job.path.display(), warning.message);
+        }
+        warnings.record(&job.path, &extracted.warnings);
+
+        // Only the write goes through the database writer
+        let insert_result = {
+            let (path, size) = (job.path.clone(), job.size);
+            let now = Utc::now().to_rfc3339();
+
+            state
+                .db_pool
+                .write(move |conn| store_hashed_file(conn, &path, size, &now, hash, &content_key, &extracted))
+                .map(|_| ())
+                .map_err(|e| format!("Failed to index {}: {}", job.path.display(), e))
+        };
+
+        // Handle the result with async operations
+        match insert_result {
+            Ok(()) => {
+                indexed_count += 1;
+            }
+            Err(error) => {
+                log::warn!("{}", error);
+                state.indexing_errors.write().await.push(error);
+            }
+        }
+    }
+
+    Ok(IndexingStats {
+        total_files,
+        indexed_files: indexed_count,
+        warnings,
+    })
+}
+
+/// Quick mode: store metadata for every job, then extract content for the
+/// files this run stored
+///
+/// Files whose extraction fails stay listable without their new content.
+async fn run_quick_indexing(
+    jobs: Vec<IndexJob>,
+    scanned_roots: Vec<PathBuf>,
+    profile_roots: ProfileRoots,
+    app: AppHandle,
+    state: Arc<AppState>,
+) -> Result<IndexingStats, CortexError> {
+    let total_files = jobs.len();
+
+    // Pass 1: metadata only
+    let QuickMetadata {
+        deferred,
+        moved,
+        errors: metadata_errors,
+    } = store_quick_metadata(&state.db_pool, &jobs, &scanned_roots, &Utc::now().to_rfc3339())?;
+
+    for error in &metadata_errors {
+        log::warn!("{}", error);
+    }
+    state.indexing_errors.write().await.extend(metadata_errors);
+
+    log::info!(
+        "Quick index stored metadata for {} files ({} moved)",
+        total_files,
+        moved
+    );
+
+    // Pass 2: extract content for the files stored in pass 1
+    let pending_total = deferred.len();
+    let mut indexed_count = moved;
+    let mut warnings = WarningSummary::default();
+
+    for (idx, pending) in deferred.into_iter().enumerate() {
+        if *state.stop_indexing.read().await {
+            log::info!("Deferred extraction stopped at file {}/{}", idx + 1, pending_total);
+            break;
+        }
+
+        let current_file = idx + 1;
+
+        {
+            let mut progress = state.indexing_progress.write().await;
+            *progress = Some(crate::indexer::ScanProgress {
+                total_files: pending_total,
+                current_file,
+            });
+        }
+
+        if idx == 0 || idx == pending_total - 1 || idx % 10 == 0 {
+            let _ = app.emit(
+                "indexing:progress",
+                IndexProgressEvent {
+                    total_files: pending_total,
+                    indexed_files: indexed_count,
+                    current_file: pending.path.clone(),
+                    progress_percentage: (current_file as f64 / pending_total as f64) * 100.0,
+                },
+            );
+        }
+
+        let options = profile_roots.extract_options(Path::new(&pending.path));
+        let result = extract_deferred_file(&state.db_pool, pending.file_id, pending.hash, options);
+
+        match result {
+            Ok(file_warnings) => {
+                indexed_count += 1;
+                warnings.record(Path::new(&pending.path), &file_warnings);
+            }
+            Err(e) => {
+                let error = format!("Failed to extract {}: {}", pending.path, e);
+                log::warn!("{}", error);
+                state.indexing_errors.write().await.push(error);
+
+                let _ = app.emit(
+                    "indexing:error",
+                    IndexErrorEvent {
+                        file_path: pending.path.clone(),
+                        error: e.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    Ok(IndexingStats {
+        total_files,
+        indexed_files: indexed_count,
+        warnings,
+    })
+}
+
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{create_tables, get_file_by_id, get_file_content};
+    use crate::indexer::extractors::METADATA_PAGE_COUNT;
+    use crate::indexer::{Extractor, ExtractorRegistry};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reindex_by_type_only_touches_that_type() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        let txt_path = temp_dir.path().join("notes.txt");
+        let md_path = temp_dir.path().join("readme.md");
+        std::fs::write(&txt_path, "original text").unwrap();
+        std::fs::write(&md_path, "original markdown").unwrap();
+
+        let now = Utc::now().to_rfc3339();
+        let (txt_id, md_id) = {
+            let (txt_path, md_path) = (txt_path.clone(), md_path.clone());
+            db.write(move |conn| {
+                let txt_id = store_indexed_file(
+                    conn,
+                    &txt_path,
+                    13,
+                    &now,
+                    &ContentExtractor::extract(&txt_path).unwrap(),
+                )?;
+                let md_id = store_indexed_file(
+                    conn,
+                    &md_path,
+                    17,
+                    &now,
+                    &ContentExtractor::extract(&md_path).unwrap(),
+                )?;
+                Ok((txt_id, md_id))
+            })
+            .unwrap()
+        };
+
+        // Both files change on disk, but only .txt gets reindexed
+        std::fs::write(&txt_path, "updated text content").unwrap();
+        std::fs::write(&md_path, "updated markdown").unwrap();
+
+        let summary = reindex_files_by_type(&db, "txt").unwrap();
+        assert_eq!(summary.reindexed_files, 1);
+        assert_eq!(summary.removed_files, 0);
+        assert!(summary.errors.is_empty());
+
+        db.read(|conn| {
+            let txt_content = get_file_content(conn, txt_id)?.unwrap();
+            assert_eq!(txt_content.text_content.as_deref(), Some("updated text content"));
+            assert_eq!(get_file_by_id(conn, txt_id)?.size, 20);
+
+            let md_content = get_file_content(conn, md_id)?.unwrap();
+            assert_eq!(md_content.text_content.as_deref(), Some("original markdown"));
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_reindex_by_type_soft_deletes_missing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        let path = temp_dir.path().join("gone.txt");
+        std::fs::write(&path, "soon gone").unwrap();
+
+        let now = Utc::now().to_rfc3339();
+        let extracted = ContentExtractor::extract(&path).unwrap();
+        let file_id = {
+            let (path, now) = (path.clone(), now.clone());
+            db.write(move |conn| store_indexed_file(conn, &path, 9, &now, &extracted)).unwrap()
+        };
+
+        std::fs::remove_file(&path).unwrap();
+
+        let summary = reindex_files_by_type(&db, "txt").unwrap();
+        assert_eq!(summary.reindexed_files, 0);
+        assert_eq!(summary.removed_files, 1);
+
+        assert!(db.read(|conn| get_file_by_id(conn, file_id)).unwrap().is_deleted);
+
+        // Indexing the file again once it's back revives the same row
+        std::fs::write(&path, "back again").unwrap();
+        let extracted = ContentExtractor::extract(&path).unwrap();
+        let revived = db.write(move |conn| store_indexed_file(conn, &path, 10, &now, &extracted));
+        assert_eq!(revived.unwrap(), file_id);
+        assert!(!db.read(|conn| get_file_by_id(conn, file_id)).unwrap().is_deleted);
+    }
+
+    #[test]
+    fn test_indexing_populates_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let first = temp_dir.path().join("first.txt");
+        let copy = temp_dir.path().join("copy.txt");
+        let other = temp_dir.path().join("other.txt");
+        std::fs::write(&first, "duplicate content").unwrap();
+        std::fs::write(&copy, "duplicate content").unwrap();
+        std::fs::write(&other, "different content").unwrap();
+
+        let now = Utc::now().to_rfc3339();
+        let mut hashes = Vec::new();
+        for path in [&first, &copy, &other] {
+            let file_id = store_indexed_file(
+                &conn,
+                path,
+                17,
+                &now,
+                &ContentExtractor::extract(path).unwrap(),
+            ).unwrap();
+            hashes.push(get_file_by_id(&conn, file_id).unwrap().hash.unwrap());
+        }
+
+        assert_eq!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+
+        // Re-indexing changed content refreshes the stored hash
+        std::fs::write(&first, "changed content").unwrap();
+        let file_id = store_indexed_file(
+            &conn,
+            &first,
+            15,
+            &now,
+            &ContentExtractor::extract(&first).unwrap(),
+        ).unwrap();
+        assert_ne!(get_file_by_id(&conn, file_id).unwrap().hash.unwrap(), hashes[0]);
+    }
+
+    #[test]
+    fn test_backfill_hashes_fills_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let present = temp_dir.path().join("present.txt");
+        std::fs::write(&present, "hash me later").unwrap();
+        let present_id = insert_file(&conn, &present.to_string_lossy(), "present.txt", "txt", 13,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "").unwrap();
+        let missing_id = insert_file(&conn, &temp_dir.path().join("missing.txt").to_string_lossy(),
+            "missing.txt", "txt", 1, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "").unwrap();
+
+        let summary = backfill_file_hashes(&conn).unwrap();
+        assert_eq!(summary.hashed_files, 1);
+        assert_eq!(summary.skipped_files, 1);
+
+        assert_eq!(
+            get_file_by_id(&conn, present_id).unwrap().hash,
+            Some(hash_file(&present).unwrap())
+        );
+        assert_eq!(get_file_by_id(&conn, missing_id).unwrap().hash, None);
+
+        // Nothing left to do for rows that already have a hash
+        assert_eq!(backfill_file_hashes(&conn).unwrap().hashed_files, 0);
+    }
+
+    #[test]
+    fn test_deferred_extraction_warnings_are_aggregated() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        // Invalid UTF-8 decodes with replacement characters
+        let mut paths = Vec::new();
+        for i in 0..7 {
+            let path = temp_dir.path().join(format!("latin{}.txt", i));
+            std::fs::write(&path, [b"caf\xe9 menu ".as_slice(), i.to_string().as_bytes()].concat()).unwrap();
+            paths.push(path);
+        }
+        // Not a zip archive, so read as plain text instead
+        let broken = temp_dir.path().join("draft.docx");
+        std::fs::write(&broken, "Quarterly budget draft").unwrap();
+        let clean = temp_dir.path().join("clean.txt");
+        std::fs::write(&clean, "nothing to report").unwrap();
+        paths.extend([broken.clone(), clean]);
+
+        let pending = db
+            .write(move |conn| {
+                for path in &paths {
+                    store_file_metadata(conn, path, 20, &now)?;
+                }
+                list_files_without_content(conn, None)
+            })
+            .unwrap();
+
+        let mut summary = WarningSummary::default();
+        for file in pending {
+            let warnings = extract_deferred_file(&db, file.id, None, ExtractOptions::default()).unwrap();
+            summary.record(Path::new(&file.path), &warnings);
+        }
+
+        assert_eq!(summary.files_with_warnings, 8);
+        let count = |kind| summary.kinds.iter().find(|k| k.kind == kind).unwrap();
+
+        let lossy = count(crate::indexer::WarningKind::LossyDecoding);
+        assert_eq!(lossy.files, 7);
+        assert_eq!(lossy.sample_paths.len(), crate::indexer::WARNING_SAMPLE_PATHS);
+
+        let fallback = count(crate::indexer::WarningKind::TextFallback);
+        assert_eq!(fallback.files, 1);
+        assert_eq!(fallback.sample_paths, vec![broken.display().to_string()]);
+    }
+
+    #[test]
+    fn test_quick_index_listable_before_searchable() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        let path = temp_dir.path().join("deferred.txt");
+        std::fs::write(&path, "lazily extracted platypus notes").unwrap();
+
+        let now = Utc::now().to_rfc3339();
+        let file_id = db.write(move |conn| store_file_metadata(conn, &path, 31, &now)).unwrap();
+
+        // Listable right away, but no content to search yet
+        let pending = db
+            .read(|conn| {
+                let listed = crate::db::list_files(conn, 10, 0)?;
+                assert_eq!(listed.len(), 1);
+                assert_eq!(listed[0].id, file_id);
+                assert!(crate::db::search_files_fts(conn, "platypus", 10)?.is_empty());
+                list_files_without_content(conn, None)
+            })
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+
+        for file in &pending {
+            extract_deferred_file(&db, file.id, None, ExtractOptions::default()).unwrap();
+        }
+
+        db.read(|conn| {
+            let results = crate::db::search_files_fts(conn, "platypus", 10)?;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].file_id, file_id);
+            assert!(list_files_without_content(conn, None)?.is_empty());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_quick_index_extracts_only_this_runs_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let db = DatabasePool::open_in_memory().unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        let edited = root.join("edited.txt");
+        let moved_from = root.join("old-name.txt");
+        let moved_to = root.join("new-name.txt");
+        let fresh = root.join("fresh.txt");
+        let elsewhere = root.join("elsewhere.txt");
+        std::fs::write(&edited, "first draft").unwrap();
+        std::fs::write(&moved_from, "migrating pelican notes").unwrap();
+        std::fs::write(&elsewhere, "left for another run").unwrap();
+
+        let (edited_id, moved_id, elsewhere_id) = {
+            let (paths, now) = ([edited.clone(), moved_from.clone(), elsewhere.clone()], now.clone());
+            db.write(move |conn| {
+                let index = |path: &Path| {
+                    let size = std::fs::metadata(path).unwrap().len();
+                    store_indexed_file(conn, path, size, &now, &ContentExtractor::extract(path).unwrap())
+                };
+                Ok((index(&paths[0])?, index(&paths[1])?, store_file_metadata(conn, &paths[2], 20, &now)?))
+            })
+            .unwrap()
+        };
+
+        std::fs::write(&edited, "second draft").unwrap();
+        std::fs::rename(&moved_from, &moved_to).unwrap();
+        std::fs::write(&fresh, "brand new heron notes").unwrap();
+
+        let jobs: Vec<IndexJob> = [&edited, &moved_to, &fresh]
+            .iter()
+            .map(|path| {
+                let size = std::fs::metadata(path).unwrap().len();
+                IndexJob::new(path.to_path_buf(), size, std::time::SystemTime::now())
+            })
+            .collect();
+        let metadata = store_quick_metadata(&db, &jobs, &[root.to_path_buf()], &now).unwrap();
+        assert!(metadata.errors.is_empty());
+        assert_eq!(metadata.moved, 1);
+        assert_eq!(metadata.deferred.len(), 2);
+
+        for pending in metadata.deferred {
+            extract_deferred_file(&db, pending.file_id, pending.hash, ExtractOptions::default()).unwrap();
+        }
+
+        db.read(|conn| {
+            // The edited file is re-extracted though it already had content
+            let content = get_file_content(conn, edited_id)?.unwrap();
+            assert_eq!(content.text_content.as_deref(), Some("second draft"));
+
+            // The moved file kept its row and content
+            let moved = get_file_by_id(conn, moved_id)?;
+            assert_eq!(moved.path, moved_to.to_string_lossy());
+            let content = get_file_content(conn, moved_id)?.unwrap();
+            assert_eq!(content.text_content.as_deref(), Some("migrating pelican notes"));
+
+            let fresh = get_file_by_path(conn, &fresh.to_string_lossy())?.unwrap();
+            assert!(fresh.hash.is_some());
+            assert!(get_file_content(conn, fresh.id)?.is_some());
+
+            // A content-less file from outside this run is left alone
+            assert!(get_file_content(conn, elsewhere_id)?.is_none());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_unchanged_file_reuses_extraction() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let path = temp_dir.path().join("report.txt");
+        let copy = temp_dir.path().join("report-copy.txt");
+        std::fs::write(&path, "expensive to parse").unwrap();
+        std::fs::write(&copy, "expensive to parse").unwrap();
+
+        let extractions = std::cell::Cell::new(0);
+        let counting = |p: &Path| {
+            extractions.set(extractions.get() + 1);
+            ContentExtractor::extract(p)
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let file_id = index_file_cached(&conn, &path, 18, &now, &[], counting).unwrap();
+        assert_eq!(extractions.get(), 1);
+
+        // Re-indexing the unchanged file and indexing an identical copy both hit the cache
+        assert_eq!(index_file_cached(&conn, &path, 18, &now, &[], counting).unwrap(), file_id);
+        index_file_cached(&conn, &copy, 18, &now, &[], counting).unwrap();
+        assert_eq!(extractions.get(), 1);
+
+        let content = get_file_content(&conn, file_id).unwrap().unwrap();
+        assert_eq!(content.text_content.as_deref(), Some("expensive to parse"));
+
+        // Changed bytes are extracted again
+        std::fs::write(&path, "revised and expensive to parse").unwrap();
+        index_file_cached(&conn, &path, 30, &now, &[], counting).unwrap();
+        assert_eq!(extractions.get(), 2);
+        let content = get_file_content(&conn, file_id).unwrap().unwrap();
+        assert_eq!(content.text_content.as_deref(), Some("revised and expensive to parse"));
+    }
+
+    struct RecipeExtractor;
+
+    impl Extractor for RecipeExtractor {
+        fn name(&self) -> &str {
+            "recipe"
+        }
+
+        fn label(&self) -> &str {
+            "Recipe cards"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["recipe"]
+        }
+
+        fn extract(&self, path: &Path) -> crate::error::Result<ExtractedContent> {
+            let text = std::fs::read_to_string(path)?;
+            let mut content = ExtractedContent::new(text.replace(';', "\n"));
+            content.summary = Some("Recipe card".to_string());
+            Ok(content)
+        }
+    }
+
+    #[test]
+    fn test_new_extractor_invalidates_cached_extraction() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let path = temp_dir.path().join("pancakes.recipe");
+        std::fs::write(&path, "flour;milk;eggs").unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        let mut registry = ExtractorRegistry::with_builtins();
+        let index = |registry: &ExtractorRegistry| {
+            index_file_keyed(&conn, &path, 15, &now, &[], &registry.content_key(&path), |p| {
+                registry.extract_with_options(p, ExtractOptions::default())
+            })
+            .unwrap()
+        };
+
+        // Indexed as plain text before anything handles the extension
+        let file_id = index(&registry);
+        let content = get_file_content(&conn, file_id).unwrap().unwrap();
+        assert_eq!(content.summary.as_deref(), Some("flour;milk;eggs"));
+
+        // The same bytes are extracted again once an extractor claims them
+        registry.register(RecipeExtractor);
+        index(&registry);
+        let content = get_file_content(&conn, file_id).unwrap().unwrap();
+        assert_eq!(content.text_content.as_deref(), Some("flour\nmilk\neggs"));
+        assert_eq!(content.summary.as_deref(), Some("Recipe card"));
+
+        // ...and that extraction is cached in turn
+        let key = registry.content_key(&path);
+        index_file_keyed(&conn, &path, 15, &now, &[], &key, |_| panic!("should be cached")).unwrap();
+    }
+
+    #[test]
+    fn test_indexed_links_searchable_by_domain() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let notes = temp_dir.path().join("notes.md");
+        let refs = temp_dir.path().join("refs.txt");
+        let copy = temp_dir.path().join("refs-copy.txt");
+        std::fs::write(&notes, "Based on [the paper](https://arxiv.org/abs/2301.00001).").unwrap();
+        std::fs::write(&refs, "Mirror: https://export.arxiv.org/abs/2301.00001, docs at https://docs.rs/").unwrap();
+        std::fs::copy(&refs, &copy).unwrap();
+
+        let notes_id = index_path(&conn, &notes);
+        let refs_id = index_path(&conn, &refs);
+
+        // The copy and the unchanged re-index reuse cached content, links included
+        let now = Utc::now().to_rfc3339();
+        let size = std::fs::metadata(&refs).unwrap().len();
+        let copy_id = index_file_cached(&conn, &copy, size, &now, &[], |_| panic!("should be cached")).unwrap();
+        index_file_cached(&conn, &refs, size, &now, &[], |_| panic!("should be cached")).unwrap();
+
+        let found = crate::db::search_by_link_domain(&conn, "arxiv.org", 10).unwrap();
+        let ids: Vec<i64> = found.iter().map(|f| f.file.id).collect();
+        assert_eq!(ids, vec![notes_id, copy_id, refs_id]);
+        assert_eq!(found[0].urls, vec!["https://arxiv.org/abs/2301.00001"]);
+        assert_eq!(found[2].urls, vec!["https://export.arxiv.org/abs/2301.00001"]);
+
+        let found = crate::db::search_by_link_domain(&conn, "docs.rs", 10).unwrap();
+        assert_eq!(found.len(), 2);
+
+        // Links removed from a file stop matching once it is re-indexed
+        std::fs::write(&refs, "No links any more").unwrap();
+        index_path(&conn, &refs);
+        let found = crate::db::search_by_link_domain(&conn, "docs.rs", 10).unwrap();
+        assert_eq!(found.iter().map(|f| f.file.id).collect::<Vec<_>>(), vec![copy_id]);
+    }
+
+    #[test]
+    fn test_document_title_preferred_for_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        let paper = temp_dir.path().join("paper.pdf");
+        std::fs::write(&paper, "pdf bytes").unwrap();
+        let mut extracted = ExtractedContent::new("arXiv:1706.03762v7 [cs.CL] 2 Aug 2023".to_string());
+        extracted.metadata.insert(METADATA_TITLE.to_string(), "Attention Is All You Need".to_string());
+        extracted.metadata.insert(METADATA_PAGE_COUNT.to_string(), "15".to_string());
+        let paper_id = store_indexed_file(&conn, &paper, 9, &now, &extracted).unwrap();
+
+        let content = get_file_content(&conn, paper_id).unwrap().unwrap();
+        assert_eq!(content.summary.as_deref(), Some("Attention Is All You Need"));
+        // The properties themselves are stored alongside the content
+        assert_eq!(content.metadata, extracted.metadata);
+
+        // and reused with it for identical bytes
+        let hash = hash_file(&paper).unwrap();
+        let cached = cached_extraction(&conn, &ContentExtractor::content_key(&paper), Some(&hash)).unwrap();
+        assert_eq!(cached.unwrap().metadata, extracted.metadata);
+
+        // Without a title the first line is kept
+        let notes = temp_dir.path().join("notes.txt");
+        std::fs::write(&notes, "Meeting notes\nsecond line").unwrap();
+        let notes_id = index_path(&conn, &notes);
+        let content = get_file_content(&conn, notes_id).unwrap().unwrap();
+        assert_eq!(content.summary.as_deref(), Some("Meeting notes"));
+    }
+
+    #[test]
+    fn test_moved_file_keeps_its_row() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let old_path = temp_dir.path().join("inbox").join("report.txt");
+        let new_path = temp_dir.path().join("archive").join("2026").join("q3-report.md");
+        std::fs::create_dir_all(old_path.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(new_path.parent().unwrap()).unwrap();
+        std::fs::write(&old_path, "quarterly figures").unwrap();
+
+        let extractions = std::cell::Cell::new(0);
+        let counting = |p: &Path| {
+            extractions.set(extractions.get() + 1);
+            ContentExtractor::extract(p)
+        };
+
+        let roots = vec![temp_dir.path().to_path_buf()];
+        let now = Utc::now().to_rfc3339();
+        let file_id = index_file_cached(&conn, &old_path, 17, &now, &roots, counting).unwrap();
+        crate::db::add_tags_to_files(&conn, &[file_id], &["finance".to_string()]).unwrap();
+        crate::db::add_bookmark(&conn, file_id).unwrap();
+
+        // Moved across the tree, then indexed at its new path
+        std::fs::rename(&old_path, &new_path).unwrap();
+        assert_eq!(index_file_cached(&conn, &new_path, 17, &now, &roots, counting).unwrap(), file_id);
+        assert_eq!(extractions.get(), 1);
+
+        let file = get_file_by_id(&conn, file_id).unwrap();
+        assert_eq!(file.path, new_path.to_string_lossy());
+        assert_eq!(file.filename, "q3-report.md");
+        assert_eq!(file.file_type, "md");
+        assert!(get_file_by_path(&conn, &old_path.to_string_lossy()).unwrap().is_none());
+        assert_eq!(count_rows(&conn, "files"), 1);
+
+        assert_eq!(crate::db::get_file_tags(&conn, file_id).unwrap(), vec!["finance"]);
+        assert_eq!(crate::db::list_bookmarks(&conn).unwrap()[0].id, file_id);
+        let hits = crate::db::search_files_fts(&conn, "q3", 10).unwrap();
+        assert_eq!(hits.iter().map(|h| h.file_id).collect::<Vec<_>>(), vec![file_id]);
+
+        // A move already seen as a deletion restores the file
+        mark_file_deleted(&conn, file_id).unwrap();
+        std::fs::rename(&new_path, &old_path).unwrap();
+        assert_eq!(index_file_cached(&conn, &old_path, 17, &now, &roots, counting).unwrap(), file_id);
+        assert!(!get_file_by_id(&conn, file_id).unwrap().is_deleted);
+        assert_eq!(extractions.get(), 1);
+
+        // A copy is a duplicate, not a move
+        let copy = temp_dir.path().join("copy.txt");
+        std::fs::copy(&old_path, &copy).unwrap();
+        assert_ne!(index_file_cached(&conn, &copy, 17, &now, &roots, counting).unwrap(), file_id);
+        assert!(!get_file_by_id(&conn, file_id).unwrap().is_deleted);
+    }
+
+    #[test]
+    fn test_move_detection_respects_scanned_roots_and_language() {
+        use rusqlite::OptionalExtension;
+
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        let inbox = temp_dir.path().join("inbox");
+        let drive = temp_dir.path().join("drive");
+        std::fs::create_dir_all(&inbox).unwrap();
+        std::fs::create_dir_all(&drive).unwrap();
+
+        // A file missing outside the scanned roots may be on an unmounted
+        // drive, so the same bytes in the inbox are a new file
+        let offline = drive.join("spec.txt");
+        std::fs::write(&offline, "shared specification").unwrap();
+        let offline_id = index_file_cached(&conn, &offline, 20, &now, &[], ContentExtractor::extract).unwrap();
+        std::fs::remove_file(&offline).unwrap();
+
+        let copy = inbox.join("spec.txt");
+        std::fs::write(&copy, "shared specification").unwrap();
+        let roots = vec![inbox.clone()];
+        let copy_id = index_file_cached(&conn, &copy, 20, &now, &roots, ContentExtractor::extract).unwrap();
+        assert_ne!(copy_id, offline_id);
+        assert_eq!(get_file_by_id(&conn, offline_id).unwrap().path, offline.to_string_lossy());
+
+        // Renaming to a source extension splits out the comments
+        let comments = |file_id: i64| -> Option<String> {
+            conn.query_row("SELECT comments FROM code_fts WHERE rowid = ?1", [file_id], |row| row.get(0))
+                .optional()
+                .unwrap()
+        };
+        let notes = inbox.join("notes.txt");
+        let source = "fn main() {}\n// TODO wire up the parser\n";
+        std::fs::write(&notes, source).unwrap();
+        let notes_id = index_file_cached(&conn, &notes, 40, &now, &roots, ContentExtractor::extract).unwrap();
+        assert_eq!(comments(notes_id), None);
+
+        let renamed = inbox.join("main.rs");
+        std::fs::rename(&notes, &renamed).unwrap();
+        assert_eq!(index_file_cached(&conn, &renamed, 40, &now, &roots, |_| panic!("moved")).unwrap(), notes_id);
+        assert!(comments(notes_id).unwrap().contains("wire up the parser"));
+
+        // ...and back to plain text drops them again
+        std::fs::rename(&renamed, &notes).unwrap();
+        assert_eq!(index_file_cached(&conn, &notes, 40, &now, &roots, |_| panic!("moved")).unwrap(), notes_id);
+        assert_eq!(comments(notes_id), None);
+    }
+
+    fn planned_jobs(planned: &PlannedRoots) -> Vec<IndexJob> {
+        planned
+            .roots
+            .iter()
+            .flat_map(|root| {
+                let scanner = root.scanner(FileScanner::new().with_progress_count(false));
+                scanner.scan_directory(&root.path).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_auto_index_runs_incrementally_over_configured_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let notes = temp_dir.path().join("notes");
+        let code = temp_dir.path().join("code");
+        let ignored = temp_dir.path().join("ignored");
+        for dir in [&notes, &code, &ignored] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(notes.join("todo.txt"), "buy milk").unwrap();
+        std::fs::write(code.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(ignored.join("other.txt"), "not configured").unwrap();
+
+        let mut config = crate::db::CortexConfig {
+            auto_index_roots: [&notes, &code]
+                .iter()
+                .map(|dir| IndexTarget { path: dir.to_string_lossy().into_owned(), profile: None })
+                .collect(),
+            ..Default::default()
+        };
+        crate::db::set_config(&conn, &config).unwrap();
+        assert!(auto_index_plan(&conn).unwrap().is_none());
+
+        config.auto_index_on_startup = true;
+        crate::db::set_config(&conn, &config).unwrap();
+        let planned = auto_index_plan(&conn).unwrap().unwrap();
+        let roots: Vec<PathBuf> = planned.roots.iter().map(|root| root.path.clone()).collect();
+        let expected: Vec<PathBuf> = [&notes, &code]
+            .iter()
+            .map(|dir| crate::paths::normalize_root(&dir.to_string_lossy()).unwrap())
+            .collect();
+        assert_eq!(roots, expected);
+
+        // The first run indexes everything under the configured roots
+        let jobs = changed_jobs(&conn, planned_jobs(&planned)).unwrap();
+        assert_eq!(jobs.len(), 2);
+        let now = Utc::now().to_rfc3339();
+        for job in &jobs {
+            index_file_cached(&conn, &job.path, job.size, &now, &[], ContentExtractor::extract).unwrap();
+        }
+
+        // Later runs only pick up what changed
+        let planned = auto_index_plan(&conn).unwrap().unwrap();
+        assert!(changed_jobs(&conn, planned_jobs(&planned)).unwrap().is_empty());
+
+        std::fs::write(notes.join("todo.txt"), "buy milk and eggs").unwrap();
+        let changed = changed_jobs(&conn, planned_jobs(&planned)).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].path.ends_with("todo.txt"));
+    }
+
+    fn count_rows(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn index_path(conn: &Connection, path: &Path) -> i64 {
+        let now = Utc::now().to_rfc3339();
+        let size = std::fs::metadata(path).unwrap().len();
+        store_indexed_file(conn, path, size, &now, &ContentExtractor::extract(path).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_identical_files_share_content_and_embedding() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let original = temp_dir.path().join("report.txt");
+        let copy_dir = temp_dir.path().join("backup");
+        std::fs::create_dir(&copy_dir).unwrap();
+        let copy = copy_dir.join("report-copy.txt");
+        std::fs::write(&original, "quarterly numbers for the echidna project").unwrap();
+        std::fs::write(&copy, "quarterly numbers for the echidna project").unwrap();
+
+        let original_id = index_path(&conn, &original);
+        let copy_id = index_path(&conn, &copy);
+
+        // Distinct metadata rows, one content record
+        assert_ne!(original_id, copy_id);
+        assert_eq!(get_file_by_id(&conn, copy_id).unwrap().filename, "report-copy.txt");
+        assert_eq!(count_rows(&conn, "file_content"), 1);
+
+        // Search finds the content once, listing both paths
+        let results = crate::db::search_files_fts_filtered(&conn, "echidna", 10, false).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, original_id);
+        assert_eq!(results[0].duplicate_paths, vec![copy.to_string_lossy().into_owned()]);
+
+        let copy_content = get_file_content(&conn, copy_id).unwrap().unwrap();
+        assert_eq!(copy_content.file_id, copy_id);
+        assert_eq!(
+            copy_content.text_content.as_deref(),
+            Some("quarterly numbers for the echidna project")
+        );
+
+        // Only the owner needs an embedding, and the copy reads it
+        let pending = crate::db::get_files_without_embeddings(&conn, 10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, original_id);
+
+        let vector = vec![0.5f32; crate::db::EMBEDDING_DIM];
+        crate::db::upsert_embedding(&conn, original_id, &vector, "test-model").unwrap();
+        crate::db::upsert_embedding(&conn, copy_id, &vector, "test-model").unwrap();
+        assert_eq!(count_rows(&conn, "file_embeddings"), 1);
+        assert_eq!(
+            crate::db::get_embedding(&conn, copy_id).unwrap().unwrap().embedding,
+            vector
+        );
+    }
+
+    #[test]
+    fn test_duplicate_takes_over_when_owner_changes_or_is_deleted() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let a = temp_dir.path().join("a.txt");
+        let b = temp_dir.path().join("b.txt");
+        let c = temp_dir.path().join("c.txt");
+        for path in [&a, &b, &c] {
+            std::fs::write(path, "shared koala content").unwrap();
+        }
+
+        let a_id = index_path(&conn, &a);
+        let b_id = index_path(&conn, &b);
+        let c_id = index_path(&conn, &c);
+        assert_eq!(count_rows(&conn, "file_content"), 1);
+
+        // Owner's content changes: the duplicates keep the old content
+        std::fs::write(&a, "completely new wombat content").unwrap();
+        index_path(&conn, &a);
+        assert_eq!(count_rows(&conn, "file_content"), 2);
+        assert_eq!(
+            get_file_content(&conn, c_id).unwrap().unwrap().text_content.as_deref(),
+            Some("shared koala content")
+        );
+
+        // New owner is soft-deleted: the remaining duplicate stays searchable
+        mark_file_deleted(&conn, b_id).unwrap();
+        let results = crate::db::search_files_fts(&conn, "koala", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, c_id);
+
+        let results = crate::db::search_files_fts(&conn, "wombat", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, a_id);
+    }
+
+    #[test]
+    fn test_promoted_duplicate_keeps_code_and_comments_searchable() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let a = temp_dir.path().join("a.rs");
+        let b = temp_dir.path().join("b.rs");
+        for path in [&a, &b] {
+            std::fs::write(path, "// platypus helper\nfn helper() {}\n").unwrap();
+        }
+        index_path(&conn, &a);
+        let b_id = index_path(&conn, &b);
+
+        // The owner changes, so the duplicate takes over the old content
+        std::fs::write(&a, "fn unrelated() {}\n").unwrap();
+        index_path(&conn, &a);
+
+        let comments: String = conn
+            .query_row("SELECT comments FROM code_fts WHERE rowid = ?1", [b_id], |row| row.get(0))
+            .unwrap();
+        assert!(comments.contains("platypus"));
+    }
+
+    #[test]
+    fn test_missing_content_and_embeddings_in_mixed_state() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let add = |name: &str, modified: &str| {
+            insert_file(&conn, &format!("/test/{}", name), name, "txt", 10, modified, modified,
+                None, "/test").unwrap()
+        };
+
+        let metadata_only = add("metadata.txt", "2025-11-01T00:00:00Z");
+        let with_content = add("content.txt", "2025-11-02T00:00:00Z");
+        let embedded = add("embedded.txt", "2025-11-03T00:00:00Z");
+        let deleted = add("deleted.txt", "2025-11-04T00:00:00Z");
+        mark_file_deleted(&conn, deleted).unwrap();
+
+        upsert_file_content(&conn, with_content, Some("extracted text"), None).unwrap();
+        upsert_file_content(&conn, embedded, Some("embedded text"), None).unwrap();
+        let vector = vec![0.5f32; crate::db::EMBEDDING_DIM];
+        crate::db::upsert_embedding(&conn, embedded, &vector, "test-model").unwrap();
+
+        let ids = |files: Vec<File>| files.into_iter().map(|f| f.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(list_files_without_content(&conn, None).unwrap()), vec![metadata_only]);
+
+        // Newest first, and the limit applies
+        assert_eq!(
+            ids(get_files_without_embeddings(&conn, 10).unwrap()),
+            vec![with_content, metadata_only]
+        );
+        assert_eq!(ids(get_files_without_embeddings(&conn, 1).unwrap()), vec![with_content]);
+
+        let another = add("another.txt", "2025-11-05T00:00:00Z");
+        assert_eq!(
+            ids(list_files_without_content(&conn, Some(1)).unwrap()),
+            vec![metadata_only]
+        );
+        assert_eq!(list_files_without_content(&conn, None).unwrap().len(), 2);
+        assert!(ids(list_files_without_content(&conn, None).unwrap()).contains(&another));
+    }
+
+    #[test]
+    fn test_newly_eligible_indexes_only_skipped_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        std::fs::write(temp_dir.path().join("small.txt"), "tiny note").unwrap();
+        std::fs::write(temp_dir.path().join("large.txt"), "a much larger report ".repeat(20))
+            .unwrap();
+        let paths = vec![temp_dir.path().to_string_lossy().to_string()];
+
+        // The old limit skips the large file
+        let strict = FileScanner::new().with_progress_count(false).with_max_file_size(100);
+        let summary = index_new_files(&db, &strict, &paths).unwrap();
+        assert_eq!(summary.indexed_files, 1);
+        let small_path = temp_dir.path().join("small.txt").to_string_lossy().to_string();
+        let small = db.read(|conn| get_file_by_path(conn, &small_path)).unwrap().unwrap();
+
+        // Raising it picks up only the large file
+        let relaxed = FileScanner::new().with_progress_count(false).with_max_file_size(10_000);
+        let summary = index_new_files(&db, &relaxed, &paths).unwrap();
+        assert_eq!(summary.scanned_files, 2);
+        assert_eq!(summary.indexed_files, 1);
+        assert!(summary.errors.is_empty());
+
+        db.read(|conn| {
+            let large = get_file_by_path(conn, &temp_dir.path().join("large.txt").to_string_lossy())?;
+            assert!(large.is_some());
+            let small_after = get_file_by_path(conn, &small.path)?.unwrap();
+            assert_eq!(small_after.last_indexed, small.last_indexed);
+            assert_eq!(small_after.modified_at, small.modified_at);
+            Ok(())
+        })
+        .unwrap();
+
+        // Nothing is left to index
+        let summary = index_new_files(&db, &relaxed, &paths).unwrap();
+        assert_eq!(summary.indexed_files, 0);
+    }
+
+    #[test]
+    fn test_overlapping_roots_index_each_file_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        let parent = temp_dir.path().join("docs");
+        let child = parent.join("notes");
+        std::fs::create_dir_all(&child).unwrap();
+        std::fs::write(parent.join("index.txt"), "table of contents").unwrap();
+        std::fs::write(child.join("meeting.txt"), "meeting notes").unwrap();
+
+        // The child comes first and the parent is spelled two more ways
+        let paths = vec![
+            child.to_string_lossy().to_string(),
+            format!("{}/", parent.to_string_lossy()),
+            parent.join(".").to_string_lossy().to_string(),
+        ];
+
+        let scanner = FileScanner::new().with_progress_count(false);
+        let summary = index_new_files(&db, &scanner, &paths).unwrap();
+        assert_eq!(summary.scanned_files, 2);
+        assert_eq!(summary.indexed_files, 2);
+        assert!(summary.errors.is_empty());
+        db.read(|conn| {
+            assert_eq!(count_rows(conn, "files"), 2);
+
+            let meeting = std::fs::canonicalize(child.join("meeting.txt")).unwrap();
+            assert!(get_file_by_path(conn, &meeting.to_string_lossy())?.is_some());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_paths_stored_before_normalization_are_canonicalized() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = DatabasePool::open_in_memory().unwrap();
+
+        let docs = temp_dir.path().join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("a.txt"), "alpha").unwrap();
+        std::fs::write(docs.join("b.txt"), "beta").unwrap();
+        let canonical_docs = std::fs::canonicalize(&docs).unwrap();
+        fn as_str(p: &Path) -> String {
+            p.to_string_lossy().into_owned()
+        }
+
+        // As an older version stored them: scanned from `docs/.`, with `b`
+        // indexed again since under its canonical path, and a file that has
+        // since gone
+        {
+            let (docs, canonical_docs) = (docs.clone(), canonical_docs.clone());
+            db.write(move |conn| {
+                let dotted = docs.join(".");
+                let add = |path: &Path, last_indexed: &str| {
+                    let id = insert_file(conn, &as_str(path), "x.txt", "txt", 5, "2025-01-01T00:00:00Z",
+                        "2025-01-01T00:00:00Z", None, &as_str(&dotted)).unwrap();
+                    conn.execute("UPDATE files SET last_indexed = ?2 WHERE id = ?1", rusqlite::params![id, last_indexed])
+                        .unwrap();
+                    id
+                };
+                add(&dotted.join("a.txt"), "2025-01-01T00:00:00Z");
+                add(&dotted.join("b.txt"), "2025-01-01T00:00:00Z");
+                add(&canonical_docs.join("b.txt"), "2025-06-01T00:00:00Z");
+                add(&docs.join("gone").join("..").join("c.txt"), "2025-01-01T00:00:00Z");
+
+                conn.execute_batch("PRAGMA user_version = 16")?;
+                crate::db::run_migrations(conn)
+            })
+            .unwrap();
+        }
+
+        // A scan of the normalized root finds both files already indexed
+        let scanner = FileScanner::new().with_progress_count(false);
+        let summary = index_new_files(&db, &scanner, &[as_str(&docs)]).unwrap();
+        assert_eq!(summary.scanned_files, 2);
+        assert_eq!(summary.indexed_files, 0);
+
+        db.read(|conn| {
+            assert_eq!(count_rows(conn, "files"), 3);
+            let a = get_file_by_path(conn, &as_str(&canonical_docs.join("a.txt")))?.unwrap();
+            assert_eq!(a.root_path, as_str(&canonical_docs));
+            let b = get_file_by_path(conn, &as_str(&canonical_docs.join("b.txt")))?.unwrap();
+            assert_eq!(b.last_indexed, "2025-06-01T00:00:00Z");
+            assert!(get_file_by_path(conn, &as_str(&docs.join("c.txt")))?.is_some());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_preview_markdown_extraction() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("guide.md");
+        std::fs::write(&path, "# Setup Guide\n\nInstall with **cargo** and [read more](https://example.com).\n").unwrap();
+
+        let preview = preview_file_extraction(&path).unwrap();
+        assert_eq!(preview.extractor, "markdown");
+        assert_eq!(preview.file_type, "md");
+        assert!(preview.text.contains("Setup Guide"));
+        assert!(preview.text.contains("Install with cargo and read more"));
+        assert!(!preview.text.contains('#') && !preview.text.contains("**") && !preview.text.contains("https"));
+        assert_eq!(preview.word_count, preview.text.split_whitespace().count());
+        assert!(preview.warnings.is_empty());
+
+        assert!(preview_file_extraction(&temp_dir.path().join("missing.md")).is_err());
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, [0x89, b'P', b'N', b'G']).unwrap();
+        assert!(matches!(
+            preview_file_extraction(&image),
+            Err(CortexError::InvalidPath { .. })
+        ));
+    }
+}
@@ -1,4 +1,8 @@
 pub mod ai_commands;
+pub mod bookmarks;
+pub mod collections;
 pub mod export;
 pub mod indexing;
 pub mod search;
+pub mod tags;
+pub mod watch;
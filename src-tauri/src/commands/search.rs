@@ -1,11 +1,33 @@
-use crate::db::{get_file_by_id, get_file_content, search_files_fts, SearchResult};
+use crate::db::{
+    attach_duplicate_paths, choose_snippet, get_file_by_id, get_file_content, group_by_collection,
+    list_files_filtered, list_largest_files, mark_file_deleted, search_files_fts_filtered,
+    CollectionGroup, ContentStats, DatabaseHealth, DatabaseRecovery, File, FileTreeNode,
+    SearchResult,
+};
 use crate::error::CortexError;
 use crate::state::AppState;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use std::time::Instant;
 use tauri::State;
 
+/// Candidate lists longer than this are bound through a temp table instead
+/// of an `IN (...)` list, staying clear of SQLite's parameter limit
+const REFINE_IN_LIST_LIMIT: usize = 500;
+
+/// FTS matches checked for case-exact terms before paging a case-sensitive
+/// search
+const CASE_SENSITIVE_CANDIDATES: usize = 5000;
+
+/// Top FTS matches reranked for a search capped per file type
+const DIVERSITY_CANDIDATES: usize = 1000;
+
+/// Top FTS matches checked on disk before paging a search with
+/// `verify_exists` set
+const VERIFY_CANDIDATES: usize = 1000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchFilters {
     pub file_type: Option<String>,
@@ -13,6 +35,62 @@ pub struct SearchFilters {
     pub max_size: Option<i64>,
     pub date_from: Option<String>,
     pub date_to: Option<String>,
+    /// Only return files carrying these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Require every tag in `tags` rather than any of them
+    #[serde(default)]
+    pub match_all_tags: bool,
+    /// Match the query against source files' code or comments alone
+    #[serde(default)]
+    pub search_scope: SearchScope,
+}
+
+/// Which part of a file a search matches against
+///
+/// The code and comment scopes only find source files in a language the
+/// indexer can split (Rust, C-family, JavaScript/TypeScript, Python), and
+/// only once they've been indexed since that split was added.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    /// Filename and full content
+    #[default]
+    All,
+    /// Source code with its comments removed
+    CodeOnly,
+    /// Only the comments in source code
+    CommentsOnly,
+}
+
+impl SearchScope {
+    /// Clause restricting matches to this scope's `code_fts` column
+    fn clause(self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::CodeOnly => Some("f.id IN (SELECT rowid FROM code_fts WHERE code_fts.code MATCH ?)"),
+            Self::CommentsOnly => {
+                Some("f.id IN (SELECT rowid FROM code_fts WHERE code_fts.comments MATCH ?)")
+            }
+        }
+    }
+}
+
+/// Paging and matching options for [`search_files`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// At most 1000; 50 if unset
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    /// Return soft-deleted files too, with `is_deleted = true`
+    pub include_deleted: bool,
+    /// Check each result on disk, soft-deleting files that have disappeared
+    pub verify_exists: bool,
+    /// Only return files containing the query terms with their exact case
+    pub case_sensitive: bool,
+    /// At most this many results of each file type
+    pub max_per_type: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,25 +98,54 @@ pub struct SearchResults {
     pub results: Vec<SearchResult>,
     pub total: usize,
     pub query_time_ms: u64,
+    /// Caveats about the results, e.g. that only part of the matches were
+    /// checked
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// A matched span in a file's full content, in UTF-16 code units so it
+/// indexes the content as a JavaScript string directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightRange {
+    pub start: usize,
+    /// Exclusive
+    pub end: usize,
 }
 
 /// Search files using FTS5 with optional filters and pagination
+///
+/// Soft-deleted files are excluded unless `options.include_deleted` is set,
+/// in which case they are returned with `is_deleted = true`.
+///
+/// With `verify_exists` set, each result is checked on disk and files that
+/// have disappeared since indexing are soft-deleted. This happens before
+/// paging, so pages stay full, and covers the best [`VERIFY_CANDIDATES`]
+/// matches. Off by default since it costs a filesystem call per match.
+///
+/// With `case_sensitive` set, only files matching the query with each term
+/// in its exact case are returned, e.g. `HTTPServer` but not `httpserver`.
+/// Only the best [`CASE_SENSITIVE_CANDIDATES`] FTS matches are checked; the
+/// response carries a warning when there were more.
+///
+/// With `max_per_type` set, at most that many results of each file type are
+/// returned, taken in turn from each type (see [`diversify_by_type`]), so one
+/// dominant type can't crowd out the rest.
 #[tauri::command]
 pub async fn search_files(
     query: String,
     filters: Option<SearchFilters>,
-    limit: Option<usize>,
-    offset: Option<usize>,
+    options: Option<SearchOptions>,
     state: State<'_, AppState>,
 ) -> Result<SearchResults, String> {
     let start_time = Instant::now();
+    let options = options.unwrap_or_default();
 
     log::info!(
-        "Searching for: '{}' with filters: {:?}, limit: {:?}, offset: {:?}",
+        "Searching for: '{}' with filters: {:?}, options: {:?}",
         query,
         filters,
-        limit,
-        offset
+        options
     );
 
     // Validate query
@@ -53,17 +160,80 @@ pub async fn search_files(
     let db = state.db.lock().unwrap();
     let conn = db.get_connection();
 
-    let search_limit = limit.unwrap_or(50).min(1000); // Max 1000 results
-    let search_offset = offset.unwrap_or(0);
+    let search_limit = options.limit.unwrap_or(50).min(1000); // Max 1000 results
+    let search_offset = options.offset.unwrap_or(0);
+    let include_deleted = options.include_deleted;
+    let case_sensitive = options.case_sensitive;
+    let max_per_type = options.max_per_type.filter(|&max| max > 0);
+    let verify_exists = options.verify_exists;
+    let mut warnings = Vec::new();
+
+    // Case-exact and verified matches are subsets of the FTS matches and
+    // diversified ones a reordering of the best of them, so all are paged
+    // afterwards
+    let post_filtered = case_sensitive || max_per_type.is_some() || verify_exists;
+    let (fetch_limit, fetch_offset) = if case_sensitive {
+        (CASE_SENSITIVE_CANDIDATES, 0)
+    } else if max_per_type.is_some() {
+        (DIVERSITY_CANDIDATES, 0)
+    } else if verify_exists {
+        (VERIFY_CANDIDATES, 0)
+    } else {
+        (search_limit, search_offset)
+    };
 
     // If we have filters or offset, use custom query; otherwise use simple FTS search
-    let results = if filters.is_some() || search_offset > 0 {
-        perform_filtered_search(conn, &query, filters, search_limit, search_offset)
+    let results = if filters.is_some() || fetch_offset > 0 {
+        perform_filtered_search(
+            conn,
+            &query,
+            filters,
+            fetch_limit,
+            fetch_offset,
+            include_deleted,
+            None,
+        )
+        .map_err(|e| e.to_string())?
+    } else {
+        search_files_fts_filtered(conn, &query, fetch_limit, include_deleted)
             .map_err(|e| e.to_string())?
+    };
+
+    if post_filtered && results.len() >= fetch_limit {
+        warnings.push(format!(
+            "Only the best {} matches were checked; refine the query to see the rest",
+            fetch_limit
+        ));
+    }
+
+    let results = if case_sensitive {
+        filter_case_sensitive(conn, &query, results).map_err(|e| e.to_string())?
+    } else {
+        results
+    };
+
+    // Before diversifying, so a vanished file doesn't take its type's slot
+    let results = if verify_exists {
+        verify_results_exist(conn, results, include_deleted).map_err(|e| e.to_string())?
+    } else {
+        results
+    };
+
+    let results = match max_per_type {
+        Some(max) => diversify_by_type(conn, results, max).map_err(|e| e.to_string())?,
+        None => results,
+    };
+
+    let results = if post_filtered {
+        results.into_iter().skip(search_offset).take(search_limit).collect()
     } else {
-        search_files_fts(conn, &query, search_limit).map_err(|e| e.to_string())?
+        results
     };
 
+    if let Err(e) = crate::db::record_search(conn, &query) {
+        log::warn!("Failed to record search history: {}", e);
+    }
+
     let query_time = start_time.elapsed();
 
     log::info!(
@@ -76,9 +246,365 @@ pub async fn search_files(
         total: results.len(),
         results,
         query_time_ms: query_time.as_millis() as u64,
+        warnings,
+    })
+}
+
+/// Search again within a previous result set
+///
+/// Only files in `previous_file_ids` can match, so adding a term narrows the
+/// results progressively instead of starting over.
+#[tauri::command]
+pub async fn refine_search(
+    previous_file_ids: Vec<i64>,
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<SearchResults, String> {
+    let start_time = Instant::now();
+
+    log::info!(
+        "Refining {} results with: '{}'",
+        previous_file_ids.len(),
+        query
+    );
+
+    if query.trim().is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: query.clone(),
+            reason: "Query cannot be empty".to_string(),
+        }
+        .to_string());
+    }
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    let results = perform_filtered_search(
+        conn,
+        &query,
+        filters,
+        limit.unwrap_or(50).min(1000),
+        0,
+        false,
+        Some(&previous_file_ids),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let query_time = start_time.elapsed();
+
+    Ok(SearchResults {
+        total: results.len(),
+        results,
+        query_time_ms: query_time.as_millis() as u64,
+        warnings: Vec::new(),
+    })
+}
+
+/// A file matched by one or more of the queries in a [`multi_search`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiSearchResult {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    /// The queries that matched this file, in request order
+    pub matched_queries: Vec<String>,
+}
+
+/// Run several independent queries and merge their results (OR semantics)
+///
+/// Each file appears once with the best score any query gave it.
+#[tauri::command]
+pub async fn multi_search(
+    queries: Vec<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MultiSearchResult>, String> {
+    log::info!("Multi-search over {} queries", queries.len());
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    run_multi_search(conn, &queries, limit.unwrap_or(50).min(1000)).map_err(|e| e.to_string())
+}
+
+/// Merge the results of `queries`, de-duplicated by file and ranked by score
+///
+/// Blank queries are ignored; it is an error if every query is blank.
+pub fn run_multi_search(
+    conn: &Connection,
+    queries: &[String],
+    limit: usize,
+) -> Result<Vec<MultiSearchResult>, CortexError> {
+    let queries: Vec<&str> = queries
+        .iter()
+        .map(|q| q.trim())
+        .filter(|q| !q.is_empty())
+        .collect();
+
+    if queries.is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: String::new(),
+            reason: "At least one non-empty query is required".to_string(),
+        });
+    }
+
+    let mut merged: HashMap<i64, MultiSearchResult> = HashMap::new();
+
+    for query in queries {
+        for result in search_files_fts_filtered(conn, query, limit, false)? {
+            match merged.get_mut(&result.file_id) {
+                Some(existing) => {
+                    // bm25 rank: lower is better
+                    if result.score < existing.result.score {
+                        existing.result = result;
+                    }
+                    if !existing.matched_queries.iter().any(|q| q == query) {
+                        existing.matched_queries.push(query.to_string());
+                    }
+                }
+                None => {
+                    merged.insert(
+                        result.file_id,
+                        MultiSearchResult {
+                            result,
+                            matched_queries: vec![query.to_string()],
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    let mut results: Vec<MultiSearchResult> = merged.into_values().collect();
+    results.sort_by(|a, b| {
+        a.result
+            .score
+            .partial_cmp(&b.result.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.result.file_id.cmp(&b.result.file_id))
+    });
+    results.truncate(limit);
+
+    Ok(results)
+}
+
+/// Search and return only the matching file paths, best match first
+///
+/// Skips snippet generation, so it is cheaper than [`search_files`] for
+/// callers that only need to open the files.
+#[tauri::command]
+pub async fn search_paths(
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    log::info!("Searching paths for: '{}' with filters: {:?}", query, filters);
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    find_matching_paths(conn, &query, filters, limit.unwrap_or(50).min(1000))
+        .map_err(|e| e.to_string())
+}
+
+/// Search results bucketed by collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupedSearchResults {
+    pub groups: Vec<CollectionGroup>,
+    /// Distinct files matched; one in several collections counts once
+    pub total: usize,
+    pub query_time_ms: u64,
+}
+
+/// Search and group the results by the collections their files are in
+///
+/// Files in no collection go to an "Uncollected" bucket at the end, and a
+/// file in several collections is listed in each.
+#[tauri::command]
+pub async fn search_grouped_by_collection(
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<GroupedSearchResults, String> {
+    let start_time = Instant::now();
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    let results = find_matching_results(conn, &query, filters, limit.unwrap_or(50).min(1000))
+        .map_err(|e| e.to_string())?;
+    let total = results.len();
+    let groups = group_by_collection(conn, results).map_err(|e| e.to_string())?;
+
+    Ok(GroupedSearchResults {
+        groups,
+        total,
+        query_time_ms: start_time.elapsed().as_millis() as u64,
     })
 }
 
+/// The live files matching `query` and `filters`, best match first
+pub fn find_matching_results(
+    conn: &Connection,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: usize,
+) -> Result<Vec<SearchResult>, CortexError> {
+    if query.trim().is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: query.to_string(),
+            reason: "Query cannot be empty".to_string(),
+        });
+    }
+
+    if filters.is_some() {
+        perform_filtered_search(conn, query, filters, limit, 0, false, None)
+    } else {
+        Ok(search_files_fts_filtered(conn, query, limit, false)?)
+    }
+}
+
+/// Paths of the live files matching `query` and `filters`, in search order
+pub fn find_matching_paths(
+    conn: &Connection,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: usize,
+) -> Result<Vec<String>, CortexError> {
+    if query.trim().is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: query.to_string(),
+            reason: "Query cannot be empty".to_string(),
+        });
+    }
+
+    let Some(FilteredMatch {
+        where_clause,
+        mut params,
+        ..
+    }) = build_filtered_match(conn, query, filters, false, None)?
+    else {
+        return Ok(Vec::new());
+    };
+    params.push(Box::new(limit));
+
+    let sql = format!(
+        "SELECT f.path,
+                rank * (CASE WHEN f.pinned = 1 THEN {} ELSE 1.0 END) as score
+         FROM files_fts
+         INNER JOIN files f ON files_fts.rowid = f.id
+         WHERE {}
+         ORDER BY score
+         LIMIT ?",
+        crate::db::operations::PINNED_RANK_BOOST,
+        where_clause
+    );
+
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
+    let mut stmt = conn.prepare(&sql)?;
+    let paths = stmt
+        .query_map(params_refs.as_slice(), |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+
+    Ok(paths)
+}
+
+/// Ranges where `query` matches within a file's full content, for
+/// highlighting in a document viewer (see [`HighlightRange`])
+///
+/// Matching is done by FTS itself, so stemming and phrase queries behave as
+/// in search; a phrase comes back as one contiguous range.
+#[tauri::command]
+pub async fn get_highlight_ranges(
+    file_id: i64,
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<HighlightRange>, String> {
+    let db = state.db.lock().unwrap();
+    find_highlight_ranges(db.get_connection(), file_id, &query).map_err(|e| e.to_string())
+}
+
+/// Match ranges for [`get_highlight_ranges`]; empty if the file doesn't
+/// match `query`
+pub fn find_highlight_ranges(
+    conn: &Connection,
+    file_id: i64,
+    query: &str,
+) -> Result<Vec<HighlightRange>, CortexError> {
+    if query.trim().is_empty() {
+        return Err(CortexError::InvalidQuery {
+            query: query.to_string(),
+            reason: "Query cannot be empty".to_string(),
+        });
+    }
+
+    // Duplicates are indexed under the file they share content with
+    let owner_id = crate::db::resolve_content_owner(conn, file_id)?;
+
+    // Control characters can't come from extracted text in practice, so
+    // they delimit the marked spans unambiguously
+    let highlighted: Option<String> = match conn.query_row(
+        "SELECT highlight(files_fts, 1, char(1), char(2))
+         FROM files_fts WHERE files_fts MATCH ?1 AND rowid = ?2",
+        rusqlite::params![query, owner_id],
+        |row| row.get(0),
+    ) {
+        Ok(text) => text,
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut ranges = Vec::new();
+    let mut position = 0;
+    let mut start = 0;
+    for c in highlighted.unwrap_or_default().chars() {
+        match c {
+            '\u{1}' => start = position,
+            '\u{2}' => ranges.push(HighlightRange { start, end: position }),
+            _ => position += c.len_utf16(),
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// List indexed files with pagination
+///
+/// Soft-deleted files are only included when `include_deleted` is set, so the
+/// UI can offer a trash view before they are purged.
+#[tauri::command]
+pub async fn list_indexed_files(
+    limit: Option<usize>,
+    offset: Option<usize>,
+    include_deleted: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<File>, String> {
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    list_files_filtered(
+        conn,
+        limit.unwrap_or(100).min(1000),
+        offset.unwrap_or(0),
+        include_deleted.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Restore a soft-deleted file so it shows up in search again
+#[tauri::command]
+pub async fn restore_file(file_id: i64, state: State<'_, AppState>) -> Result<(), String> {
+    log::info!("Restoring file ID: {}", file_id);
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    crate::db::operations::restore_file(conn, file_id).map_err(|e| e.to_string())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileDetail {
     pub id: i64,
@@ -92,6 +618,8 @@ pub struct FileDetail {
     pub full_content: Option<String>,
     pub word_count: Option<i64>,
     pub summary: Option<String>,
+    /// Document properties such as a PDF's author and page count
+    pub metadata: HashMap<String, String>,
 }
 
 /// Get detailed information about a specific file
@@ -113,7 +641,7 @@ pub async fn get_file_detail(
     // Get file content
     let content = get_file_content(conn, file_id).map_err(|e| e.to_string())?;
 
-    let (content_preview, full_content, word_count, summary) = match content {
+    let (content_preview, full_content, word_count, summary, metadata) = match content {
         Some(c) => {
             let preview = c.text_content.as_ref().map(|text| {
                 let preview_len = 500.min(text.len());
@@ -126,9 +654,9 @@ pub async fn get_file_detail(
                 None
             };
 
-            (preview, full, c.word_count, c.summary)
+            (preview, full, c.word_count, c.summary, c.metadata)
         }
-        None => (None, None, None, None),
+        None => (None, None, None, None, HashMap::new()),
     };
 
     Ok(FileDetail {
@@ -143,6 +671,7 @@ pub async fn get_file_detail(
         full_content,
         word_count,
         summary,
+        metadata,
     })
 }
 
@@ -156,11 +685,34 @@ pub struct SearchStats {
 /// Get search/indexing statistics
 #[tauri::command]
 pub async fn get_search_stats(state: State<'_, AppState>) -> Result<SearchStats, String> {
-    let db = state.db.lock().unwrap();
-    let conn = db.get_connection();
+    let (total_files, indexed_files, total_size) = state
+        .db_pool
+        .read(crate::db::get_db_stats)
+        .map_err(|e| e.to_string())?;
+
+    Ok(SearchStats {
+        total_files,
+        indexed_files,
+        total_size_bytes: total_size,
+    })
+}
 
-    let (total_files, indexed_files, total_size) =
-        crate::db::get_db_stats(conn).map_err(|e| e.to_string())?;
+/// Word-count totals and distribution over the indexed text
+#[tauri::command]
+pub async fn get_content_stats(state: State<'_, AppState>) -> Result<ContentStats, String> {
+    state
+        .db_pool
+        .read(crate::db::get_content_stats)
+        .map_err(|e| e.to_string())
+}
+
+/// Recount the cached statistics from the tables, e.g. if they look off
+#[tauri::command]
+pub async fn recompute_stats(state: State<'_, AppState>) -> Result<SearchStats, String> {
+    let (total_files, indexed_files, total_size) = state
+        .db_pool
+        .write(crate::db::recompute_stats)
+        .map_err(|e| e.to_string())?;
 
     Ok(SearchStats {
         total_files,
@@ -169,21 +721,468 @@ pub async fn get_search_stats(state: State<'_, AppState>) -> Result<SearchStats,
     })
 }
 
-// Private helper function for filtered search
-fn perform_filtered_search(
+/// Database connection diagnostics, e.g. whether memory-mapped I/O is active
+#[tauri::command]
+pub async fn get_db_health(state: State<'_, AppState>) -> Result<DatabaseHealth, String> {
+    let db = state.db.lock().unwrap();
+    db.health().map_err(|e| e.to_string())
+}
+
+/// Set when the database was found corrupt at startup and replaced with an
+/// empty one, so the UI can offer to re-index
+#[tauri::command]
+pub async fn get_db_recovery(state: State<'_, AppState>) -> Result<Option<DatabaseRecovery>, String> {
+    let db = state.db.lock().unwrap();
+    Ok(db.recovery().cloned())
+}
+
+/// The index's settings
+#[tauri::command]
+pub async fn get_config(state: State<'_, AppState>) -> Result<crate::db::CortexConfig, String> {
+    state.db_pool.read(crate::db::get_config).map_err(|e| e.to_string())
+}
+
+/// Change the index's settings
+///
+/// Turning content compression on or off also rewrites the stored content
+/// to match, so the savings (or the plain text) apply right away.
+#[tauri::command]
+pub async fn set_config(config: crate::db::CortexConfig, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .db_pool
+        .write(move |conn| {
+            let previous = crate::db::get_config(conn)?;
+            crate::db::set_config(conn, &config)?;
+
+            if previous.compress_content != config.compress_content {
+                let rewritten = crate::db::apply_content_compression(conn)?;
+                log::info!("Rewrote {} content rows after changing compression", rewritten);
+            }
+            Ok(())
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Space taken by extracted text and how much compression saves
+#[tauri::command]
+pub async fn get_content_storage_stats(
+    state: State<'_, AppState>,
+) -> Result<crate::db::ContentStorageStats, String> {
+    state
+        .db_pool
+        .read(crate::db::get_content_storage_stats)
+        .map_err(|e| e.to_string())
+}
+
+/// The largest indexed files, to see what takes up space in the index
+#[tauri::command]
+pub async fn get_largest_files(limit: Option<usize>, state: State<'_, AppState>) -> Result<Vec<File>, String> {
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    list_largest_files(conn, None, limit.unwrap_or(20).min(1000)).map_err(|e| e.to_string())
+}
+
+/// The largest indexed files of one type (extension, without the dot)
+#[tauri::command]
+pub async fn get_largest_files_by_type(
+    file_type: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<File>, String> {
+    let file_type = file_type.trim().trim_start_matches('.');
+
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    list_largest_files(conn, Some(file_type), limit.unwrap_or(20).min(1000))
+        .map_err(|e| e.to_string())
+}
+
+/// Files referencing `domain` or its subdomains, with the matching URLs
+///
+/// e.g. `arxiv.org` finds every file that links to a paper there.
+#[tauri::command]
+pub async fn search_by_link_domain(
+    domain: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::db::LinkedFile>, String> {
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    crate::db::search_by_link_domain(conn, &domain, limit.unwrap_or(50).min(1000))
+        .map_err(|e| e.to_string())
+}
+
+/// Indexed files under `root_path` as a nested tree with per-directory
+/// sizes and file counts
+///
+/// Built from the index, not the disk, so it reflects exactly what is indexed.
+#[tauri::command]
+pub async fn get_file_tree(root_path: String, state: State<'_, AppState>) -> Result<FileTreeNode, String> {
+    let db = state.db.lock().unwrap();
+    let conn = db.get_connection();
+
+    crate::db::get_file_tree(conn, &root_path).map_err(|e| e.to_string())
+}
+
+/// An FTS query as a boolean expression over its literal terms
+#[derive(Debug, Clone, PartialEq)]
+enum CaseQuery {
+    Term(String),
+    And(Vec<CaseQuery>),
+    Or(Vec<CaseQuery>),
+    Not(Box<CaseQuery>, Box<CaseQuery>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CaseToken {
+    Word(String),
+    Phrase(Vec<String>),
+    And,
+    Or,
+    Not,
+    Near,
+    Open,
+    Close,
+    Comma,
+}
+
+fn is_term_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Split an FTS query into words, phrases and operators
+///
+/// Column filters (`filename:`), prefix stars and `^` are dropped, since a
+/// term's case is checked anywhere in the filename or content.
+fn case_tokens(query: &str) -> Vec<CaseToken> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut phrase = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    phrase.push(c);
+                }
+                let words: Vec<String> = phrase
+                    .split(|c: char| !is_term_char(c))
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if !words.is_empty() {
+                    tokens.push(CaseToken::Phrase(words));
+                }
+            }
+            '(' => tokens.push(CaseToken::Open),
+            ')' => tokens.push(CaseToken::Close),
+            ',' => tokens.push(CaseToken::Comma),
+            c if is_term_char(c) => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| is_term_char(**c)) {
+                    word.push(c);
+                    chars.next();
+                }
+                if chars.peek() == Some(&':') {
+                    chars.next();
+                    continue;
+                }
+                tokens.push(match word.as_str() {
+                    "AND" => CaseToken::And,
+                    "OR" => CaseToken::Or,
+                    "NOT" => CaseToken::Not,
+                    "NEAR" => CaseToken::Near,
+                    _ => CaseToken::Word(word),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+/// Parser for [`CaseQuery`] with FTS5 precedence: NOT binds tightest, then
+/// AND (explicit or implied by adjacent terms), then OR
+struct CaseParser {
+    tokens: Vec<CaseToken>,
+    pos: usize,
+}
+
+impl CaseParser {
+    fn peek(&self) -> Option<&CaseToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<CaseToken> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Option<CaseQuery> {
+        let mut alternatives: Vec<CaseQuery> = self.and().into_iter().collect();
+        while self.peek() == Some(&CaseToken::Or) {
+            self.next();
+            alternatives.extend(self.and());
+        }
+        Self::group(alternatives, CaseQuery::Or)
+    }
+
+    fn and(&mut self) -> Option<CaseQuery> {
+        let mut terms: Vec<CaseQuery> = self.not().into_iter().collect();
+        loop {
+            match self.peek() {
+                Some(CaseToken::And) => {
+                    self.next();
+                }
+                Some(CaseToken::Word(_) | CaseToken::Phrase(_) | CaseToken::Open | CaseToken::Near) => {}
+                _ => break,
+            }
+            terms.extend(self.not());
+        }
+        Self::group(terms, CaseQuery::And)
+    }
+
+    fn not(&mut self) -> Option<CaseQuery> {
+        let mut query = self.primary();
+        while self.peek() == Some(&CaseToken::Not) {
+            self.next();
+            let excluded = self.primary();
+            query = match (query, excluded) {
+                (Some(query), Some(excluded)) => Some(CaseQuery::Not(Box::new(query), Box::new(excluded))),
+                (query, _) => query,
+            };
+        }
+        query
+    }
+
+    fn primary(&mut self) -> Option<CaseQuery> {
+        match self.next()? {
+            CaseToken::Word(word) => Some(CaseQuery::Term(word)),
+            CaseToken::Phrase(words) => {
+                Self::group(words.into_iter().map(CaseQuery::Term).collect(), CaseQuery::And)
+            }
+            CaseToken::Open => {
+                let query = self.or();
+                if self.peek() == Some(&CaseToken::Close) {
+                    self.next();
+                }
+                query
+            }
+            // NEAR(a b, 10): every term, ignoring the distance
+            CaseToken::Near if self.peek() == Some(&CaseToken::Open) => {
+                self.next();
+                let mut terms = Vec::new();
+                while let Some(token) = self.next() {
+                    match token {
+                        CaseToken::Word(word) => terms.push(CaseQuery::Term(word)),
+                        CaseToken::Phrase(words) => terms.extend(words.into_iter().map(CaseQuery::Term)),
+                        CaseToken::Comma => {
+                            while !matches!(self.next(), Some(CaseToken::Close) | None) {}
+                            break;
+                        }
+                        CaseToken::Close => break,
+                        _ => {}
+                    }
+                }
+                Self::group(terms, CaseQuery::And)
+            }
+            CaseToken::Near => Some(CaseQuery::Term("NEAR".to_string())),
+            _ => None,
+        }
+    }
+
+    fn group(mut queries: Vec<CaseQuery>, combine: fn(Vec<CaseQuery>) -> CaseQuery) -> Option<CaseQuery> {
+        match queries.len() {
+            0 => None,
+            1 => queries.pop(),
+            _ => Some(combine(queries)),
+        }
+    }
+}
+
+impl CaseQuery {
+    fn parse(query: &str) -> Option<Self> {
+        CaseParser { tokens: case_tokens(query), pos: 0 }.or()
+    }
+
+    fn matches(&self, filename: &str, content: &str) -> bool {
+        match self {
+            Self::Term(term) => filename.contains(term.as_str()) || content.contains(term.as_str()),
+            Self::And(queries) => queries.iter().all(|q| q.matches(filename, content)),
+            Self::Or(queries) => queries.iter().any(|q| q.matches(filename, content)),
+            Self::Not(query, excluded) => {
+                query.matches(filename, content) && !excluded.matches(filename, content)
+            }
+        }
+    }
+}
+
+/// Keep the results whose filename or content satisfies `query` with each
+/// term in its exact case
+///
+/// FTS5 folds case when indexing, so this re-checks the stored text of the
+/// files it matched, evaluating AND, OR, NOT and NEAR the way FTS does.
+pub fn filter_case_sensitive(
     conn: &Connection,
     query: &str,
-    filters: Option<SearchFilters>,
-    limit: usize,
-    offset: usize,
+    results: Vec<SearchResult>,
+) -> Result<Vec<SearchResult>, CortexError> {
+    let Some(query) = CaseQuery::parse(query) else {
+        return Ok(results);
+    };
+    let mut kept = Vec::with_capacity(results.len());
+
+    for result in results {
+        let content = get_file_content(conn, result.file_id)?
+            .and_then(|c| c.text_content)
+            .unwrap_or_default();
+
+        if query.matches(&result.filename, &content) {
+            kept.push(result);
+        }
+    }
+
+    Ok(kept)
+}
+
+/// At most `max_per_type` results of each file type, interleaved
+///
+/// Types take turns in the order of their best result: the best of each
+/// type comes first, then the second best of each, and so on. Within a type
+/// the relevance order is kept.
+pub fn diversify_by_type(
+    conn: &Connection,
+    results: Vec<SearchResult>,
+    max_per_type: usize,
+) -> Result<Vec<SearchResult>, CortexError> {
+    let mut stmt = conn.prepare_cached("SELECT file_type FROM files WHERE id = ?1")?;
+    let mut groups: Vec<(String, Vec<SearchResult>)> = Vec::new();
+
+    for result in results {
+        let file_type = stmt
+            .query_row([result.file_id], |row| row.get::<_, String>(0))?
+            .to_lowercase();
+
+        match groups.iter_mut().find(|(group_type, _)| *group_type == file_type) {
+            Some((_, group)) if group.len() >= max_per_type => {}
+            Some((_, group)) => group.push(result),
+            None => groups.push((file_type, vec![result])),
+        }
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().map(|(_, group)| group.into_iter()).collect();
+    let mut diversified = Vec::new();
+    loop {
+        let before = diversified.len();
+        diversified.extend(groups.iter_mut().filter_map(Iterator::next));
+        if diversified.len() == before {
+            return Ok(diversified);
+        }
+    }
+}
+
+/// Soft-delete results whose files no longer exist on disk
+///
+/// Missing files are dropped from the results, or kept with
+/// `is_deleted = true` when `include_deleted` is set.
+pub fn verify_results_exist(
+    conn: &Connection,
+    results: Vec<SearchResult>,
+    include_deleted: bool,
 ) -> Result<Vec<SearchResult>, CortexError> {
-    let mut where_clauses = vec!["files_fts MATCH ?1", "f.is_deleted = 0"];
+    let mut verified = Vec::with_capacity(results.len());
+
+    for mut result in results {
+        if !result.is_deleted && !Path::new(&result.path).exists() {
+            log::info!("Soft-deleting missing file from search results: {}", result.path);
+            mark_file_deleted(conn, result.file_id)?;
+            result.is_deleted = true;
+        }
+
+        if include_deleted || !result.is_deleted {
+            verified.push(result);
+        }
+    }
+
+    Ok(verified)
+}
+
+/// WHERE clause matching files tagged with any (or all) of `tag_count`
+/// bound tags
+fn tag_filter_clause(tag_count: usize, match_all: bool) -> String {
+    let placeholders = vec!["?"; tag_count].join(", ");
+
+    if match_all {
+        format!(
+            "f.id IN (SELECT file_id FROM file_tags WHERE tag IN ({}) \
+             GROUP BY file_id HAVING COUNT(*) = {})",
+            placeholders, tag_count
+        )
+    } else {
+        format!("f.id IN (SELECT file_id FROM file_tags WHERE tag IN ({}))", placeholders)
+    }
+}
+
+/// WHERE clause and bound parameters shared by the filtered FTS queries
+struct FilteredMatch {
+    where_clause: String,
+    params: Vec<Box<dyn rusqlite::ToSql>>,
+    uses_candidate_table: bool,
+}
+
+/// Build the WHERE clause for an FTS match narrowed by `filters` and
+/// `candidates`
+///
+/// Returns `None` when an empty candidate list rules out every file.
+fn build_filtered_match(
+    conn: &Connection,
+    query: &str,
+    filters: Option<SearchFilters>,
+    include_deleted: bool,
+    candidates: Option<&[i64]>,
+) -> Result<Option<FilteredMatch>, CortexError> {
+    let mut tag_clause = None;
+    let mut scope_clause = None;
+    let mut candidate_clause = None;
+    let mut where_clauses = vec!["files_fts MATCH ?1"];
+    if !include_deleted {
+        where_clauses.push("f.is_deleted = 0");
+    }
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(query.to_string())];
 
+    let mut uses_candidate_table = false;
+    if let Some(ids) = candidates {
+        if ids.is_empty() {
+            return Ok(None);
+        }
+
+        if ids.len() > REFINE_IN_LIST_LIMIT {
+            load_refine_candidates(conn, ids)?;
+            uses_candidate_table = true;
+            where_clauses.push("f.id IN (SELECT id FROM temp.refine_candidates)");
+        } else {
+            candidate_clause = Some(format!("f.id IN ({})", vec!["?"; ids.len()].join(", ")));
+            params.extend(ids.iter().map(|&id| Box::new(id) as Box<dyn rusqlite::ToSql>));
+        }
+    }
+
+    if let Some(clause) = &candidate_clause {
+        where_clauses.push(clause);
+    }
+
     if let Some(f) = filters {
         if let Some(file_type) = f.file_type {
+            // Stored file types are lowercase (see `insert_file`)
             where_clauses.push("f.file_type = ?");
-            params.push(Box::new(file_type));
+            params.push(Box::new(file_type.to_lowercase()));
         }
 
         if let Some(min_size) = f.min_size {
@@ -205,21 +1204,75 @@ fn perform_filtered_search(
             where_clauses.push("f.modified_at <= ?");
             params.push(Box::new(date_to));
         }
+
+        let tags: Vec<String> = f
+            .tags
+            .iter()
+            .filter_map(|t| crate::db::normalize_tag(t))
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if !tags.is_empty() {
+            tag_clause = Some(tag_filter_clause(tags.len(), f.match_all_tags));
+            params.extend(tags.into_iter().map(|t| Box::new(t) as Box<dyn rusqlite::ToSql>));
+        }
+
+        // Pushed after the tag clause below, matching the parameter order
+        if let Some(clause) = f.search_scope.clause() {
+            scope_clause = Some(clause);
+            params.push(Box::new(query.to_string()));
+        }
     }
 
+    if let Some(clause) = &tag_clause {
+        where_clauses.push(clause);
+    }
+
+    if let Some(clause) = scope_clause {
+        where_clauses.push(clause);
+    }
+
+    Ok(Some(FilteredMatch {
+        where_clause: where_clauses.join(" AND "),
+        params,
+        uses_candidate_table,
+    }))
+}
+
+// Private helper function for filtered search
+fn perform_filtered_search(
+    conn: &Connection,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: usize,
+    offset: usize,
+    include_deleted: bool,
+    candidates: Option<&[i64]>,
+) -> Result<Vec<SearchResult>, CortexError> {
+    let Some(FilteredMatch {
+        where_clause,
+        mut params,
+        uses_candidate_table,
+    }) = build_filtered_match(conn, query, filters, include_deleted, candidates)?
+    else {
+        return Ok(Vec::new());
+    };
+
     params.push(Box::new(limit));
     params.push(Box::new(offset));
 
     let sql = format!(
         "SELECT f.id, f.path, f.filename,
                 snippet(files_fts, 1, '<mark>', '</mark>', '...', 32) as snippet,
-                rank
+                rank * (CASE WHEN f.pinned = 1 THEN {} ELSE 1.0 END) as score, f.is_deleted,
+                snippet(files_fts, 0, '<mark>', '</mark>', '...', 32) as filename_snippet
          FROM files_fts
          INNER JOIN files f ON files_fts.rowid = f.id
          WHERE {}
-         ORDER BY rank
+         ORDER BY score
          LIMIT ? OFFSET ?",
-        where_clauses.join(" AND ")
+        crate::db::operations::PINNED_RANK_BOOST,
+        where_clause
     );
 
     let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
@@ -232,11 +1285,541 @@ fn perform_filtered_search(
                 file_id: row.get(0)?,
                 path: row.get(1)?,
                 filename: row.get(2)?,
-                snippet: row.get(3)?,
+                snippet: choose_snippet(row.get(3)?, row.get(6)?),
                 score: row.get(4)?,
+                is_deleted: row.get(5)?,
+                duplicate_paths: Vec::new(),
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
-    Ok(results)
+    if uses_candidate_table {
+        conn.execute("DELETE FROM temp.refine_candidates", [])?;
+    }
+
+    attach_duplicate_paths(conn, results)
+}
+
+/// Fill the connection's temp candidate table with `ids`
+fn load_refine_candidates(conn: &Connection, ids: &[i64]) -> Result<(), CortexError> {
+    conn.execute(
+        "CREATE TEMP TABLE IF NOT EXISTS refine_candidates (id INTEGER PRIMARY KEY)",
+        [],
+    )?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM temp.refine_candidates", [])?;
+    {
+        let mut stmt = tx.prepare("INSERT OR IGNORE INTO temp.refine_candidates (id) VALUES (?1)")?;
+        for id in ids {
+            stmt.execute([id])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_file, upsert_file_content, Database};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_search_scope_separates_comments_from_code() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let temp_dir = TempDir::new().unwrap();
+
+        let sources = [
+            ("config.rs", "fn parse_config() {\n    // TODO handle tabs\n    let width = 4;\n}\n"),
+            ("labels.py", "LABEL = \"TODO\"  # shown on new tasks\n"),
+        ];
+        let mut ids = Vec::new();
+        for (name, source) in sources {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, source).unwrap();
+            let id = crate::commands::indexing::index_file_cached(
+                conn, &path, source.len() as u64, "2025-11-29T00:00:00Z", &[],
+                crate::indexer::ContentExtractor::extract,
+            ).unwrap();
+            ids.push(id);
+        }
+        let (config, labels) = (ids[0], ids[1]);
+
+        let search = |query: &str, search_scope: SearchScope| -> Vec<i64> {
+            let filters = SearchFilters {
+                file_type: None,
+                min_size: None,
+                max_size: None,
+                date_from: None,
+                date_to: None,
+                tags: Vec::new(),
+                match_all_tags: false,
+                search_scope,
+            };
+            let mut ids: Vec<i64> = perform_filtered_search(conn, query, Some(filters), 10, 0, false, None)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.file_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // The `// TODO` comment, but not the string literal holding "TODO"
+        assert_eq!(search("TODO", SearchScope::All), vec![config, labels]);
+        assert_eq!(search("TODO", SearchScope::CommentsOnly), vec![config]);
+        assert_eq!(search("TODO", SearchScope::CodeOnly), vec![labels]);
+
+        assert_eq!(search("width", SearchScope::CodeOnly), vec![config]);
+        assert!(search("width", SearchScope::CommentsOnly).is_empty());
+        assert_eq!(search("tasks", SearchScope::CommentsOnly), vec![labels]);
+    }
+
+    #[test]
+    fn test_verify_exists_drops_files_deleted_on_disk() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let temp_dir = TempDir::new().unwrap();
+
+        let mut ids = Vec::new();
+        for name in ["present.txt", "vanished.txt"] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, "phantom platypus").unwrap();
+            let id = insert_file(conn, &path.to_string_lossy(), name, "txt", 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some("phantom platypus"), None).unwrap();
+            ids.push(id);
+        }
+        let (present, vanished) = (ids[0], ids[1]);
+        std::fs::remove_file(temp_dir.path().join("vanished.txt")).unwrap();
+
+        // Without verification the phantom result is still returned
+        let results = search_files_fts_filtered(conn, "platypus", 10, false).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let verified = verify_results_exist(conn, results, false).unwrap();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].file_id, present);
+        assert!(get_file_by_id(conn, vanished).unwrap().is_deleted);
+
+        // When deleted files are requested it comes back marked
+        let results = search_files_fts_filtered(conn, "platypus", 10, true).unwrap();
+        let verified = verify_results_exist(conn, results, true).unwrap();
+        assert_eq!(verified.len(), 2);
+        assert!(verified.iter().any(|r| r.file_id == vanished && r.is_deleted));
+    }
+
+    #[test]
+    fn test_filter_search_by_tags() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let mut ids = Vec::new();
+        for name in ["both.txt", "work.txt", "untagged.txt"] {
+            let id = insert_file(conn, &format!("/test/{}", name), name, "txt", 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some("quarterly budget figures"), None).unwrap();
+            ids.push(id);
+        }
+        let (both, work) = (ids[0], ids[1]);
+
+        crate::db::add_tags_to_files(conn, &[both, work], &["work".to_string()]).unwrap();
+        crate::db::add_tags_to_files(conn, &[both], &["finance".to_string()]).unwrap();
+
+        let search = |tags: &[&str], match_all: bool| -> Vec<i64> {
+            let filters = SearchFilters {
+                file_type: None,
+                min_size: None,
+                max_size: None,
+                date_from: None,
+                date_to: None,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                match_all_tags: match_all,
+                search_scope: SearchScope::All,
+            };
+            let mut ids: Vec<i64> = perform_filtered_search(conn, "budget", Some(filters), 10, 0, false, None)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.file_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(search(&[], false).len(), 3);
+        assert_eq!(search(&["Work"], false), vec![both, work]);
+        assert_eq!(search(&["work", "finance"], false), vec![both, work]);
+        assert_eq!(search(&["work", "finance"], true), vec![both]);
+        assert_eq!(search(&["work", "WORK"], true), vec![both, work]);
+        assert!(search(&["missing"], false).is_empty());
+    }
+
+    #[test]
+    fn test_file_type_filter_ignores_case() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let mut ids = Vec::new();
+        for (name, file_type) in [("report.PDF", "PDF"), ("notes.pdf", "pdf"), ("memo.txt", "txt")] {
+            let id = insert_file(conn, &format!("/test/{}", name), name, file_type, 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some("annual audit"), None).unwrap();
+            ids.push(id);
+        }
+        assert_eq!(get_file_by_id(conn, ids[0]).unwrap().file_type, "pdf");
+
+        // A row stored mixed-case before types were normalized is backfilled
+        conn.execute("UPDATE files SET file_type = 'Pdf' WHERE id = ?1", [ids[1]]).unwrap();
+        conn.execute_batch("PRAGMA user_version = 8").unwrap();
+        crate::db::run_migrations(conn).unwrap();
+
+        for file_type in ["pdf", "PDF", "Pdf"] {
+            let filters = SearchFilters {
+                file_type: Some(file_type.to_string()),
+                min_size: None,
+                max_size: None,
+                date_from: None,
+                date_to: None,
+                tags: Vec::new(),
+                match_all_tags: false,
+                search_scope: SearchScope::All,
+            };
+            let mut found: Vec<i64> = perform_filtered_search(conn, "audit", Some(filters), 10, 0, false, None)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.file_id)
+                .collect();
+            found.sort();
+            assert_eq!(found, vec![ids[0], ids[1]], "filtering by {}", file_type);
+        }
+    }
+
+    #[test]
+    fn test_refine_narrows_previous_results() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let docs = [
+            ("red_apple.txt", "apple pie with red apples"),
+            ("green_apple.txt", "apple crumble with green apples"),
+            ("red_car.txt", "a red sports car"),
+        ];
+        for (name, text) in docs {
+            let id = insert_file(conn, &format!("/test/{}", name), name, "txt", 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some(text), None).unwrap();
+        }
+
+        let initial: Vec<i64> = search_files_fts_filtered(conn, "apple", 10, false)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.file_id)
+            .collect();
+        assert_eq!(initial.len(), 2);
+
+        // "red" alone matches the car too, but refining keeps only apples
+        let refined = perform_filtered_search(conn, "red", None, 10, 0, false, Some(&initial)).unwrap();
+        assert_eq!(refined.len(), 1);
+        assert_eq!(refined[0].filename, "red_apple.txt");
+
+        // Same answer through the temp table path for large candidate sets
+        let mut many = initial.clone();
+        many.extend(10_000..10_000 + REFINE_IN_LIST_LIMIT as i64);
+        let refined = perform_filtered_search(conn, "red", None, 10, 0, false, Some(&many)).unwrap();
+        assert_eq!(refined.len(), 1);
+        assert_eq!(refined[0].filename, "red_apple.txt");
+
+        assert!(perform_filtered_search(conn, "red", None, 10, 0, false, Some(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_multi_search_dedupes_keeping_best_score() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let docs = [
+            ("both.txt", "rust rust rust tokio"),
+            ("rust.txt", "rust notes and some other words to dilute the score"),
+            ("tokio.txt", "tokio runtime"),
+        ];
+        for (name, text) in docs {
+            let id = insert_file(conn, &format!("/test/{}", name), name, "txt", 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some(text), None).unwrap();
+        }
+
+        let queries = vec!["rust".to_string(), "tokio".to_string(), "  ".to_string()];
+        let results = run_multi_search(conn, &queries, 10).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let both = results.iter().find(|r| r.result.filename == "both.txt").unwrap();
+        assert_eq!(both.matched_queries, vec!["rust", "tokio"]);
+
+        let best = |query: &str| {
+            search_files_fts_filtered(conn, query, 10, false)
+                .unwrap()
+                .into_iter()
+                .find(|r| r.filename == "both.txt")
+                .unwrap()
+                .score
+        };
+        assert_eq!(both.result.score, best("rust").min(best("tokio")));
+
+        // Ranked best (lowest bm25) first
+        assert!(results.windows(2).all(|w| w[0].result.score <= w[1].result.score));
+
+        assert!(run_multi_search(conn, &["".to_string()], 10).is_err());
+    }
+
+    #[test]
+    fn test_case_sensitive_search_distinguishes_identifiers() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let add = |name: &str, text: &str| {
+            let id = insert_file(conn, &format!("/src/{}", name), name, "rs", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/src").unwrap();
+            upsert_file_content(conn, id, Some(text), None).unwrap();
+            id
+        };
+        let upper = add("server.rs", "struct HTTPServer { port: u16 }");
+        let lower = add("legacy.rs", "fn httpserver() -> u16 { 80 }");
+
+        // FTS alone can't tell them apart
+        let all = search_files_fts_filtered(conn, "HTTPServer", 10, false).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let exact = filter_case_sensitive(conn, "HTTPServer", all.clone()).unwrap();
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].file_id, upper);
+
+        let exact = filter_case_sensitive(conn, "httpserver", all).unwrap();
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].file_id, lower);
+
+        // Operators and prefix syntax aren't treated as terms
+        let prefixed = search_files_fts_filtered(conn, "HTTP* AND port", 10, false).unwrap();
+        let exact = filter_case_sensitive(conn, "HTTP* AND port", prefixed).unwrap();
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].file_id, upper);
+
+        // Either alternative of an OR is enough
+        let either = search_files_fts_filtered(conn, "HTTPServer OR legacy", 10, false).unwrap();
+        let exact = filter_case_sensitive(conn, "HTTPServer OR legacy", either).unwrap();
+        assert_eq!(exact.len(), 2);
+
+        // NOT excludes rather than requires its term
+        let without = search_files_fts_filtered(conn, "u16 NOT port", 10, false).unwrap();
+        let exact = filter_case_sensitive(conn, "u16 NOT port", without).unwrap();
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].file_id, lower);
+    }
+
+    #[test]
+    fn test_case_query_follows_fts_precedence() {
+        let term = |t: &str| CaseQuery::Term(t.to_string());
+
+        assert_eq!(
+            CaseQuery::parse("a b OR c NOT d"),
+            Some(CaseQuery::Or(vec![
+                CaseQuery::And(vec![term("a"), term("b")]),
+                CaseQuery::Not(Box::new(term("c")), Box::new(term("d"))),
+            ]))
+        );
+        assert_eq!(
+            CaseQuery::parse("filename:Foo AND (\"Bar baz\" OR NEAR(One two, 5))"),
+            Some(CaseQuery::And(vec![
+                term("Foo"),
+                CaseQuery::Or(vec![
+                    CaseQuery::And(vec![term("Bar"), term("baz")]),
+                    CaseQuery::And(vec![term("One"), term("two")]),
+                ]),
+            ]))
+        );
+        assert_eq!(CaseQuery::parse("*"), None);
+    }
+
+    #[test]
+    fn test_diversify_caps_each_file_type() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        // Logs match best, then Markdown, then a single PDF
+        let mut files = Vec::new();
+        for (name, repeats) in [
+            ("a.log", 9), ("b.log", 8), ("c.LOG", 7), ("d.log", 6),
+            ("e.md", 5), ("f.md", 4), ("g.md", 3), ("h.pdf", 1),
+        ] {
+            let ext = name.rsplit('.').next().unwrap();
+            let id = insert_file(conn, &format!("/test/{}", name), name, ext, 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            let text = format!("{} filler text to vary the length", "timeout ".repeat(repeats));
+            upsert_file_content(conn, id, Some(&text), None).unwrap();
+            files.push((name, id));
+        }
+        let id = |name: &str| files.iter().find(|(n, _)| *n == name).unwrap().1;
+
+        let ranked = search_files_fts_filtered(conn, "timeout", 100, false).unwrap();
+        let ranked_ids: Vec<i64> = ranked.iter().map(|r| r.file_id).collect();
+        assert_eq!(ranked_ids.len(), 8);
+        assert_eq!(ranked_ids[0], id("a.log"));
+
+        let diversified = diversify_by_type(conn, ranked.clone(), 2).unwrap();
+        let ids: Vec<i64> = diversified.iter().map(|r| r.file_id).collect();
+        assert_eq!(ids, vec![id("a.log"), id("e.md"), id("h.pdf"), id("b.log"), id("f.md")]);
+
+        // Within each type, results keep their relevance order
+        for file_type in ["log", "md"] {
+            let in_type = |list: &[i64]| -> Vec<i64> {
+                list.iter()
+                    .copied()
+                    .filter(|&file_id| {
+                        let name = &files.iter().find(|(_, i)| *i == file_id).unwrap().0;
+                        name.to_lowercase().ends_with(file_type)
+                    })
+                    .collect()
+            };
+            let expected: Vec<i64> = in_type(&ranked_ids).into_iter().take(2).collect();
+            assert_eq!(in_type(&ids), expected);
+        }
+
+        // A cap above every type's count only interleaves
+        assert_eq!(diversify_by_type(conn, ranked, 10).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_search_paths_match_full_search() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        for (name, text) in [
+            ("one.md", "lighthouse keeper notes"),
+            ("two.md", "lighthouse lighthouse lighthouse"),
+            ("three.txt", "lighthouse beam"),
+            ("other.md", "unrelated content"),
+        ] {
+            let ext = name.rsplit('.').next().unwrap();
+            let id = insert_file(conn, &format!("/test/{}", name), name, ext, 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some(text), None).unwrap();
+        }
+
+        let full: Vec<String> = search_files_fts_filtered(conn, "lighthouse", 50, false)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.path)
+            .collect();
+        assert_eq!(full.len(), 3);
+        assert_eq!(find_matching_paths(conn, "lighthouse", None, 50).unwrap(), full);
+
+        let filters = SearchFilters {
+            file_type: Some("md".to_string()),
+            min_size: None,
+            max_size: None,
+            date_from: None,
+            date_to: None,
+            tags: Vec::new(),
+            match_all_tags: false,
+            search_scope: SearchScope::All,
+        };
+        let full: Vec<String> =
+            perform_filtered_search(conn, "lighthouse", Some(filters.clone()), 50, 0, false, None)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect();
+        assert_eq!(full.len(), 2);
+        assert_eq!(find_matching_paths(conn, "lighthouse", Some(filters), 50).unwrap(), full);
+
+        assert!(matches!(
+            find_matching_paths(conn, "  ", None, 50),
+            Err(CortexError::InvalidQuery { .. })
+        ));
+    }
+
+    #[test]
+    fn test_highlight_ranges_match_fts_terms_and_phrases() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let text = "Ünïcode 🦀 first. Rust makes systems programming safe; programs in rust are fun.";
+        let id = insert_file(conn, "/test/rust.txt", "rust.txt", "txt", 16,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(conn, id, Some(text), None).unwrap();
+
+        let range_of = |word: &str, from: usize| {
+            let byte = from + text[from..].find(word).unwrap();
+            let start = text[..byte].encode_utf16().count();
+            HighlightRange { start, end: start + word.encode_utf16().count() }
+        };
+        let rust = text.find("Rust").unwrap();
+
+        // Terms match case-insensitively and by stem ("programs" ~ "programming")
+        let ranges = find_highlight_ranges(conn, id, "rust programming").unwrap();
+        assert_eq!(ranges, vec![
+            range_of("Rust", 0),
+            range_of("programming", 0),
+            range_of("programs", 0),
+            range_of("rust", rust + 1),
+        ]);
+
+        // The crab is two UTF-16 units, so the first "Rust" starts at 18
+        // where counting characters would give 17
+        assert_eq!(ranges[0].start, 18);
+
+        // A phrase is one contiguous range
+        let ranges = find_highlight_ranges(conn, id, "\"systems programming\"").unwrap();
+        assert_eq!(ranges, vec![range_of("systems programming", 0)]);
+
+        assert!(find_highlight_ranges(conn, id, "python").unwrap().is_empty());
+        assert!(find_highlight_ranges(conn, id, " ").is_err());
+    }
+
+    #[test]
+    fn test_results_split_across_collections_and_uncollected() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let add = |name: &str, text: &str| {
+            let id = insert_file(conn, &format!("/test/{}", name), name, "txt", 16,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(conn, id, Some(text), None).unwrap();
+            id
+        };
+        let plan = add("plan.txt", "quarterly budget plan");
+        let notes = add("notes.txt", "budget meeting notes");
+        let loose = add("loose.txt", "budget scratchpad");
+        let other = add("other.txt", "holiday photos");
+
+        let work = crate::db::create_collection(conn, "work").unwrap();
+        let archive = crate::db::create_collection(conn, "archive").unwrap();
+        crate::db::add_files_to_collection(conn, work.id, &[plan, notes, other]).unwrap();
+        crate::db::add_files_to_collection(conn, archive.id, &[notes]).unwrap();
+
+        let results = find_matching_results(conn, "budget", None, 50).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let groups = group_by_collection(conn, results).unwrap();
+        let summary: Vec<(Option<i64>, &str, usize)> = groups
+            .iter()
+            .map(|group| (group.collection_id, group.name.as_str(), group.count))
+            .collect();
+        assert_eq!(summary, vec![
+            (Some(archive.id), "archive", 1),
+            (Some(work.id), "work", 2),
+            (None, crate::db::UNCOLLECTED, 1),
+        ]);
+
+        let ids = |group: &CollectionGroup| {
+            let mut ids: Vec<i64> = group.results.iter().map(|r| r.file_id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&groups[0]), vec![notes]);
+        assert_eq!(ids(&groups[1]), vec![plan, notes]);
+        assert_eq!(ids(&groups[2]), vec![loose]);
+    }
 }
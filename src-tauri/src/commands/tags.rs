@@ -0,0 +1,73 @@
+use crate::state::AppState;
+use tauri::State;
+
+/// Attach tags to many files at once
+///
+/// Returns the number of new file/tag pairs; tags a file already has are
+/// skipped.
+#[tauri::command]
+pub async fn add_tags_to_files(
+    file_ids: Vec<i64>,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    log::info!("Adding {} tags to {} files", tags.len(), file_ids.len());
+
+    state
+        .db_pool
+        .write(move |conn| crate::db::tags::add_tags_to_files(conn, &file_ids, &tags))
+        .map_err(|e| e.to_string())
+}
+
+/// Detach a tag from a file
+#[tauri::command]
+pub async fn remove_file_tag(
+    file_id: i64,
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::tags::remove_tag_from_file(conn, file_id, &tag))
+        .map_err(|e| e.to_string())
+}
+
+/// Tags on a single file
+#[tauri::command]
+pub async fn get_file_tags(file_id: i64, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    state
+        .db_pool
+        .read(|conn| crate::db::tags::get_file_tags(conn, file_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Every tag in use with its file count, for building a tag facet
+#[tauri::command]
+pub async fn list_tags(state: State<'_, AppState>) -> Result<Vec<(String, i64)>, String> {
+    state.db_pool.read(crate::db::tags::list_tags).map_err(|e| e.to_string())
+}
+
+/// Pin a file so it ranks higher in search and is always included in
+/// context exports
+#[tauri::command]
+pub async fn pin_file(file_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::pin_file(conn, file_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Undo [`pin_file`]
+#[tauri::command]
+pub async fn unpin_file(file_id: i64, state: State<'_, AppState>) -> Result<bool, String> {
+    state
+        .db_pool
+        .write(move |conn| crate::db::unpin_file(conn, file_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Every pinned file
+#[tauri::command]
+pub async fn get_pinned_files(state: State<'_, AppState>) -> Result<Vec<crate::db::File>, String> {
+    state.db_pool.read(crate::db::list_pinned_files).map_err(|e| e.to_string())
+}
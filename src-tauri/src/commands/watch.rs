@@ -0,0 +1,179 @@
+//! Keeping the index in step with watched directories
+//!
+//! While watching, changed files are indexed as they are saved and removed
+//! ones are soft-deleted once they have stayed missing for the grace period,
+//! so an atomic save never hides a file from search.
+
+use crate::commands::indexing::index_file_cached;
+use crate::db::{get_file_by_path, mark_file_deleted, Database};
+use crate::error::CortexError;
+use crate::indexer::{
+    is_supported_path, ContentExtractor, FileWatcher, IndexJob, ProfileRoots, WatchLoop,
+    DEFAULT_DELETE_GRACE,
+};
+use crate::paths::normalize_roots;
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::State;
+
+/// Start watching `paths`, replacing any earlier watch
+///
+/// A removed file is soft-deleted only if it is still missing
+/// `delete_grace_ms` later (default 2000), so editors that save by replacing
+/// the file don't make it flicker out of the index.
+#[tauri::command]
+pub async fn start_watching(
+    paths: Vec<String>,
+    delete_grace_ms: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let normalized = normalize_roots(&paths);
+    for (path, e) in &normalized.unresolved {
+        log::warn!("Not watching {}: {}", path, e);
+    }
+
+    let grace = delete_grace_ms.map_or(DEFAULT_DELETE_GRACE, Duration::from_millis);
+    let watch = watch_roots(Arc::clone(&state.db), normalized.roots, grace).map_err(|e| e.to_string())?;
+
+    // The previous loop, if any, stops when dropped
+    *state.watcher.lock().unwrap() = Some(watch);
+    Ok(())
+}
+
+/// Stop watching; returns whether a watch was running
+#[tauri::command]
+pub async fn stop_watching(state: State<'_, AppState>) -> Result<bool, String> {
+    let watch = state.watcher.lock().unwrap().take();
+    let was_watching = watch.is_some();
+    if let Some(watch) = watch {
+        watch.stop();
+    }
+
+    Ok(was_watching)
+}
+
+/// Watch `roots` on a background thread, applying changes to `db`
+///
+/// The database lock is taken per file, so indexing and searches
+/// interleave with the watcher's writes.
+pub fn watch_roots(
+    db: Arc<Mutex<Database>>,
+    roots: Vec<PathBuf>,
+    grace: Duration,
+) -> Result<WatchLoop, CortexError> {
+    let watcher = FileWatcher::with_delete_grace(roots.clone(), grace)?;
+    let changed_db = Arc::clone(&db);
+
+    Ok(WatchLoop::spawn(
+        watcher,
+        move |job| {
+            let db = changed_db.lock().unwrap();
+            if let Err(e) = index_changed_file(db.get_connection(), &job, &roots) {
+                log::warn!("Failed to index {}: {}", job.path.display(), e);
+            }
+        },
+        move |removed| {
+            let db = db.lock().unwrap();
+            match delete_removed_files(db.get_connection(), &removed) {
+                Ok(0) => {}
+                Ok(deleted) => log::info!("Soft-deleted {} removed files", deleted),
+                Err(e) => log::warn!("Failed to soft-delete removed files: {}", e),
+            }
+        },
+    ))
+}
+
+/// Index a file the watcher saw change, if the scanner would index it
+fn index_changed_file(conn: &Connection, job: &IndexJob, roots: &[PathBuf]) -> Result<(), CortexError> {
+    if !is_supported_path(&job.path) {
+        return Ok(());
+    }
+
+    let modified_at = DateTime::<Utc>::from(job.modified).to_rfc3339();
+    let profile_roots = ProfileRoots::load(conn)?;
+    let extract =
+        |path: &Path| ContentExtractor::extract_with_options(path, profile_roots.extract_options(path));
+    index_file_cached(conn, &job.path, job.size, &modified_at, roots, extract)?;
+    Ok(())
+}
+
+/// Soft-delete the indexed files among `removed`, returning how many were
+/// deleted
+pub fn delete_removed_files(conn: &Connection, removed: &[PathBuf]) -> Result<usize, CortexError> {
+    let mut deleted = 0;
+    for path in removed {
+        if let Some(file) = get_file_by_path(conn, &path.to_string_lossy())? {
+            if !file.is_deleted {
+                mark_file_deleted(conn, file.id)?;
+                deleted += 1;
+            }
+        }
+    }
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::indexing::store_indexed_file;
+    use crate::indexer::ContentExtractor;
+    use std::path::Path;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    fn index(db: &Mutex<Database>, path: &Path) -> i64 {
+        let db = db.lock().unwrap();
+        let size = std::fs::metadata(path).unwrap().len();
+        let extracted = ContentExtractor::extract(path).unwrap();
+        store_indexed_file(db.get_connection(), path, size, &Utc::now().to_rfc3339(), &extracted).unwrap()
+    }
+
+    fn is_deleted(db: &Mutex<Database>, path: &Path) -> bool {
+        let db = db.lock().unwrap();
+        get_file_by_path(db.get_connection(), &path.to_string_lossy()).unwrap().unwrap().is_deleted
+    }
+
+    #[test]
+    fn test_watcher_soft_deletes_only_files_that_stay_removed() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let removed = root.join("old-notes.txt");
+        let saved = root.join("notes.txt");
+        std::fs::write(&removed, "obsolete notes").unwrap();
+        std::fs::write(&saved, "initial content").unwrap();
+
+        let db = Arc::new(Mutex::new(Database::open_in_memory().unwrap()));
+        index(&db, &removed);
+        index(&db, &saved);
+
+        let grace = Duration::from_millis(300);
+        let watch = watch_roots(Arc::clone(&db), vec![root.clone()], grace).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        // An atomic save removes and recreates within the grace period
+        std::fs::remove_file(&removed).unwrap();
+        std::fs::remove_file(&saved).unwrap();
+        std::fs::write(&saved, "saved content").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !is_deleted(&db, &removed) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        std::thread::sleep(grace * 2);
+        watch.stop();
+
+        assert!(is_deleted(&db, &removed), "a removed file is soft-deleted after the grace period");
+        assert!(!is_deleted(&db, &saved), "a recreated file is never soft-deleted");
+
+        // ...and its new content was indexed
+        let db = db.lock().unwrap();
+        let file = get_file_by_path(db.get_connection(), &saved.to_string_lossy()).unwrap().unwrap();
+        let content = crate::db::get_file_content(db.get_connection(), file.id).unwrap().unwrap();
+        assert_eq!(content.text_content.as_deref(), Some("saved content"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Bookmarks
+//!
+//! A flat personal list of files saved for later, lighter than tags or
+//! collections. Bookmarks outlive soft deletion so the list doesn't silently
+//! shrink; the listed file's `is_deleted` shows it is gone.
+
+use crate::db::operations::file_from_row;
+use crate::db::schema::File;
+use crate::error::{CortexError, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Bookmark a file
+///
+/// Returns false if it was already bookmarked. Fails with `FileNotFound`
+/// for unknown ids and for soft-deleted files, which have nothing left to
+/// come back to.
+pub fn add_bookmark(conn: &Connection, file_id: i64) -> Result<bool> {
+    let file: Option<(String, bool)> = conn
+        .query_row(
+            "SELECT path, is_deleted FROM files WHERE id = ?1",
+            params![file_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    match file {
+        None => Err(CortexError::FileNotFound {
+            path: format!("file id {}", file_id),
+        }),
+        Some((path, true)) => Err(CortexError::FileNotFound { path }),
+        Some(_) => {
+            let added = conn.execute(
+                "INSERT OR IGNORE INTO bookmarks (file_id, created_at) VALUES (?1, ?2)",
+                params![file_id, chrono::Utc::now().to_rfc3339()],
+            )?;
+            Ok(added > 0)
+        }
+    }
+}
+
+/// Remove a bookmark; returns false if the file wasn't bookmarked
+pub fn remove_bookmark(conn: &Connection, file_id: i64) -> Result<bool> {
+    let removed = conn.execute("DELETE FROM bookmarks WHERE file_id = ?1", params![file_id])?;
+    Ok(removed > 0)
+}
+
+/// Bookmarked files with their current metadata, most recently bookmarked
+/// first
+///
+/// Includes files soft-deleted since they were bookmarked, with
+/// `is_deleted` set.
+pub fn list_bookmarks(conn: &Connection) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
+                f.last_indexed, f.hash, f.root_path, f.is_deleted
+         FROM bookmarks b
+         JOIN files f ON f.id = b.file_id
+         ORDER BY b.created_at DESC, b.rowid DESC",
+    )?;
+
+    let files = stmt
+        .query_map([], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::{insert_file, mark_file_deleted, update_file};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(conn, &format!("/test/{}", name), name, "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+    }
+
+    #[test]
+    fn test_bookmark_round_trip() {
+        let conn = setup_test_db();
+        let a = add_file(&conn, "a.txt");
+        let b = add_file(&conn, "b.txt");
+
+        assert!(add_bookmark(&conn, a).unwrap());
+        assert!(add_bookmark(&conn, b).unwrap());
+        assert!(!add_bookmark(&conn, a).unwrap());
+
+        let ids: Vec<i64> = list_bookmarks(&conn).unwrap().iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![b, a]);
+
+        // Listing reads the file's metadata as it is now
+        update_file(&conn, a, Some(4096), Some("2026-01-01T00:00:00Z"), None).unwrap();
+        let listed = list_bookmarks(&conn).unwrap();
+        let a_listed = listed.iter().find(|f| f.id == a).unwrap();
+        assert_eq!(a_listed.size, 4096);
+        assert_eq!(a_listed.modified_at, "2026-01-01T00:00:00Z");
+
+        assert!(remove_bookmark(&conn, b).unwrap());
+        assert!(!remove_bookmark(&conn, b).unwrap());
+        let ids: Vec<i64> = list_bookmarks(&conn).unwrap().iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![a]);
+    }
+
+    #[test]
+    fn test_deleted_files_and_bookmarks() {
+        let conn = setup_test_db();
+        let kept = add_file(&conn, "kept.txt");
+        let gone = add_file(&conn, "gone.txt");
+
+        assert!(matches!(add_bookmark(&conn, 9999), Err(CortexError::FileNotFound { .. })));
+
+        mark_file_deleted(&conn, gone).unwrap();
+        match add_bookmark(&conn, gone) {
+            Err(CortexError::FileNotFound { path }) => assert_eq!(path, "/test/gone.txt"),
+            other => panic!("expected FileNotFound, got {:?}", other),
+        }
+
+        // A bookmark stays, flagged, when its file is deleted afterwards
+        add_bookmark(&conn, kept).unwrap();
+        mark_file_deleted(&conn, kept).unwrap();
+        let listed = list_bookmarks(&conn).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].is_deleted);
+    }
+}
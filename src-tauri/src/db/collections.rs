@@ -0,0 +1,263 @@
+//! Collections
+//!
+//! Named groups of files picked by the user, e.g. everything for one
+//! project. A file can be in any number of collections, and search results
+//! can be grouped by them.
+
+use crate::db::schema::SearchResult;
+use crate::error::{CortexError, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Name of the bucket for results in no collection
+pub const UNCOLLECTED: &str = "Uncollected";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    /// Member files, soft-deleted ones included
+    pub file_count: i64,
+}
+
+/// Search results that belong to one collection, or to none
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionGroup {
+    /// `None` for the uncollected bucket
+    pub collection_id: Option<i64>,
+    pub name: String,
+    pub results: Vec<SearchResult>,
+    pub count: usize,
+}
+
+/// Create an empty collection
+///
+/// Fails with `InvalidInput` if the name is blank or already taken.
+pub fn create_collection(conn: &Connection, name: &str) -> Result<Collection> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CortexError::InvalidInput {
+            message: "Collection names can't be empty".to_string(),
+        });
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO collections (name, created_at) VALUES (?1, ?2)",
+        params![name, now],
+    )?;
+    if inserted == 0 {
+        return Err(CortexError::InvalidInput {
+            message: format!("Collection already exists: {}", name),
+        });
+    }
+
+    Ok(Collection {
+        id: conn.last_insert_rowid(),
+        name: name.to_string(),
+        created_at: now,
+        file_count: 0,
+    })
+}
+
+/// Delete a collection, leaving its files indexed; returns false if there
+/// was no such collection
+pub fn delete_collection(conn: &Connection, collection_id: i64) -> Result<bool> {
+    let deleted = conn.execute("DELETE FROM collections WHERE id = ?1", params![collection_id])?;
+    Ok(deleted > 0)
+}
+
+/// Every collection with its file count, by name
+pub fn list_collections(conn: &Connection) -> Result<Vec<Collection>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.created_at, COUNT(cf.file_id)
+         FROM collections c
+         LEFT JOIN collection_files cf ON cf.collection_id = c.id
+         GROUP BY c.id
+         ORDER BY c.name, c.id",
+    )?;
+
+    let collections = stmt
+        .query_map([], |row| {
+            Ok(Collection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+                file_count: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(collections)
+}
+
+/// Add files to a collection in one transaction
+///
+/// Unknown file ids and files already in the collection are skipped.
+/// Returns the number of files added; fails with `InvalidInput` if the
+/// collection doesn't exist.
+pub fn add_files_to_collection(conn: &Connection, collection_id: i64, file_ids: &[i64]) -> Result<usize> {
+    ensure_collection(conn, collection_id)?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let tx = conn.unchecked_transaction()?;
+    let mut added = 0;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO collection_files (collection_id, file_id, added_at)
+             SELECT ?1, id, ?3 FROM files WHERE id = ?2",
+        )?;
+
+        for file_id in file_ids {
+            added += stmt.execute(params![collection_id, file_id, now])?;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(added)
+}
+
+/// Remove files from a collection; returns how many were in it
+pub fn remove_files_from_collection(conn: &Connection, collection_id: i64, file_ids: &[i64]) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut removed = 0;
+
+    for file_id in file_ids {
+        removed += tx.execute(
+            "DELETE FROM collection_files WHERE collection_id = ?1 AND file_id = ?2",
+            params![collection_id, file_id],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(removed)
+}
+
+/// Whether a collection with this id exists
+pub fn collection_exists(conn: &Connection, collection_id: i64) -> Result<bool> {
+    let exists = conn
+        .query_row("SELECT 1 FROM collections WHERE id = ?1", params![collection_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+
+    Ok(exists)
+}
+
+fn ensure_collection(conn: &Connection, collection_id: i64) -> Result<()> {
+    if collection_exists(conn, collection_id)? {
+        Ok(())
+    } else {
+        Err(CortexError::InvalidInput {
+            message: format!("Unknown collection: {}", collection_id),
+        })
+    }
+}
+
+/// Bucket `results` by the collections their files are in
+///
+/// A file in several collections is listed in each. Results keep their
+/// order within a bucket; collections come by name, and the
+/// [`UNCOLLECTED`] bucket last. Collections with no results are left out.
+pub fn group_by_collection(conn: &Connection, results: Vec<SearchResult>) -> Result<Vec<CollectionGroup>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name
+         FROM collection_files cf
+         JOIN collections c ON c.id = cf.collection_id
+         WHERE cf.file_id = ?1",
+    )?;
+
+    let mut groups: HashMap<i64, CollectionGroup> = HashMap::new();
+    let mut uncollected = Vec::new();
+
+    for result in results {
+        let collections = stmt
+            .query_map(params![result.file_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if collections.is_empty() {
+            uncollected.push(result);
+            continue;
+        }
+
+        for (id, name) in collections {
+            groups
+                .entry(id)
+                .or_insert_with(|| CollectionGroup {
+                    collection_id: Some(id),
+                    name,
+                    results: Vec::new(),
+                    count: 0,
+                })
+                .results
+                .push(result.clone());
+        }
+    }
+
+    let mut groups: Vec<CollectionGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.collection_id.cmp(&b.collection_id)));
+
+    if !uncollected.is_empty() {
+        groups.push(CollectionGroup {
+            collection_id: None,
+            name: UNCOLLECTED.to_string(),
+            results: uncollected,
+            count: 0,
+        });
+    }
+
+    for group in &mut groups {
+        group.count = group.results.len();
+    }
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::{delete_file, insert_file};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(conn, &format!("/test/{}", name), name, "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+    }
+
+    #[test]
+    fn test_collection_membership() {
+        let conn = setup_test_db();
+        let a = add_file(&conn, "a.txt");
+        let b = add_file(&conn, "b.txt");
+
+        let work = create_collection(&conn, " work ").unwrap();
+        assert_eq!(work.name, "work");
+        assert!(matches!(create_collection(&conn, "work"), Err(CortexError::InvalidInput { .. })));
+        assert!(matches!(create_collection(&conn, "  "), Err(CortexError::InvalidInput { .. })));
+
+        assert_eq!(add_files_to_collection(&conn, work.id, &[a, b, a, 9999]).unwrap(), 2);
+        assert!(matches!(add_files_to_collection(&conn, 9999, &[a]), Err(CortexError::InvalidInput { .. })));
+        assert_eq!(list_collections(&conn).unwrap()[0].file_count, 2);
+
+        // Memberships go with the file, and with the collection
+        delete_file(&conn, b).unwrap();
+        assert_eq!(list_collections(&conn).unwrap()[0].file_count, 1);
+        assert_eq!(remove_files_from_collection(&conn, work.id, &[a, b]).unwrap(), 1);
+
+        add_files_to_collection(&conn, work.id, &[a]).unwrap();
+        assert!(delete_collection(&conn, work.id).unwrap());
+        assert!(!delete_collection(&conn, work.id).unwrap());
+        let members: i64 = conn.query_row("SELECT COUNT(*) FROM collection_files", [], |row| row.get(0)).unwrap();
+        assert_eq!(members, 0);
+    }
+}
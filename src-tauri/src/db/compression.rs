@@ -0,0 +1,198 @@
+//! Compressed file content
+//!
+//! With [`CortexConfig::compress_content`](crate::db::CortexConfig) set,
+//! extracted text is stored gzip-compressed in `file_content.text_compressed`
+//! instead of as plain text. Reads decompress it transparently, and the FTS
+//! index still holds the plain text, so searching is unaffected.
+
+use crate::db::config::get_config;
+use crate::db::operations::upsert_file_content;
+use crate::error::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// How much space compression saves in `file_content`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentStorageStats {
+    /// Rows with extracted text
+    pub rows: i64,
+    pub compressed_rows: i64,
+    /// Bytes of extracted text, uncompressed
+    pub text_bytes: i64,
+    /// Bytes actually stored for it
+    pub stored_bytes: i64,
+    pub saved_bytes: i64,
+}
+
+/// Gzip `text`
+pub fn compress_text(text: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish()
+}
+
+/// Undo [`compress_text`]
+pub fn decompress_text(bytes: &[u8]) -> std::io::Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(bytes).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// A `file_content` row's text, selected as `text_content` at `idx`
+/// followed by `text_compressed`
+pub(crate) fn read_text(row: &Row, idx: usize) -> rusqlite::Result<Option<String>> {
+    match row.get::<_, Option<Vec<u8>>>(idx + 1)? {
+        Some(bytes) => decompress_text(&bytes)
+            .map(Some)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx + 1, Type::Blob, Box::new(e))),
+        None => row.get(idx),
+    }
+}
+
+/// Space used by extracted text, compressed and not
+pub fn get_content_storage_stats(conn: &Connection) -> Result<ContentStorageStats> {
+    let (rows, compressed_rows, text_bytes, stored_bytes): (i64, i64, i64, i64) = conn.query_row(
+        "SELECT COUNT(*),
+                COALESCE(SUM(is_compressed), 0),
+                COALESCE(SUM(CASE WHEN is_compressed = 1 THEN text_size
+                                  ELSE length(CAST(text_content AS BLOB)) END), 0),
+                COALESCE(SUM(CASE WHEN is_compressed = 1 THEN length(text_compressed)
+                                  ELSE length(CAST(text_content AS BLOB)) END), 0)
+         FROM file_content
+         WHERE text_content IS NOT NULL OR is_compressed = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+
+    Ok(ContentStorageStats {
+        rows,
+        compressed_rows,
+        text_bytes,
+        stored_bytes,
+        saved_bytes: text_bytes - stored_bytes,
+    })
+}
+
+/// Rewrite existing content to match the current `compress_content`
+/// setting, returning how many rows were rewritten
+///
+/// Freed pages are only returned to the filesystem by a later `VACUUM`.
+pub fn apply_content_compression(conn: &Connection) -> Result<usize> {
+    let compress = get_config(conn)?.compress_content;
+
+    let rows: Vec<(i64, Option<String>, Option<String>)> = {
+        let mut stmt = conn.prepare(
+            "SELECT file_id, text_content, text_compressed, summary FROM file_content
+             WHERE is_compressed = ?1 AND (text_content IS NOT NULL OR is_compressed = 1)",
+        )?;
+        let rows = stmt
+            .query_map(params![!compress], |row| Ok((row.get(0)?, read_text(row, 1)?, row.get(3)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    for (file_id, text, summary) in &rows {
+        upsert_file_content(&tx, *file_id, text.as_deref(), summary.as_deref())?;
+    }
+    tx.commit()?;
+
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::config::{set_config, CortexConfig};
+    use crate::db::schema::create_tables;
+    use crate::db::{get_file_content, insert_file, mark_file_deleted, restore_file, search_files_fts};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(conn, &format!("/test/{}", name), name, "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+    }
+
+    fn set_compression(conn: &Connection, compress_content: bool) {
+        set_config(conn, &CortexConfig { compress_content, ..Default::default() }).unwrap();
+    }
+
+    fn search_ids(conn: &Connection, query: &str) -> Vec<i64> {
+        search_files_fts(conn, query, 10).unwrap().iter().map(|r| r.file_id).collect()
+    }
+
+    #[test]
+    fn test_compressed_content_round_trips_and_stays_searchable() {
+        let conn = setup_test_db();
+        set_compression(&conn, true);
+
+        let id = add_file(&conn, "notes.txt");
+        let text = "the quokka is a small marsupial. ".repeat(200);
+        upsert_file_content(&conn, id, Some(&text), Some("quokka notes")).unwrap();
+
+        let (stored, compressed): (Option<String>, bool) = conn
+            .query_row("SELECT text_content, is_compressed FROM file_content WHERE file_id = ?1",
+                params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(stored, None);
+        assert!(compressed);
+
+        let content = get_file_content(&conn, id).unwrap().unwrap();
+        assert_eq!(content.text_content.as_deref(), Some(text.as_str()));
+        assert_eq!(content.word_count, Some(1200));
+        assert_eq!(search_ids(&conn, "marsupial"), vec![id]);
+
+        let stats = get_content_storage_stats(&conn).unwrap();
+        assert_eq!(stats.compressed_rows, 1);
+        assert_eq!(stats.text_bytes, text.len() as i64);
+        assert!(stats.saved_bytes > stats.text_bytes / 2);
+
+        // Updates and restores keep the index in step with compressed rows
+        upsert_file_content(&conn, id, Some(&"a wombat burrow. ".repeat(200)), None).unwrap();
+        assert!(search_ids(&conn, "marsupial").is_empty());
+        assert_eq!(search_ids(&conn, "wombat"), vec![id]);
+
+        mark_file_deleted(&conn, id).unwrap();
+        restore_file(&conn, id).unwrap();
+        assert_eq!(search_ids(&conn, "wombat"), vec![id]);
+    }
+
+    #[test]
+    fn test_apply_content_compression_rewrites_existing_rows() {
+        let conn = setup_test_db();
+
+        let long = add_file(&conn, "long.txt");
+        let short = add_file(&conn, "short.txt");
+        let long_text = "pangolin scales overlap like tiles. ".repeat(100);
+        upsert_file_content(&conn, long, Some(&long_text), None).unwrap();
+        upsert_file_content(&conn, short, Some("pangolin"), None).unwrap();
+        assert_eq!(get_content_storage_stats(&conn).unwrap().saved_bytes, 0);
+
+        set_compression(&conn, true);
+        apply_content_compression(&conn).unwrap();
+
+        // Text too short to shrink stays plain
+        let stats = get_content_storage_stats(&conn).unwrap();
+        assert_eq!((stats.rows, stats.compressed_rows), (2, 1));
+        assert!(stats.saved_bytes > 0);
+        let mut ids = search_ids(&conn, "pangolin");
+        ids.sort();
+        assert_eq!(ids, vec![long, short]);
+
+        set_compression(&conn, false);
+        assert_eq!(apply_content_compression(&conn).unwrap(), 1);
+        assert_eq!(get_content_storage_stats(&conn).unwrap().compressed_rows, 0);
+        assert_eq!(get_file_content(&conn, long).unwrap().unwrap().text_content, Some(long_text));
+        assert_eq!(search_ids(&conn, "tiles"), vec![long]);
+    }
+}
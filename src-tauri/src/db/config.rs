@@ -0,0 +1,156 @@
+//! Index settings
+//!
+//! Settings that change how the index stores its data are kept in the
+//! database itself, so they travel with it and every connection sees the
+//! same values.
+
+use crate::ai::ChunkConfig;
+use crate::db::WriterConfig;
+use crate::error::{CortexError, Result};
+use crate::indexer::{ExtractOptions, IndexTarget, IndexingProfile, PlannedRoot, ProfileRoot};
+use crate::paths::path_key;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const COMPRESS_CONTENT: &str = "compress_content";
+const INDEXING_PROFILES: &str = "indexing_profiles";
+const AUTO_INDEX_ON_STARTUP: &str = "auto_index_on_startup";
+const AUTO_INDEX_ROOTS: &str = "auto_index_roots";
+const EMBEDDING_CHUNKS: &str = "embedding_chunks";
+const DATABASE_POOL: &str = "database_pool";
+const PROFILE_ROOTS: &str = "profile_roots";
+const EXTRACTION: &str = "extraction";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CortexConfig {
+    /// Store extracted text gzip-compressed, trading some CPU on reads and
+    /// writes for a smaller database
+    #[serde(default)]
+    pub compress_content: bool,
+    /// Named scanner settings an indexing root can opt into
+    #[serde(default)]
+    pub indexing_profiles: Vec<IndexingProfile>,
+    /// Re-index `auto_index_roots` incrementally in the background on launch
+    #[serde(default)]
+    pub auto_index_on_startup: bool,
+    #[serde(default)]
+    pub auto_index_roots: Vec<IndexTarget>,
+    /// Chunking every embedding is made with, set by `reembed_all`; the
+    /// model defaults when unset
+    #[serde(default)]
+    pub embedding_chunks: Option<ChunkConfig>,
+    /// Queued writes and idle read connections of the database pool; takes
+    /// effect on the next launch
+    #[serde(default)]
+    pub database_pool: WriterConfig,
+    /// Extractor settings for every root; a root's indexing profile can
+    /// override them
+    #[serde(default)]
+    pub extraction: ExtractOptions,
+}
+
+fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()?)
+}
+
+fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+fn get_json_setting<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T> {
+    match get_setting(conn, key)? {
+        Some(json) => serde_json::from_str(&json).map_err(|e| CortexError::Internal {
+            message: format!("Invalid {} setting: {}", key, e),
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+fn set_json_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<()> {
+    let json = serde_json::to_string(value).map_err(|e| CortexError::Internal {
+        message: format!("Invalid {} setting: {}", key, e),
+    })?;
+    set_setting(conn, key, &json)
+}
+
+fn get_flag(conn: &Connection, key: &str) -> Result<bool> {
+    Ok(get_setting(conn, key)?.is_some_and(|value| value == "1"))
+}
+
+fn set_flag(conn: &Connection, key: &str, value: bool) -> Result<()> {
+    set_setting(conn, key, if value { "1" } else { "0" })
+}
+
+/// The index's settings; unset ones take their defaults
+pub fn get_config(conn: &Connection) -> Result<CortexConfig> {
+    Ok(CortexConfig {
+        compress_content: get_flag(conn, COMPRESS_CONTENT)?,
+        indexing_profiles: get_json_setting(conn, INDEXING_PROFILES)?,
+        auto_index_on_startup: get_flag(conn, AUTO_INDEX_ON_STARTUP)?,
+        auto_index_roots: get_json_setting(conn, AUTO_INDEX_ROOTS)?,
+        embedding_chunks: get_json_setting(conn, EMBEDDING_CHUNKS)?,
+        database_pool: get_json_setting(conn, DATABASE_POOL)?,
+        extraction: get_json_setting(conn, EXTRACTION)?,
+    })
+}
+
+/// Store `config` as the index's settings
+///
+/// Only affects data written afterwards; see
+/// [`apply_content_compression`](crate::db::apply_content_compression) to
+/// rewrite existing content, and `reembed_all` to re-embed under new
+/// chunking. Fails if a profile name is empty or used twice, or the
+/// chunking is out of range.
+pub fn set_config(conn: &Connection, config: &CortexConfig) -> Result<()> {
+    for (i, profile) in config.indexing_profiles.iter().enumerate() {
+        let message = if profile.name.trim().is_empty() {
+            "Indexing profile names can't be empty".to_string()
+        } else if config.indexing_profiles[..i].iter().any(|p| p.name == profile.name) {
+            format!("Duplicate indexing profile: {}", profile.name)
+        } else {
+            continue;
+        };
+        return Err(CortexError::InvalidInput { message });
+    }
+    if let Some(message) = config.embedding_chunks.and_then(|chunks| chunks.validation_error()) {
+        return Err(CortexError::InvalidInput { message });
+    }
+
+    set_flag(conn, COMPRESS_CONTENT, config.compress_content)?;
+    set_json_setting(conn, INDEXING_PROFILES, &config.indexing_profiles)?;
+    set_flag(conn, AUTO_INDEX_ON_STARTUP, config.auto_index_on_startup)?;
+    set_json_setting(conn, AUTO_INDEX_ROOTS, &config.auto_index_roots)?;
+    set_json_setting(conn, EMBEDDING_CHUNKS, &config.embedding_chunks)?;
+    set_json_setting(conn, DATABASE_POOL, &config.database_pool)?;
+    set_json_setting(conn, EXTRACTION, &config.extraction)?;
+
+    Ok(())
+}
+
+/// Roots last indexed under a named profile
+pub fn get_profile_roots(conn: &Connection) -> Result<Vec<ProfileRoot>> {
+    get_json_setting(conn, PROFILE_ROOTS)
+}
+
+/// Record the profile each of `roots` is indexed under
+///
+/// Replaces what was recorded for these roots and any root inside them, so
+/// a root re-indexed without a profile goes back to the defaults.
+pub fn record_profile_roots(conn: &Connection, roots: &[PlannedRoot]) -> Result<()> {
+    let key = |path: &std::path::Path| std::path::PathBuf::from(path_key(&path.to_string_lossy()));
+    let mut recorded = get_profile_roots(conn)?;
+    recorded.retain(|recorded| roots.iter().all(|root| !key(&recorded.path).starts_with(key(&root.path))));
+    recorded.extend(roots.iter().filter(|root| !root.profile.name.is_empty()).map(|root| ProfileRoot {
+        path: root.path.clone(),
+        profile: root.profile.name.clone(),
+    }));
+    set_json_setting(conn, PROFILE_ROOTS, &recorded)
+}
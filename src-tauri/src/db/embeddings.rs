@@ -0,0 +1,501 @@
+//! Embedding storage (Phase 2: AI Features)
+//!
+//! Persists one embedding vector per file in the `file_embeddings` table.
+//!
+//! ## BLOB format
+//!
+//! Vectors are stored as a compact binary BLOB so `get_all_embeddings` can
+//! load large corpora without any text parsing:
+//!
+//! ```text
+//! [0..2]  magic b"CX"
+//! [2]     format version (1)
+//! [3]     model id (1 = all-MiniLM-L6-v2)
+//! [4..8]  dimension, u32 little-endian
+//! [8..]   dimension * f32, little-endian
+//! ```
+
+use crate::db::operations::{file_from_row, resolve_content_owner};
+use crate::db::schema::{File, FileEmbedding};
+use crate::error::{CortexError, Result};
+use rusqlite::{params, Connection};
+
+/// Embedding dimension produced by all-MiniLM-L6-v2
+pub const EMBEDDING_DIM: usize = 384;
+
+/// Model id written into the BLOB header for all-MiniLM-L6-v2
+pub const MODEL_ID_MINILM_L6_V2: u8 = 1;
+
+const BLOB_MAGIC: &[u8; 2] = b"CX";
+const BLOB_FORMAT_VERSION: u8 = 1;
+const BLOB_HEADER_LEN: usize = 8;
+
+/// Insert or update file embedding
+pub fn upsert_embedding(
+    conn: &Connection,
+    file_id: i64,
+    embedding: &[f32],
+    model_version: &str,
+) -> Result<()> {
+    if embedding.len() != EMBEDDING_DIM {
+        return Err(CortexError::Internal {
+            message: format!(
+                "Invalid embedding dimension: expected {}, got {}",
+                EMBEDDING_DIM,
+                embedding.len()
+            ),
+        });
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let bytes = encode_embedding(embedding);
+
+    // Duplicates share their owner's embedding
+    let file_id = resolve_content_owner(conn, file_id)?;
+
+    conn.execute(
+        "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(file_id) DO UPDATE SET
+            embedding = excluded.embedding,
+            model_version = excluded.model_version,
+            created_at = excluded.created_at",
+        params![file_id, bytes, model_version, now],
+    )?;
+
+    Ok(())
+}
+
+/// Get embedding for a file (a duplicate gets its owner's embedding)
+pub fn get_embedding(conn: &Connection, file_id: i64) -> Result<Option<FileEmbedding>> {
+    let mut stmt = conn.prepare(
+        "SELECT ?1, embedding, model_version, created_at
+         FROM file_embeddings WHERE file_id = ?2"
+    )?;
+
+    let owner_id = resolve_content_owner(conn, file_id)?;
+    let result = stmt.query_row(params![file_id, owner_id], |row| {
+        let bytes: Vec<u8> = row.get(1)?;
+        Ok((row.get(0)?, bytes, row.get(2)?, row.get(3)?))
+    });
+
+    match result {
+        Ok((file_id, bytes, model_version, created_at)) => Ok(Some(FileEmbedding {
+            file_id,
+            embedding: decode_embedding(&bytes)?,
+            model_version,
+            created_at,
+        })),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Get all embeddings (for semantic search)
+///
+/// Rows whose BLOB fails to decode are skipped with a warning so a single
+/// corrupt vector can't break search for the whole corpus.
+pub fn get_all_embeddings(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>> {
+    query_embeddings(conn, "SELECT file_id, embedding FROM file_embeddings", [])
+}
+
+/// Embeddings of non-deleted files stored under `model_version`, for
+/// similarity searches
+///
+/// Soft-deleted files keep their embeddings in case they are restored, and
+/// embeddings whose file row is gone (see [`list_orphaned_embeddings`]) wait
+/// for cleanup; neither can be a search result. Embeddings of another
+/// version, e.g. of summaries rather than full text, aren't comparable.
+pub fn get_live_embeddings(conn: &Connection, model_version: &str) -> Result<Vec<(i64, Vec<f32>)>> {
+    query_embeddings(
+        conn,
+        "SELECT e.file_id, e.embedding FROM file_embeddings e
+         JOIN files f ON f.id = e.file_id
+         WHERE f.is_deleted = 0 AND e.model_version = ?1",
+        params![model_version],
+    )
+}
+
+/// Decode the `(file_id, embedding)` rows `sql` selects, skipping corrupt ones
+fn query_embeddings(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut stmt = conn.prepare(sql)?;
+
+    let rows = stmt
+        .query_map(params, |row| {
+            let file_id: i64 = row.get(0)?;
+            let bytes: Vec<u8> = row.get(1)?;
+            Ok((file_id, bytes))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut embeddings = Vec::with_capacity(rows.len());
+    for (file_id, bytes) in rows {
+        match decode_embedding(&bytes) {
+            Ok(embedding) => embeddings.push((file_id, embedding)),
+            Err(e) => log::warn!("Skipping corrupt embedding for file {}: {}", file_id, e),
+        }
+    }
+
+    Ok(embeddings)
+}
+
+/// Ids of embeddings whose file no longer exists
+///
+/// `cleanup_orphans` and `repair_index` delete them.
+pub fn list_orphaned_embeddings(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_id FROM file_embeddings WHERE file_id NOT IN (SELECT id FROM files) ORDER BY file_id",
+    )?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// Delete embedding for a file
+pub fn delete_embedding(conn: &Connection, file_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM file_embeddings WHERE file_id = ?1",
+        params![file_id],
+    )?;
+    Ok(())
+}
+
+/// Count total embeddings
+pub fn count_embeddings(conn: &Connection) -> Result<i64> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM file_embeddings",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Count files without embeddings whose content is too large to embed
+///
+/// Mirrors `EmbeddingConfig::exceeds_document_limits`: more than `max_chars`
+/// characters or more than `max_lines` lines.
+pub fn count_files_over_embedding_limits(
+    conn: &Connection,
+    max_chars: usize,
+    max_lines: usize,
+) -> Result<i64> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*)
+         FROM (
+             -- Compressed text can only be read back from the FTS index here
+             SELECT COALESCE(c.text_content, t.content) AS text
+             FROM files f
+             JOIN file_content c ON c.file_id = f.id
+             LEFT JOIN files_fts t ON t.rowid = c.file_id AND c.is_compressed = 1
+             LEFT JOIN file_embeddings e ON e.file_id = f.id
+             WHERE e.file_id IS NULL AND f.duplicate_of IS NULL AND f.is_deleted = 0
+         )
+         WHERE length(text) > ?1
+            OR length(text) - length(replace(text, char(10), '')) + 1 > ?2",
+        params![max_chars as i64, max_lines as i64],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Delete every stored embedding, returning how many were removed
+pub fn clear_embeddings(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute("DELETE FROM file_embeddings", [])?)
+}
+
+/// Get files without embeddings
+pub fn get_files_without_embeddings(conn: &Connection, limit: usize) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
+                f.last_indexed, f.hash, f.root_path, f.is_deleted
+         FROM files f
+         LEFT JOIN file_embeddings e ON f.id = e.file_id
+         WHERE e.file_id IS NULL AND f.duplicate_of IS NULL AND f.is_deleted = 0
+         ORDER BY f.modified_at DESC, f.id
+         LIMIT ?1"
+    )?;
+
+    let files = stmt
+        .query_map(params![limit], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Encode an embedding into the compact BLOB format (header + little-endian f32s)
+pub fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BLOB_HEADER_LEN + embedding.len() * 4);
+
+    bytes.extend_from_slice(BLOB_MAGIC);
+    bytes.push(BLOB_FORMAT_VERSION);
+    bytes.push(MODEL_ID_MINILM_L6_V2);
+    bytes.extend_from_slice(&(embedding.len() as u32).to_le_bytes());
+
+    for value in embedding {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// Decode a BLOB produced by [`encode_embedding`]
+///
+/// Rejects blobs with a bad header or whose payload length doesn't match
+/// the declared dimension (truncated or padded data).
+pub fn decode_embedding(bytes: &[u8]) -> Result<Vec<f32>> {
+    if bytes.len() < BLOB_HEADER_LEN {
+        return Err(corrupt_embedding(format!(
+            "blob is {} bytes, shorter than the {}-byte header",
+            bytes.len(),
+            BLOB_HEADER_LEN
+        )));
+    }
+
+    if &bytes[0..2] != BLOB_MAGIC {
+        return Err(corrupt_embedding("missing header magic".to_string()));
+    }
+
+    if bytes[2] != BLOB_FORMAT_VERSION {
+        return Err(corrupt_embedding(format!(
+            "unsupported format version {}",
+            bytes[2]
+        )));
+    }
+
+    let dimension = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let payload = &bytes[BLOB_HEADER_LEN..];
+
+    if payload.len() != dimension * 4 {
+        return Err(corrupt_embedding(format!(
+            "header declares {} dimensions but payload holds {} bytes",
+            dimension,
+            payload.len()
+        )));
+    }
+
+    Ok(payload
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+/// Re-encode embeddings written before the BLOB header existed
+///
+/// Legacy rows are raw little-endian f32 bytes with no header. Called from
+/// the schema migration that introduced the header.
+pub fn reencode_legacy_embeddings(conn: &Connection) -> Result<usize> {
+    let legacy_rows: Vec<(i64, Vec<u8>)> = {
+        let mut stmt = conn.prepare("SELECT file_id, embedding FROM file_embeddings")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .filter(|(_, bytes)| decode_embedding(bytes).is_err() && bytes.len() % 4 == 0)
+            .collect()
+    };
+
+    for (file_id, bytes) in &legacy_rows {
+        let embedding: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        conn.execute(
+            "UPDATE file_embeddings SET embedding = ?1 WHERE file_id = ?2",
+            params![encode_embedding(&embedding), file_id],
+        )?;
+    }
+
+    Ok(legacy_rows.len())
+}
+
+fn corrupt_embedding(reason: String) -> CortexError {
+    CortexError::DatabaseError {
+        message: format!("Corrupt embedding blob: {}", reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::operations::{insert_file, mark_file_deleted};
+    use crate::db::schema::create_tables;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn insert_test_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(
+            conn,
+            &format!("/test/{}", name),
+            name,
+            "txt",
+            100,
+            "2025-11-29T00:00:00Z",
+            "2025-11-29T00:00:00Z",
+            None,
+            "/test",
+        ).unwrap()
+    }
+
+    fn test_vector(seed: f32) -> Vec<f32> {
+        (0..EMBEDDING_DIM).map(|i| seed + i as f32 * 0.001).collect()
+    }
+
+    #[test]
+    fn test_upsert_and_get_embedding() {
+        let conn = setup_test_db();
+        let file_id = insert_test_file(&conn, "a.txt");
+
+        let vector = test_vector(0.5);
+        upsert_embedding(&conn, file_id, &vector, "test-model").unwrap();
+
+        let stored = get_embedding(&conn, file_id).unwrap().unwrap();
+        assert_eq!(stored.file_id, file_id);
+        assert_eq!(stored.embedding, vector);
+        assert_eq!(stored.model_version, "test-model");
+
+        // Upsert replaces the existing vector
+        let replacement = test_vector(-0.25);
+        upsert_embedding(&conn, file_id, &replacement, "test-model-2").unwrap();
+
+        let stored = get_embedding(&conn, file_id).unwrap().unwrap();
+        assert_eq!(stored.embedding, replacement);
+        assert_eq!(stored.model_version, "test-model-2");
+        assert_eq!(count_embeddings(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_upsert_rejects_wrong_dimension() {
+        let conn = setup_test_db();
+        let file_id = insert_test_file(&conn, "a.txt");
+
+        let result = upsert_embedding(&conn, file_id, &[1.0, 2.0, 3.0], "test-model");
+        assert!(result.is_err());
+        assert_eq!(count_embeddings(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_get_missing_embedding() {
+        let conn = setup_test_db();
+        assert!(get_embedding(&conn, 42).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_all_and_delete_embeddings() {
+        let conn = setup_test_db();
+        let id1 = insert_test_file(&conn, "a.txt");
+        let id2 = insert_test_file(&conn, "b.txt");
+
+        upsert_embedding(&conn, id1, &test_vector(0.1), "test-model").unwrap();
+        upsert_embedding(&conn, id2, &test_vector(0.2), "test-model").unwrap();
+
+        let all = get_all_embeddings(&conn).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|(_, v)| v.len() == EMBEDDING_DIM));
+
+        delete_embedding(&conn, id1).unwrap();
+        let all = get_all_embeddings(&conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].0, id2);
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let vector = test_vector(0.75);
+        let bytes = encode_embedding(&vector);
+
+        assert_eq!(bytes.len(), BLOB_HEADER_LEN + EMBEDDING_DIM * 4);
+        assert_eq!(&bytes[0..2], BLOB_MAGIC);
+        assert_eq!(bytes[3], MODEL_ID_MINILM_L6_V2);
+        assert_eq!(decode_embedding(&bytes).unwrap(), vector);
+
+        // Empty vectors still round-trip
+        assert!(decode_embedding(&encode_embedding(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_blob() {
+        let bytes = encode_embedding(&test_vector(0.1));
+
+        assert!(decode_embedding(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_embedding(&bytes[..bytes.len() - 4]).is_err());
+        assert!(decode_embedding(&bytes[..4]).is_err());
+        assert!(decode_embedding(&[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_corrupt_header() {
+        let mut bad_magic = encode_embedding(&test_vector(0.1));
+        bad_magic[0] = b'Z';
+        assert!(decode_embedding(&bad_magic).is_err());
+
+        let mut bad_version = encode_embedding(&test_vector(0.1));
+        bad_version[2] = 99;
+        assert!(decode_embedding(&bad_version).is_err());
+
+        let mut bad_dimension = encode_embedding(&test_vector(0.1));
+        bad_dimension[4..8].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(decode_embedding(&bad_dimension).is_err());
+    }
+
+    #[test]
+    fn test_get_all_skips_corrupt_rows() {
+        let conn = setup_test_db();
+        let id1 = insert_test_file(&conn, "a.txt");
+        let id2 = insert_test_file(&conn, "b.txt");
+
+        upsert_embedding(&conn, id1, &test_vector(0.1), "test-model").unwrap();
+        conn.execute(
+            "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+             VALUES (?1, ?2, 'test-model', '2025-11-29T00:00:00Z')",
+            params![id2, vec![1u8, 2, 3]],
+        ).unwrap();
+
+        let all = get_all_embeddings(&conn).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].0, id1);
+
+        assert!(get_embedding(&conn, id2).is_err());
+    }
+
+    #[test]
+    fn test_reencode_legacy_embeddings() {
+        let conn = setup_test_db();
+        let file_id = insert_test_file(&conn, "a.txt");
+
+        let vector = test_vector(0.3);
+        let legacy: Vec<u8> = vector.iter().flat_map(|f| f.to_le_bytes()).collect();
+        conn.execute(
+            "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+             VALUES (?1, ?2, 'test-model', '2025-11-29T00:00:00Z')",
+            params![file_id, legacy],
+        ).unwrap();
+
+        assert_eq!(reencode_legacy_embeddings(&conn).unwrap(), 1);
+        assert_eq!(get_embedding(&conn, file_id).unwrap().unwrap().embedding, vector);
+
+        // Already-encoded rows are left alone
+        assert_eq!(reencode_legacy_embeddings(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_files_without_embeddings() {
+        let conn = setup_test_db();
+        let id1 = insert_test_file(&conn, "a.txt");
+        let id2 = insert_test_file(&conn, "b.txt");
+        let id3 = insert_test_file(&conn, "c.txt");
+
+        upsert_embedding(&conn, id1, &test_vector(0.1), "test-model").unwrap();
+        mark_file_deleted(&conn, id3).unwrap();
+
+        let missing = get_files_without_embeddings(&conn, 10).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].id, id2);
+    }
+}
@@ -0,0 +1,197 @@
+//! Links referenced by indexed files
+//!
+//! Each file's `http(s)` URLs are stored with their host, so "every file
+//! referencing arxiv.org" is one indexed lookup. A file's links are replaced
+//! whenever it is re-indexed.
+
+use crate::db::operations::file_from_row;
+use crate::db::schema::File;
+use crate::error::{CortexError, Result};
+use crate::indexer::extractors::link_domain;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// A file and the URLs in it that matched a domain search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedFile {
+    pub file: File,
+    /// In the order they appear in the file
+    pub urls: Vec<String>,
+}
+
+/// Host a domain search matches against, or None if blank
+///
+/// Accepts a bare domain (`ArXiv.org`) or a whole URL, whose host is used.
+pub fn normalize_link_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim();
+    if domain.contains("://") {
+        return link_domain(domain);
+    }
+
+    let domain = domain.trim_matches(['.', '/']).to_lowercase();
+    if domain.is_empty() {
+        None
+    } else {
+        Some(domain)
+    }
+}
+
+/// Replace the links stored for a file
+///
+/// URLs without an `http(s)` host are skipped, as are repeats.
+pub fn set_file_links(conn: &Connection, file_id: i64, urls: &[String]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM file_links WHERE file_id = ?1", params![file_id])?;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO file_links (file_id, url, domain) VALUES (?1, ?2, ?3)",
+        )?;
+        for url in urls {
+            if let Some(domain) = link_domain(url) {
+                stmt.execute(params![file_id, url, domain])?;
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// A file's links, in the order they were stored
+pub fn get_file_links(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT url FROM file_links WHERE file_id = ?1 ORDER BY rowid")?;
+
+    let urls = stmt
+        .query_map(params![file_id], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(urls)
+}
+
+/// Non-deleted files linking to `domain` or any of its subdomains, by path
+///
+/// `arxiv.org` matches `export.arxiv.org` but not `notarxiv.org`. At most
+/// `limit` files are returned. Fails with `InvalidQuery` for a blank domain.
+pub fn search_by_link_domain(conn: &Connection, domain: &str, limit: usize) -> Result<Vec<LinkedFile>> {
+    let domain = normalize_link_domain(domain).ok_or_else(|| CortexError::InvalidQuery {
+        query: domain.to_string(),
+        reason: "Domain cannot be empty".to_string(),
+    })?;
+
+    let mut stmt = conn.prepare(
+        "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
+                f.last_indexed, f.hash, f.root_path, f.is_deleted, l.url
+         FROM file_links l
+         JOIN files f ON f.id = l.file_id
+         WHERE (l.domain = ?1 OR l.domain LIKE '%.' || ?1) AND f.is_deleted = 0
+         ORDER BY f.path, l.rowid",
+    )?;
+
+    let rows = stmt.query_map(params![domain], |row| {
+        Ok((
+            file_from_row(row)?,
+            row.get::<_, String>(11)?,
+        ))
+    })?;
+
+    // Rows come grouped by file
+    let mut files: Vec<LinkedFile> = Vec::new();
+    for row in rows {
+        let (file, url) = row?;
+        if let Some(last) = files.last_mut().filter(|last| last.file.id == file.id) {
+            last.urls.push(url);
+        } else if files.len() == limit {
+            break;
+        } else {
+            files.push(LinkedFile { file, urls: vec![url] });
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::{delete_file, insert_file, mark_file_deleted};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(conn, &format!("/test/{}", name), name, "md", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+    }
+
+    fn urls(list: &[&str]) -> Vec<String> {
+        list.iter().map(|url| url.to_string()).collect()
+    }
+
+    #[test]
+    fn test_search_by_link_domain() {
+        let conn = setup_test_db();
+        let paper = add_file(&conn, "paper.md");
+        let notes = add_file(&conn, "notes.md");
+        let other = add_file(&conn, "other.md");
+
+        set_file_links(&conn, paper, &urls(&[
+            "https://arxiv.org/abs/1",
+            "https://example.com/",
+            "https://export.arxiv.org/abs/2",
+            "docs/setup.md",
+        ])).unwrap();
+        set_file_links(&conn, notes, &urls(&["http://ARXIV.org/abs/3"])).unwrap();
+        set_file_links(&conn, other, &urls(&["https://notarxiv.org/"])).unwrap();
+
+        assert_eq!(get_file_links(&conn, paper).unwrap(), urls(&[
+            "https://arxiv.org/abs/1",
+            "https://example.com/",
+            "https://export.arxiv.org/abs/2",
+        ]));
+
+        // Subdomains match, lookalikes don't; by path
+        let found = search_by_link_domain(&conn, " ArXiv.org ", 10).unwrap();
+        let ids: Vec<i64> = found.iter().map(|f| f.file.id).collect();
+        assert_eq!(ids, vec![notes, paper]);
+        assert_eq!(found[1].urls, urls(&["https://arxiv.org/abs/1", "https://export.arxiv.org/abs/2"]));
+
+        // A URL searches by its host, and the limit counts files
+        let found = search_by_link_domain(&conn, "https://arxiv.org/list", 1).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file.id, notes);
+        let found = search_by_link_domain(&conn, "export.arxiv.org", 10).unwrap();
+        assert_eq!(found[0].urls, urls(&["https://export.arxiv.org/abs/2"]));
+
+        assert!(matches!(search_by_link_domain(&conn, "  ", 10), Err(CortexError::InvalidQuery { .. })));
+    }
+
+    #[test]
+    fn test_file_links_replaced_and_removed() {
+        let conn = setup_test_db();
+        let a = add_file(&conn, "a.md");
+        let b = add_file(&conn, "b.md");
+
+        set_file_links(&conn, a, &urls(&["https://arxiv.org/abs/1"])).unwrap();
+        set_file_links(&conn, a, &urls(&["https://example.com/", "https://example.com/"])).unwrap();
+        assert_eq!(get_file_links(&conn, a).unwrap(), urls(&["https://example.com/"]));
+        assert!(search_by_link_domain(&conn, "arxiv.org", 10).unwrap().is_empty());
+
+        // Soft-deleted files are hidden, hard-deleted ones lose their links
+        set_file_links(&conn, b, &urls(&["https://example.com/b"])).unwrap();
+        mark_file_deleted(&conn, a).unwrap();
+        let found = search_by_link_domain(&conn, "example.com", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file.id, b);
+
+        delete_file(&conn, b).unwrap();
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM file_links WHERE file_id = ?1", params![b], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+}
@@ -0,0 +1,469 @@
+//! Data-integrity maintenance
+//!
+//! `file_content` rows and their `files_fts` mirror are normally removed by
+//! the FK cascade and the FTS delete trigger. A connection opened without
+//! foreign-key enforcement or a manual edit can leave them behind.
+//!
+//! [`repair_index`] goes further, bringing `files_fts` and `code_fts` back in
+//! line with `file_content` whatever went wrong.
+//!
+//! Large purges are also run from here, in batches small enough not to hold
+//! the write lock for long, as is SQLite's full [`check_integrity`], which is
+//! too slow to run on every startup.
+
+use crate::db::compression::read_text;
+use crate::db::operations::{index_content_if_missing, refresh_code_split};
+use crate::error::Result;
+use crate::indexer::{split_comments, CodeLanguage};
+use rusqlite::{Connection, OptionalExtension};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrphanReport {
+    /// Content rows whose file no longer exists
+    pub orphaned_content: usize,
+    /// FTS rows with no content row behind them
+    pub orphaned_fts: usize,
+    /// Embeddings whose file no longer exists
+    pub orphaned_embeddings: usize,
+}
+
+const ORPHANED_CONTENT: &str =
+    "FROM file_content WHERE file_id NOT IN (SELECT id FROM files)";
+
+// Covers FTS rows left by a removed file as well as by removed content
+const ORPHANED_FTS: &str =
+    "FROM files_fts WHERE rowid NOT IN (SELECT file_id FROM file_content)";
+
+const ORPHANED_EMBEDDINGS: &str =
+    "FROM file_embeddings WHERE file_id NOT IN (SELECT id FROM files)";
+
+/// Count orphaned content, FTS rows and embeddings without changing anything
+pub fn find_orphans(conn: &Connection) -> Result<OrphanReport> {
+    let count = |from: &str| -> Result<usize> {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) {}", from), [], |row| row.get(0))?;
+        Ok(count as usize)
+    };
+
+    Ok(OrphanReport {
+        orphaned_content: count(ORPHANED_CONTENT)?,
+        orphaned_fts: count(ORPHANED_FTS)?,
+        orphaned_embeddings: count(ORPHANED_EMBEDDINGS)?,
+    })
+}
+
+/// Delete orphaned content, FTS rows and embeddings, returning how many were
+/// removed
+pub fn cleanup_orphans(conn: &Connection) -> Result<OrphanReport> {
+    let tx = conn.unchecked_transaction()?;
+
+    let orphaned_content = tx.execute(&format!("DELETE {}", ORPHANED_CONTENT), [])?;
+    // Content deletes above already dropped their FTS rows via trigger
+    let orphaned_fts = tx.execute(&format!("DELETE {}", ORPHANED_FTS), [])?;
+    let orphaned_embeddings = tx.execute(&format!("DELETE {}", ORPHANED_EMBEDDINGS), [])?;
+
+    tx.commit()?;
+
+    Ok(OrphanReport {
+        orphaned_content,
+        orphaned_fts,
+        orphaned_embeddings,
+    })
+}
+
+/// What [`repair_index`] found and fixed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Content rows before the repair
+    pub content_rows: usize,
+    /// FTS rows before the repair; differs from `content_rows` when the two
+    /// were out of step
+    pub fts_rows: usize,
+    /// The FTS index failed its integrity check and was rebuilt
+    pub rebuilt_fts_index: bool,
+    /// Content rows whose file no longer exists, deleted
+    pub orphaned_content: usize,
+    /// FTS rows with no content row behind them, deleted
+    pub orphaned_fts: usize,
+    /// Embeddings whose file no longer exists, deleted
+    pub orphaned_embeddings: usize,
+    /// FTS rows whose filename or text no longer matched, indexed again
+    pub stale_fts: usize,
+    /// Content rows with no FTS row, indexed
+    pub missing_fts: usize,
+    /// The code and comments index failed its integrity check and was rebuilt
+    #[serde(default)]
+    pub rebuilt_code_fts_index: bool,
+    /// Code and comments rows with no content row behind them, deleted
+    #[serde(default)]
+    pub orphaned_code_fts: usize,
+    /// Source files whose code and comments were missing, outdated, or
+    /// indexed though their language isn't split, split again
+    #[serde(default)]
+    pub stale_code_fts: usize,
+}
+
+impl RepairReport {
+    /// Whether anything needed fixing
+    pub fn repaired_anything(&self) -> bool {
+        self.rebuilt_fts_index
+            || self.rebuilt_code_fts_index
+            || self.orphaned_content + self.orphaned_fts + self.orphaned_embeddings > 0
+            || self.stale_fts + self.missing_fts > 0
+            || self.orphaned_code_fts + self.stale_code_fts > 0
+    }
+}
+
+// Compressed text can't be compared in SQL, so only their filenames are;
+// `stale_compressed_fts` compares their text
+const STALE_FTS: &str =
+    "SELECT fts.rowid FROM files_fts fts
+     JOIN files f ON f.id = fts.rowid
+     JOIN file_content fc ON fc.file_id = fts.rowid
+     WHERE fts.filename IS NOT f.filename
+        OR (fc.is_compressed = 0 AND fts.content IS NOT fc.text_content)";
+
+const COMPRESSED_FTS: &str =
+    "SELECT fts.rowid, fts.content, fc.text_content, fc.text_compressed
+     FROM files_fts fts
+     JOIN file_content fc ON fc.file_id = fts.rowid
+     WHERE fc.is_compressed = 1";
+
+const MISSING_FTS: &str =
+    "SELECT file_id FROM file_content WHERE file_id NOT IN (SELECT rowid FROM files_fts)";
+
+// Duplicates have no content row, so this also covers rows left by a file
+// that became one
+const ORPHANED_CODE_FTS: &str =
+    "FROM code_fts WHERE rowid NOT IN (SELECT file_id FROM file_content)";
+
+/// FTS rows of compressed content whose text differs from the decompressed
+/// content
+fn stale_compressed_fts(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(COMPRESSED_FTS)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, read_text(row, 2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, indexed, text)| indexed != text)
+        .map(|(file_id, _, _)| file_id)
+        .collect())
+}
+
+/// Content rows whose `code_fts` row doesn't match what splitting their
+/// text in their file's language gives: missing, outdated, or present for a
+/// file that isn't split
+fn stale_code_fts(conn: &Connection) -> Result<Vec<i64>> {
+    let files: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT f.id, f.path FROM file_content fc JOIN files f ON f.id = fc.file_id",
+        )?;
+        let files = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+        files
+    };
+
+    let mut text_stmt =
+        conn.prepare("SELECT text_content, text_compressed FROM file_content WHERE file_id = ?1")?;
+    let mut split_stmt = conn.prepare("SELECT code, comments FROM code_fts WHERE rowid = ?1")?;
+    let mut stale = Vec::new();
+
+    for (file_id, path) in files {
+        let indexed: Option<(String, String)> = split_stmt
+            .query_row([file_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+
+        let expected = match CodeLanguage::from_path(Path::new(&path)) {
+            Some(language) => text_stmt
+                .query_row([file_id], |row| read_text(row, 0))?
+                .map(|text| split_comments(&text, language)),
+            None => None,
+        };
+
+        let up_to_date = match (&indexed, &expected) {
+            (None, None) => true,
+            (Some((code, comments)), Some(split)) => *code == split.code && *comments == split.comments,
+            _ => false,
+        };
+        if !up_to_date {
+            stale.push(file_id);
+        }
+    }
+
+    Ok(stale)
+}
+
+/// Check the search index against the stored content and fix what differs,
+/// in one transaction
+///
+/// A corrupt FTS index is rebuilt, orphaned content, FTS rows and
+/// embeddings are deleted, and content missing from the index or indexed
+/// with outdated text is indexed again. Afterwards every content row has
+/// exactly one up-to-date FTS row, and source files one up-to-date code and
+/// comments row.
+pub fn repair_index(conn: &Connection) -> Result<RepairReport> {
+    let tx = conn.unchecked_transaction()?;
+    let count = |sql: &str| -> Result<usize> {
+        let count: i64 = tx.query_row(sql, [], |row| row.get(0))?;
+        Ok(count as usize)
+    };
+    let ids = |sql: &str| -> Result<Vec<i64>> {
+        let mut stmt = tx.prepare(sql)?;
+        let ids = stmt.query_map([], |row| row.get(0))?.collect::<std::result::Result<_, _>>()?;
+        Ok(ids)
+    };
+
+    let mut report = RepairReport {
+        content_rows: count("SELECT COUNT(*) FROM file_content")?,
+        fts_rows: count("SELECT COUNT(*) FROM files_fts")?,
+        ..RepairReport::default()
+    };
+
+    // Fails when the index no longer matches the rows FTS stores
+    if let Err(e) = tx.execute("INSERT INTO files_fts(files_fts) VALUES('integrity-check')", []) {
+        log::warn!("FTS integrity check failed, rebuilding: {}", e);
+        tx.execute("INSERT INTO files_fts(files_fts) VALUES('rebuild')", [])?;
+        report.rebuilt_fts_index = true;
+    }
+
+    report.orphaned_content = tx.execute(&format!("DELETE {}", ORPHANED_CONTENT), [])?;
+    report.orphaned_fts = tx.execute(&format!("DELETE {}", ORPHANED_FTS), [])?;
+    report.orphaned_embeddings = tx.execute(&format!("DELETE {}", ORPHANED_EMBEDDINGS), [])?;
+
+    let mut stale = ids(STALE_FTS)?;
+    for file_id in stale_compressed_fts(&tx)? {
+        if !stale.contains(&file_id) {
+            stale.push(file_id);
+        }
+    }
+    for &file_id in &stale {
+        tx.execute("DELETE FROM files_fts WHERE rowid = ?1", [file_id])?;
+    }
+    report.stale_fts = stale.len();
+
+    // Includes the stale rows just deleted
+    let unindexed = ids(MISSING_FTS)?;
+    for &file_id in &unindexed {
+        index_content_if_missing(&tx, file_id)?;
+    }
+    report.missing_fts = unindexed.len() - stale.len();
+
+    if let Err(e) = tx.execute("INSERT INTO code_fts(code_fts) VALUES('integrity-check')", []) {
+        log::warn!("Code FTS integrity check failed, rebuilding: {}", e);
+        tx.execute("INSERT INTO code_fts(code_fts) VALUES('rebuild')", [])?;
+        report.rebuilt_code_fts_index = true;
+    }
+
+    report.orphaned_code_fts = tx.execute(&format!("DELETE {}", ORPHANED_CODE_FTS), [])?;
+
+    let stale_code = stale_code_fts(&tx)?;
+    for &file_id in &stale_code {
+        let path: String = tx.query_row("SELECT path FROM files WHERE id = ?1", [file_id], |row| row.get(0))?;
+        refresh_code_split(&tx, file_id, Path::new(&path))?;
+    }
+    report.stale_code_fts = stale_code.len();
+
+    tx.commit()?;
+
+    Ok(report)
+}
+
+/// Files hard-deleted per transaction by a batched purge
+pub const PURGE_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeSummary {
+    pub total_files: usize,
+    /// Files actually removed; unknown ids don't count
+    pub deleted_files: usize,
+    /// Files handed to a completed batch, deleted or not
+    pub processed_files: usize,
+    /// Stopped before every batch ran; the completed batches stay deleted
+    pub cancelled: bool,
+}
+
+/// Delete `file_ids` in batches of `batch_size`, each through its own call
+/// to `delete_batch`
+///
+/// `delete_batch` is expected to commit a transaction per batch (e.g.
+/// [`delete_files`](crate::db::delete_files)), so other writers get the lock
+/// between batches and a cancelled purge leaves whole batches behind.
+/// `cancel` is checked before each batch, and `on_progress` receives the
+/// processed and total counts after each one.
+pub fn purge_in_batches<D, P>(
+    file_ids: &[i64],
+    batch_size: usize,
+    cancel: &AtomicBool,
+    mut delete_batch: D,
+    mut on_progress: P,
+) -> Result<PurgeSummary>
+where
+    D: FnMut(&[i64]) -> Result<usize>,
+    P: FnMut(usize, usize),
+{
+    let mut summary = PurgeSummary {
+        total_files: file_ids.len(),
+        ..PurgeSummary::default()
+    };
+
+    for batch in file_ids.chunks(batch_size.max(1)) {
+        if cancel.load(Ordering::SeqCst) {
+            log::info!(
+                "Purge cancelled after {} of {} files",
+                summary.processed_files,
+                summary.total_files
+            );
+            summary.cancelled = true;
+            break;
+        }
+
+        summary.deleted_files += delete_batch(batch)?;
+        summary.processed_files += batch.len();
+        on_progress(summary.processed_files, summary.total_files);
+
+        std::thread::yield_now();
+    }
+
+    Ok(summary)
+}
+
+/// Most problems [`check_integrity`] lists
+const MAX_INTEGRITY_PROBLEMS: usize = 100;
+
+/// Run SQLite's full `integrity_check`, returning the problems it found;
+/// empty when the database is healthy
+///
+/// Startup only runs `quick_check`, which skips checking that indexes match
+/// their tables. This reads every page, so it can take a while on a large
+/// index.
+pub fn check_integrity(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_PROBLEMS))?;
+    let results = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(results.into_iter().filter(|result| result != "ok").collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::{insert_file, search_files_fts, upsert_file_content};
+
+    #[test]
+    fn test_orphaned_rows_detected_and_cleaned() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let live = insert_file(&conn, "/test/live.txt", "live.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, live, Some("surviving heron"), None).unwrap();
+
+        assert_eq!(find_orphans(&conn).unwrap(), OrphanReport::default());
+
+        // Rows an unenforced cascade or manual edit could leave behind
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute(
+            "INSERT INTO file_content (file_id, text_content) VALUES (999, 'orphaned heron')",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO files_fts (rowid, filename, content) VALUES (998, 'gone.txt', 'stray heron')",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+             VALUES (997, x'00', 'test-model', '2025-11-29T00:00:00Z')",
+            [],
+        ).unwrap();
+
+        let found = find_orphans(&conn).unwrap();
+        assert_eq!(found.orphaned_content, 1);
+        assert_eq!(found.orphaned_fts, 1);
+        assert_eq!(found.orphaned_embeddings, 1);
+
+        assert_eq!(cleanup_orphans(&conn).unwrap(), found);
+        assert_eq!(find_orphans(&conn).unwrap(), OrphanReport::default());
+
+        // The live file is untouched
+        let results = search_files_fts(&conn, "heron", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, live);
+    }
+
+    #[test]
+    fn test_batched_purge_completes_and_cancels_cleanly() {
+        use crate::db::{delete_files, get_file_count, list_deleted_file_ids};
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        for i in 0..1_000 {
+            let path = format!("/test/{}.txt", i);
+            let id = insert_file(&conn, &path, "f.txt", "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(&conn, id, Some("purged walrus"), None).unwrap();
+        }
+        conn.execute("UPDATE files SET is_deleted = 1 WHERE id > 100", []).unwrap();
+
+        let consistent = |conn: &Connection| {
+            let rows = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+            assert_eq!(find_orphans(conn).unwrap(), OrphanReport::default());
+            assert_eq!(rows("SELECT COUNT(*) FROM files"), rows("SELECT COUNT(*) FROM file_content"));
+            assert_eq!(rows("SELECT COUNT(*) FROM files_fts"), rows("SELECT COUNT(*) FROM file_content"));
+        };
+
+        // Cancel from the progress callback after the third batch
+        let cancel = AtomicBool::new(false);
+        let trash = list_deleted_file_ids(&conn).unwrap();
+        let mut reported = Vec::new();
+        let summary = purge_in_batches(&trash, 100, &cancel, |batch| delete_files(&conn, batch, true),
+            |processed, total| {
+                reported.push((processed, total));
+                if reported.len() == 3 {
+                    cancel.store(true, Ordering::SeqCst);
+                }
+            },
+        )
+        .unwrap();
+
+        assert!(summary.cancelled);
+        assert_eq!(summary.total_files, 900);
+        assert_eq!(summary.deleted_files, 300);
+        assert_eq!(reported, vec![(100, 900), (200, 900), (300, 900)]);
+        assert_eq!(list_deleted_file_ids(&conn).unwrap().len(), 600);
+        consistent(&conn);
+
+        // Resuming finishes the rest; live files are untouched
+        cancel.store(false, Ordering::SeqCst);
+        let trash = list_deleted_file_ids(&conn).unwrap();
+        let summary = purge_in_batches(&trash, 100, &cancel, |batch| delete_files(&conn, batch, true),
+            |_, _| {}).unwrap();
+        assert!(!summary.cancelled);
+        assert_eq!(summary.deleted_files, 600);
+        assert!(list_deleted_file_ids(&conn).unwrap().is_empty());
+        assert_eq!(get_file_count(&conn).unwrap(), 100);
+        assert_eq!(search_files_fts(&conn, "walrus", 1_000).unwrap().len(), 100);
+        consistent(&conn);
+    }
+
+    #[test]
+    fn test_integrity_check_of_healthy_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let id = insert_file(&conn, "/test/a.txt", "a.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, id, Some("healthy heron"), None).unwrap();
+
+        assert!(check_integrity(&conn).unwrap().is_empty());
+    }
+}
@@ -1,79 +1,493 @@
 mod schema;
 pub mod operations;
+pub mod embeddings;
+pub mod search_history;
+pub mod tags;
+pub mod bookmarks;
+pub mod collections;
+pub mod links;
+pub mod prompt_templates;
+pub mod compression;
+pub mod config;
+pub mod maintenance;
+pub mod portable;
+pub mod tree;
+pub mod writer;
 
 pub use schema::*;
 pub use operations::*;
+pub use embeddings::*;
+pub use search_history::*;
+pub use tags::*;
+pub use bookmarks::*;
+pub use collections::*;
+pub use links::*;
+pub use prompt_templates::*;
+pub use compression::*;
+pub use config::*;
+pub use maintenance::*;
+pub use portable::*;
+pub use tree::*;
+pub use writer::{DatabasePool, DbWriter, ReadPool, WriteTicket, WriterConfig};
 
 use crate::error::{CortexError, Result};
 use rusqlite::Connection;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable overriding the database location
+pub const DB_PATH_VAR: &str = "CORTEX_DB_PATH";
+
+/// Memory-map size requested for the database file (30GB)
+const REQUESTED_MMAP_SIZE: u64 = 30_000_000_000;
+
+/// Largest memory map worth requesting on this platform
+///
+/// A 32-bit process can't map more than a fraction of its 4GB address space.
+#[cfg(target_pointer_width = "32")]
+const MAX_MMAP_SIZE: u64 = 256 * 1024 * 1024;
+
+#[cfg(not(target_pointer_width = "32"))]
+const MAX_MMAP_SIZE: u64 = REQUESTED_MMAP_SIZE;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseHealth {
+    /// Memory-map size SQLite is actually using, in bytes (0 = disabled)
+    pub mmap_size: i64,
+    pub journal_mode: String,
+    pub schema_version: i64,
+}
+
+/// Request `requested` bytes of memory-mapped I/O, clamped to `max`, and
+/// return the size SQLite actually applied
+///
+/// SQLite silently caps the value at its compile-time limit (or ignores it
+/// where mmap is unsupported), so the effective size is read back and logged.
+pub(crate) fn configure_mmap(conn: &Connection, requested: u64, max: u64) -> Result<i64> {
+    let target = requested.min(max);
+    if target < requested {
+        log::warn!(
+            "Clamping SQLite mmap_size from {} to {} bytes for this platform",
+            requested,
+            target
+        );
+    }
+
+    conn.execute_batch(&format!("PRAGMA mmap_size={}", target))?;
+    let effective = read_mmap_size(conn)?;
+
+    if effective == 0 && target > 0 {
+        log::warn!("SQLite memory-mapped I/O is disabled (requested {} bytes)", target);
+    } else {
+        log::info!("SQLite mmap_size: requested {} bytes, effective {} bytes", target, effective);
+    }
+
+    Ok(effective)
+}
+
+/// The connection's current mmap size; 0 where SQLite reports none (e.g.
+/// in-memory databases)
+fn read_mmap_size(conn: &Connection) -> Result<i64> {
+    match conn.query_row("PRAGMA mmap_size", [], |row| row.get(0)) {
+        Ok(size) => Ok(size),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A corrupt database file that was moved aside and replaced on open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseRecovery {
+    /// Where the corrupt file was moved to
+    pub backup_path: String,
+    /// What the corruption check reported
+    pub reason: String,
+    /// The new database is empty, so everything must be indexed again
+    pub needs_reindex: bool,
+}
+
+/// Why the database at `path` is unusable, or `None` if it is healthy or
+/// fails for an unrelated reason (e.g. permissions), which opening reports
+fn detect_corruption(path: &Path) -> Option<String> {
+    let is_corruption = |e: &rusqlite::Error| {
+        matches!(
+            e.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase)
+        )
+    };
+
+    let conn = match Connection::open(path) {
+        Ok(conn) => conn,
+        Err(e) => return is_corruption(&e).then(|| e.to_string()),
+    };
+
+    // Runs on every open, so only the quick check (no index cross-checks),
+    // stopping at the first problem; `check_integrity` runs the full one
+    match conn.query_row("PRAGMA quick_check(1)", [], |row| row.get::<_, String>(0)) {
+        Ok(result) if result == "ok" => None,
+        Ok(result) => Some(result),
+        Err(e) => is_corruption(&e).then(|| e.to_string()),
+    }
+}
+
+/// Move a corrupt database and its WAL files aside so a fresh one can be
+/// created in its place; returns the backup path of the main file
+fn back_up_corrupt_database(path: &Path) -> Result<PathBuf> {
+    let suffix = format!("corrupt-{}", chrono::Utc::now().format("%Y%m%d%H%M%S"));
+    let backup = PathBuf::from(format!("{}.{}", path.display(), suffix));
+
+    std::fs::rename(path, &backup)?;
+
+    // A stale WAL must not be replayed into the new database
+    for ext in ["-wal", "-shm"] {
+        let sidecar = PathBuf::from(format!("{}{}", path.display(), ext));
+        if sidecar.exists() {
+            std::fs::rename(&sidecar, format!("{}{}.{}", path.display(), ext, suffix))?;
+        }
+    }
+
+    Ok(backup)
+}
 
 pub struct Database {
     conn: Connection,
+    /// Set when the file was found corrupt on open and replaced
+    recovery: Option<DatabaseRecovery>,
+    /// Where clones connect to
+    location: Location,
+}
+
+/// Where a [`Database`] lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Location {
+    File(PathBuf),
+    /// URI of a shared-cache in-memory database
+    Memory(String),
+    /// A private in-memory database, which no other connection can reach
+    #[cfg(test)]
+    Private,
+}
+
+impl Location {
+    /// Open another connection to the database here
+    pub(crate) fn connect(&self) -> Result<Connection> {
+        match self {
+            Location::File(path) => connect_file(path),
+            Location::Memory(uri) => connect_in_memory(uri),
+            #[cfg(test)]
+            Location::Private => Err(CortexError::Internal {
+                message: "A private in-memory database can't be reconnected to; use Database::new_in_memory".to_string(),
+            }),
+        }
+    }
 }
 
 impl Clone for Database {
     fn clone(&self) -> Self {
         // Create a new connection to the same database
         // This is safe with SQLite WAL mode (multiple readers allowed)
-        let db_path = Self::get_db_path().expect("Failed to get database path");
-        let conn = Connection::open(&db_path).expect("Failed to clone database connection");
-
-        // Apply same performance settings
-        conn.execute_batch(
-            "PRAGMA journal_mode=WAL;
-             PRAGMA synchronous=NORMAL;
-             PRAGMA cache_size=-64000;
-             PRAGMA temp_store=MEMORY;
-             PRAGMA mmap_size=30000000000;"
-        ).expect("Failed to configure cloned connection");
+        let conn = self.location.connect().expect("Failed to clone database connection");
 
-        Self { conn }
+        Self { conn, recovery: None, location: self.location.clone() }
     }
 }
 
+/// Connect to the database file at `path` with the performance settings
+fn connect_file(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+
+    // Configure SQLite for performance
+    conn.execute_batch(
+        "PRAGMA journal_mode=WAL;
+         PRAGMA synchronous=NORMAL;
+         PRAGMA cache_size=-64000;
+         PRAGMA temp_store=MEMORY;
+         PRAGMA busy_timeout=5000;"
+    )?;
+    configure_mmap(&conn, REQUESTED_MMAP_SIZE, MAX_MMAP_SIZE)?;
+
+    Ok(conn)
+}
+
+/// Connect to the shared-cache in-memory database named by `uri`
+fn connect_in_memory(uri: &str) -> Result<Connection> {
+    // The default open flags accept URIs
+    let conn = Connection::open(uri)?;
+
+    // WAL and memory mapping don't apply to a database in memory
+    conn.execute_batch(
+        "PRAGMA cache_size=-64000;
+         PRAGMA temp_store=MEMORY;"
+    )?;
+
+    Ok(conn)
+}
+
 impl Database {
+    /// Open the database at the default location (see [`DB_PATH_VAR`])
     pub async fn new() -> Result<Self> {
-        let db_path = Self::get_db_path()?;
+        Self::create_at(&Self::get_db_path()?)
+    }
 
-        // Ensure parent directory exists
+    /// Open the database at `path` instead, e.g. for a second profile or a
+    /// project-local index; missing parent directories are created
+    pub async fn new_at(path: PathBuf) -> Result<Self> {
+        Self::create_at(&path)
+    }
+
+    /// [`open_at`](Self::open_at), creating the parent directory first
+    fn create_at(db_path: &Path) -> Result<Self> {
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let conn = Connection::open(&db_path)?;
+        Self::open_at(db_path)
+    }
 
-        // Configure SQLite for performance
-        conn.execute_batch(
-            "PRAGMA journal_mode=WAL;
-             PRAGMA synchronous=NORMAL;
-             PRAGMA cache_size=-64000;
-             PRAGMA temp_store=MEMORY;
-             PRAGMA mmap_size=30000000000;"
-        )?;
+    /// Open the database at `path`, replacing it with a fresh one if it is
+    /// corrupt
+    ///
+    /// The corrupt file is kept next to the new one, and [`recovery`](Self::recovery)
+    /// reports that the index must be rebuilt.
+    fn open_at(path: &Path) -> Result<Self> {
+        let recovery = match detect_corruption(path) {
+            Some(reason) => {
+                log::error!("Database {} is corrupt: {}", path.display(), reason);
 
-        let mut db = Self { conn };
-        db.initialize_schema().await?;
+                let backup = back_up_corrupt_database(path)?;
+                log::warn!("Moved corrupt database to {}; starting with an empty index", backup.display());
 
-        Ok(db)
+                Some(DatabaseRecovery {
+                    backup_path: backup.to_string_lossy().into_owned(),
+                    reason,
+                    needs_reindex: true,
+                })
+            }
+            None => None,
+        };
+
+        let conn = connect_file(path)?;
+        schema::create_tables(&conn)?;
+
+        Ok(Self { conn, recovery, location: Location::File(path.to_path_buf()) })
+    }
+
+    /// Open a new, empty in-memory database with the full schema, for tests
+    /// and benchmarks
+    ///
+    /// Clones share it through SQLite's shared cache, which locks per table:
+    /// a clone writing while another holds a transaction fails with
+    /// `SQLITE_LOCKED` rather than waiting. It is gone once every clone is
+    /// dropped.
+    pub async fn new_in_memory() -> Result<Self> {
+        Self::create_in_memory()
     }
 
-    fn get_db_path() -> Result<PathBuf> {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .map_err(|_| CortexError::Internal {
-                message: "Cannot determine home directory".to_string(),
-            })?;
+    fn create_in_memory() -> Result<Self> {
+        // A unique name keeps each database private to its own clones
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let uri = format!(
+            "file:cortex-memory-{}?mode=memory&cache=shared",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let conn = connect_in_memory(&uri)?;
+        schema::create_tables(&conn)?;
+
+        Ok(Self { conn, recovery: None, location: Location::Memory(uri) })
+    }
 
-        Ok(PathBuf::from(home).join(".cortex").join("db.sqlite"))
+    /// Open a private in-memory database with the full schema (unit tests
+    /// only)
+    ///
+    /// It has a connection of its own, so it never hits the shared cache's
+    /// table locks, but it can't be cloned; tests whose code under test
+    /// clones the handle use [`new_in_memory`](Self::new_in_memory).
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        schema::create_tables(&conn)?;
+        Ok(Self { conn, recovery: None, location: Location::Private })
     }
 
-    async fn initialize_schema(&mut self) -> Result<()> {
-        schema::create_tables(&self.conn)?;
-        Ok(())
+    pub(crate) fn get_db_path() -> Result<PathBuf> {
+        resolve_db_path(|name| std::env::var_os(name))
     }
 
     pub fn get_connection(&self) -> &Connection {
         &self.conn
     }
+
+    /// Where this database lives, for opening more connections to it
+    pub(crate) fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// Details of the corrupt database replaced on open, if any
+    pub fn recovery(&self) -> Option<&DatabaseRecovery> {
+        self.recovery.as_ref()
+    }
+
+    /// Connection settings as SQLite reports them, for diagnostics
+    pub fn health(&self) -> Result<DatabaseHealth> {
+        Ok(DatabaseHealth {
+            mmap_size: read_mmap_size(&self.conn)?,
+            journal_mode: self.conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?,
+            schema_version: self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?,
+        })
+    }
+}
+
+/// Where the database lives, reading environment variables through `var`
+///
+/// `CORTEX_DB_PATH` overrides everything. Otherwise it is
+/// `~/.cortex/db.sqlite`, with the home directory taken from `HOME` or
+/// `USERPROFILE`, or from the OS when neither is set (as for some services
+/// and containers). Without any home directory, the platform data directory
+/// is used instead.
+fn resolve_db_path(var: impl Fn(&str) -> Option<std::ffi::OsString>) -> Result<PathBuf> {
+    if let Some(path) = var(DB_PATH_VAR).filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = var("HOME")
+        .or_else(|| var("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(dirs::home_dir);
+    if let Some(home) = home {
+        return Ok(home.join(".cortex").join("db.sqlite"));
+    }
+
+    dirs::data_dir()
+        .map(|dir| dir.join("cortex").join("db.sqlite"))
+        .ok_or_else(|| CortexError::Internal {
+            message: format!("Cannot determine a database location; set {}", DB_PATH_VAR),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_db_path_override_without_home() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let override_path = temp_dir.path().join("custom").join("index.sqlite");
+
+        // Only CORTEX_DB_PATH is set; HOME and USERPROFILE are not
+        let env = |name: &str| (name == DB_PATH_VAR).then(|| override_path.clone().into_os_string());
+        let path = resolve_db_path(env).unwrap();
+        assert_eq!(path, override_path);
+
+        let db = Database::create_at(&path).unwrap();
+        assert!(override_path.exists());
+        assert_eq!(db.health().unwrap().journal_mode, "wal");
+
+        // An empty override is ignored
+        let env = |name: &str| match name {
+            DB_PATH_VAR => Some(std::ffi::OsString::new()),
+            "HOME" => Some(temp_dir.path().as_os_str().to_owned()),
+            _ => None,
+        };
+        assert_eq!(resolve_db_path(env).unwrap(), temp_dir.path().join(".cortex").join("db.sqlite"));
+    }
+
+    #[test]
+    fn test_mmap_size_read_back_and_clamped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("db.sqlite");
+        let conn = Connection::open(&path).unwrap();
+
+        // Simulated low-memory limit well below the 30GB request
+        let limit = 64 * 1024 * 1024;
+        let effective = configure_mmap(&conn, REQUESTED_MMAP_SIZE, limit).unwrap();
+        assert_eq!(effective, limit as i64);
+
+        let db = Database { conn, recovery: None, location: Location::File(path) };
+        assert_eq!(db.health().unwrap().mmap_size, limit as i64);
+        assert_eq!(Database::open_in_memory().unwrap().health().unwrap().mmap_size, 0);
+
+        // Unclamped, SQLite still never reports more than was requested
+        let effective = configure_mmap(&db.conn, REQUESTED_MMAP_SIZE, MAX_MMAP_SIZE).unwrap();
+        assert!(effective as u64 <= REQUESTED_MMAP_SIZE);
+    }
+
+    #[test]
+    fn test_in_memory_clones_share_data() {
+        let db = tokio_test::block_on(Database::new_in_memory()).unwrap();
+        let clone = db.clone();
+        assert_eq!(clone.health().unwrap().schema_version, schema::SCHEMA_VERSION as i64);
+
+        operations::insert_file(db.get_connection(), "/test/a.txt", "a.txt", "txt", 1,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        operations::insert_file(clone.get_connection(), "/test/b.txt", "b.txt", "txt", 1,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        assert_eq!(operations::get_file_count(db.get_connection()).unwrap(), 2);
+        assert_eq!(operations::get_file_count(clone.clone().get_connection()).unwrap(), 2);
+
+        // Another in-memory database starts out empty
+        let other = Database::open_in_memory().unwrap();
+        assert_eq!(operations::get_file_count(other.get_connection()).unwrap(), 0);
+
+        // It lives as long as any clone does
+        drop(db);
+        assert_eq!(operations::get_file_count(clone.get_connection()).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_database_at_custom_path_clones_to_it() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("profiles").join("work.sqlite");
+
+        let db = tokio_test::block_on(Database::new_at(path.clone())).unwrap();
+        assert!(path.exists());
+        operations::insert_file(db.get_connection(), "/test/a.txt", "a.txt", "txt", 1,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+
+        // The clone reads the same file, not the default location
+        let clone = db.clone();
+        assert_eq!(clone.location, Location::File(path.clone()));
+        let file = operations::get_file_by_path(clone.get_connection(), "/test/a.txt").unwrap();
+        assert_eq!(file.unwrap().filename, "a.txt");
+        assert_eq!(clone.health().unwrap().journal_mode, "wal");
+
+        // And the data outlives both connections
+        drop((db, clone));
+        let reopened = tokio_test::block_on(Database::new_at(path)).unwrap();
+        assert_eq!(operations::get_file_count(reopened.get_connection()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_database_is_backed_up_and_recreated() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("db.sqlite");
+
+        // A healthy database opens as-is
+        {
+            let db = Database::open_at(&path).unwrap();
+            assert!(db.recovery().is_none());
+            operations::insert_file(db.get_connection(), "/test/a.txt", "a.txt", "txt", 1,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        }
+        let db = Database::open_at(&path).unwrap();
+        assert!(db.recovery().is_none());
+        assert_eq!(operations::get_file_count(db.get_connection()).unwrap(), 1);
+        drop(db);
+
+        std::fs::write(&path, vec![0xAB; 8192]).unwrap();
+
+        let db = Database::open_at(&path).unwrap();
+        let recovery = db.recovery().unwrap().clone();
+        assert!(recovery.needs_reindex);
+        assert_eq!(std::fs::read(&recovery.backup_path).unwrap(), vec![0xAB; 8192]);
+
+        // The replacement is empty but fully usable
+        let conn = db.get_connection();
+        assert_eq!(operations::get_file_count(conn).unwrap(), 0);
+        operations::insert_file(conn, "/test/b.txt", "b.txt", "txt", 1,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        assert_eq!(operations::get_file_count(conn).unwrap(), 1);
+    }
 }
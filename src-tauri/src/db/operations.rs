@@ -1,8 +1,43 @@
-use crate::db::schema::{File, FileContent, FileEmbedding, SearchResult};
+use crate::db::compression::{compress_text, read_text};
+use crate::db::config::get_config;
+use crate::db::schema::{ContentStats, File, FileContent, SearchResult};
 use crate::error::{CortexError, Result};
-use rusqlite::{params, Connection};
+use crate::indexer::extractors::{split_comments, CodeLanguage, CodeSplit};
+use crate::paths::path_key;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// FTS rank multiplier for pinned files
+///
+/// bm25 ranks are negative with lower being better, so doubling moves a
+/// pinned file above an equally relevant unpinned one.
+pub(crate) const PINNED_RANK_BOOST: f64 = 2.0;
+
+/// Read a [`File`] from a row whose first eleven columns are `id, path,
+/// filename, file_type, size, created_at, modified_at, last_indexed, hash,
+/// root_path, is_deleted`
+pub(crate) fn file_from_row(row: &Row) -> rusqlite::Result<File> {
+    Ok(File {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        filename: row.get(2)?,
+        file_type: row.get(3)?,
+        size: row.get(4)?,
+        created_at: row.get(5)?,
+        modified_at: row.get(6)?,
+        last_indexed: row.get(7)?,
+        hash: row.get(8)?,
+        root_path: row.get(9)?,
+        is_deleted: row.get(10)?,
+    })
+}
 
 /// Insert a new file record into the database
+///
+/// `file_type` is stored lowercased so type filters match `PDF` and `pdf`
+/// files alike.
 pub fn insert_file(
     conn: &Connection,
     path: &str,
@@ -17,9 +52,9 @@ pub fn insert_file(
     let now = chrono::Utc::now().to_rfc3339();
 
     conn.execute(
-        "INSERT INTO files (path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![path, filename, file_type, size, created_at, modified_at, now, hash, root_path],
+        "INSERT INTO files (path, path_key, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![path, path_key(path), filename, file_type.to_lowercase(), size, created_at, modified_at, now, hash, root_path],
     )?;
 
     Ok(conn.last_insert_rowid())
@@ -32,47 +67,22 @@ pub fn get_file_by_id(conn: &Connection, file_id: i64) -> Result<File> {
          FROM files WHERE id = ?1"
     )?;
 
-    let file = stmt.query_row(params![file_id], |row| {
-        Ok(File {
-            id: row.get(0)?,
-            path: row.get(1)?,
-            filename: row.get(2)?,
-            file_type: row.get(3)?,
-            size: row.get(4)?,
-            created_at: row.get(5)?,
-            modified_at: row.get(6)?,
-            last_indexed: row.get(7)?,
-            hash: row.get(8)?,
-            root_path: row.get(9)?,
-            is_deleted: row.get(10)?,
-        })
-    })?;
+    let file = stmt.query_row(params![file_id], file_from_row)?;
 
     Ok(file)
 }
 
 /// Get a file by path
+///
+/// Matches by [`path_key`], so case variants of a path find the same file
+/// where the filesystem ignores case.
 pub fn get_file_by_path(conn: &Connection, path: &str) -> Result<Option<File>> {
     let mut stmt = conn.prepare(
         "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
-         FROM files WHERE path = ?1"
+         FROM files WHERE path_key = ?1"
     )?;
 
-    let result = stmt.query_row(params![path], |row| {
-        Ok(File {
-            id: row.get(0)?,
-            path: row.get(1)?,
-            filename: row.get(2)?,
-            file_type: row.get(3)?,
-            size: row.get(4)?,
-            created_at: row.get(5)?,
-            modified_at: row.get(6)?,
-            last_indexed: row.get(7)?,
-            hash: row.get(8)?,
-            root_path: row.get(9)?,
-            is_deleted: row.get(10)?,
-        })
-    });
+    let result = stmt.query_row(params![path_key(path)], file_from_row);
 
     match result {
         Ok(file) => Ok(Some(file)),
@@ -126,7 +136,12 @@ pub fn update_file(
 }
 
 /// Mark a file as deleted (soft delete)
+///
+/// If other files share this file's content, one of them takes over the
+/// content first so they stay searchable.
 pub fn mark_file_deleted(conn: &Connection, file_id: i64) -> Result<()> {
+    release_duplicates(conn, file_id)?;
+
     conn.execute(
         "UPDATE files SET is_deleted = 1 WHERE id = ?1",
         params![file_id],
@@ -135,13 +150,403 @@ pub fn mark_file_deleted(conn: &Connection, file_id: i64) -> Result<()> {
     Ok(())
 }
 
+/// List non-deleted files that have metadata but no extracted content yet
+///
+/// `limit` caps the number returned; `None` lists them all.
+pub fn list_files_without_content(conn: &Connection, limit: Option<usize>) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
+                f.last_indexed, f.hash, f.root_path, f.is_deleted
+         FROM files f
+         LEFT JOIN file_content fc ON f.id = fc.file_id
+         WHERE fc.file_id IS NULL AND f.duplicate_of IS NULL AND f.is_deleted = 0
+         ORDER BY f.id
+         LIMIT ?1"
+    )?;
+
+    // SQLite treats a negative LIMIT as no limit
+    let limit = limit.map_or(-1, |l| l as i64);
+
+    let files = stmt.query_map(params![limit], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Resolve the file whose content and embedding `file_id` uses
+///
+/// Returns `file_id` itself unless it is a duplicate of another file.
+pub fn resolve_content_owner(conn: &Connection, file_id: i64) -> Result<i64> {
+    let owner_id = conn.query_row(
+        "SELECT COALESCE((SELECT duplicate_of FROM files WHERE id = ?1), ?1)",
+        params![file_id],
+        |row| row.get(0),
+    )?;
+
+    Ok(owner_id)
+}
+
+/// Find a live file with the given content hash that holds its own content
+pub fn find_content_owner(conn: &Connection, hash: &str, exclude_id: i64) -> Result<Option<i64>> {
+    let result = conn.query_row(
+        "SELECT f.id FROM files f
+         INNER JOIN file_content fc ON fc.file_id = f.id
+         WHERE f.hash = ?1 AND f.id != ?2 AND f.duplicate_of IS NULL AND f.is_deleted = 0
+         ORDER BY f.id
+         LIMIT 1",
+        params![hash, exclude_id],
+        |row| row.get(0),
+    );
+
+    match result {
+        Ok(id) => Ok(Some(id)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Previously extracted content of any file whose bytes hash to `hash`
+///
+/// Identical bytes extract to identical text, so re-indexing an unchanged
+/// file, or indexing a copy of one, can reuse this instead of re-parsing.
+/// Only content stored under `extractor` (see [`set_content_extractor`])
+/// counts: anything else came from an extractor that has since changed.
+pub fn find_content_by_hash(conn: &Connection, hash: &str, extractor: &str) -> Result<Option<FileContent>> {
+    let result = conn.query_row(
+        "SELECT fc.file_id, fc.text_content, fc.text_compressed, fc.word_count, fc.summary, fc.metadata
+         FROM files f
+         INNER JOIN file_content fc ON fc.file_id = f.id
+         WHERE f.hash = ?1 AND fc.extractor = ?2
+           AND (fc.text_content IS NOT NULL OR fc.is_compressed = 1)
+         ORDER BY f.is_deleted, f.id
+         LIMIT 1",
+        params![hash, extractor],
+        |row| {
+            Ok(FileContent {
+                file_id: row.get(0)?,
+                text_content: read_text(row, 1)?,
+                word_count: row.get(3)?,
+                summary: row.get(4)?,
+                metadata: read_metadata(row, 5)?,
+            })
+        },
+    );
+
+    match result {
+        Ok(content) => Ok(Some(content)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Make `file_id` share `owner_id`'s content, dropping its own content and
+/// embedding
+///
+/// Only the owner is in the search index; its results list the duplicate's
+/// path (see [`duplicate_paths`]).
+pub fn link_duplicate(conn: &Connection, file_id: i64, owner_id: i64) -> Result<()> {
+    release_duplicates(conn, file_id)?;
+
+    conn.execute("DELETE FROM file_content WHERE file_id = ?1", params![file_id])?;
+    conn.execute("DELETE FROM file_embeddings WHERE file_id = ?1", params![file_id])?;
+    conn.execute(
+        "UPDATE files SET duplicate_of = ?2 WHERE id = ?1",
+        params![file_id, owner_id],
+    )?;
+
+    Ok(())
+}
+
+/// Mark `file_id` as holding its own content again
+pub fn unlink_duplicate(conn: &Connection, file_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE files SET duplicate_of = NULL WHERE id = ?1",
+        params![file_id],
+    )?;
+
+    Ok(())
+}
+
+/// Hand `owner_id`'s content over to one of its duplicates
+///
+/// Used before the owner is deleted or its content changes. The first live
+/// duplicate gets a copy of the content and the embedding, and the rest are
+/// re-pointed at it. The owner keeps its own content row.
+pub fn release_duplicates(conn: &Connection, owner_id: i64) -> Result<()> {
+    let new_owner: Option<i64> = match conn.query_row(
+        "SELECT id FROM files WHERE duplicate_of = ?1 ORDER BY is_deleted, id LIMIT 1",
+        params![owner_id],
+        |row| row.get(0),
+    ) {
+        Ok(id) => Some(id),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let new_owner = match new_owner {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    // Inserting (rather than moving) keeps the FTS triggers in sync
+    conn.execute(
+        "INSERT INTO file_content (file_id, text_content, text_compressed, is_compressed, text_size,
+                                   word_count, summary, extractor)
+         SELECT ?2, text_content, text_compressed, is_compressed, text_size, word_count, summary, extractor
+         FROM file_content WHERE file_id = ?1",
+        params![owner_id, new_owner],
+    )?;
+    // ...except for compressed content, which they skip
+    index_content_if_missing(conn, new_owner)?;
+    // Split by the new owner's own language, which its extension may change
+    let new_owner_path: String =
+        conn.query_row("SELECT path FROM files WHERE id = ?1", params![new_owner], |row| row.get(0))?;
+    refresh_code_split(conn, new_owner, Path::new(&new_owner_path))?;
+    conn.execute(
+        "UPDATE file_embeddings SET file_id = ?2 WHERE file_id = ?1",
+        params![owner_id, new_owner],
+    )?;
+    conn.execute(
+        "UPDATE files SET duplicate_of = NULL WHERE id = ?1",
+        params![new_owner],
+    )?;
+    conn.execute(
+        "UPDATE files SET duplicate_of = ?2 WHERE duplicate_of = ?1",
+        params![owner_id, new_owner],
+    )?;
+
+    Ok(())
+}
+
+/// Set a file's content hash without touching `last_indexed`
+pub fn set_file_hash(conn: &Connection, file_id: i64, hash: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE files SET hash = ?1 WHERE id = ?2",
+        params![hash, file_id],
+    )?;
+
+    Ok(())
+}
+
+/// List non-deleted files that have no content hash yet
+pub fn list_files_without_hash(conn: &Connection) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
+         FROM files
+         WHERE hash IS NULL AND is_deleted = 0
+         ORDER BY id"
+    )?;
+
+    let files = stmt.query_map([], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Restore a soft-deleted file
+///
+/// Soft delete only flips `is_deleted`, so the FTS row normally survives it.
+/// The row is re-created from `file_content` here in case it went missing, so
+/// a restored file is always searchable again.
+pub fn restore_file(conn: &Connection, file_id: i64) -> Result<()> {
+    // Surfaces a not-found error for unknown IDs
+    let file = get_file_by_id(conn, file_id)?;
+
+    conn.execute(
+        "UPDATE files SET is_deleted = 0 WHERE id = ?1",
+        params![file_id],
+    )?;
+
+    // Its content may have been handed to a duplicate while it was deleted;
+    // share that again rather than keeping a second copy
+    if let Some(hash) = file.hash.as_deref() {
+        if let Some(owner_id) = find_content_owner(conn, hash, file_id)? {
+            return link_duplicate(conn, file_id, owner_id);
+        }
+    }
+
+    index_content_if_missing(conn, file_id)
+}
+
+/// A file whose bytes hash to `hash` and that is gone from its indexed
+/// path, so may have been moved; soft-deleted files come first
+///
+/// Only soft-deleted files, and missing ones under `scanned_roots`, count:
+/// a file under some other root may just be on an unmounted drive.
+pub fn find_moved_file(conn: &Connection, hash: &str, scanned_roots: &[PathBuf]) -> Result<Option<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
+         FROM files
+         WHERE hash = ?1
+         ORDER BY is_deleted DESC, id"
+    )?;
+
+    let files = stmt.query_map(params![hash], file_from_row)?;
+
+    for file in files {
+        let file = file?;
+        let path = Path::new(&file.path);
+        let scanned = scanned_roots.iter().any(|root| path.starts_with(root));
+        if file.is_deleted || (scanned && !path.exists()) {
+            return Ok(Some(file));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Point a file at `new_path` after it was moved there on disk
+///
+/// The row keeps its id, so content, embedding, tags and bookmarks move
+/// with it. A soft-deleted file is restored. If the new extension changes
+/// the source language, its code and comments are split again.
+pub fn move_file(conn: &Connection, file_id: i64, new_path: &str) -> Result<()> {
+    let file = get_file_by_id(conn, file_id)?;
+
+    let path = Path::new(new_path);
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown");
+    let file_type = path.extension().and_then(|ext| ext.to_str()).unwrap_or("unknown");
+    let root_path = path.parent().and_then(|p| p.to_str()).unwrap_or("");
+
+    conn.execute(
+        "UPDATE files SET path = ?2, path_key = ?3, filename = ?4, file_type = ?5, root_path = ?6,
+                          last_indexed = ?7
+         WHERE id = ?1",
+        params![file_id, new_path, path_key(new_path), filename, file_type.to_lowercase(), root_path,
+            chrono::Utc::now().to_rfc3339()],
+    )?;
+    conn.execute(
+        "UPDATE files_fts SET filename = ?2 WHERE rowid = ?1",
+        params![file_id, filename],
+    )?;
+
+    if CodeLanguage::from_path(path) != CodeLanguage::from_path(Path::new(&file.path)) {
+        refresh_code_split(conn, file_id, path)?;
+    }
+
+    if file.is_deleted {
+        restore_file(conn, file_id)?;
+    }
+
+    Ok(())
+}
+
+/// Index a file's code and comments apart again, split from its own stored
+/// content in the language of `path`
+///
+/// Clears them when `path` isn't in a language the splitter knows or the
+/// file has no content of its own.
+pub(crate) fn refresh_code_split(conn: &Connection, file_id: i64, path: &Path) -> Result<()> {
+    let split = match (CodeLanguage::from_path(path), stored_content(conn, file_id)?) {
+        (Some(language), Some(content)) => content.text_content.map(|text| split_comments(&text, language)),
+        _ => None,
+    };
+    set_code_split(conn, file_id, split.as_ref())
+}
+
+/// Add a file's own stored content to the FTS index if it has none there
+pub(crate) fn index_content_if_missing(conn: &Connection, file_id: i64) -> Result<()> {
+    let indexed: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM files_fts WHERE rowid = ?1)",
+        params![file_id],
+        |row| row.get(0),
+    )?;
+
+    if !indexed {
+        if let Some(content) = stored_content(conn, file_id)? {
+            conn.execute(
+                "INSERT INTO files_fts(rowid, filename, content)
+                 SELECT id, filename, ?2 FROM files WHERE id = ?1",
+                params![file_id, content.text_content],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Delete a file permanently
 pub fn delete_file(conn: &Connection, file_id: i64) -> Result<()> {
+    release_duplicates(conn, file_id)?;
     conn.execute("DELETE FROM files WHERE id = ?1", params![file_id])?;
     Ok(())
 }
 
+/// Pin a file so it ranks higher in search and is always exported
+///
+/// Returns false if no such file exists.
+pub fn pin_file(conn: &Connection, file_id: i64) -> Result<bool> {
+    set_file_pinned(conn, file_id, true)
+}
+
+/// Undo [`pin_file`]; returns false if no such file exists
+pub fn unpin_file(conn: &Connection, file_id: i64) -> Result<bool> {
+    set_file_pinned(conn, file_id, false)
+}
+
+fn set_file_pinned(conn: &Connection, file_id: i64, pinned: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE files SET pinned = ?2 WHERE id = ?1",
+        params![file_id, pinned],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Non-deleted pinned files, by path
+pub fn list_pinned_files(conn: &Connection) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
+         FROM files
+         WHERE pinned = 1 AND is_deleted = 0
+         ORDER BY path"
+    )?;
+
+    let files = stmt
+        .query_map([], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Delete many files in one transaction, permanently when `hard` is set
+///
+/// Either every file is deleted or none are. Hard deletes cascade to the
+/// content, embeddings and FTS rows. Returns how many files were affected;
+/// unknown ids and files already soft-deleted don't count.
+pub fn delete_files(conn: &Connection, file_ids: &[i64], hard: bool) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut affected = 0;
+
+    for &file_id in file_ids {
+        release_duplicates(&tx, file_id)?;
+
+        affected += if hard {
+            tx.execute("DELETE FROM files WHERE id = ?1", params![file_id])?
+        } else {
+            tx.execute(
+                "UPDATE files SET is_deleted = 1 WHERE id = ?1 AND is_deleted = 0",
+                params![file_id],
+            )?
+        };
+    }
+
+    tx.commit()?;
+    Ok(affected)
+}
+
+/// Ids of all soft-deleted files, i.e. the trash
+pub fn list_deleted_file_ids(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM files WHERE is_deleted = 1 ORDER BY id")?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
+
 /// Insert or update file content
+///
+/// Clears the content's extractor and metadata; see
+/// [`set_content_extractor`] and [`set_content_metadata`].
 pub fn upsert_file_content(
     conn: &Connection,
     file_id: i64,
@@ -150,33 +555,145 @@ pub fn upsert_file_content(
 ) -> Result<()> {
     let word_count = text_content.map(|c| c.split_whitespace().count() as i64);
 
+    // Text too short to shrink is kept plain
+    let compressed = match text_content {
+        Some(text) if get_config(conn)?.compress_content => {
+            Some(compress_text(text)?).filter(|bytes| bytes.len() < text.len())
+        }
+        _ => None,
+    };
+
+    let (text_content, compressed) = match (text_content, compressed) {
+        (Some(text), Some(bytes)) => (None, Some((text, bytes))),
+        (text, _) => (text, None),
+    };
+
+    let existed: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM file_content WHERE file_id = ?1)",
+        params![file_id],
+        |row| row.get(0),
+    )?;
+
     conn.execute(
-        "INSERT INTO file_content (file_id, text_content, word_count, summary)
-         VALUES (?1, ?2, ?3, ?4)
+        "INSERT INTO file_content (file_id, text_content, text_compressed, is_compressed, text_size,
+                                   word_count, summary)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
          ON CONFLICT(file_id) DO UPDATE SET
             text_content = excluded.text_content,
+            text_compressed = excluded.text_compressed,
+            is_compressed = excluded.is_compressed,
+            text_size = excluded.text_size,
             word_count = excluded.word_count,
-            summary = excluded.summary",
-        params![file_id, text_content, word_count, summary],
+            summary = excluded.summary,
+            extractor = NULL,
+            metadata = NULL",
+        params![
+            file_id,
+            text_content,
+            compressed.as_ref().map(|(_, bytes)| bytes),
+            compressed.is_some(),
+            compressed.as_ref().map(|(text, _)| text.len() as i64),
+            word_count,
+            summary
+        ],
+    )?;
+
+    // The FTS triggers skip compressed rows, so do their work here
+    if let Some((text, _)) = compressed {
+        if existed {
+            conn.execute("UPDATE files_fts SET content = ?2 WHERE rowid = ?1", params![file_id, text])?;
+        } else {
+            conn.execute(
+                "INSERT INTO files_fts(rowid, filename, content)
+                 SELECT id, filename, ?2 FROM files WHERE id = ?1",
+                params![file_id, text],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Record which extractor produced a file's content, as an
+/// `ExtractorRegistry::content_key`, so [`find_content_by_hash`] can reuse it
+pub fn set_content_extractor(conn: &Connection, file_id: i64, extractor: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE file_content SET extractor = ?2 WHERE file_id = ?1",
+        params![file_id, extractor],
     )?;
 
     Ok(())
 }
 
+/// Record the document properties extracted with a file's content
+///
+/// An empty map stores nothing.
+pub fn set_content_metadata(conn: &Connection, file_id: i64, metadata: &HashMap<String, String>) -> Result<()> {
+    let metadata = if metadata.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(metadata).map_err(|e| CortexError::Internal {
+            message: format!("Failed to serialize content metadata: {}", e),
+        })?)
+    };
+
+    conn.execute(
+        "UPDATE file_content SET metadata = ?2 WHERE file_id = ?1",
+        params![file_id, metadata],
+    )?;
+
+    Ok(())
+}
+
+/// A `file_content` row's `metadata` JSON at `idx`, empty when unset
+pub(crate) fn read_metadata(row: &Row, idx: usize) -> rusqlite::Result<HashMap<String, String>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e))),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Replace the separately indexed code and comments of a source file
+///
+/// `None` clears them, for files the comment splitter doesn't handle.
+pub fn set_code_split(conn: &Connection, file_id: i64, split: Option<&CodeSplit>) -> Result<()> {
+    conn.execute("DELETE FROM code_fts WHERE rowid = ?1", params![file_id])?;
+
+    if let Some(split) = split {
+        conn.execute(
+            "INSERT INTO code_fts (rowid, code, comments) VALUES (?1, ?2, ?3)",
+            params![file_id, split.code, split.comments],
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Get file content by file ID
 pub fn get_file_content(conn: &Connection, file_id: i64) -> Result<Option<FileContent>> {
-    let mut stmt = conn.prepare(
-        "SELECT file_id, text_content, word_count, summary FROM file_content WHERE file_id = ?1"
-    )?;
+    // Duplicates read the content of the file they share it with
+    let owner_id = resolve_content_owner(conn, file_id)?;
 
-    let result = stmt.query_row(params![file_id], |row| {
-        Ok(FileContent {
-            file_id: row.get(0)?,
-            text_content: row.get(1)?,
-            word_count: row.get(2)?,
-            summary: row.get(3)?,
-        })
-    });
+    Ok(stored_content(conn, owner_id)?.map(|content| FileContent { file_id, ..content }))
+}
+
+/// The `file_content` row of `file_id` itself, decompressed
+fn stored_content(conn: &Connection, file_id: i64) -> Result<Option<FileContent>> {
+    let result = conn.query_row(
+        "SELECT file_id, text_content, text_compressed, word_count, summary, metadata
+         FROM file_content WHERE file_id = ?1",
+        params![file_id],
+        |row| {
+            Ok(FileContent {
+                file_id: row.get(0)?,
+                text_content: read_text(row, 1)?,
+                word_count: row.get(3)?,
+                summary: row.get(4)?,
+                metadata: read_metadata(row, 5)?,
+            })
+        },
+    );
 
     match result {
         Ok(content) => Ok(Some(content)),
@@ -187,26 +704,12 @@ pub fn get_file_content(conn: &Connection, file_id: i64) -> Result<Option<FileCo
 
 /// Get total file count
 pub fn get_file_count(conn: &Connection) -> Result<i64> {
-    let count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM files WHERE is_deleted = 0",
-        [],
-        |row| row.get(0),
-    )?;
-
-    Ok(count)
+    Ok(get_db_stats(conn)?.0)
 }
 
 /// Get indexed file count (files with content)
 pub fn get_indexed_file_count(conn: &Connection) -> Result<i64> {
-    let count: i64 = conn.query_row(
-        "SELECT COUNT(DISTINCT f.id) FROM files f
-         INNER JOIN file_content fc ON f.id = fc.file_id
-         WHERE f.is_deleted = 0",
-        [],
-        |row| row.get(0),
-    )?;
-
-    Ok(count)
+    Ok(get_db_stats(conn)?.1)
 }
 
 /// List files with pagination
@@ -214,40 +717,97 @@ pub fn list_files(
     conn: &Connection,
     limit: usize,
     offset: usize,
+) -> Result<Vec<File>> {
+    list_files_filtered(conn, limit, offset, false)
+}
+
+/// List files with pagination, optionally including soft-deleted files
+///
+/// Deleted rows keep `is_deleted = true` so callers can show them as trash.
+pub fn list_files_filtered(
+    conn: &Connection,
+    limit: usize,
+    offset: usize,
+    include_deleted: bool,
 ) -> Result<Vec<File>> {
     let mut stmt = conn.prepare(
         "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
          FROM files
-         WHERE is_deleted = 0
+         WHERE (?3 OR is_deleted = 0)
          ORDER BY modified_at DESC
          LIMIT ?1 OFFSET ?2"
     )?;
 
-    let files = stmt.query_map(params![limit, offset], |row| {
-        Ok(File {
-            id: row.get(0)?,
-            path: row.get(1)?,
-            filename: row.get(2)?,
-            file_type: row.get(3)?,
-            size: row.get(4)?,
-            created_at: row.get(5)?,
-            modified_at: row.get(6)?,
-            last_indexed: row.get(7)?,
-            hash: row.get(8)?,
-            root_path: row.get(9)?,
-            is_deleted: row.get(10)?,
-        })
-    })?
-    .collect::<std::result::Result<Vec<_>, _>>()?;
+    let files = stmt.query_map(params![limit, offset, include_deleted], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(files)
 }
 
+/// The `limit` largest non-deleted files, optionally of one file type
+pub fn list_largest_files(
+    conn: &Connection,
+    file_type: Option<&str>,
+    limit: usize,
+) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
+         FROM files
+         WHERE is_deleted = 0 AND (?1 IS NULL OR file_type = ?1)
+         ORDER BY size DESC, id
+         LIMIT ?2"
+    )?;
+
+    let file_type = file_type.map(str::to_lowercase);
+    let files = stmt.query_map(params![file_type, limit], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// List all non-deleted files with the given file type (extension)
+pub fn list_files_by_type(conn: &Connection, file_type: &str) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, filename, file_type, size, created_at, modified_at, last_indexed, hash, root_path, is_deleted
+         FROM files
+         WHERE file_type = ?1 AND is_deleted = 0
+         ORDER BY path"
+    )?;
+
+    let files = stmt.query_map(params![file_type.to_lowercase()], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Pick the snippet to show for an FTS hit
+///
+/// The content excerpt is used when the match is in the content; for
+/// filename-only hits the highlighted filename is more useful than an
+/// unrelated stretch of content.
+pub fn choose_snippet(content_snippet: String, filename_snippet: String) -> String {
+    if content_snippet.contains("<mark>") || !filename_snippet.contains("<mark>") {
+        content_snippet
+    } else {
+        filename_snippet
+    }
+}
+
 /// Search files using FTS5
 pub fn search_files_fts(
     conn: &Connection,
     query: &str,
     limit: usize,
+) -> Result<Vec<SearchResult>> {
+    search_files_fts_filtered(conn, query, limit, false)
+}
+
+/// Search files using FTS5, optionally including soft-deleted files
+pub fn search_files_fts_filtered(
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+    include_deleted: bool,
 ) -> Result<Vec<SearchResult>> {
     // Validate query is not empty
     if query.trim().is_empty() {
@@ -257,139 +817,151 @@ pub fn search_files_fts(
         });
     }
 
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT f.id, f.path, f.filename,
                 snippet(files_fts, 1, '<mark>', '</mark>', '...', 32) as snippet,
-                rank
+                rank * (CASE WHEN f.pinned = 1 THEN {} ELSE 1.0 END) as score, f.is_deleted,
+                snippet(files_fts, 0, '<mark>', '</mark>', '...', 32) as filename_snippet
          FROM files_fts
          INNER JOIN files f ON files_fts.rowid = f.id
-         WHERE files_fts MATCH ?1 AND f.is_deleted = 0
-         ORDER BY rank
-         LIMIT ?2"
-    )?;
+         WHERE files_fts MATCH ?1 AND (?3 OR f.is_deleted = 0)
+         ORDER BY score
+         LIMIT ?2",
+        PINNED_RANK_BOOST
+    ))?;
 
-    let results = stmt.query_map(params![query, limit], |row| {
+    let results = stmt.query_map(params![query, limit, include_deleted], |row| {
         Ok(SearchResult {
             file_id: row.get(0)?,
             path: row.get(1)?,
             filename: row.get(2)?,
-            snippet: row.get(3)?,
+            snippet: choose_snippet(row.get(3)?, row.get(6)?),
             score: row.get(4)?,
+            is_deleted: row.get(5)?,
+            duplicate_paths: Vec::new(),
         })
     })?
     .collect::<std::result::Result<Vec<_>, _>>()?;
 
-    Ok(results)
+    attach_duplicate_paths(conn, results)
 }
 
-/// Get database statistics
-pub fn get_db_stats(conn: &Connection) -> Result<(i64, i64, i64)> {
-    let total_files = get_file_count(conn)?;
-    let indexed_files = get_indexed_file_count(conn)?;
-
-    let total_size: i64 = conn.query_row(
-        "SELECT COALESCE(SUM(size), 0) FROM files WHERE is_deleted = 0",
-        [],
-        |row| row.get(0),
+/// Paths of the live files sharing `file_id`'s content, by path
+pub fn duplicate_paths(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT path FROM files WHERE duplicate_of = ?1 AND is_deleted = 0 ORDER BY path",
     )?;
+    let paths = stmt
+        .query_map(params![file_id], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
 
-    Ok((total_files, indexed_files, total_size))
+    Ok(paths)
 }
 
-// ============================================================================
-// Embedding Operations (Phase 2: AI Features)
-// ============================================================================
-
-/// Insert or update file embedding
-pub fn upsert_embedding(
-    conn: &Connection,
-    file_id: i64,
-    embedding: &[f32],
-    model_version: &str,
-) -> Result<()> {
-    if embedding.len() != 384 {
-        return Err(CortexError::Internal {
-            message: format!(
-                "Invalid embedding dimension: expected 384, got {}",
-                embedding.len()
-            ),
-        });
+/// Fill in each result's [`duplicate_paths`], since only the file owning
+/// shared content is in the search index
+pub fn attach_duplicate_paths(conn: &Connection, mut results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+    for result in &mut results {
+        result.duplicate_paths = duplicate_paths(conn, result.file_id)?;
     }
+    Ok(results)
+}
 
-    let now = chrono::Utc::now().to_rfc3339();
-
-    // Convert f32 vector to bytes
-    let bytes: Vec<u8> = embedding
-        .iter()
-        .flat_map(|f| f.to_le_bytes())
-        .collect();
-
-    conn.execute(
-        "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
-         VALUES (?1, ?2, ?3, ?4)
-         ON CONFLICT(file_id) DO UPDATE SET
-            embedding = excluded.embedding,
-            model_version = excluded.model_version,
-            created_at = excluded.created_at",
-        params![file_id, bytes, model_version, now],
+/// Get database statistics: (total files, indexed files, total size)
+///
+/// Read from the running totals that triggers keep in `db_stats`.
+pub fn get_db_stats(conn: &Connection) -> Result<(i64, i64, i64)> {
+    let stats = conn.query_row(
+        "SELECT total_files, indexed_files, total_size FROM db_stats WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
     )?;
 
-    Ok(())
+    Ok(stats)
 }
 
-/// Get embedding for a file
-pub fn get_embedding(conn: &Connection, file_id: i64) -> Result<Option<FileEmbedding>> {
-    let mut stmt = conn.prepare(
-        "SELECT file_id, embedding, model_version, created_at
-         FROM file_embeddings WHERE file_id = ?1"
+/// Word counts of the text indexed for live files
+///
+/// Counts each `file_content` row once, so a duplicate's shared text isn't
+/// counted twice. All fields are zero when nothing has text.
+pub fn get_content_stats(conn: &Connection) -> Result<ContentStats> {
+    const LIVE_WORD_COUNTS: &str = "FROM file_content c
+         INNER JOIN files f ON f.id = c.file_id
+         WHERE f.is_deleted = 0 AND c.word_count IS NOT NULL";
+
+    let (files_with_content, total_words, average_words, min_words, max_words) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(c.word_count), 0), COALESCE(AVG(c.word_count), 0.0),
+                    COALESCE(MIN(c.word_count), 0), COALESCE(MAX(c.word_count), 0) {}",
+            LIVE_WORD_COUNTS
+        ),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
     )?;
 
-    let result = stmt.query_row(params![file_id], |row| {
-        let bytes: Vec<u8> = row.get(1)?;
-
-        // Convert bytes back to f32 vector
-        let embedding: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
-
-        Ok(FileEmbedding {
-            file_id: row.get(0)?,
-            embedding,
-            model_version: row.get(2)?,
-            created_at: row.get(3)?,
-        })
-    });
-
-    match result {
-        Ok(emb) => Ok(Some(emb)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
-    }
+    // The middle one or two counts in sorted order
+    let median_words = if files_with_content > 0 {
+        conn.query_row(
+            &format!(
+                "SELECT AVG(word_count) FROM (
+                     SELECT c.word_count {} ORDER BY c.word_count
+                     LIMIT 2 - ?1 % 2 OFFSET (?1 - 1) / 2
+                 )",
+                LIVE_WORD_COUNTS
+            ),
+            params![files_with_content],
+            |row| row.get(0),
+        )?
+    } else {
+        0.0
+    };
+
+    Ok(ContentStats {
+        files_with_content,
+        total_words,
+        average_words,
+        min_words,
+        median_words,
+        max_words,
+    })
 }
 
-/// Get all embeddings (for semantic search)
-pub fn get_all_embeddings(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>> {
-    let mut stmt = conn.prepare(
-        "SELECT file_id, embedding FROM file_embeddings"
+/// Recount the stats from the tables and store them as the running totals
+///
+/// The triggers keep the totals exact, so this is only needed to reconcile
+/// after edits made with the triggers absent (e.g. an older build).
+pub fn recompute_stats(conn: &Connection) -> Result<(i64, i64, i64)> {
+    let total_files: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM files WHERE is_deleted = 0",
+        [],
+        |row| row.get(0),
     )?;
 
-    let embeddings = stmt
-        .query_map([], |row| {
-            let file_id: i64 = row.get(0)?;
-            let bytes: Vec<u8> = row.get(1)?;
+    // Duplicates count as indexed: they share another file's content
+    let indexed_files: i64 = conn.query_row(
+        "SELECT COUNT(DISTINCT f.id) FROM files f
+         LEFT JOIN file_content fc ON f.id = fc.file_id
+         WHERE f.is_deleted = 0 AND (fc.file_id IS NOT NULL OR f.duplicate_of IS NOT NULL)",
+        [],
+        |row| row.get(0),
+    )?;
 
-            // Convert bytes to f32 vector
-            let embedding: Vec<f32> = bytes
-                .chunks_exact(4)
-                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
+    let total_size: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(size), 0) FROM files WHERE is_deleted = 0",
+        [],
+        |row| row.get(0),
+    )?;
 
-            Ok((file_id, embedding))
-        })?
-        .collect::<std::result::Result<Vec<_>, _>>()?;
+    conn.execute(
+        "INSERT INTO db_stats (id, total_files, indexed_files, total_size) VALUES (1, ?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET
+            total_files = excluded.total_files,
+            indexed_files = excluded.indexed_files,
+            total_size = excluded.total_size",
+        params![total_files, indexed_files, total_size],
+    )?;
 
-    Ok(embeddings)
+    Ok((total_files, indexed_files, total_size))
 }
 
 /// Get multiple files by IDs (for semantic search results)
@@ -412,73 +984,7 @@ pub fn get_files_by_ids(conn: &Connection, file_ids: &[i64]) -> Result<Vec<File>
     let params: Vec<&dyn rusqlite::ToSql> = file_ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
 
     let files = stmt
-        .query_map(params.as_slice(), |row| {
-            Ok(File {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                filename: row.get(2)?,
-                file_type: row.get(3)?,
-                size: row.get(4)?,
-                created_at: row.get(5)?,
-                modified_at: row.get(6)?,
-                last_indexed: row.get(7)?,
-                hash: row.get(8)?,
-                root_path: row.get(9)?,
-                is_deleted: row.get(10)?,
-            })
-        })?
-        .collect::<std::result::Result<Vec<_>, _>>()?;
-
-    Ok(files)
-}
-
-/// Delete embedding for a file
-pub fn delete_embedding(conn: &Connection, file_id: i64) -> Result<()> {
-    conn.execute(
-        "DELETE FROM file_embeddings WHERE file_id = ?1",
-        params![file_id],
-    )?;
-    Ok(())
-}
-
-/// Count total embeddings
-pub fn count_embeddings(conn: &Connection) -> Result<i64> {
-    let count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM file_embeddings",
-        [],
-        |row| row.get(0),
-    )?;
-    Ok(count)
-}
-
-/// Get files without embeddings
-pub fn get_files_without_embeddings(conn: &Connection, limit: usize) -> Result<Vec<File>> {
-    let mut stmt = conn.prepare(
-        "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
-                f.last_indexed, f.hash, f.root_path, f.is_deleted
-         FROM files f
-         LEFT JOIN file_embeddings e ON f.id = e.file_id
-         WHERE e.file_id IS NULL AND f.is_deleted = 0
-         ORDER BY f.modified_at DESC
-         LIMIT ?1"
-    )?;
-
-    let files = stmt
-        .query_map(params![limit], |row| {
-            Ok(File {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                filename: row.get(2)?,
-                file_type: row.get(3)?,
-                size: row.get(4)?,
-                created_at: row.get(5)?,
-                modified_at: row.get(6)?,
-                last_indexed: row.get(7)?,
-                hash: row.get(8)?,
-                root_path: row.get(9)?,
-                is_deleted: row.get(10)?,
-            })
-        })?
+        .query_map(params.as_slice(), file_from_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(files)
@@ -598,6 +1104,150 @@ mod tests {
         assert_eq!(count, 0);
     }
 
+    #[test]
+    fn test_delete_files_batch_soft_and_hard() {
+        let conn = setup_test_db();
+
+        let ids: Vec<i64> = (0..4)
+            .map(|i| {
+                let name = format!("file{}.txt", i);
+                let id = insert_file(&conn, &format!("/test/{}", name), &name, "txt", 10,
+                    "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+                upsert_file_content(&conn, id, Some("shared pangolin text"), None).unwrap();
+                id
+            })
+            .collect();
+        let fts_rows = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM files_fts", [], |row| row.get(0)).unwrap()
+        };
+
+        // Soft: unknown ids don't count, and a repeat is a no-op
+        assert_eq!(delete_files(&conn, &[ids[0], ids[1], 999], false).unwrap(), 2);
+        assert_eq!(delete_files(&conn, &[ids[0]], false).unwrap(), 0);
+        assert_eq!(get_file_count(&conn).unwrap(), 2);
+        assert_eq!(search_files_fts(&conn, "pangolin", 10).unwrap().len(), 2);
+        assert_eq!(fts_rows(&conn), 4);
+
+        // Hard: rows, content and FTS entries are all gone
+        assert_eq!(delete_files(&conn, &[ids[1], ids[2]], true).unwrap(), 2);
+        assert!(get_file_by_id(&conn, ids[2]).is_err());
+        assert!(get_file_content(&conn, ids[2]).unwrap().is_none());
+        assert_eq!(fts_rows(&conn), 2);
+
+        let results = search_files_fts(&conn, "pangolin", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, ids[3]);
+    }
+
+    #[test]
+    fn test_content_stats_over_known_files() {
+        let conn = setup_test_db();
+        assert_eq!(get_content_stats(&conn).unwrap(), ContentStats::default());
+
+        let mut ids = Vec::new();
+        for (i, words) in [3usize, 10, 5, 2].into_iter().enumerate() {
+            let name = format!("file{}.txt", i);
+            let id = insert_file(&conn, &format!("/test/{}", name), &name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(&conn, id, Some(&"word ".repeat(words)), None).unwrap();
+            ids.push(id);
+        }
+
+        // Neither metadata-only nor deleted files count
+        insert_file(&conn, "/test/image.png", "image.png", "png", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        let deleted = insert_file(&conn, "/test/gone.txt", "gone.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, deleted, Some(&"word ".repeat(100)), None).unwrap();
+        mark_file_deleted(&conn, deleted).unwrap();
+
+        let stats = get_content_stats(&conn).unwrap();
+        assert_eq!(stats.files_with_content, 4);
+        assert_eq!(stats.total_words, 20);
+        assert_eq!(stats.average_words, 5.0);
+        assert_eq!(stats.min_words, 2);
+        assert_eq!(stats.max_words, 10);
+        // Middle of 2, 3, 5, 10
+        assert_eq!(stats.median_words, 4.0);
+
+        // An odd count has a single middle value
+        delete_file(&conn, ids[1]).unwrap();
+        let stats = get_content_stats(&conn).unwrap();
+        assert_eq!(stats.total_words, 10);
+        assert_eq!(stats.median_words, 3.0);
+        assert_eq!(stats.max_words, 5);
+    }
+
+    #[test]
+    fn test_pinned_file_ranks_above_equally_relevant_one() {
+        let conn = setup_test_db();
+
+        let add = |name: &str| {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            upsert_file_content(&conn, id, Some("axolotl habitat notes"), None).unwrap();
+            id
+        };
+        let first = add("a.txt");
+        let second = add("b.txt");
+
+        let ranked = |conn: &Connection| -> Vec<i64> {
+            search_files_fts(conn, "axolotl", 10).unwrap().iter().map(|r| r.file_id).collect()
+        };
+        assert_eq!(ranked(&conn), vec![first, second]);
+
+        assert!(pin_file(&conn, second).unwrap());
+        assert_eq!(ranked(&conn), vec![second, first]);
+        assert_eq!(list_pinned_files(&conn).unwrap()[0].id, second);
+
+        assert!(unpin_file(&conn, second).unwrap());
+        assert_eq!(ranked(&conn), vec![first, second]);
+        assert!(list_pinned_files(&conn).unwrap().is_empty());
+        assert!(!pin_file(&conn, 999).unwrap());
+    }
+
+    #[test]
+    fn test_cached_stats_match_full_recompute() {
+        let conn = setup_test_db();
+
+        let cached_matches_recount = |conn: &Connection| {
+            let cached = get_db_stats(conn).unwrap();
+            assert_eq!(cached, recompute_stats(conn).unwrap());
+            cached
+        };
+
+        let add = |name: &str, size: i64| {
+            insert_file(&conn, &format!("/test/{}", name), name, "txt", size,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+        };
+
+        let a = add("a.txt", 100);
+        let b = add("b.txt", 200);
+        let c = add("c.txt", 400);
+        assert_eq!(cached_matches_recount(&conn), (3, 0, 700));
+
+        upsert_file_content(&conn, a, Some("alpha"), None).unwrap();
+        upsert_file_content(&conn, a, Some("alpha again"), None).unwrap();
+        upsert_file_content(&conn, b, Some("beta"), None).unwrap();
+        assert_eq!(cached_matches_recount(&conn), (3, 2, 700));
+
+        // A duplicate counts as indexed without content of its own
+        link_duplicate(&conn, c, a).unwrap();
+        assert_eq!(cached_matches_recount(&conn), (3, 3, 700));
+
+        // Deleting the owner hands its content to the duplicate
+        mark_file_deleted(&conn, a).unwrap();
+        assert_eq!(cached_matches_recount(&conn), (2, 2, 600));
+
+        restore_file(&conn, a).unwrap();
+        update_file(&conn, b, Some(250), Some("2025-11-30T00:00:00Z"), None).unwrap();
+        cached_matches_recount(&conn);
+
+        delete_file(&conn, b).unwrap();
+        delete_files(&conn, &[a, c], true).unwrap();
+        assert_eq!(cached_matches_recount(&conn), (0, 0, 0));
+    }
+
     #[test]
     fn test_upsert_file_content() {
         let conn = setup_test_db();
@@ -680,6 +1330,23 @@ mod tests {
         assert!(err.is_err());
     }
 
+    #[test]
+    fn test_filename_only_match_snippets_filename() {
+        let conn = setup_test_db();
+
+        let by_name = insert_file(&conn, "/test/budget_2025.txt", "budget_2025.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        let by_content = insert_file(&conn, "/test/notes.txt", "notes.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, by_name, Some("Quarterly figures for the finance team"), None).unwrap();
+        upsert_file_content(&conn, by_content, Some("Remember to review the budget"), None).unwrap();
+
+        let results = search_files_fts(&conn, "budget", 10).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let snippet = |id: i64| results.iter().find(|r| r.file_id == id).unwrap().snippet.clone();
+        assert_eq!(snippet(by_name), "<mark>budget</mark>_2025.txt");
+        assert!(snippet(by_content).contains("review the <mark>budget</mark>"));
+    }
+
     #[test]
     fn test_list_files_pagination() {
         let conn = setup_test_db();
@@ -712,6 +1379,104 @@ mod tests {
         assert_eq!(page3.len(), 1);
     }
 
+    #[test]
+    fn test_largest_files() {
+        let conn = setup_test_db();
+
+        for (name, file_type, size) in [
+            ("small.txt", "txt", 10),
+            ("huge.pdf", "pdf", 5000),
+            ("big.txt", "txt", 900),
+            ("medium.pdf", "pdf", 300),
+            ("gone.txt", "txt", 99_999),
+        ] {
+            let id = insert_file(&conn, &format!("/test/{}", name), name, file_type, size,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+            if name == "gone.txt" {
+                mark_file_deleted(&conn, id).unwrap();
+            }
+        }
+
+        let largest = list_largest_files(&conn, None, 3).unwrap();
+        let names: Vec<&str> = largest.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(names, vec!["huge.pdf", "big.txt", "medium.pdf"]);
+
+        let txt = list_largest_files(&conn, Some("txt"), 10).unwrap();
+        let sizes: Vec<i64> = txt.iter().map(|f| f.size).collect();
+        assert_eq!(sizes, vec![900, 10]);
+    }
+
+    #[test]
+    fn test_include_deleted_files() {
+        let conn = setup_test_db();
+
+        let kept = insert_file(&conn, "/test/kept.txt", "kept.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        let trashed = insert_file(&conn, "/test/trashed.txt", "trashed.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, kept, Some("recoverable notes"), None).unwrap();
+        upsert_file_content(&conn, trashed, Some("recoverable draft"), None).unwrap();
+        mark_file_deleted(&conn, trashed).unwrap();
+
+        // Excluded by default
+        let files = list_files_filtered(&conn, 10, 0, false).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].id, kept);
+
+        let results = search_files_fts_filtered(&conn, "recoverable", 10, false).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].is_deleted);
+
+        // Included and marked when requested
+        let files = list_files_filtered(&conn, 10, 0, true).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|f| f.id == trashed && f.is_deleted));
+
+        let results = search_files_fts_filtered(&conn, "recoverable", 10, true).unwrap();
+        assert_eq!(results.len(), 2);
+        let deleted: Vec<_> = results.iter().filter(|r| r.is_deleted).collect();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].file_id, trashed);
+    }
+
+    #[test]
+    fn test_restore_file_makes_it_searchable_again() {
+        let conn = setup_test_db();
+
+        let file_id = insert_file(&conn, "/test/restore.txt", "restore.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, file_id, Some("undeletable quokka facts"), None).unwrap();
+        assert_eq!(search_files_fts(&conn, "quokka", 10).unwrap().len(), 1);
+
+        mark_file_deleted(&conn, file_id).unwrap();
+        assert!(search_files_fts(&conn, "quokka", 10).unwrap().is_empty());
+
+        // Soft delete keeps the FTS row; restoring must not duplicate it
+        let fts_rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM files_fts WHERE rowid = ?1", params![file_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fts_rows, 1);
+
+        restore_file(&conn, file_id).unwrap();
+        assert!(!get_file_by_id(&conn, file_id).unwrap().is_deleted);
+
+        let results = search_files_fts(&conn, "quokka", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_id, file_id);
+    }
+
+    #[test]
+    fn test_restore_file_resyncs_missing_fts_row() {
+        let conn = setup_test_db();
+
+        let file_id = insert_file(&conn, "/test/resync.txt", "resync.txt", "txt", 100, "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+        upsert_file_content(&conn, file_id, Some("wombat burrow notes"), None).unwrap();
+        mark_file_deleted(&conn, file_id).unwrap();
+        conn.execute("DELETE FROM files_fts WHERE rowid = ?1", params![file_id]).unwrap();
+
+        restore_file(&conn, file_id).unwrap();
+
+        assert_eq!(search_files_fts(&conn, "wombat", 10).unwrap().len(), 1);
+        assert!(restore_file(&conn, 9999).is_err());
+    }
+
     #[test]
     fn test_db_stats() {
         let conn = setup_test_db();
@@ -724,4 +1489,35 @@ mod tests {
         assert_eq!(indexed, 0); // No content yet
         assert_eq!(size, 300); // 100 + 200
     }
+
+    #[test]
+    #[cfg(any(target_os = "macos", windows))]
+    fn test_case_variant_paths_resolve_to_same_file() {
+        let conn = setup_test_db();
+
+        let id = insert_file(&conn, "/Users/x/Doc.md", "Doc.md", "md", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/Users/x").unwrap();
+
+        let found = get_file_by_path(&conn, "/users/x/doc.md").unwrap().unwrap();
+        assert_eq!(found.id, id);
+        assert_eq!(found.path, "/Users/x/Doc.md");
+
+        // A second row for a case variant is rejected
+        assert!(insert_file(&conn, "/USERS/X/DOC.MD", "DOC.MD", "md", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/USERS/X").is_err());
+    }
+
+    #[test]
+    #[cfg(not(any(target_os = "macos", windows)))]
+    fn test_case_variant_paths_are_distinct_files() {
+        let conn = setup_test_db();
+
+        let upper = insert_file(&conn, "/home/x/Doc.md", "Doc.md", "md", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/home/x").unwrap();
+        let lower = insert_file(&conn, "/home/x/doc.md", "doc.md", "md", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/home/x").unwrap();
+
+        assert_ne!(upper, lower);
+        assert_eq!(get_file_by_path(&conn, "/home/x/doc.md").unwrap().unwrap().id, lower);
+    }
 }
@@ -0,0 +1,448 @@
+//! Portable index archives
+//!
+//! Moves a whole index between machines without re-scanning the source
+//! files. Unlike copying the SQLite file, the archive is plain JSON with an
+//! explicit format version, so it can be inspected and is independent of the
+//! database layout. Files keep their content, tags, links, bookmarks,
+//! collections and embeddings, and custom prompt templates come along;
+//! ids are reassigned on import. Code and comment indexes aren't archived,
+//! being derived from the text, and are rebuilt on import.
+
+use crate::db::compression::read_text;
+use crate::db::embeddings::{decode_embedding, encode_embedding};
+use crate::db::operations::{read_metadata, refresh_code_split};
+use crate::db::schema::SCHEMA_VERSION;
+use crate::error::{CortexError, Result};
+use crate::export::PromptVariable;
+use crate::indexer::extractors::link_domain;
+use crate::paths::path_key;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Version of the archive layout, bumped on incompatible changes
+pub const INDEX_ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexArchive {
+    pub format_version: u32,
+    /// Schema version of the database the archive was exported from
+    pub schema_version: i32,
+    pub exported_at: String,
+    pub files: Vec<ArchivedFile>,
+    #[serde(default)]
+    pub collections: Vec<ArchivedCollection>,
+    #[serde(default)]
+    pub prompt_templates: Vec<ArchivedPromptTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// Id in the exporting database; only used to link duplicates
+    pub id: i64,
+    pub path: String,
+    pub filename: String,
+    pub file_type: String,
+    pub size: i64,
+    pub created_at: String,
+    pub modified_at: String,
+    pub last_indexed: String,
+    pub hash: Option<String>,
+    pub root_path: String,
+    pub is_deleted: bool,
+    pub pinned: bool,
+    /// Archive id of the file whose content this one shares
+    pub duplicate_of: Option<i64>,
+    pub content: Option<ArchivedContent>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub embedding: Option<ArchivedEmbedding>,
+    /// URLs referenced by the content, in stored order
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// When the file was bookmarked, if it is
+    #[serde(default)]
+    pub bookmarked_at: Option<String>,
+    /// Names of the collections the file is in
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCollection {
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPromptTemplate {
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub body: String,
+    pub variables: Vec<PromptVariable>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedContent {
+    pub text_content: Option<String>,
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedEmbedding {
+    pub vector: Vec<f32>,
+    pub model_version: String,
+    pub created_at: String,
+}
+
+/// Counts of what an export wrote or an import restored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexArchiveSummary {
+    pub files: usize,
+    pub contents: usize,
+    pub tags: usize,
+    pub embeddings: usize,
+    #[serde(default)]
+    pub links: usize,
+    #[serde(default)]
+    pub bookmarks: usize,
+    #[serde(default)]
+    pub collections: usize,
+    #[serde(default)]
+    pub prompt_templates: usize,
+}
+
+/// Read every file, including soft-deleted ones, into an archive
+pub fn build_index_archive(conn: &Connection) -> Result<IndexArchive> {
+    let mut files: Vec<ArchivedFile> = {
+        let mut stmt = conn.prepare(
+            "SELECT f.id, f.path, f.filename, f.file_type, f.size, f.created_at, f.modified_at,
+                    f.last_indexed, f.hash, f.root_path, f.is_deleted, f.pinned, f.duplicate_of,
+                    c.file_id IS NOT NULL, c.text_content, c.text_compressed, c.summary, c.metadata
+             FROM files f
+             LEFT JOIN file_content c ON c.file_id = f.id
+             ORDER BY f.id",
+        )?;
+
+        let files = stmt
+            .query_map([], |row| {
+                let has_content: bool = row.get(13)?;
+                Ok(ArchivedFile {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    filename: row.get(2)?,
+                    file_type: row.get(3)?,
+                    size: row.get(4)?,
+                    created_at: row.get(5)?,
+                    modified_at: row.get(6)?,
+                    last_indexed: row.get(7)?,
+                    hash: row.get(8)?,
+                    root_path: row.get(9)?,
+                    is_deleted: row.get(10)?,
+                    pinned: row.get(11)?,
+                    duplicate_of: row.get(12)?,
+                    content: if has_content {
+                        Some(ArchivedContent {
+                            text_content: read_text(row, 14)?,
+                            summary: row.get(16)?,
+                            metadata: read_metadata(row, 17)?,
+                        })
+                    } else {
+                        None
+                    },
+                    tags: Vec::new(),
+                    embedding: None,
+                    links: Vec::new(),
+                    bookmarked_at: None,
+                    collections: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        files
+    };
+
+    let index: HashMap<i64, usize> = files.iter().enumerate().map(|(i, f)| (f.id, i)).collect();
+
+    let mut stmt = conn.prepare("SELECT file_id, tag FROM file_tags ORDER BY file_id, tag")?;
+    let tags = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for tag in tags {
+        let (file_id, tag) = tag?;
+        if let Some(&i) = index.get(&file_id) {
+            files[i].tags.push(tag);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT file_id, url FROM file_links ORDER BY rowid")?;
+    let links = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for link in links {
+        let (file_id, url) = link?;
+        if let Some(&i) = index.get(&file_id) {
+            files[i].links.push(url);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT file_id, created_at FROM bookmarks")?;
+    let bookmarks = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for bookmark in bookmarks {
+        let (file_id, created_at) = bookmark?;
+        if let Some(&i) = index.get(&file_id) {
+            files[i].bookmarked_at = Some(created_at);
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT cf.file_id, c.name FROM collection_files cf
+         JOIN collections c ON c.id = cf.collection_id
+         ORDER BY cf.file_id, c.name",
+    )?;
+    let memberships = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for membership in memberships {
+        let (file_id, name) = membership?;
+        if let Some(&i) = index.get(&file_id) {
+            files[i].collections.push(name);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT name, created_at FROM collections ORDER BY id")?;
+    let collections = stmt
+        .query_map([], |row| {
+            Ok(ArchivedCollection {
+                name: row.get(0)?,
+                created_at: row.get(1)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let prompt_templates = crate::db::list_prompt_templates(conn)?
+        .into_iter()
+        .map(|template| ArchivedPromptTemplate {
+            name: template.name,
+            description: template.description,
+            icon: template.icon,
+            body: template.body,
+            variables: template.variables,
+            created_at: template.created_at,
+        })
+        .collect();
+
+    let mut stmt =
+        conn.prepare("SELECT file_id, embedding, model_version, created_at FROM file_embeddings")?;
+    let embeddings = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Vec<u8>>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+    for embedding in embeddings {
+        let (file_id, bytes, model_version, created_at) = embedding?;
+        let Some(&i) = index.get(&file_id) else {
+            continue;
+        };
+
+        match decode_embedding(&bytes) {
+            Ok(vector) => {
+                files[i].embedding = Some(ArchivedEmbedding {
+                    vector,
+                    model_version,
+                    created_at,
+                })
+            }
+            Err(e) => log::warn!("Leaving corrupt embedding for file {} out of the archive: {}", file_id, e),
+        }
+    }
+
+    Ok(IndexArchive {
+        format_version: INDEX_ARCHIVE_VERSION,
+        schema_version: SCHEMA_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        files,
+        collections,
+        prompt_templates,
+    })
+}
+
+/// Write the whole index to `out` as a JSON archive
+pub fn export_index_archive<W: Write>(conn: &Connection, out: W) -> Result<IndexArchiveSummary> {
+    let archive = build_index_archive(conn)?;
+
+    serde_json::to_writer(out, &archive).map_err(|e| CortexError::Internal {
+        message: format!("Failed to write index archive: {}", e),
+    })?;
+
+    Ok(summarize(&archive))
+}
+
+/// Restore an archive written by [`export_index_archive`] into an empty
+/// index
+///
+/// Archives from a newer format or schema than this build understands are
+/// rejected, as is importing over existing files. The import is all or
+/// nothing.
+pub fn import_index_archive<R: Read>(conn: &Connection, input: R) -> Result<IndexArchiveSummary> {
+    let archive: IndexArchive = serde_json::from_reader(input).map_err(|e| CortexError::Internal {
+        message: format!("Failed to read index archive: {}", e),
+    })?;
+
+    if archive.format_version != INDEX_ARCHIVE_VERSION {
+        return Err(CortexError::Internal {
+            message: format!(
+                "Unsupported index archive format {} (expected {})",
+                archive.format_version, INDEX_ARCHIVE_VERSION
+            ),
+        });
+    }
+
+    if archive.schema_version > SCHEMA_VERSION {
+        return Err(CortexError::Internal {
+            message: format!(
+                "Index archive is from a newer schema ({}) than this version supports ({})",
+                archive.schema_version, SCHEMA_VERSION
+            ),
+        });
+    }
+
+    let existing: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?;
+    if existing > 0 {
+        return Err(CortexError::Internal {
+            message: format!("Index archives can only be imported into an empty index ({} files present)", existing),
+        });
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let mut ids = HashMap::with_capacity(archive.files.len());
+
+    let mut collection_ids = HashMap::with_capacity(archive.collections.len());
+    for collection in &archive.collections {
+        tx.execute(
+            "INSERT OR IGNORE INTO collections (name, created_at) VALUES (?1, ?2)",
+            params![collection.name, collection.created_at],
+        )?;
+        let id: i64 =
+            tx.query_row("SELECT id FROM collections WHERE name = ?1", params![collection.name], |row| row.get(0))?;
+        collection_ids.insert(collection.name.as_str(), id);
+    }
+
+    for template in &archive.prompt_templates {
+        let variables = serde_json::to_string(&template.variables).map_err(|e| CortexError::Internal {
+            message: format!("Invalid prompt template variables: {}", e),
+        })?;
+        tx.execute(
+            "INSERT INTO prompt_templates (name, description, icon, body, variables, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![template.name, template.description, template.icon, template.body, variables, template.created_at],
+        )?;
+    }
+
+    for file in &archive.files {
+        tx.execute(
+            "INSERT INTO files (path, path_key, filename, file_type, size, created_at, modified_at,
+                                last_indexed, hash, root_path, is_deleted, pinned)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                file.path,
+                path_key(&file.path),
+                file.filename,
+                file.file_type.to_lowercase(),
+                file.size,
+                file.created_at,
+                file.modified_at,
+                file.last_indexed,
+                file.hash,
+                file.root_path,
+                file.is_deleted,
+                file.pinned,
+            ],
+        )?;
+        ids.insert(file.id, tx.last_insert_rowid());
+    }
+
+    for file in &archive.files {
+        let file_id = ids[&file.id];
+
+        if let Some(owner) = file.duplicate_of.and_then(|owner| ids.get(&owner)) {
+            tx.execute("UPDATE files SET duplicate_of = ?1 WHERE id = ?2", params![owner, file_id])?;
+        }
+
+        if let Some(content) = &file.content {
+            crate::db::upsert_file_content(
+                &tx,
+                file_id,
+                content.text_content.as_deref(),
+                content.summary.as_deref(),
+            )?;
+            crate::db::set_content_metadata(&tx, file_id, &content.metadata)?;
+            // Code and comments aren't archived, being derived from the text
+            refresh_code_split(&tx, file_id, Path::new(&file.path))?;
+        }
+
+        for tag in &file.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO file_tags (file_id, tag, created_at) VALUES (?1, ?2, ?3)",
+                params![file_id, tag, archive.exported_at],
+            )?;
+        }
+
+        for url in &file.links {
+            if let Some(domain) = link_domain(url) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO file_links (file_id, url, domain) VALUES (?1, ?2, ?3)",
+                    params![file_id, url, domain],
+                )?;
+            }
+        }
+
+        if let Some(created_at) = &file.bookmarked_at {
+            tx.execute(
+                "INSERT OR IGNORE INTO bookmarks (file_id, created_at) VALUES (?1, ?2)",
+                params![file_id, created_at],
+            )?;
+        }
+
+        for name in &file.collections {
+            if let Some(collection_id) = collection_ids.get(name.as_str()) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO collection_files (collection_id, file_id, added_at) VALUES (?1, ?2, ?3)",
+                    params![collection_id, file_id, archive.exported_at],
+                )?;
+            }
+        }
+
+        if let Some(embedding) = &file.embedding {
+            tx.execute(
+                "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    file_id,
+                    encode_embedding(&embedding.vector),
+                    embedding.model_version,
+                    embedding.created_at
+                ],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(summarize(&archive))
+}
+
+fn summarize(archive: &IndexArchive) -> IndexArchiveSummary {
+    IndexArchiveSummary {
+        files: archive.files.len(),
+        contents: archive.files.iter().filter(|f| f.content.is_some()).count(),
+        tags: archive.files.iter().map(|f| f.tags.len()).sum(),
+        embeddings: archive.files.iter().filter(|f| f.embedding.is_some()).count(),
+        links: archive.files.iter().map(|f| f.links.len()).sum(),
+        bookmarks: archive.files.iter().filter(|f| f.bookmarked_at.is_some()).count(),
+        collections: archive.collections.len(),
+        prompt_templates: archive.prompt_templates.len(),
+    }
+}
@@ -0,0 +1,185 @@
+//! User-defined prompt templates
+//!
+//! Reusable prompts the user writes alongside the built-in ones. A
+//! template's body marks each variable as its upper-cased key in braces,
+//! the same as the built-in templates do, e.g. `{FEATURE_NAME}`.
+
+use crate::error::{CortexError, Result};
+use crate::export::PromptVariable;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// Prefix that tells user template ids apart from the built-in ones
+pub const CUSTOM_TEMPLATE_PREFIX: &str = "custom_";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomPromptTemplate {
+    /// `custom_` followed by the row id
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub body: String,
+    pub variables: Vec<PromptVariable>,
+    pub created_at: String,
+}
+
+fn invalid(query: &str, reason: impl Into<String>) -> CortexError {
+    CortexError::InvalidQuery {
+        query: query.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Row id of a `custom_<n>` template id
+fn row_id(template_id: &str) -> Option<i64> {
+    template_id.strip_prefix(CUSTOM_TEMPLATE_PREFIX)?.parse().ok()
+}
+
+fn read_template(row: &Row) -> rusqlite::Result<CustomPromptTemplate> {
+    let variables: String = row.get(5)?;
+    Ok(CustomPromptTemplate {
+        id: format!("{}{}", CUSTOM_TEMPLATE_PREFIX, row.get::<_, i64>(0)?),
+        name: row.get(1)?,
+        description: row.get(2)?,
+        icon: row.get(3)?,
+        body: row.get(4)?,
+        variables: serde_json::from_str(&variables)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Store a new template and return it
+///
+/// Fails with `InvalidQuery` if the name or body is blank, or a variable
+/// key is blank, used twice, or not made of letters, digits and
+/// underscores.
+pub fn create_prompt_template(
+    conn: &Connection,
+    name: &str,
+    description: &str,
+    icon: &str,
+    body: &str,
+    variables: &[PromptVariable],
+) -> Result<CustomPromptTemplate> {
+    if name.trim().is_empty() {
+        return Err(invalid(name, "Template name cannot be empty"));
+    }
+    if body.trim().is_empty() {
+        return Err(invalid(body, "Template body cannot be empty"));
+    }
+    for (i, variable) in variables.iter().enumerate() {
+        let key = &variable.key;
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(invalid(key, "Variable keys may only use letters, digits and underscores"));
+        }
+        if variables[..i].iter().any(|v| v.key.eq_ignore_ascii_case(key)) {
+            return Err(invalid(key, "Duplicate variable key"));
+        }
+    }
+
+    let variables_json = serde_json::to_string(variables).map_err(|e| CortexError::Internal {
+        message: format!("Failed to serialize template variables: {}", e),
+    })?;
+    conn.execute(
+        "INSERT INTO prompt_templates (name, description, icon, body, variables, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![name.trim(), description, icon, body, variables_json, chrono::Utc::now().to_rfc3339()],
+    )?;
+
+    let id = format!("{}{}", CUSTOM_TEMPLATE_PREFIX, conn.last_insert_rowid());
+    get_prompt_template(conn, &id)?.ok_or_else(|| CortexError::Internal {
+        message: format!("Prompt template {} vanished after insert", id),
+    })
+}
+
+/// The user template with `template_id`, if there is one
+pub fn get_prompt_template(conn: &Connection, template_id: &str) -> Result<Option<CustomPromptTemplate>> {
+    let Some(id) = row_id(template_id) else {
+        return Ok(None);
+    };
+
+    Ok(conn
+        .query_row(
+            "SELECT id, name, description, icon, body, variables, created_at
+             FROM prompt_templates WHERE id = ?1",
+            params![id],
+            read_template,
+        )
+        .optional()?)
+}
+
+/// User templates, oldest first
+pub fn list_prompt_templates(conn: &Connection) -> Result<Vec<CustomPromptTemplate>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, icon, body, variables, created_at
+         FROM prompt_templates ORDER BY id",
+    )?;
+
+    let templates = stmt
+        .query_map([], read_template)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(templates)
+}
+
+/// Delete a user template; returns false if there was none with that id
+pub fn delete_prompt_template(conn: &Connection, template_id: &str) -> Result<bool> {
+    let Some(id) = row_id(template_id) else {
+        return Ok(false);
+    };
+
+    let deleted = conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn variable(key: &str) -> PromptVariable {
+        PromptVariable {
+            key: key.to_string(),
+            label: key.to_string(),
+            placeholder: String::new(),
+            required: true,
+            default: None,
+        }
+    }
+
+    #[test]
+    fn test_prompt_template_round_trip_and_validation() {
+        let conn = setup_test_db();
+
+        let created = create_prompt_template(&conn, " Release Notes ", "Summarize a release", "📝",
+            "Write notes for {VERSION}.", &[variable("version")]).unwrap();
+        assert_eq!(created.name, "Release Notes");
+        assert_eq!(created.variables, vec![variable("version")]);
+        assert_eq!(list_prompt_templates(&conn).unwrap(), vec![created.clone()]);
+        assert_eq!(get_prompt_template(&conn, "add_feature").unwrap(), None);
+
+        for (name, body, variables) in [
+            ("  ", "body", vec![]),
+            ("Name", " ", vec![]),
+            ("Name", "body", vec![variable("has space")]),
+            ("Name", "body", vec![variable("key"), variable("KEY")]),
+        ] {
+            assert!(matches!(
+                create_prompt_template(&conn, name, "", "", body, &variables),
+                Err(CortexError::InvalidQuery { .. })
+            ));
+        }
+
+        assert!(delete_prompt_template(&conn, &created.id).unwrap());
+        assert!(!delete_prompt_template(&conn, &created.id).unwrap());
+        assert!(list_prompt_templates(&conn).unwrap().is_empty());
+    }
+}
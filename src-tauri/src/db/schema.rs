@@ -1,6 +1,7 @@
 use crate::error::Result;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
@@ -23,6 +24,10 @@ pub struct FileContent {
     pub text_content: Option<String>,
     pub word_count: Option<i64>,
     pub summary: Option<String>,
+    /// Document properties recorded by the extractor, e.g. a PDF's title
+    /// and page count
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +37,13 @@ pub struct SearchResult {
     pub filename: String,
     pub snippet: String,
     pub score: f64,
+    /// True when the file has been soft-deleted (only returned when deleted
+    /// files are explicitly included)
+    #[serde(default)]
+    pub is_deleted: bool,
+    /// Live files with the same content, which are indexed through this one
+    #[serde(default)]
+    pub duplicate_paths: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +54,22 @@ pub struct FileEmbedding {
     pub created_at: String,
 }
 
+/// Word-count statistics over the indexed text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContentStats {
+    /// Live files with extracted text; duplicates share their owner's
+    pub files_with_content: i64,
+    pub total_words: i64,
+    pub average_words: f64,
+    pub min_words: i64,
+    /// Mean of the two middle counts when there is an even number of files
+    pub median_words: f64,
+    pub max_words: i64,
+}
+
+/// Current schema version, tracked in `PRAGMA user_version`
+pub const SCHEMA_VERSION: i32 = 18;
+
 pub fn create_tables(conn: &Connection) -> Result<()> {
     // Files table
     conn.execute(
@@ -125,7 +153,103 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
         [],
     )?;
 
-    // File embeddings table (Phase 2: AI Features)
+    run_migrations(conn)?;
+
+    Ok(())
+}
+
+/// Apply any versioned migrations this database hasn't seen yet
+///
+/// Each step must be idempotent: databases created before versioning
+/// existed report version 0 but may already contain some of these tables.
+pub fn run_migrations(conn: &Connection) -> Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    if version < 1 {
+        create_embedding_tables(conn)?;
+    }
+
+    if version < 2 {
+        // Migration 2: embeddings gained a dimension/model header
+        let converted = crate::db::embeddings::reencode_legacy_embeddings(conn)?;
+        if converted > 0 {
+            log::info!("Re-encoded {} legacy embeddings with BLOB header", converted);
+        }
+    }
+
+    if version < 3 {
+        add_duplicate_tracking(conn)?;
+    }
+
+    if version < 4 {
+        create_search_history_table(conn)?;
+    }
+
+    if version < 5 {
+        create_tag_tables(conn)?;
+    }
+
+    if version < 6 {
+        add_pinned_column(conn)?;
+    }
+
+    if version < 7 {
+        create_stats_table(conn)?;
+    }
+
+    if version < 8 {
+        add_path_key_column(conn)?;
+    }
+
+    if version < 9 {
+        lowercase_file_types(conn)?;
+    }
+
+    if version < 10 {
+        create_code_fts_table(conn)?;
+    }
+
+    if version < 11 {
+        create_bookmarks_table(conn)?;
+    }
+
+    if version < 12 {
+        add_content_compression(conn)?;
+    }
+
+    if version < 13 {
+        create_collections_tables(conn)?;
+    }
+
+    if version < 14 {
+        create_prompt_templates_table(conn)?;
+    }
+
+    if version < 15 {
+        create_file_links_table(conn)?;
+    }
+
+    if version < 16 {
+        add_content_extractor_column(conn)?;
+    }
+
+    if version < 17 {
+        canonicalize_file_paths(conn)?;
+    }
+
+    if version < 18 {
+        add_content_metadata_column(conn)?;
+    }
+
+    if version < SCHEMA_VERSION {
+        conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+    }
+
+    Ok(())
+}
+
+/// Migration 1: file embeddings table (Phase 2: AI Features)
+fn create_embedding_tables(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS file_embeddings (
             file_id INTEGER PRIMARY KEY,
@@ -144,3 +268,515 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
 
     Ok(())
 }
+
+/// Migration 3: content deduplication by hash
+///
+/// A file whose content hash matches an already-indexed file stores no
+/// content or embedding of its own; `duplicate_of` points at the file that
+/// holds them.
+fn add_duplicate_tracking(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('files') WHERE name = 'duplicate_of'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE files ADD COLUMN duplicate_of INTEGER REFERENCES files(id) ON DELETE SET NULL",
+            [],
+        )?;
+    }
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_files_hash ON files(hash)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_files_duplicate_of ON files(duplicate_of)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 4: search history with cached query embeddings
+fn create_search_history_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS search_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            query TEXT NOT NULL UNIQUE,
+            search_count INTEGER NOT NULL DEFAULT 1,
+            last_searched_at TEXT NOT NULL,
+            embedding BLOB,
+            embedding_model TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_history_last ON search_history(last_searched_at)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 5: user-assigned file tags
+fn create_tag_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_tags (
+            file_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (file_id, tag),
+            FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_file_tags_tag ON file_tags(tag)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 6: user-pinned files, which rank and export first
+fn add_pinned_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('files') WHERE name = 'pinned'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE files ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Migration 7: running totals for the dashboard stats
+///
+/// Triggers keep `db_stats` in step with every change to `files` and
+/// `file_content`, so reading the stats doesn't scan the tables. The totals
+/// match `get_db_stats`' definitions: non-deleted files, those with content
+/// (or sharing a duplicate's), and their total size.
+fn create_stats_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS db_stats (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            total_files INTEGER NOT NULL DEFAULT 0,
+            indexed_files INTEGER NOT NULL DEFAULT 0,
+            total_size INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    conn.execute("INSERT OR IGNORE INTO db_stats (id) VALUES (1)", [])?;
+
+    // A file's contribution to each total
+    let live = |row: &str| format!("({}.is_deleted IS 0)", row);
+    let size = |row: &str| format!("(CASE WHEN {}.is_deleted IS 0 THEN {}.size ELSE 0 END)", row, row);
+    let indexed = |row: &str| {
+        format!(
+            "({row}.is_deleted IS 0 AND ({row}.duplicate_of IS NOT NULL
+                OR EXISTS (SELECT 1 FROM file_content WHERE file_id = {row}.id)))"
+        )
+    };
+
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER IF NOT EXISTS db_stats_file_insert AFTER INSERT ON files BEGIN
+                UPDATE db_stats SET
+                    total_files = total_files + {},
+                    indexed_files = indexed_files + {},
+                    total_size = total_size + {}
+                WHERE id = 1;
+             END",
+            live("new"),
+            indexed("new"),
+            size("new")
+        ),
+        [],
+    )?;
+
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER IF NOT EXISTS db_stats_file_update
+             AFTER UPDATE OF is_deleted, size, duplicate_of ON files BEGIN
+                UPDATE db_stats SET
+                    total_files = total_files - {} + {},
+                    indexed_files = indexed_files - {} + {},
+                    total_size = total_size - {} + {}
+                WHERE id = 1;
+             END",
+            live("old"),
+            live("new"),
+            indexed("old"),
+            indexed("new"),
+            size("old"),
+            size("new")
+        ),
+        [],
+    )?;
+
+    // BEFORE, while the content row still exists; the cascade that removes
+    // it then finds no parent and leaves the totals alone
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER IF NOT EXISTS db_stats_file_delete BEFORE DELETE ON files BEGIN
+                UPDATE db_stats SET
+                    total_files = total_files - {},
+                    indexed_files = indexed_files - {},
+                    total_size = total_size - {}
+                WHERE id = 1;
+             END",
+            live("old"),
+            indexed("old"),
+            size("old")
+        ),
+        [],
+    )?;
+
+    // Content only changes the indexed count of live files that aren't
+    // already counted as duplicates
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS db_stats_content_insert AFTER INSERT ON file_content BEGIN
+            UPDATE db_stats SET indexed_files = indexed_files + (
+                SELECT COUNT(*) FROM files
+                WHERE id = new.file_id AND is_deleted IS 0 AND duplicate_of IS NULL
+            )
+            WHERE id = 1;
+         END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS db_stats_content_delete AFTER DELETE ON file_content BEGIN
+            UPDATE db_stats SET indexed_files = indexed_files - (
+                SELECT COUNT(*) FROM files
+                WHERE id = old.file_id AND is_deleted IS 0 AND duplicate_of IS NULL
+            )
+            WHERE id = 1;
+         END",
+        [],
+    )?;
+
+    crate::db::operations::recompute_stats(conn)?;
+
+    Ok(())
+}
+
+/// Migration 8: `path_key`, the path as the filesystem compares it
+///
+/// Where paths ignore case, rows already stored under case variants of one
+/// path are the same file; the most recently indexed row is kept and the
+/// others are removed before the key is made unique.
+fn add_path_key_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('files') WHERE name = 'path_key'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_column {
+        conn.execute("ALTER TABLE files ADD COLUMN path_key TEXT", [])?;
+    }
+
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, path FROM files WHERE path_key IS NULL
+             ORDER BY last_indexed DESC, id DESC",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let mut stale = Vec::new();
+    for (id, path) in rows {
+        let key = crate::paths::path_key(&path);
+        if seen.insert(key.clone()) {
+            conn.execute("UPDATE files SET path_key = ?1 WHERE id = ?2", rusqlite::params![key, id])?;
+        } else {
+            stale.push(id);
+        }
+    }
+
+    if !stale.is_empty() {
+        log::info!("Removing {} files indexed under another case of their path", stale.len());
+        crate::db::operations::delete_files(conn, &stale, true)?;
+    }
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_files_path_key ON files(path_key)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 17: paths stored as scanned from their canonical root
+///
+/// Roots used to be scanned as given, so `~/docs/.` or a symlinked root
+/// stored paths a scan of the normalized root wouldn't match, and the next
+/// scan indexed every file again. Where several rows now name one file, the
+/// most recently indexed row is kept and the others are removed.
+fn canonicalize_file_paths(conn: &Connection) -> Result<()> {
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, path FROM files ORDER BY last_indexed DESC, id DESC")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let mut moved = Vec::new();
+    let mut stale = Vec::new();
+    for (id, path) in rows {
+        let canonical = crate::paths::canonical_file_path(std::path::Path::new(&path));
+        let canonical_str = canonical.to_string_lossy().into_owned();
+        if !seen.insert(crate::paths::path_key(&canonical_str)) {
+            stale.push(id);
+        } else if canonical_str != path {
+            let root_path = canonical.parent().map(|dir| dir.to_string_lossy().into_owned()).unwrap_or_default();
+            moved.push((id, canonical_str, root_path));
+        }
+    }
+
+    // Stale rows first, so no kept row is renamed onto one
+    if !stale.is_empty() {
+        log::info!("Removing {} files indexed under another form of their path", stale.len());
+        crate::db::operations::delete_files(conn, &stale, true)?;
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for (id, path, root_path) in &moved {
+        tx.execute(
+            "UPDATE files SET path = ?2, path_key = ?3, root_path = ?4 WHERE id = ?1",
+            rusqlite::params![id, path, crate::paths::path_key(path), root_path],
+        )?;
+    }
+    tx.commit()?;
+
+    if !moved.is_empty() {
+        log::info!("Canonicalized the stored paths of {} files", moved.len());
+    }
+
+    Ok(())
+}
+
+/// Migration 9: file types stored as indexed, e.g. `PDF`, are lowercased to
+/// match what `insert_file` now stores
+fn lowercase_file_types(conn: &Connection) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE files SET file_type = lower(file_type) WHERE file_type <> lower(file_type)",
+        [],
+    )?;
+
+    if updated > 0 {
+        log::info!("Lowercased the file type of {} files", updated);
+    }
+
+    Ok(())
+}
+
+/// Migration 10: source files' code and comments, indexed apart
+///
+/// Rows are keyed by file id like `files_fts`, but only source files in a
+/// language the comment splitter knows get one. The indexer writes them
+/// alongside the content; deleting the content removes them.
+fn create_code_fts_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS code_fts USING fts5(
+            code,
+            comments,
+            tokenize='porter'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS code_fts_delete AFTER DELETE ON file_content BEGIN
+            DELETE FROM code_fts WHERE rowid = old.file_id;
+         END",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 11: bookmarked files
+fn create_bookmarks_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bookmarks (
+            file_id INTEGER PRIMARY KEY,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 12: optionally gzip-compressed content, and index settings
+///
+/// A compressed row keeps its text in `text_compressed` (with its
+/// uncompressed size in `text_size`) and leaves `text_content` NULL. SQL
+/// can't decompress it, so the FTS triggers skip such rows and the writer
+/// indexes their text itself.
+fn add_content_compression(conn: &Connection) -> Result<()> {
+    let has_columns: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('file_content') WHERE name = 'is_compressed'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_columns {
+        conn.execute_batch(
+            "ALTER TABLE file_content ADD COLUMN text_compressed BLOB;
+             ALTER TABLE file_content ADD COLUMN is_compressed INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE file_content ADD COLUMN text_size INTEGER;",
+        )?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+         );
+
+         DROP TRIGGER IF EXISTS files_fts_insert;
+         CREATE TRIGGER files_fts_insert AFTER INSERT ON file_content
+         WHEN new.is_compressed = 0 BEGIN
+            INSERT INTO files_fts(rowid, filename, content)
+            SELECT f.id, f.filename, new.text_content
+            FROM files f WHERE f.id = new.file_id;
+         END;
+
+         DROP TRIGGER IF EXISTS files_fts_update;
+         CREATE TRIGGER files_fts_update AFTER UPDATE ON file_content
+         WHEN new.is_compressed = 0 BEGIN
+            UPDATE files_fts SET content = new.text_content
+            WHERE rowid = new.file_id;
+         END;",
+    )?;
+
+    Ok(())
+}
+
+/// Migration 13: named collections of files
+///
+/// A file can belong to any number of collections; deleting either side
+/// removes the membership.
+fn create_collections_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS collections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL,
+            created_at TEXT NOT NULL
+         );
+
+         CREATE TABLE IF NOT EXISTS collection_files (
+            collection_id INTEGER NOT NULL,
+            file_id INTEGER NOT NULL,
+            added_at TEXT NOT NULL,
+            PRIMARY KEY (collection_id, file_id),
+            FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+            FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+         );
+
+         CREATE INDEX IF NOT EXISTS idx_collection_files_file ON collection_files(file_id);",
+    )?;
+
+    Ok(())
+}
+
+/// Migration 14: user-defined prompt templates
+///
+/// `variables` holds the template's `PromptVariable`s as JSON.
+fn create_prompt_templates_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prompt_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            body TEXT NOT NULL,
+            variables TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Migration 15: URLs referenced by each file's content
+///
+/// `domain` is the URL's lowercase host, for finding every file that links
+/// to a site.
+fn create_file_links_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS file_links (
+            file_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            PRIMARY KEY (file_id, url),
+            FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+         );
+
+         CREATE INDEX IF NOT EXISTS idx_file_links_domain ON file_links(domain);",
+    )?;
+
+    Ok(())
+}
+
+/// Migration 16: which extractor produced each file's content
+///
+/// Content is only reused for identical bytes when the same extractor
+/// version would produce it again. Rows from before this migration have no
+/// extractor, so each file is extracted afresh the next time it's indexed.
+fn add_content_extractor_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('file_content') WHERE name = 'extractor'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_column {
+        conn.execute("ALTER TABLE file_content ADD COLUMN extractor TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Migration 18: document properties of each file's content, as a JSON
+/// object
+///
+/// Rows from before this migration have none until their file is extracted
+/// again.
+fn add_content_metadata_column(conn: &Connection) -> Result<()> {
+    let has_column: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('file_content') WHERE name = 'metadata'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !has_column {
+        conn.execute("ALTER TABLE file_content ADD COLUMN metadata TEXT", [])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Search history
+//!
+//! Records each distinct keyword query with how often and when it was last
+//! run. Query embeddings are cached alongside so related-search suggestions
+//! don't re-embed the whole history on every call.
+
+use crate::db::embeddings::{decode_embedding, encode_embedding};
+use crate::error::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHistoryEntry {
+    pub id: i64,
+    pub query: String,
+    pub search_count: i64,
+    pub last_searched_at: String,
+}
+
+/// Record a search, bumping the count if the query was seen before
+///
+/// Queries are trimmed; blank queries are ignored.
+pub fn record_search(conn: &Connection, query: &str) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO search_history (query, search_count, last_searched_at)
+         VALUES (?1, 1, ?2)
+         ON CONFLICT(query) DO UPDATE SET
+            search_count = search_count + 1,
+            last_searched_at = excluded.last_searched_at",
+        params![query, now],
+    )?;
+
+    Ok(())
+}
+
+/// Most recent searches first
+pub fn list_search_history(conn: &Connection, limit: usize) -> Result<Vec<SearchHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, query, search_count, last_searched_at
+         FROM search_history
+         ORDER BY last_searched_at DESC, id DESC
+         LIMIT ?1"
+    )?;
+
+    let entries = stmt
+        .query_map(params![limit], |row| {
+            Ok(SearchHistoryEntry {
+                id: row.get(0)?,
+                query: row.get(1)?,
+                search_count: row.get(2)?,
+                last_searched_at: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// All history entries with their cached embedding, if one exists for
+/// `model_version`
+///
+/// Corrupt cached vectors are treated as missing so they get re-embedded.
+pub fn get_history_with_embeddings(
+    conn: &Connection,
+    model_version: &str,
+) -> Result<Vec<(SearchHistoryEntry, Option<Vec<f32>>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, query, search_count, last_searched_at,
+                CASE WHEN embedding_model = ?1 THEN embedding END
+         FROM search_history
+         ORDER BY id"
+    )?;
+
+    let rows = stmt
+        .query_map(params![model_version], |row| {
+            let bytes: Option<Vec<u8>> = row.get(4)?;
+            Ok((
+                SearchHistoryEntry {
+                    id: row.get(0)?,
+                    query: row.get(1)?,
+                    search_count: row.get(2)?,
+                    last_searched_at: row.get(3)?,
+                },
+                bytes,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(entry, bytes)| {
+            let embedding = bytes.and_then(|b| decode_embedding(&b).ok());
+            (entry, embedding)
+        })
+        .collect())
+}
+
+/// Cache the embedding for a history entry
+pub fn set_history_embedding(
+    conn: &Connection,
+    history_id: i64,
+    embedding: &[f32],
+    model_version: &str,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE search_history SET embedding = ?1, embedding_model = ?2 WHERE id = ?3",
+        params![encode_embedding(embedding), model_version, history_id],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::EMBEDDING_DIM;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_record_search_dedupes_and_counts() {
+        let conn = setup_test_db();
+
+        record_search(&conn, "rust lifetimes").unwrap();
+        record_search(&conn, "  rust lifetimes ").unwrap();
+        record_search(&conn, "tokio runtime").unwrap();
+        record_search(&conn, "   ").unwrap();
+
+        let history = list_search_history(&conn, 10).unwrap();
+        assert_eq!(history.len(), 2);
+
+        let rust = history.iter().find(|e| e.query == "rust lifetimes").unwrap();
+        assert_eq!(rust.search_count, 2);
+    }
+
+    #[test]
+    fn test_embedding_cache_is_per_model() {
+        let conn = setup_test_db();
+        record_search(&conn, "cached query").unwrap();
+        let id = list_search_history(&conn, 1).unwrap()[0].id;
+
+        let vector = vec![0.25f32; EMBEDDING_DIM];
+        set_history_embedding(&conn, id, &vector, "model-a").unwrap();
+
+        let cached = get_history_with_embeddings(&conn, "model-a").unwrap();
+        assert_eq!(cached[0].1.as_deref(), Some(vector.as_slice()));
+
+        let other_model = get_history_with_embeddings(&conn, "model-b").unwrap();
+        assert!(other_model[0].1.is_none());
+    }
+}
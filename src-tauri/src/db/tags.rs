@@ -0,0 +1,122 @@
+//! File tags
+//!
+//! Tags are free-form labels users attach to files. They are stored trimmed
+//! and lowercased so "Work" and "work " are the same tag.
+
+use crate::error::Result;
+use rusqlite::{params, Connection};
+
+/// Normalized form a tag is stored and matched in, or None if blank
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+/// Attach every tag to every file in one transaction
+///
+/// Tags a file already has are left alone. Returns the number of new
+/// file/tag pairs.
+pub fn add_tags_to_files(conn: &Connection, file_ids: &[i64], tags: &[String]) -> Result<usize> {
+    let tags: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+    if file_ids.is_empty() || tags.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let tx = conn.unchecked_transaction()?;
+    let mut added = 0;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO file_tags (file_id, tag, created_at)
+             SELECT id, ?2, ?3 FROM files WHERE id = ?1",
+        )?;
+
+        for file_id in file_ids {
+            for tag in &tags {
+                added += stmt.execute(params![file_id, tag, now])?;
+            }
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(added)
+}
+
+/// Detach a tag from a file
+pub fn remove_tag_from_file(conn: &Connection, file_id: i64, tag: &str) -> Result<()> {
+    if let Some(tag) = normalize_tag(tag) {
+        conn.execute(
+            "DELETE FROM file_tags WHERE file_id = ?1 AND tag = ?2",
+            params![file_id, tag],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Tags on a file, alphabetically
+pub fn get_file_tags(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM file_tags WHERE file_id = ?1 ORDER BY tag")?;
+
+    let tags = stmt
+        .query_map(params![file_id], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+
+    Ok(tags)
+}
+
+/// Every tag in use with how many files carry it, most used first
+pub fn list_tags(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT tag, COUNT(*) FROM file_tags GROUP BY tag ORDER BY COUNT(*) DESC, tag",
+    )?;
+
+    let tags = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::insert_file;
+    use crate::db::schema::create_tables;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        conn
+    }
+
+    fn add_file(conn: &Connection, name: &str) -> i64 {
+        insert_file(conn, &format!("/test/{}", name), name, "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap()
+    }
+
+    #[test]
+    fn test_bulk_add_tags() {
+        let conn = setup_test_db();
+        let a = add_file(&conn, "a.txt");
+        let b = add_file(&conn, "b.txt");
+
+        let tags = vec!["Work".to_string(), " urgent ".to_string(), "".to_string()];
+        assert_eq!(add_tags_to_files(&conn, &[a, b], &tags).unwrap(), 4);
+
+        // Re-adding is a no-op, unknown file ids are ignored
+        assert_eq!(add_tags_to_files(&conn, &[a, 9999], &["work".to_string()]).unwrap(), 0);
+
+        assert_eq!(get_file_tags(&conn, a).unwrap(), vec!["urgent", "work"]);
+        assert_eq!(list_tags(&conn).unwrap(), vec![("urgent".to_string(), 2), ("work".to_string(), 2)]);
+
+        remove_tag_from_file(&conn, b, "URGENT").unwrap();
+        assert_eq!(get_file_tags(&conn, b).unwrap(), vec!["work"]);
+    }
+}
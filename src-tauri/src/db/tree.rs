@@ -0,0 +1,166 @@
+//! Indexed file tree
+//!
+//! Builds a nested directory view from the stored file paths rather than by
+//! walking the disk, so it shows exactly what is indexed.
+
+use crate::error::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTreeNode {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    /// Set for file nodes only
+    pub file_id: Option<i64>,
+    /// Bytes of this file, or of every file below this directory
+    pub size: i64,
+    /// 1 for a file, or the number of files below this directory
+    pub file_count: i64,
+    /// Directories first, then files, each sorted by name
+    pub children: Vec<FileTreeNode>,
+}
+
+/// Intermediate directory while paths are being inserted
+#[derive(Default)]
+struct DirBuilder {
+    dirs: BTreeMap<String, DirBuilder>,
+    files: BTreeMap<String, (i64, String, i64)>,
+}
+
+impl DirBuilder {
+    fn insert(&mut self, relative: &Path, file_id: i64, path: String, size: i64) {
+        let mut parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        let Some(filename) = parts.pop() else {
+            return;
+        };
+
+        let mut dir = self;
+        for part in parts {
+            dir = dir.dirs.entry(part).or_default();
+        }
+        dir.files.insert(filename, (file_id, path, size));
+    }
+
+    fn build(self, name: String, path: &Path) -> FileTreeNode {
+        let mut children = Vec::with_capacity(self.dirs.len() + self.files.len());
+
+        for (dir_name, dir) in self.dirs {
+            let dir_path = path.join(&dir_name);
+            children.push(dir.build(dir_name, &dir_path));
+        }
+
+        for (file_name, (file_id, file_path, size)) in self.files {
+            children.push(FileTreeNode {
+                name: file_name,
+                path: file_path,
+                is_dir: false,
+                file_id: Some(file_id),
+                size,
+                file_count: 1,
+                children: Vec::new(),
+            });
+        }
+
+        FileTreeNode {
+            name,
+            path: path.to_string_lossy().into_owned(),
+            is_dir: true,
+            file_id: None,
+            size: children.iter().map(|c| c.size).sum(),
+            file_count: children.iter().map(|c| c.file_count).sum(),
+            children,
+        }
+    }
+}
+
+/// Nested tree of the non-deleted files indexed under `root_path`
+///
+/// `root_path` may be an indexed root or any directory inside one. An empty
+/// directory node is returned when nothing is indexed there.
+pub fn get_file_tree(conn: &Connection, root_path: &str) -> Result<FileTreeNode> {
+    let root = PathBuf::from(root_path);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, path, size FROM files
+         WHERE is_deleted = 0
+           AND (root_path = ?1 OR substr(path, 1, length(?1)) = ?1)
+         ORDER BY path"
+    )?;
+
+    let rows = stmt
+        .query_map(params![root_path], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut builder = DirBuilder::default();
+    for (file_id, path, size) in rows {
+        // The text prefix also matches siblings like `/docs2` for `/docs`
+        let relative = match Path::new(&path).strip_prefix(&root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => continue,
+        };
+        builder.insert(&relative, file_id, path, size);
+    }
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root_path.to_string());
+
+    Ok(builder.build(name, &root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::create_tables;
+    use crate::db::operations::{insert_file, mark_file_deleted};
+
+    fn add_file(conn: &Connection, path: &str, size: i64, root: &str) -> i64 {
+        let filename = Path::new(path).file_name().unwrap().to_str().unwrap();
+        insert_file(conn, path, filename, "txt", size, "2025-11-29T00:00:00Z",
+            "2025-11-29T00:00:00Z", None, root).unwrap()
+    }
+
+    #[test]
+    fn test_tree_nesting_and_aggregates() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        add_file(&conn, "/docs/readme.txt", 10, "/docs");
+        add_file(&conn, "/docs/notes/a.txt", 100, "/docs");
+        add_file(&conn, "/docs/notes/deep/b.txt", 1000, "/docs");
+        let gone = add_file(&conn, "/docs/notes/gone.txt", 5, "/docs");
+        mark_file_deleted(&conn, gone).unwrap();
+        add_file(&conn, "/docs2/other.txt", 7, "/docs2");
+
+        let tree = get_file_tree(&conn, "/docs").unwrap();
+        assert_eq!(tree.name, "docs");
+        assert_eq!(tree.size, 1110);
+        assert_eq!(tree.file_count, 3);
+
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["notes", "readme.txt"]);
+
+        let notes = &tree.children[0];
+        assert!(notes.is_dir);
+        assert_eq!(notes.size, 1100);
+        assert_eq!(notes.file_count, 2);
+        assert_eq!(notes.children[0].name, "deep");
+        assert_eq!(notes.children[0].children[0].path, "/docs/notes/deep/b.txt");
+
+        // A subdirectory works as the root too
+        let deep = get_file_tree(&conn, "/docs/notes/deep").unwrap();
+        assert_eq!(deep.file_count, 1);
+        assert_eq!(deep.size, 1000);
+    }
+}
@@ -0,0 +1,359 @@
+//! Dedicated writer thread and pooled readers
+//!
+//! SQLite allows a single writer at a time, while in WAL mode readers never
+//! block it. Writes are queued to a thread that owns a write connection of
+//! its own and runs them in submission order, so callers never wait on the
+//! [`Database`] mutex to write. Reads check out a connection of their own
+//! from a small pool.
+
+use crate::db::{Database, Location};
+use crate::error::{CortexError, Result};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+/// Sizing of a [`DatabasePool`], kept in the index settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WriterConfig {
+    /// Writes that can wait in the queue before `submit` blocks
+    pub queue_capacity: usize,
+    /// Idle read connections kept open for reuse
+    pub read_connections: usize,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 256,
+            read_connections: 4,
+        }
+    }
+}
+
+type WriteJob = Box<dyn FnOnce(&Connection) + Send>;
+
+/// A write submitted to the [`DbWriter`], to be waited on for its result
+pub struct WriteTicket<T> {
+    reply: Receiver<Result<T>>,
+}
+
+impl<T> WriteTicket<T> {
+    /// Block until the write has run and return its result
+    pub fn wait(self) -> Result<T> {
+        self.reply.recv().map_err(|_| CortexError::Internal {
+            message: "Database writer stopped before running the write".to_string(),
+        })?
+    }
+}
+
+/// The thread that owns the write connection and runs queued writes on it
+pub struct DbWriter {
+    sender: Option<Sender<WriteJob>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DbWriter {
+    /// Start the writer thread, which writes through `conn` until dropped
+    pub fn spawn(conn: Connection, queue_capacity: usize) -> Result<Self> {
+        let (sender, receiver) = bounded::<WriteJob>(queue_capacity.max(1));
+
+        let handle = std::thread::Builder::new()
+            .name("cortex-db-writer".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    job(&conn);
+                }
+                log::debug!("Database writer stopped");
+            })
+            .map_err(|e| CortexError::Internal {
+                message: format!("Failed to start database writer: {}", e),
+            })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    /// Queue `op` to run on the write connection after earlier writes
+    ///
+    /// Blocks only while the queue is full. A panic in `op` is reported as an
+    /// error instead of stopping the writer.
+    pub fn submit<T, F>(&self, op: F) -> Result<WriteTicket<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let (reply_tx, reply) = bounded(1);
+
+        let job: WriteJob = Box::new(move |conn| {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| op(conn)))
+                .unwrap_or_else(|_| {
+                    Err(CortexError::Internal {
+                        message: "Database write panicked".to_string(),
+                    })
+                });
+            // The caller may have stopped waiting
+            let _ = reply_tx.send(result);
+        });
+
+        self.sender
+            .as_ref()
+            .expect("writer sender is only taken on drop")
+            .send(job)
+            .map_err(|_| CortexError::Internal {
+                message: "Database writer has stopped".to_string(),
+            })?;
+
+        Ok(WriteTicket { reply })
+    }
+
+    /// Run `op` on the write connection and wait for its result
+    pub fn execute<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        self.submit(op)?.wait()
+    }
+}
+
+impl Drop for DbWriter {
+    fn drop(&mut self) {
+        // Closing the channel lets the thread finish the queued writes and exit
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Read connections to one database, opened on demand and reused
+pub struct ReadPool {
+    location: Location,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+impl ReadPool {
+    pub(crate) fn new(location: Location, max_idle: usize) -> Self {
+        Self {
+            location,
+            idle: Mutex::new(Vec::new()),
+            max_idle,
+        }
+    }
+
+    /// Check out a connection, returned to the pool when dropped
+    pub fn get(&self) -> Result<PooledConnection<'_>> {
+        let idle = self.idle.lock().unwrap().pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => self.location.connect()?,
+        };
+
+        Ok(PooledConnection {
+            pool: self,
+            conn: Some(conn),
+        })
+    }
+}
+
+/// A connection checked out of a [`ReadPool`]
+pub struct PooledConnection<'a> {
+    pool: &'a ReadPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is only taken on drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut idle = self.pool.idle.lock().unwrap();
+            if idle.len() < self.pool.max_idle {
+                idle.push(conn);
+            }
+        }
+    }
+}
+
+/// Serialized writes and concurrent reads against one database
+pub struct DatabasePool {
+    writer: DbWriter,
+    readers: ReadPool,
+}
+
+impl DatabasePool {
+    /// Open a write connection and read connections to `db`'s database
+    ///
+    /// Neither goes through `db` itself, so holding its mutex never blocks
+    /// the pool.
+    pub fn new(db: &Database, config: WriterConfig) -> Result<Self> {
+        let location = db.location().clone();
+
+        Ok(Self {
+            writer: DbWriter::spawn(location.connect()?, config.queue_capacity)?,
+            readers: ReadPool::new(location, config.read_connections),
+        })
+    }
+
+    /// A pool over a new, empty in-memory database (unit tests only)
+    ///
+    /// The database lives as long as the pool's write connection.
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        Self::new(&Database::create_in_memory()?, WriterConfig::default())
+    }
+
+    /// Run `op` on the write connection, after any writes already queued
+    pub fn write<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        self.writer.execute(op)
+    }
+
+    /// Queue `op` without waiting for it to run
+    pub fn submit_write<T, F>(&self, op: F) -> Result<WriteTicket<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        self.writer.submit(op)
+    }
+
+    /// Run `op` on a pooled read connection
+    pub fn read<T>(&self, op: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let conn = self.readers.get()?;
+        op(&conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_file_count, insert_file, upsert_file_content};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_concurrent_reads_and_queued_writes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Arc::new(Mutex::new(Database::open_at(&temp_dir.path().join("db.sqlite")).unwrap()));
+        let pool = Arc::new(DatabasePool::new(&db.lock().unwrap(), WriterConfig::default()).unwrap());
+
+        // Queued writes and pooled reads don't wait for the main connection,
+        // even while a caller holds its mutex throughout
+        let held = db.lock().unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let pool = Arc::clone(&pool);
+                std::thread::spawn(move || {
+                    let tickets: Vec<_> = (0..50)
+                        .map(|i| {
+                            pool.submit_write(move |conn| {
+                                let path = format!("/test/{}-{}.txt", w, i);
+                                let id = insert_file(conn, &path, "f.txt", "txt", 10,
+                                    "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test")?;
+                                upsert_file_content(conn, id, Some("stress test content"), None)?;
+                                Ok(id)
+                            })
+                            .unwrap()
+                        })
+                        .collect();
+
+                    for ticket in tickets {
+                        ticket.wait().unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = Arc::clone(&pool);
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    for _ in 0..100 {
+                        let count = pool.read(get_file_count).unwrap();
+                        // Committed writes never disappear
+                        assert!(count >= last);
+                        last = count;
+                    }
+                })
+            })
+            .collect();
+
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(pool.read(get_file_count).unwrap(), 200);
+        drop(held);
+
+        // Writes through the main connection interleave with queued ones
+        let direct = {
+            let db = Arc::clone(&db);
+            std::thread::spawn(move || {
+                for i in 0..50 {
+                    let db = db.lock().unwrap();
+                    insert_file(db.get_connection(), &format!("/test/direct-{}.txt", i), "f.txt", "txt", 10,
+                        "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test").unwrap();
+                }
+            })
+        };
+        let queued: Vec<_> = (0..50)
+            .map(|i| {
+                pool.submit_write(move |conn| {
+                    insert_file(conn, &format!("/test/queued-{}.txt", i), "f.txt", "txt", 10,
+                        "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test")
+                })
+                .unwrap()
+            })
+            .collect();
+        direct.join().unwrap();
+        for ticket in queued {
+            ticket.wait().unwrap();
+        }
+
+        assert_eq!(pool.read(get_file_count).unwrap(), 300);
+        assert_eq!(get_file_count(db.lock().unwrap().get_connection()).unwrap(), 300);
+
+        // A failing write reports its error and the writer keeps going
+        let duplicate = pool.write(|conn| {
+            insert_file(conn, "/test/0-0.txt", "f.txt", "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test")
+        });
+        assert!(duplicate.is_err());
+        assert!(pool.write(|_| -> Result<()> { panic!("bad write") }).is_err());
+        assert_eq!(pool.write(get_file_count).unwrap(), 300);
+    }
+
+    #[test]
+    fn test_pool_reads_the_database_it_was_opened_for() {
+        // The pool's connections reach the same in-memory database
+        let db = tokio_test::block_on(Database::new_in_memory()).unwrap();
+        let pool = DatabasePool::new(&db, WriterConfig::default()).unwrap();
+
+        pool.write(|conn| {
+            insert_file(conn, "/test/a.txt", "a.txt", "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/test")
+        })
+        .unwrap();
+
+        assert_eq!(get_file_count(db.get_connection()).unwrap(), 1);
+        assert_eq!(pool.read(get_file_count).unwrap(), 1);
+    }
+}
@@ -1,9 +1,14 @@
-use serde::Serialize;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::json;
 use std::fmt;
 
 /// User-friendly error types for Cortex
-#[derive(Debug, Serialize)]
-#[serde(tag = "type", content = "data")]
+///
+/// Serializes as `{ "type", "code", "message", "data"? }`: `type` and `data`
+/// identify the variant and its fields, `code` is a stable machine-readable
+/// identifier the frontend maps to localized strings, and `message` is the
+/// English `Display` text (for logs and fallback).
+#[derive(Debug)]
 pub enum CortexError {
     /// Database-related errors
     DatabaseError { message: String },
@@ -21,10 +26,96 @@ pub enum CortexError {
     SearchTimeout,
     InvalidQuery { query: String, reason: String },
 
+    /// AI errors
+    ModelNotReady { reason: String },
+
+    /// A command argument or setting is out of range or malformed
+    InvalidInput { message: String },
+
+    /// A long-running operation stopped at the user's request, e.g. `"Export"`
+    Cancelled { operation: String },
+
     /// General errors
     Internal { message: String },
 }
 
+impl CortexError {
+    /// Stable error code, e.g. `"E_INVALID_QUERY"`
+    ///
+    /// Codes are part of the frontend contract: never change or reuse one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DatabaseError { .. } => "E_DATABASE",
+            Self::PermissionDenied { .. } => "E_PERMISSION_DENIED",
+            Self::FileNotFound { .. } => "E_FILE_NOT_FOUND",
+            Self::InvalidPath { .. } => "E_INVALID_PATH",
+            Self::ExtractionFailed { .. } => "E_EXTRACTION_FAILED",
+            Self::IndexingInProgress => "E_INDEXING_IN_PROGRESS",
+            Self::SearchTimeout => "E_SEARCH_TIMEOUT",
+            Self::InvalidQuery { .. } => "E_INVALID_QUERY",
+            Self::ModelNotReady { .. } => "E_MODEL_NOT_READY",
+            Self::InvalidInput { .. } => "E_INVALID_INPUT",
+            Self::Cancelled { .. } => "E_CANCELLED",
+            Self::Internal { .. } => "E_INTERNAL",
+        }
+    }
+
+    fn variant_name(&self) -> &'static str {
+        match self {
+            Self::DatabaseError { .. } => "DatabaseError",
+            Self::PermissionDenied { .. } => "PermissionDenied",
+            Self::FileNotFound { .. } => "FileNotFound",
+            Self::InvalidPath { .. } => "InvalidPath",
+            Self::ExtractionFailed { .. } => "ExtractionFailed",
+            Self::IndexingInProgress => "IndexingInProgress",
+            Self::SearchTimeout => "SearchTimeout",
+            Self::InvalidQuery { .. } => "InvalidQuery",
+            Self::ModelNotReady { .. } => "ModelNotReady",
+            Self::InvalidInput { .. } => "InvalidInput",
+            Self::Cancelled { .. } => "Cancelled",
+            Self::Internal { .. } => "Internal",
+        }
+    }
+
+    /// Variant fields, for interpolating into localized messages
+    fn data(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::DatabaseError { message } => Some(json!({ "message": message })),
+            Self::PermissionDenied { path, suggestion } => {
+                Some(json!({ "path": path, "suggestion": suggestion }))
+            }
+            Self::FileNotFound { path } => Some(json!({ "path": path })),
+            Self::InvalidPath { path, reason } => Some(json!({ "path": path, "reason": reason })),
+            Self::ExtractionFailed { path, error } => {
+                Some(json!({ "path": path, "error": error }))
+            }
+            Self::IndexingInProgress | Self::SearchTimeout => None,
+            Self::InvalidQuery { query, reason } => {
+                Some(json!({ "query": query, "reason": reason }))
+            }
+            Self::ModelNotReady { reason } => Some(json!({ "reason": reason })),
+            Self::InvalidInput { message } => Some(json!({ "message": message })),
+            Self::Cancelled { operation } => Some(json!({ "operation": operation })),
+            Self::Internal { message } => Some(json!({ "message": message })),
+        }
+    }
+}
+
+impl Serialize for CortexError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let data = self.data();
+
+        let mut state = serializer.serialize_struct("CortexError", 3 + data.is_some() as usize)?;
+        state.serialize_field("type", self.variant_name())?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        if let Some(data) = data {
+            state.serialize_field("data", &data)?;
+        }
+        state.end()
+    }
+}
+
 impl fmt::Display for CortexError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -52,6 +143,15 @@ impl fmt::Display for CortexError {
             Self::InvalidQuery { query, reason } => {
                 write!(f, "Invalid query '{}': {}", query, reason)
             }
+            Self::ModelNotReady { reason } => {
+                write!(f, "Embedding model is not ready: {}", reason)
+            }
+            Self::InvalidInput { message } => {
+                write!(f, "Invalid input: {}", message)
+            }
+            Self::Cancelled { operation } => {
+                write!(f, "{} was cancelled", operation)
+            }
             Self::Internal { message } => {
                 write!(f, "Internal error: {}", message)
             }
@@ -78,3 +178,62 @@ impl From<std::io::Error> for CortexError {
 }
 
 pub type Result<T> = std::result::Result<T, CortexError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_variant_serializes_with_code() {
+        let cases = vec![
+            (CortexError::DatabaseError { message: "locked".into() }, "E_DATABASE"),
+            (
+                CortexError::PermissionDenied { path: "/x".into(), suggestion: "chmod".into() },
+                "E_PERMISSION_DENIED",
+            ),
+            (CortexError::FileNotFound { path: "/x".into() }, "E_FILE_NOT_FOUND"),
+            (
+                CortexError::InvalidPath { path: "/x".into(), reason: "bad".into() },
+                "E_INVALID_PATH",
+            ),
+            (
+                CortexError::ExtractionFailed { path: "/x".into(), error: "eof".into() },
+                "E_EXTRACTION_FAILED",
+            ),
+            (CortexError::IndexingInProgress, "E_INDEXING_IN_PROGRESS"),
+            (CortexError::SearchTimeout, "E_SEARCH_TIMEOUT"),
+            (
+                CortexError::InvalidQuery { query: "".into(), reason: "empty".into() },
+                "E_INVALID_QUERY",
+            ),
+            (CortexError::ModelNotReady { reason: "missing".into() }, "E_MODEL_NOT_READY"),
+            (CortexError::InvalidInput { message: "too big".into() }, "E_INVALID_INPUT"),
+            (CortexError::Cancelled { operation: "Export".into() }, "E_CANCELLED"),
+            (CortexError::Internal { message: "oops".into() }, "E_INTERNAL"),
+        ];
+
+        for (error, code) in cases {
+            let value = serde_json::to_value(&error).unwrap();
+            assert_eq!(value["code"], code);
+            assert_eq!(value["message"], error.to_string());
+            assert_eq!(value["type"], error.variant_name());
+        }
+    }
+
+    #[test]
+    fn test_serialized_shape_keeps_type_and_data() {
+        let value = serde_json::to_value(CortexError::InvalidQuery {
+            query: "foo(".into(),
+            reason: "unbalanced".into(),
+        })
+        .unwrap();
+
+        assert_eq!(value["type"], "InvalidQuery");
+        assert_eq!(value["data"]["query"], "foo(");
+        assert_eq!(value["data"]["reason"], "unbalanced");
+
+        // Unit variants carry no data
+        let value = serde_json::to_value(CortexError::SearchTimeout).unwrap();
+        assert!(value.get("data").is_none());
+    }
+}
@@ -1,69 +1,89 @@
 use crate::db::Database;
 use crate::error::{CortexError, Result};
 use crate::export::{
-    ContextBuilder, ExportConfig, ExportResult, ExportStats, PathValidator, PromptBuilder
+    check_cancelled, ContextBuilder, ExportConfig, ExportResult, ExportStats, PathValidator,
+    PromptBuilder
 };
 use chrono::Utc;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 /// Bundles export files into a complete VS Code Claude context package
 pub struct BundleBuilder {
     db: Database,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl BundleBuilder {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self { db, cancel: None }
+    }
+
+    /// Stop with `CortexError::Cancelled` once `cancel` is set
+    ///
+    /// A cancelled bundle writes nothing to the output directory.
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
     }
 
     /// Create a complete export bundle
+    ///
+    /// Every file is built before any is written, so a cancelled or failed
+    /// build leaves no partial bundle behind.
     pub async fn create_bundle(&self, config: &ExportConfig) -> Result<ExportResult> {
         // Validate and sanitize output path (security)
         let output_dir = PathValidator::validate_export_path(&config.output_path)?;
-        self.ensure_directory(&output_dir)?;
+        let cancel = self.cancel.as_deref();
+        let mut outputs: Vec<(PathBuf, String)> = Vec::new();
 
         // Build CONTEXT.md
-        let context_builder = ContextBuilder::new(self.db.clone());
+        let mut context_builder = ContextBuilder::new(self.db.clone());
+        if let Some(flag) = &self.cancel {
+            context_builder = context_builder.with_cancel_flag(flag.clone());
+        }
         let (context_content, stats) = context_builder.build_context(config).await?;
-        let context_file = self.write_file(
-            &output_dir,
-            "CONTEXT.md",
-            &context_content
-        )?;
+        let context_file = output_dir.join("CONTEXT.md");
+        outputs.push((context_file.clone(), config.line_ending.apply(&context_content)));
+        check_cancelled(cancel)?;
 
         // Build STARTER_PROMPT.md
-        let prompt_builder = PromptBuilder::new(self.db.clone());
+        let prompt_builder = PromptBuilder::new(self.db.clone()).with_changed_since(config.changed_since);
         let starter_prompt = prompt_builder.build_starter_prompt(config).await?;
-        let starter_prompt_file = self.write_file(
-            &output_dir,
-            "STARTER_PROMPT.md",
-            &starter_prompt
-        )?;
+        let starter_prompt_file = output_dir.join("STARTER_PROMPT.md");
+        outputs.push((starter_prompt_file.clone(), config.line_ending.apply(&starter_prompt)));
+        check_cancelled(cancel)?;
 
         // Build prompt templates (if enabled)
         let mut prompt_files = Vec::new();
         if config.include_prompts {
             let prompts_dir = output_dir.join("prompts");
-            self.ensure_directory(&prompts_dir)?;
 
             // Create default prompt templates
             let templates = self.create_default_templates(&prompt_builder).await?;
             for (name, content) in templates {
-                let file_path = self.write_file(&prompts_dir, &name, &content)?;
-                prompt_files.push(file_path);
+                let file_path = prompts_dir.join(name);
+                prompt_files.push(file_path.to_string_lossy().to_string());
+                outputs.push((file_path, config.line_ending.apply(&content)));
             }
         }
 
         // Create .claude/ directory with settings
-        let claude_dir = output_dir.join(".claude");
-        self.ensure_directory(&claude_dir)?;
         let settings = self.create_claude_settings(config)?;
-        self.write_file(&claude_dir, "settings.json", &settings)?;
+        outputs.push((
+            output_dir.join(".claude").join("settings.json"),
+            config.line_ending.apply(&settings),
+        ));
 
         // Create README for the export
         let readme = self.create_export_readme(config, &stats)?;
-        self.write_file(&output_dir, "README.md", &readme)?;
+        outputs.push((output_dir.join("README.md"), config.line_ending.apply(&readme)));
+
+        // Last chance to stop before the output directory is touched
+        check_cancelled(cancel)?;
+        self.write_outputs(&outputs)?;
 
         // Update stats with actual prompt count
         let mut final_stats = stats;
@@ -74,32 +94,73 @@ impl BundleBuilder {
         };
 
         Ok(ExportResult {
-            context_file,
-            starter_prompt_file,
+            context_file: context_file.to_string_lossy().to_string(),
+            starter_prompt_file: starter_prompt_file.to_string_lossy().to_string(),
             prompt_files,
             stats: final_stats,
             exported_at: Utc::now(),
         })
     }
 
-    /// Ensure a directory exists, create if necessary
-    fn ensure_directory(&self, path: &Path) -> Result<()> {
+    /// Write each `(path, content)` pair, creating directories as needed
+    ///
+    /// If a write fails, the output directory is put back as it was:
+    /// files the export overwrote get their old contents back, and the
+    /// files and directories it created are removed.
+    fn write_outputs(&self, outputs: &[(PathBuf, String)]) -> Result<()> {
+        let mut created_dirs = Vec::new();
+        // Each file touched so far, with its contents from before the export
+        let mut written: Vec<(&Path, Option<Vec<u8>>)> = Vec::new();
+
+        let result = outputs.iter().try_for_each(|(path, content)| {
+            if let Some(dir) = path.parent() {
+                self.ensure_directory(dir, &mut created_dirs)?;
+            }
+            let previous = if path.is_file() {
+                Some(fs::read(path).map_err(|e| CortexError::Internal {
+                    message: format!("Failed to back up {}: {}", path.display(), e)
+                })?)
+            } else {
+                None
+            };
+            written.push((path, previous));
+            self.write_file(path, content)
+        });
+
+        if result.is_err() {
+            for (path, previous) in written.into_iter().rev() {
+                let restored = match previous {
+                    Some(previous) => fs::write(path, previous),
+                    None => fs::remove_file(path),
+                };
+                if let Err(e) = restored {
+                    log::warn!("Failed to roll back {}: {}", path.display(), e);
+                }
+            }
+            for dir in created_dirs.iter().rev() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+
+        result
+    }
+
+    /// Ensure a directory exists, create if necessary, noting it in `created`
+    fn ensure_directory(&self, path: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
         if !path.exists() {
             fs::create_dir_all(path).map_err(|e| CortexError::Internal {
                 message: format!("Failed to create directory {}: {}", path.display(), e)
             })?;
+            created.push(path.to_path_buf());
         }
         Ok(())
     }
 
     /// Write content to a file
-    fn write_file(&self, dir: &Path, filename: &str, content: &str) -> Result<String> {
-        let file_path = dir.join(filename);
-        fs::write(&file_path, content).map_err(|e| CortexError::Internal {
+    fn write_file(&self, file_path: &Path, content: &str) -> Result<()> {
+        fs::write(file_path, content).map_err(|e| CortexError::Internal {
             message: format!("Failed to write file {}: {}", file_path.display(), e)
-        })?;
-
-        Ok(file_path.to_string_lossy().to_string())
+        })
     }
 
     /// Create default prompt templates
@@ -142,76 +203,22 @@ impl BundleBuilder {
         templates.push(("REFACTOR.md".to_string(), refactor_prompt));
 
         // ADD_TESTS.md template
-        let test_prompt = self.create_test_template()?;
+        let test_prompt = builder.build_test_prompt("{MODULE_PATH}").await?
+            + "**Replace {MODULE_PATH} with the actual module path and begin implementation.**\n";
         templates.push(("ADD_TESTS.md".to_string(), test_prompt));
 
         // DOCUMENTATION.md template
-        let docs_prompt = self.create_documentation_template()?;
+        let docs_prompt = builder.build_documentation_prompt("{MODULE_PATH}").await?
+            + "**Replace {MODULE_PATH} with the actual module path and begin documentation.**\n";
         templates.push(("DOCUMENTATION.md".to_string(), docs_prompt));
 
-        Ok(templates)
-    }
-
-    /// Create test template
-    fn create_test_template(&self) -> Result<String> {
-        Ok(
-            "# ADD TESTS\n\n\
-             **Generated by Cortex**\n\n\
-             ---\n\n\
-             ## Testing Task\n\n\
-             Module/Feature to test: `{MODULE_PATH}`\n\n\
-             ## Test Requirements\n\n\
-             Please create comprehensive tests following these guidelines:\n\n\
-             1. **Unit Tests**: Test individual functions in isolation\n\
-             2. **Integration Tests**: Test interactions between components\n\
-             3. **Edge Cases**: Test boundary conditions and error cases\n\
-             4. **Coverage**: Aim for high code coverage\n\n\
-             ## Test Structure\n\n\
-             Based on CONTEXT.md, follow the existing test patterns:\n\
-             - Test file location\n\
-             - Test naming conventions\n\
-             - Assertion style\n\
-             - Mock/fixture usage\n\n\
-             ## Expected Deliverables\n\n\
-             - [ ] Unit tests for all public functions\n\
-             - [ ] Integration tests (if applicable)\n\
-             - [ ] Edge case tests\n\
-             - [ ] All tests passing\n\
-             - [ ] Test coverage report\n\n\
-             ---\n\n\
-             **Replace {MODULE_PATH} with the actual module path and begin implementation.**\n".to_string()
-        )
-    }
+        // User-defined templates, named after their titles
+        for custom in crate::db::list_prompt_templates(self.db.get_connection())? {
+            let file_name = prompt_file_name(&custom.name, &templates);
+            templates.push((file_name, custom.body));
+        }
 
-    /// Create documentation template
-    fn create_documentation_template(&self) -> Result<String> {
-        Ok(
-            "# DOCUMENTATION UPDATE\n\n\
-             **Generated by Cortex**\n\n\
-             ---\n\n\
-             ## Documentation Task\n\n\
-             Module/Feature to document: `{MODULE_PATH}`\n\n\
-             ## Documentation Requirements\n\n\
-             Please create or update documentation including:\n\n\
-             1. **Code Comments**: Inline documentation for complex logic\n\
-             2. **Doc Comments**: Function/class documentation\n\
-             3. **README**: User-facing documentation\n\
-             4. **Architecture Docs**: System design documentation\n\n\
-             ## Documentation Style\n\n\
-             Follow existing documentation patterns from CONTEXT.md:\n\
-             - Markdown format\n\
-             - Code examples where helpful\n\
-             - Clear, concise language\n\
-             - Diagrams for complex concepts (ASCII or Mermaid)\n\n\
-             ## Expected Deliverables\n\n\
-             - [ ] Updated code comments\n\
-             - [ ] Doc comments for public APIs\n\
-             - [ ] README updates (if applicable)\n\
-             - [ ] Architecture documentation (if needed)\n\
-             - [ ] Usage examples\n\n\
-             ---\n\n\
-             **Replace {MODULE_PATH} with the actual module path and begin documentation.**\n".to_string()
-        )
+        Ok(templates)
     }
 
     /// Create VS Code Claude settings
@@ -255,6 +262,7 @@ impl BundleBuilder {
              - **Files with Embeddings**: {}\n\
              - **Total Size**: {}\n\
              - **Estimated Tokens**: ~{}\n\n\
+             {}\
              ## How to Use\n\n\
              ### In VS Code Claude:\n\n\
              1. **Start a new session** in VS Code Claude\n\
@@ -284,11 +292,147 @@ impl BundleBuilder {
             stats.total_files,
             stats.files_with_embeddings,
             crate::export::format_file_size(stats.total_size_bytes),
-            stats.total_chunks
+            stats.estimated_tokens,
+            Self::context_budget_report(stats)
         ))
     }
+
+    /// README section on how much of the token budget CONTEXT.md uses and
+    /// which files' contents were left out
+    fn context_budget_report(stats: &ExportStats) -> String {
+        /// Omitted files listed by name before the rest are summarized
+        const MAX_LISTED_OMISSIONS: usize = 20;
+
+        let mut report = String::from("## Context Budget\n\n");
+
+        match stats.token_budget {
+            Some(budget) => {
+                let used = if budget > 0 {
+                    stats.estimated_tokens as f64 / budget as f64 * 100.0
+                } else {
+                    100.0
+                };
+                report.push_str(&format!(
+                    "- **Estimated Tokens**: ~{} of {} ({:.0}% of budget)\n",
+                    stats.estimated_tokens, budget, used
+                ));
+            }
+            None => report.push_str(&format!(
+                "- **Estimated Tokens**: ~{} (no budget set)\n",
+                stats.estimated_tokens
+            )),
+        }
+
+        report.push_str(&format!(
+            "- **Files with Contents Included**: {}\n\
+             - **Files with Contents Omitted**: {}\n\n",
+            stats.included_files.len(),
+            stats.omitted_files.len()
+        ));
+
+        if !stats.omitted_files.is_empty() {
+            report.push_str("Omitted from CONTEXT.md:\n\n");
+            for path in stats.omitted_files.iter().take(MAX_LISTED_OMISSIONS) {
+                report.push_str(&format!("- `{}`\n", path));
+            }
+            if stats.omitted_files.len() > MAX_LISTED_OMISSIONS {
+                report.push_str(&format!(
+                    "- ...and {} more\n",
+                    stats.omitted_files.len() - MAX_LISTED_OMISSIONS
+                ));
+            }
+            report.push('\n');
+        }
+
+        report
+    }
 }
 
 // Tests removed: test_ensure_directory was an incomplete stub that didn't test
 // actual functionality. Integration tests for the full bundle creation workflow
 // should be added instead.
+
+/// `NAME.md` for a user template called `name`, numbered if an earlier
+/// template already has that file name
+fn prompt_file_name(name: &str, taken: &[(String, String)]) -> String {
+    let mut stem = String::new();
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() {
+            stem.extend(c.to_uppercase());
+        } else if !stem.is_empty() && !stem.ends_with('_') {
+            stem.push('_');
+        }
+    }
+    let stem = match stem.trim_end_matches('_') {
+        "" => "CUSTOM_PROMPT",
+        stem => stem,
+    };
+
+    let mut file_name = format!("{}.md", stem);
+    let mut n = 2;
+    while taken.iter().any(|(taken, _)| taken == &file_name) {
+        file_name = format!("{}_{}.md", stem, n);
+        n += 1;
+    }
+    file_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_prompt_template;
+    use crate::export::PromptVariable;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_custom_prompt_templates_listed_rendered_and_exported() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+        let variables = vec![PromptVariable {
+            key: "version".to_string(),
+            label: "Version".to_string(),
+            placeholder: "1.2.0".to_string(),
+            required: true,
+            default: None,
+        }];
+        let notes = create_prompt_template(conn, "Release notes", "", "📝",
+            "# Release {VERSION}\n\nSummarize the changes in {VERSION}.\n", &variables).unwrap();
+        create_prompt_template(conn, "Add Feature!", "", "", "Shadows a built-in name", &[]).unwrap();
+        assert_eq!(crate::db::list_prompt_templates(conn).unwrap().len(), 2);
+
+        let builder = PromptBuilder::new(Database::open_in_memory().unwrap());
+        let files = tokio_test::block_on(BundleBuilder::new(db).create_default_templates(&builder)).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(&names[5..], ["RELEASE_NOTES.md", "ADD_FEATURE_2.md"]);
+        assert_eq!(files[5].1, notes.body);
+
+        // Rendering reads the template from the builder's own database
+        let db = Database::open_in_memory().unwrap();
+        let notes = create_prompt_template(db.get_connection(), "Release notes", "", "",
+            &notes.body, &variables).unwrap();
+        let builder = PromptBuilder::new(db);
+        let values = HashMap::from([("version".to_string(), "2.0".to_string())]);
+        let rendered = tokio_test::block_on(builder.render_template(&notes.id, &values)).unwrap();
+        assert_eq!(rendered, "# Release 2.0\n\nSummarize the changes in 2.0.\n");
+        assert!(tokio_test::block_on(builder.render_template(&notes.id, &HashMap::new())).is_err());
+    }
+
+    #[test]
+    fn test_failed_write_restores_overwritten_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let context = temp_dir.path().join("CONTEXT.md");
+        fs::write(&context, "from the last export").unwrap();
+
+        let outputs = vec![
+            (context.clone(), "new context".to_string()),
+            (temp_dir.path().join("prompts").join("ADD_FEATURE.md"), "new prompt".to_string()),
+            // A file can't be written under another file
+            (context.join("nested.md"), "unwritable".to_string()),
+        ];
+        let bundler = BundleBuilder::new(Database::open_in_memory().unwrap());
+        assert!(bundler.write_outputs(&outputs).is_err());
+
+        assert_eq!(fs::read_to_string(&context).unwrap(), "from the last export");
+        assert!(!temp_dir.path().join("prompts").exists());
+    }
+}
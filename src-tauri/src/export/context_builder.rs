@@ -1,18 +1,35 @@
 use crate::db::{Database, File};
 use crate::error::Result;
-use crate::export::{ExportConfig, ExportStats};
+use crate::export::{changed_since, check_cancelled, ContextOptions, ExportConfig, ExportStats};
 use chrono::Utc;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Which candidate files made it into the "File Contents" section
+#[derive(Debug, Default)]
+struct FileContentSelection {
+    included: Vec<String>,
+    omitted: Vec<String>,
+}
 
 /// Builds comprehensive CONTEXT.md file for VS Code Claude
 pub struct ContextBuilder {
     db: Database,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl ContextBuilder {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self { db, cancel: None }
+    }
+
+    /// Stop with `CortexError::Cancelled` once `cancel` is set, checked
+    /// between files
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
     }
 
     /// Build the complete CONTEXT.md content
@@ -32,11 +49,17 @@ impl ContextBuilder {
         // 3. Architecture Summary
         context.push_str(&self.build_architecture_summary(&files)?);
 
+        let options = &config.context;
+
         // 4. Project Structure
-        context.push_str(&self.build_project_structure(&files)?);
+        if options.include_file_tree {
+            context.push_str(&self.build_project_structure(&files)?);
+        }
 
         // 5. Key Files
-        context.push_str(&self.build_key_files(&files)?);
+        if options.include_file_summaries {
+            context.push_str(&self.build_key_files(&files)?);
+        }
 
         // 6. Dependencies
         context.push_str(&self.build_dependencies(&files)?);
@@ -44,23 +67,44 @@ impl ContextBuilder {
         // 7. Code Patterns
         context.push_str(&self.build_code_patterns(&files)?);
 
-        // 8. Current State
-        context.push_str(&self.build_current_state(&files)?);
+        // 9. Current State, built ahead of File Contents so the token budget
+        // accounts for it
+        let current_state = if options.include_stats {
+            self.build_current_state(&files)?
+        } else {
+            String::new()
+        };
+
+        // 8. File Contents
+        let mut selection = FileContentSelection::default();
+        if options.include_full_content {
+            let fixed_len = context.len() + current_state.len();
+            let (contents, selected) =
+                self.build_file_contents(&files, options, config.summaries_only, fixed_len)?;
+            context.push_str(&contents);
+            selection = selected;
+        }
+
+        context.push_str(&current_state);
 
         // Calculate stats
-        let stats = self.calculate_stats(&files)?;
+        let mut stats = self.calculate_stats(&files)?;
+        stats.estimated_tokens = crate::export::estimate_tokens(&context);
+        stats.token_budget = options.max_tokens;
+        stats.included_files = selection.included;
+        stats.omitted_files = selection.omitted;
 
         Ok((context, stats))
     }
 
     /// Get files to export based on configuration
-    fn get_files_for_export(&self, _config: &ExportConfig) -> Result<Vec<File>> {
+    fn get_files_for_export(&self, config: &ExportConfig) -> Result<Vec<File>> {
         let conn = self.db.get_connection();
 
         // For now, get all indexed files (collection support will be added in Phase 3)
         let files = crate::db::operations::list_files(conn, 10000, 0)?;
 
-        Ok(files)
+        Ok(changed_since(files, config.changed_since))
     }
 
     /// Build the markdown header
@@ -164,7 +208,8 @@ impl ContextBuilder {
             // Get file content summary
             let content = crate::db::operations::get_file_content(conn, file.id)?;
             let summary = content
-                .and_then(|c| c.summary)
+                .as_ref()
+                .and_then(crate::export::summary_or_generate)
                 .unwrap_or_else(|| "No summary available".to_string());
 
             key_files.push_str(&format!("### `{}`\n\n", file.path));
@@ -245,12 +290,127 @@ impl ContextBuilder {
         Ok(patterns)
     }
 
+    /// Build full file contents section for the most relevant files
+    ///
+    /// Pinned files are always included, even past
+    /// `max_files_with_full_content`. With `summaries_only`, each file's summary stands in for its body.
+    /// Under a `max_tokens` budget, other files are skipped once the section
+    /// plus the `fixed_len` bytes of the other sections would exceed it;
+    /// pinned files count against the budget but are never dropped by it.
+    fn build_file_contents(
+        &self,
+        files: &[File],
+        options: &ContextOptions,
+        summaries_only: bool,
+        fixed_len: usize,
+    ) -> Result<(String, FileContentSelection)> {
+        let mut contents = String::from("## File Contents\n\n");
+        let mut selection = FileContentSelection::default();
+        let conn = self.db.get_connection();
+
+        // Same estimate as `estimate_tokens`, on the length the context will have
+        let fits = |len: usize| {
+            options
+                .max_tokens
+                .is_none_or(|budget| (fixed_len + len) / 4 <= budget)
+        };
+
+        // Pinned files first and always, then important files, then the
+        // rest in their existing (most recent) order. Only pins among this
+        // export's files count, so other roots and unchanged files stay out.
+        let candidate_ids: std::collections::HashSet<i64> = files.iter().map(|f| f.id).collect();
+        let pinned: Vec<File> = crate::db::operations::list_pinned_files(conn)?
+            .into_iter()
+            .filter(|f| candidate_ids.contains(&f.id))
+            .collect();
+        let pinned_ids: std::collections::HashSet<i64> = pinned.iter().map(|f| f.id).collect();
+        let important = self.identify_important_files(files);
+        let mut seen = std::collections::HashSet::new();
+        let ordered = pinned
+            .iter()
+            .chain(important.iter())
+            .chain(files.iter())
+            .filter(|f| seen.insert(f.id));
+
+        // Not even the heading fits, so the section is left out unless a
+        // pinned file needs it
+        if pinned.is_empty() && !fits(contents.len()) {
+            selection.omitted = ordered.map(|f| f.path.clone()).collect();
+            return Ok((String::new(), selection));
+        }
+
+        for file in ordered {
+            check_cancelled(self.cancel.as_deref())?;
+
+            let within_limit = selection.included.len() < options.max_files_with_full_content
+                || pinned_ids.contains(&file.id);
+            let entry = if within_limit {
+                self.file_content_entry(conn, file, options, summaries_only)?
+            } else {
+                None
+            };
+
+            match entry {
+                Some(entry) if pinned_ids.contains(&file.id) || fits(contents.len() + entry.len()) => {
+                    contents.push_str(&entry);
+                    selection.included.push(file.path.clone());
+                }
+                _ => selection.omitted.push(file.path.clone()),
+            }
+        }
+
+        Ok((contents, selection))
+    }
+
+    /// The "File Contents" entry for `file`, or `None` if it has no text
+    /// (or summary) to show
+    fn file_content_entry(
+        &self,
+        conn: &rusqlite::Connection,
+        file: &File,
+        options: &ContextOptions,
+        summaries_only: bool,
+    ) -> Result<Option<String>> {
+        let content = match crate::db::operations::get_file_content(conn, file.id)? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        if summaries_only {
+            return Ok(crate::export::summary_or_generate(&content)
+                .map(|summary| format!("### `{}`\n\n{}\n\n", file.path, summary.trim_end())));
+        }
+
+        let text = match content.text_content {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return Ok(None),
+        };
+
+        let mut entry = format!("### `{}`\n\n", file.path);
+
+        match Self::fence_language(&file.file_type).filter(|_| options.use_code_fences) {
+            Some(language) => {
+                // Use a longer fence if the content itself contains one
+                let fence = "`".repeat(Self::longest_backtick_run(&text).max(2) + 1);
+                entry.push_str(&format!("{}{}\n", fence, language));
+                entry.push_str(text.trim_end());
+                entry.push_str(&format!("\n{}\n\n", fence));
+            }
+            None => {
+                entry.push_str(text.trim_end());
+                entry.push_str("\n\n");
+            }
+        }
+
+        Ok(Some(entry))
+    }
+
     /// Build current state section
     fn build_current_state(&self, files: &[File]) -> Result<String> {
         let conn = self.db.get_connection();
         let (total_files, indexed_files, total_size) = crate::db::operations::get_db_stats(conn)?;
 
-        let embedding_count = crate::db::operations::count_embeddings(conn)?;
+        let embedding_count = crate::db::embeddings::count_embeddings(conn)?;
         let coverage = if total_files > 0 {
             (indexed_files as f64 / total_files as f64 * 100.0) as i64
         } else {
@@ -288,7 +448,7 @@ impl ContextBuilder {
     fn calculate_stats(&self, files: &[File]) -> Result<ExportStats> {
         let conn = self.db.get_connection();
         let total_files = files.len();
-        let files_with_embeddings = crate::db::operations::count_embeddings(conn)? as usize;
+        let files_with_embeddings = crate::db::embeddings::count_embeddings(conn)? as usize;
         let total_size_bytes: i64 = files.iter().map(|f| f.size).sum();
 
         // Estimate chunk count (assuming ~500 tokens per chunk, ~4 chars per token)
@@ -305,6 +465,11 @@ impl ContextBuilder {
             total_size_bytes,
             files_with_embeddings,
             prompts_generated: 0, // Will be updated by bundler
+            // Filled in once the context is assembled
+            estimated_tokens: 0,
+            token_budget: None,
+            included_files: Vec::new(),
+            omitted_files: Vec::new(),
         })
     }
 
@@ -482,6 +647,32 @@ impl ContextBuilder {
         important
     }
 
+    /// Language tag for fencing code files (None for prose/document types)
+    fn fence_language(file_type: &str) -> Option<&'static str> {
+        let language = match file_type.to_lowercase().as_str() {
+            "rs" => "rust",
+            "js" => "javascript",
+            "ts" => "typescript",
+            "py" => "python",
+            "java" => "java",
+            "c" | "h" => "c",
+            "cpp" | "hpp" => "cpp",
+            "json" => "json",
+            "yaml" | "yml" => "yaml",
+            "toml" => "toml",
+            "xml" => "xml",
+            "html" => "html",
+            "css" => "css",
+            _ => return None,
+        };
+
+        Some(language)
+    }
+
+    fn longest_backtick_run(text: &str) -> usize {
+        text.split(|c| c != '`').map(|run| run.len()).max().unwrap_or(0)
+    }
+
     fn extract_rust_dependencies(&self, cargo_toml: &str) -> String {
         let mut deps = String::from("```toml\n");
 
@@ -512,3 +703,211 @@ impl ContextBuilder {
         deps
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_file, upsert_file_content};
+
+    fn setup_builder() -> ContextBuilder {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let main_id = insert_file(conn, "/proj/src/main.rs", "main.rs", "rs", 120,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, main_id,
+            Some("fn main() {\n    println!(\"hello from the full body\");\n}"),
+            Some("Program entry point")).unwrap();
+
+        let notes_id = insert_file(conn, "/proj/notes.txt", "notes.txt", "txt", 80,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, notes_id,
+            Some("Plain notes that only appear with full content enabled"),
+            Some("Project notes")).unwrap();
+
+        ContextBuilder::new(db)
+    }
+
+    #[test]
+    fn test_disabling_full_content_keeps_only_summaries() {
+        let builder = setup_builder();
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+        let (full, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        config.context.include_full_content = false;
+        let (summaries_only, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        assert!(summaries_only.len() < full.len());
+
+        assert!(full.contains("## File Contents"));
+        assert!(full.contains("```rust\nfn main()"));
+        assert!(full.contains("Plain notes that only appear"));
+
+        assert!(!summaries_only.contains("## File Contents"));
+        assert!(!summaries_only.contains("hello from the full body"));
+        assert!(summaries_only.contains("Program entry point"));
+    }
+
+    #[test]
+    fn test_cancelled_build_stops_at_file_contents() {
+        let builder = setup_builder().with_cancel_flag(Arc::new(AtomicBool::new(true)));
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+        let result = tokio_test::block_on(builder.build_context(&config));
+        assert!(matches!(result, Err(crate::error::CortexError::Cancelled { .. })));
+    }
+
+    #[test]
+    fn test_sections_and_fences_are_configurable() {
+        let builder = setup_builder();
+
+        let config = ExportConfig {
+            context: ContextOptions {
+                include_file_tree: false,
+                include_file_summaries: false,
+                include_full_content: true,
+                include_stats: false,
+                use_code_fences: false,
+                max_files_with_full_content: 1,
+                max_tokens: None,
+            },
+            ..ExportConfig::default()
+        };
+        let (context, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        assert!(!context.contains("## Project Structure"));
+        assert!(!context.contains("## Key Files"));
+        assert!(!context.contains("## Current State"));
+        assert!(!context.contains("```rust"));
+
+        // Only the most relevant file (main.rs) gets its body
+        assert!(context.contains("hello from the full body"));
+        assert!(!context.contains("Plain notes that only appear"));
+    }
+
+    #[test]
+    fn test_pinned_file_always_exported() {
+        let builder = setup_builder();
+        let conn = builder.db.get_connection();
+
+        let notes_id = crate::db::get_file_by_path(conn, "/proj/notes.txt").unwrap().unwrap().id;
+        crate::db::pin_file(conn, notes_id).unwrap();
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+
+        // The pinned notes are included even with no budget, and otherwise
+        // take the slot main.rs would get as the most relevant file
+        for budget in [0, 1] {
+            config.context.max_files_with_full_content = budget;
+            let (context, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+            assert!(context.contains("Plain notes that only appear"));
+            assert!(!context.contains("hello from the full body"));
+        }
+    }
+
+    #[test]
+    fn test_pinned_files_outside_the_export_stay_out() {
+        let builder = setup_builder();
+        let conn = builder.db.get_connection();
+
+        let notes_id = crate::db::get_file_by_path(conn, "/proj/notes.txt").unwrap().unwrap().id;
+        crate::db::pin_file(conn, notes_id).unwrap();
+        conn.execute(
+            "UPDATE files SET modified_at = '2020-01-01T00:00:00Z', last_indexed = '2020-01-01T00:00:00Z' WHERE id = ?1",
+            [notes_id],
+        )
+        .unwrap();
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+        config.changed_since = Some("2025-01-01T00:00:00Z".parse().unwrap());
+        let (context, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        assert!(!context.contains("Plain notes that only appear"));
+        assert!(!stats.included_files.iter().chain(&stats.omitted_files).any(|p| p == "/proj/notes.txt"));
+        assert!(context.contains("hello from the full body"));
+    }
+
+    #[test]
+    fn test_token_budget_report_accounts_for_every_candidate() {
+        let builder = setup_builder();
+        let conn = builder.db.get_connection();
+
+        for i in 0..3 {
+            let name = format!("chapter{}.md", i);
+            let body = format!("Chapter {} of the handbook\n\n{}", i, "long handbook prose ".repeat(200));
+            let id = insert_file(conn, &format!("/proj/docs/{}", name), &name, "md", body.len() as i64,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+            upsert_file_content(conn, id, Some(&body), None).unwrap();
+        }
+        let candidates = crate::db::get_file_count(conn).unwrap() as usize;
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+        let (context, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+        assert_eq!(stats.token_budget, None);
+        assert_eq!(stats.estimated_tokens, crate::export::estimate_tokens(&context));
+        assert_eq!(stats.included_files.len(), candidates);
+        assert!(stats.omitted_files.is_empty());
+
+        // Room for everything but about one chapter
+        let budget = stats.estimated_tokens - 800;
+        config.context.max_tokens = Some(budget);
+        let (context, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        assert_eq!(stats.token_budget, Some(budget));
+        assert_eq!(stats.estimated_tokens, crate::export::estimate_tokens(&context));
+        assert!(stats.estimated_tokens <= budget);
+        assert!(!stats.included_files.is_empty() && !stats.omitted_files.is_empty());
+        assert_eq!(stats.included_files.len() + stats.omitted_files.len(), candidates);
+        assert!(stats.omitted_files.iter().all(|path| !context.contains(&format!("### `{}`", path))));
+
+        // A budget too small for any file still reports every candidate
+        config.context.max_tokens = Some(0);
+        let (_, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+        assert!(stats.included_files.is_empty());
+        assert_eq!(stats.omitted_files.len(), candidates);
+
+        // ...except pinned files, which the budget never drops
+        let notes_id = crate::db::get_file_by_path(conn, "/proj/notes.txt").unwrap().unwrap().id;
+        crate::db::pin_file(conn, notes_id).unwrap();
+        let (context, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+        assert_eq!(stats.included_files, vec!["/proj/notes.txt".to_string()]);
+        assert_eq!(stats.omitted_files.len(), candidates - 1);
+        assert!(context.contains("Plain notes that only appear"));
+    }
+
+    #[test]
+    fn test_summaries_only_export_is_smaller() {
+        let builder = setup_builder();
+        let conn = builder.db.get_connection();
+
+        // A large body with no stored summary falls back to a generated one
+        let body = format!("Design overview for the parser\n\n{}", "detailed parser internals ".repeat(400));
+        let design_id = insert_file(conn, "/proj/DESIGN.md", "DESIGN.md", "md", body.len() as i64,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, design_id, Some(&body), None).unwrap();
+
+        let mut config = ExportConfig::default();
+        config.context.include_full_content = true;
+        let (full, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        config.summaries_only = true;
+        let (summaries, _) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+
+        assert!(summaries.len() * 4 < full.len());
+
+        assert!(summaries.contains("Program entry point"));
+        assert!(summaries.contains("Project notes"));
+        assert!(summaries.contains("Design overview for the parser"));
+
+        assert!(!summaries.contains("hello from the full body"));
+        assert!(!summaries.contains("Plain notes that only appear"));
+        assert!(!summaries.contains("detailed parser internals"));
+    }
+}
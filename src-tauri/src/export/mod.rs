@@ -6,16 +6,20 @@ pub mod path_validator;
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use crate::db::File;
+use crate::error::{CortexError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // Re-exports for convenience
 pub use context_builder::ContextBuilder;
-pub use prompt_builder::PromptBuilder;
+pub use prompt_builder::{template_variables, PromptBuilder};
 pub use bundler::BundleBuilder;
 pub use rake_exporter::RakeExporter;
 pub use path_validator::PathValidator;
 
 /// Configuration for exporting context to VS Code Claude
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ExportConfig {
     /// Optional collection ID to export (None = entire project)
     pub collection_id: Option<String>,
@@ -34,6 +38,18 @@ pub struct ExportConfig {
 
     /// Additional context to include
     pub custom_context: Option<String>,
+
+    /// Which CONTEXT.md sections to generate and how
+    pub context: ContextOptions,
+
+    /// Use file summaries in place of full text
+    pub summaries_only: bool,
+
+    /// Line endings of the written files
+    pub line_ending: LineEnding,
+
+    /// Only export files modified or re-indexed after this time
+    pub changed_since: Option<DateTime<Utc>>,
 }
 
 impl Default for ExportConfig {
@@ -45,6 +61,90 @@ impl Default for ExportConfig {
             include_prompts: true,
             project_name: None,
             custom_context: None,
+            context: ContextOptions::default(),
+            summaries_only: false,
+            line_ending: LineEnding::default(),
+            changed_since: None,
+        }
+    }
+}
+
+/// Line endings used in exported files
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+
+    /// `\r\n`, which some Windows tools need to show line breaks
+    CrLf,
+
+    /// `\r\n` on Windows, `\n` elsewhere
+    #[default]
+    Platform,
+}
+
+impl LineEnding {
+    fn resolve(self) -> Self {
+        match self {
+            Self::Platform if cfg!(windows) => Self::CrLf,
+            Self::Platform => Self::Lf,
+            other => other,
+        }
+    }
+
+    /// `text` with every line ending (`\n` or `\r\n`) converted to this one
+    pub fn apply(self, text: &str) -> String {
+        let normalized = text.replace("\r\n", "\n");
+        match self.resolve() {
+            Self::CrLf => normalized.replace('\n', "\r\n"),
+            _ => normalized,
+        }
+    }
+}
+
+/// Controls the layout of the generated CONTEXT.md
+///
+/// Lets users trade detail for token budget depending on the target LLM.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextOptions {
+    /// Include the "Project Structure" directory tree
+    pub include_file_tree: bool,
+
+    /// Include the "Key Files" section with per-file summaries
+    pub include_file_summaries: bool,
+
+    /// Include a "File Contents" section with full file bodies
+    pub include_full_content: bool,
+
+    /// Include the "Current State" statistics section
+    pub include_stats: bool,
+
+    /// Wrap code files in language-tagged code fences
+    pub use_code_fences: bool,
+
+    /// Maximum number of files (most relevant first) given full bodies
+    pub max_files_with_full_content: usize,
+
+    /// Estimated token budget for the whole CONTEXT.md
+    ///
+    /// Only the "File Contents" section shrinks to fit: file bodies that
+    /// would push the total past the budget are left out. Pinned files
+    /// count toward it but are always kept.
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            include_file_tree: true,
+            include_file_summaries: true,
+            include_full_content: false,
+            include_stats: true,
+            use_code_fences: true,
+            max_files_with_full_content: 20,
+            max_tokens: None,
         }
     }
 }
@@ -85,6 +185,19 @@ pub struct ExportStats {
 
     /// Number of prompts generated
     pub prompts_generated: usize,
+
+    /// Estimated tokens in CONTEXT.md (see [`estimate_tokens`])
+    pub estimated_tokens: usize,
+
+    /// The `max_tokens` budget the export was built under, if any
+    pub token_budget: Option<usize>,
+
+    /// Paths of files whose body (or summary) went into "File Contents"
+    pub included_files: Vec<String>,
+
+    /// Paths of files considered for "File Contents" but left out by the
+    /// file limit, the token budget or missing content
+    pub omitted_files: Vec<String>,
 }
 
 /// Export statistics summary for UI display
@@ -123,6 +236,43 @@ pub struct RakeExportConfig {
 
     /// Export mode: full, incremental, or collection
     pub export_mode: RakeExportMode,
+
+    /// Export one summary chunk per file instead of the full text
+    #[serde(default)]
+    pub summaries_only: bool,
+
+    /// How file text is split into chunks
+    #[serde(default)]
+    pub chunk_strategy: ChunkStrategy,
+
+    /// Line endings of the written JSON file
+    #[serde(default)]
+    pub line_ending: LineEnding,
+
+    /// Largest file to write, in bytes; a bigger export is split into
+    /// numbered parts with a manifest (None = no limit)
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Only export files modified or re-indexed after this time
+    #[serde(default)]
+    pub changed_since: Option<DateTime<Utc>>,
+}
+
+/// How file text is split into Rake chunks
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// A fixed number of words per chunk, ignoring text structure
+    #[default]
+    FixedWords,
+
+    /// Whole sentences, grouped until the chunk reaches the target size
+    Sentences,
+
+    /// One chunk per paragraph (separated by blank lines); paragraphs over
+    /// the target size are split by words
+    Paragraphs,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -138,6 +288,26 @@ pub enum RakeExportMode {
     Collection,
 }
 
+impl RakeExportMode {
+    /// Parse a mode name (`full`, `incremental` or `collection`), ignoring case
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.to_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "incremental" => Some(Self::Incremental),
+            "collection" => Some(Self::Collection),
+            _ => None,
+        }
+    }
+}
+
+/// One problem found by validating a Rake export config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RakeConfigProblem {
+    /// The config field at fault, e.g. `tenant_id`
+    pub field: String,
+    pub message: String,
+}
+
 /// Rake export package (compatible with Rake V1 format)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RakeExportPackage {
@@ -160,7 +330,7 @@ pub struct RakeExportPackage {
     pub chunks: Vec<RakeChunk>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RakeExportMetadata {
     /// Cortex version that generated the export
     pub cortex_version: String,
@@ -187,6 +357,37 @@ pub struct RakeExportMetadata {
     pub export_mode: String,
 }
 
+/// Progress of a Rake export, reported once per processed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RakeExportProgress {
+    pub files_processed: usize,
+    pub total_files: usize,
+}
+
+/// Index of a Rake export split across several files, written beside
+/// them
+///
+/// Each part is a complete [`RakeExportPackage`] holding a run of the
+/// export's chunks, so parts can be imported independently.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RakeExportManifest {
+    pub version: String,
+    pub tenant_id: String,
+    pub export_timestamp: DateTime<Utc>,
+    pub total_files: usize,
+    pub total_chunks: usize,
+    /// In chunk order
+    pub parts: Vec<RakeExportPart>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RakeExportPart {
+    /// Relative to the manifest's directory
+    pub file_name: String,
+    pub chunks: usize,
+    pub bytes: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RakeChunk {
     /// Unique chunk ID
@@ -310,7 +511,7 @@ pub enum PromptCategory {
     Integration,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PromptVariable {
     /// Variable key (e.g., "feature_name")
     pub key: String,
@@ -328,6 +529,46 @@ pub struct PromptVariable {
     pub default: Option<String>,
 }
 
+/// `Err(Cancelled)` once `cancel` has been set, e.g. by `cancel_export`
+///
+/// Exports call this between files so a cancelled export stops promptly.
+pub(crate) fn check_cancelled(cancel: Option<&AtomicBool>) -> Result<()> {
+    match cancel {
+        Some(flag) if flag.load(Ordering::SeqCst) => Err(CortexError::Cancelled {
+            operation: "Export".to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// `files` modified or re-indexed after `since`; all of them if `None`
+///
+/// Files whose timestamps don't parse are kept, so a filter never silently
+/// drops them.
+pub(crate) fn changed_since(files: Vec<File>, since: Option<DateTime<Utc>>) -> Vec<File> {
+    let Some(since) = since else {
+        return files;
+    };
+
+    let after = |timestamp: &str| {
+        DateTime::parse_from_rfc3339(timestamp).map(|time| time > since)
+    };
+
+    files
+        .into_iter()
+        .filter(|file| match (after(&file.modified_at), after(&file.last_indexed)) {
+            (Ok(modified), Ok(indexed)) => modified || indexed,
+            (Ok(changed), Err(_)) | (Err(_), Ok(changed)) => changed,
+            (Err(_), Err(_)) => true,
+        })
+        .collect()
+}
+
+/// Rough token count of `text`, at about 4 bytes per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
 /// Helper function to format file size as human-readable string
 pub fn format_file_size(bytes: i64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
@@ -342,6 +583,22 @@ pub fn format_file_size(bytes: i64) -> String {
     format!("{:.2} {}", size, UNITS[unit_index])
 }
 
+/// Stored summary for a file, or a short one generated from its text when
+/// none was saved at index time
+pub fn summary_or_generate(content: &crate::db::FileContent) -> Option<String> {
+    content
+        .summary
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| {
+            content
+                .text_content
+                .as_deref()
+                .and_then(crate::indexer::extractors::ExtractedContent::generate_summary)
+        })
+        .filter(|s| !s.trim().is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,5 +619,61 @@ mod tests {
         assert_eq!(config.include_embeddings, false);
         assert_eq!(config.include_prompts, true);
         assert!(config.collection_id.is_none());
+        assert!(config.context.include_file_tree);
+        assert!(!config.context.include_full_content);
+    }
+
+    #[test]
+    fn test_changed_since_filters_both_export_paths() {
+        use crate::db::{insert_file, upsert_file_content, Database};
+
+        // Each exporter takes its database by value, so each gets a fresh
+        // one
+        let setup = || {
+            let db = Database::open_in_memory().unwrap();
+            let conn = db.get_connection();
+            for (name, modified_at) in [("old.rs", "2026-01-05T00:00:00Z"), ("new.rs", "2026-03-10T00:00:00Z")] {
+                let id = insert_file(conn, &format!("/proj/{}", name), name, "rs", 10,
+                    modified_at, modified_at, None, "/proj").unwrap();
+                upsert_file_content(conn, id, Some(&format!("// contents of {}", name)), None).unwrap();
+                conn.execute("UPDATE files SET last_indexed = ?1 WHERE id = ?2",
+                    rusqlite::params![modified_at, id]).unwrap();
+            }
+            db
+        };
+
+        let since: DateTime<Utc> = "2026-03-01T00:00:00Z".parse().unwrap();
+
+        let config = ExportConfig { changed_since: Some(since), ..ExportConfig::default() };
+        let builder = ContextBuilder::new(setup());
+        let (_, stats) = tokio_test::block_on(builder.build_context(&config)).unwrap();
+        assert_eq!(stats.total_files, 1);
+        let (_, stats) = tokio_test::block_on(builder.build_context(&ExportConfig::default())).unwrap();
+        assert_eq!(stats.total_files, 2);
+
+        let rake_config = RakeExportConfig {
+            collection_id: None,
+            tenant_id: "tenant".to_string(),
+            output_path: String::new(),
+            include_embeddings: false,
+            export_mode: RakeExportMode::Incremental,
+            summaries_only: false,
+            chunk_strategy: ChunkStrategy::default(),
+            line_ending: LineEnding::default(),
+            max_output_bytes: None,
+            changed_since: Some(since),
+        };
+        let db = setup();
+        // Re-indexing counts as a change even if the file's own time is older
+        db.get_connection()
+            .execute("UPDATE files SET last_indexed = '2026-03-02T00:00:00Z' WHERE filename = 'old.rs'", [])
+            .unwrap();
+        let exporter = RakeExporter::new(setup());
+        let package = tokio_test::block_on(exporter.export(&rake_config, None)).unwrap();
+        let paths: Vec<&str> = package.chunks.iter().map(|c| c.metadata.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["/proj/new.rs"]);
+
+        let package = tokio_test::block_on(RakeExporter::new(db).export(&rake_config, None)).unwrap();
+        assert_eq!(package.metadata.total_files, 2);
     }
 }
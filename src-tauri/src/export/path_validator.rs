@@ -1,4 +1,5 @@
 use crate::error::{CortexError, Result};
+use crate::paths::{strip_verbatim_prefix, to_long_path};
 use std::path::{Component, Path, PathBuf};
 
 /// Validates export output paths to prevent path traversal attacks
@@ -12,6 +13,10 @@ impl PathValidator {
     /// 2. For absolute paths, ensures they're within safe directories
     /// 3. Canonicalizes paths to resolve symlinks and relative components
     ///
+    /// On Windows, verbatim (`\\?\`) and UNC prefixes are normalized before
+    /// comparing against the home/temp/working directories, and paths longer
+    /// than MAX_PATH are returned in extended-length form so they can be written.
+    ///
     /// # Arguments
     /// * `path` - The user-provided path to validate
     ///
@@ -77,8 +82,11 @@ impl PathValidator {
                         message: format!("Failed to canonicalize path: {}", e),
                     })?;
 
+                // canonicalize returns verbatim paths on Windows
+                let canonical = strip_verbatim_prefix(&canonical);
+
                 // Verify the canonical path doesn't escape the base directory
-                if !is_absolute && !canonical.starts_with(&base_dir) {
+                if !is_absolute && !canonical.starts_with(strip_verbatim_prefix(&base_dir)) {
                     return Err(CortexError::InvalidPath {
                         path: path.to_string(),
                         reason: "Path resolves outside of working directory".to_string(),
@@ -91,13 +99,13 @@ impl PathValidator {
                     reason: "Path must include a filename".to_string(),
                 })?;
 
-                return Ok(canonical.join(filename));
+                return Ok(to_long_path(&canonical.join(filename)));
             }
         }
 
         // Parent doesn't exist yet - that's OK for new exports
         // Just return the full path (already validated)
-        Ok(full_path)
+        Ok(to_long_path(&full_path))
     }
 
     /// Validate that an absolute path is within safe directories
@@ -110,8 +118,11 @@ impl PathValidator {
         // Get system temp directory
         let temp_dir = std::env::temp_dir();
 
-        // Check if path is within home directory or temp directory
-        let is_safe = path.starts_with(&home_dir) || path.starts_with(&temp_dir);
+        // Check if path is within home directory or temp directory,
+        // comparing ordinary forms so `\\?\C:\...` matches `C:\...`
+        let normalized = strip_verbatim_prefix(path);
+        let is_safe = normalized.starts_with(strip_verbatim_prefix(&home_dir))
+            || normalized.starts_with(strip_verbatim_prefix(&temp_dir));
 
         if !is_safe {
             return Err(CortexError::InvalidPath {
@@ -190,4 +201,49 @@ mod tests {
             assert!(result.is_ok());
         }
     }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_accept_long_path_in_temp() {
+        // Deeply nested directory well past MAX_PATH
+        let mut deep = std::env::temp_dir().join("cortex-long-path-test");
+        while deep.as_os_str().len() < 300 {
+            deep = deep.join("nested-directory-segment");
+        }
+        std::fs::create_dir_all(crate::paths::to_extended_length(&deep)).unwrap();
+
+        let target = deep.join("export");
+        let validated = PathValidator::validate_export_path(target.to_str().unwrap()).unwrap();
+
+        assert!(validated.to_string_lossy().starts_with(r"\\?\"));
+        std::fs::create_dir_all(&validated).unwrap();
+        std::fs::write(validated.join("CONTEXT.md"), "ok").unwrap();
+
+        let _ = std::fs::remove_dir_all(crate::paths::to_extended_length(
+            &std::env::temp_dir().join("cortex-long-path-test"),
+        ));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_accept_verbatim_home_path() {
+        if let Some(home) = dirs::home_dir() {
+            let verbatim = crate::paths::to_extended_length(&home.join("test-export"));
+            let result = PathValidator::validate_export_path(verbatim.to_str().unwrap());
+            assert!(result.is_ok());
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_reject_unc_path_outside_safe_dirs() {
+        for path in [r"\\server\share\export", r"\\?\UNC\server\share\export"] {
+            match PathValidator::validate_export_path(path).unwrap_err() {
+                CortexError::InvalidPath { reason, .. } => {
+                    assert!(reason.contains("home directory"));
+                }
+                other => panic!("Expected InvalidPath error, got {:?}", other),
+            }
+        }
+    }
 }
@@ -1,23 +1,104 @@
-use crate::db::{Database, File};
-use crate::error::Result;
-use crate::export::ExportConfig;
-use chrono::Utc;
+use crate::db::{get_prompt_template, Database, File};
+use crate::error::{CortexError, Result};
+use crate::export::{changed_since, ExportConfig, PromptVariable};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::path::Path;
 
+fn variable(key: &str, label: &str, placeholder: &str, default: Option<&str>) -> PromptVariable {
+    PromptVariable {
+        key: key.to_string(),
+        label: label.to_string(),
+        placeholder: placeholder.to_string(),
+        required: default.is_none(),
+        default: default.map(str::to_string),
+    }
+}
+
+/// Variables a prompt template takes, or `None` for an unknown template
+///
+/// Each appears in the template text as its upper-cased key in braces,
+/// e.g. `feature_name` as `{FEATURE_NAME}`.
+pub fn template_variables(template_id: &str) -> Option<Vec<PromptVariable>> {
+    let module_path = || variable("module_path", "Module path", "src/export/bundler.rs", None);
+
+    let variables = match template_id {
+        "add_feature" => vec![
+            variable("feature_name", "Feature name", "Dark mode", None),
+            variable("feature_description", "Feature description", "What the feature should do", None),
+        ],
+        "fix_bug" => vec![
+            variable("bug_description", "Bug description", "What goes wrong", None),
+            variable("steps_to_reproduce", "Steps to reproduce", "1. Open...", Some("Not yet known.")),
+        ],
+        "refactor" => vec![
+            module_path(),
+            variable(
+                "refactoring_goals",
+                "Refactoring goals",
+                "What should improve",
+                Some("- Improve code readability\n- Reduce complexity\n- Follow best practices"),
+            ),
+        ],
+        "add_tests" | "documentation" => vec![module_path()],
+        _ => return None,
+    };
+
+    Some(variables)
+}
+
+/// Replace each `{KEY}` token in `template` with its value, in one pass so
+/// values are never substituted into themselves
+fn substitute(template: &str, values: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let token = &rest[start + 1..];
+        match token.find('}').and_then(|end| values.get(&token[..end]).map(|value| (end, value))) {
+            Some((end, value)) => {
+                rendered.push_str(value);
+                rest = &token[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = token;
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
 /// Builds STARTER_PROMPT.md and feature-specific prompts
 pub struct PromptBuilder {
     db: Database,
+    changed_since: Option<DateTime<Utc>>,
 }
 
 impl PromptBuilder {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self { db, changed_since: None }
+    }
+
+    /// Describe only files modified or re-indexed after `since`, like the
+    /// rest of an incremental export; the starter prompt follows its
+    /// config's `changed_since` instead
+    pub fn with_changed_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.changed_since = since;
+        self
+    }
+
+    fn list_files(&self, since: Option<DateTime<Utc>>) -> Result<Vec<File>> {
+        let files = crate::db::operations::list_files(self.db.get_connection(), 10000, 0)?;
+        Ok(changed_since(files, since))
     }
 
     /// Build the STARTER_PROMPT.md content
     pub async fn build_starter_prompt(&self, config: &ExportConfig) -> Result<String> {
-        let conn = self.db.get_connection();
-        let files = crate::db::operations::list_files(conn, 10000, 0)?;
+        let files = self.list_files(config.changed_since)?;
 
         let project_name = config.project_name.clone()
             .unwrap_or_else(|| self.detect_project_name(&files));
@@ -128,8 +209,7 @@ impl PromptBuilder {
         feature_name: &str,
         feature_description: &str,
     ) -> Result<String> {
-        let conn = self.db.get_connection();
-        let files = crate::db::operations::list_files(conn, 10000, 0)?;
+        let files = self.list_files(self.changed_since)?;
 
         let mut prompt = String::from(&format!(
             "# FEATURE IMPLEMENTATION: {}\n\n\
@@ -185,8 +265,7 @@ impl PromptBuilder {
         bug_description: &str,
         steps_to_reproduce: &str,
     ) -> Result<String> {
-        let conn = self.db.get_connection();
-        let files = crate::db::operations::list_files(conn, 10000, 0)?;
+        let files = self.list_files(self.changed_since)?;
 
         let mut prompt = String::from(
             "# BUG FIX IMPLEMENTATION\n\n\
@@ -279,6 +358,123 @@ impl PromptBuilder {
         Ok(prompt)
     }
 
+    /// Build ADD_TESTS.md prompt template
+    pub async fn build_test_prompt(&self, module_path: &str) -> Result<String> {
+        Ok(format!(
+            "# ADD TESTS\n\n\
+             **Generated by Cortex**\n\n\
+             ---\n\n\
+             ## Testing Task\n\n\
+             Module/Feature to test: `{}`\n\n\
+             ## Test Requirements\n\n\
+             Please create comprehensive tests following these guidelines:\n\n\
+             1. **Unit Tests**: Test individual functions in isolation\n\
+             2. **Integration Tests**: Test interactions between components\n\
+             3. **Edge Cases**: Test boundary conditions and error cases\n\
+             4. **Coverage**: Aim for high code coverage\n\n\
+             ## Test Structure\n\n\
+             Based on CONTEXT.md, follow the existing test patterns:\n\
+             - Test file location\n\
+             - Test naming conventions\n\
+             - Assertion style\n\
+             - Mock/fixture usage\n\n\
+             ## Expected Deliverables\n\n\
+             - [ ] Unit tests for all public functions\n\
+             - [ ] Integration tests (if applicable)\n\
+             - [ ] Edge case tests\n\
+             - [ ] All tests passing\n\
+             - [ ] Test coverage report\n\n\
+             ---\n\n",
+            module_path
+        ))
+    }
+
+    /// Build DOCUMENTATION.md prompt template
+    pub async fn build_documentation_prompt(&self, module_path: &str) -> Result<String> {
+        Ok(format!(
+            "# DOCUMENTATION UPDATE\n\n\
+             **Generated by Cortex**\n\n\
+             ---\n\n\
+             ## Documentation Task\n\n\
+             Module/Feature to document: `{}`\n\n\
+             ## Documentation Requirements\n\n\
+             Please create or update documentation including:\n\n\
+             1. **Code Comments**: Inline documentation for complex logic\n\
+             2. **Doc Comments**: Function/class documentation\n\
+             3. **README**: User-facing documentation\n\
+             4. **Architecture Docs**: System design documentation\n\n\
+             ## Documentation Style\n\n\
+             Follow existing documentation patterns from CONTEXT.md:\n\
+             - Markdown format\n\
+             - Code examples where helpful\n\
+             - Clear, concise language\n\
+             - Diagrams for complex concepts (ASCII or Mermaid)\n\n\
+             ## Expected Deliverables\n\n\
+             - [ ] Updated code comments\n\
+             - [ ] Doc comments for public APIs\n\
+             - [ ] README updates (if applicable)\n\
+             - [ ] Architecture documentation (if needed)\n\
+             - [ ] Usage examples\n\n\
+             ---\n\n",
+            module_path
+        ))
+    }
+
+    /// Render a built-in or user prompt template with `variables` filled in
+    ///
+    /// Blank or missing optional variables take their defaults, or are left
+    /// empty without one. Fails with `InvalidQuery` for an unknown template
+    /// or a missing required variable.
+    pub async fn render_template(&self, template_id: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let token = |key: &str| format!("{{{}}}", key.to_uppercase());
+
+        let (definitions, template) = match template_variables(template_id) {
+            Some(definitions) => {
+                let template = match template_id {
+                    "add_feature" => {
+                        self.build_feature_prompt(&token("feature_name"), &token("feature_description")).await?
+                    }
+                    "fix_bug" => {
+                        self.build_bugfix_prompt(&token("bug_description"), &token("steps_to_reproduce")).await?
+                    }
+                    "refactor" => {
+                        self.build_refactor_prompt(&token("module_path"), &token("refactoring_goals")).await?
+                    }
+                    "add_tests" => self.build_test_prompt(&token("module_path")).await?,
+                    _ => self.build_documentation_prompt(&token("module_path")).await?,
+                };
+                (definitions, template)
+            }
+            None => match get_prompt_template(self.db.get_connection(), template_id)? {
+                Some(custom) => (custom.variables, custom.body),
+                None => {
+                    return Err(CortexError::InvalidQuery {
+                        query: template_id.to_string(),
+                        reason: "Unknown prompt template".to_string(),
+                    });
+                }
+            },
+        };
+
+        let mut values = HashMap::new();
+        for definition in &definitions {
+            let provided = variables.get(&definition.key).filter(|value| !value.trim().is_empty());
+            let value = match provided.or(definition.default.as_ref()) {
+                Some(value) => value.clone(),
+                None if definition.required => {
+                    return Err(CortexError::InvalidQuery {
+                        query: definition.key.clone(),
+                        reason: format!("Missing required variable: {}", definition.label),
+                    });
+                }
+                None => String::new(),
+            };
+            values.insert(definition.key.to_uppercase(), value);
+        }
+
+        Ok(substitute(&template, &values))
+    }
+
     // Helper methods
 
     fn detect_project_name(&self, files: &[File]) -> String {
@@ -480,3 +676,70 @@ impl PromptBuilder {
         Ok(reference)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template_id: &str, variables: &[(&str, &str)]) -> Result<String> {
+        let builder = PromptBuilder::new(Database::open_in_memory().unwrap());
+        let variables = variables.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        tokio_test::block_on(builder.render_template(template_id, &variables))
+    }
+
+    #[test]
+    fn test_render_feature_template() {
+        let rendered = render("add_feature", &[
+            ("feature_name", "Dark Mode"),
+            ("feature_description", "Follow the system theme, {FEATURE_NAME} and all"),
+        ])
+        .unwrap();
+
+        assert!(rendered.starts_with("# FEATURE IMPLEMENTATION: Dark Mode\n"));
+        // Values are inserted as-is, placeholders in them included
+        assert!(rendered.contains("## Feature Request\n\nFollow the system theme, {FEATURE_NAME} and all\n"));
+
+        match render("add_feature", &[("feature_name", "Dark Mode"), ("feature_description", "  ")]) {
+            Err(CortexError::InvalidQuery { query, .. }) => assert_eq!(query, "feature_description"),
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+        assert!(render("release_notes", &[]).is_err());
+
+        // Optional variables fall back to their defaults
+        let rendered = render("fix_bug", &[("bug_description", "Search hangs")]).unwrap();
+        assert!(rendered.contains("## Steps to Reproduce\n\nNot yet known.\n"));
+        assert!(!rendered.contains('{'));
+    }
+
+    #[test]
+    fn test_prompts_describe_only_changed_files() {
+        use crate::db::insert_file;
+
+        let db = Database::open_in_memory().unwrap();
+        for (path, name, file_type, modified_at) in [
+            ("/proj/src/main.rs", "main.rs", "rs", "2026-01-05T00:00:00Z"),
+            ("/proj/web/index.ts", "index.ts", "ts", "2026-03-10T00:00:00Z"),
+        ] {
+            let id = insert_file(db.get_connection(), path, name, file_type, 10,
+                modified_at, modified_at, None, "/proj").unwrap();
+            db.get_connection()
+                .execute("UPDATE files SET last_indexed = ?1 WHERE id = ?2", rusqlite::params![modified_at, id])
+                .unwrap();
+        }
+
+        let since: DateTime<Utc> = "2026-03-01T00:00:00Z".parse().unwrap();
+        let config = ExportConfig { changed_since: Some(since), ..ExportConfig::default() };
+        let builder = PromptBuilder::new(db).with_changed_since(Some(since));
+
+        let starter = tokio_test::block_on(builder.build_starter_prompt(&config)).unwrap();
+        assert!(starter.contains("/proj/web/index.ts"));
+        assert!(!starter.contains("/proj/src/main.rs"));
+
+        let feature = tokio_test::block_on(builder.build_feature_prompt("Dark Mode", "")).unwrap();
+        assert!(feature.contains("**TypeScript/Svelte code must:**"));
+        assert!(!feature.contains("**Rust code must:**"));
+
+        let starter = tokio_test::block_on(builder.build_starter_prompt(&ExportConfig::default())).unwrap();
+        assert!(starter.contains("/proj/src/main.rs"));
+    }
+}
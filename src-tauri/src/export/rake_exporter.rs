@@ -1,33 +1,63 @@
 use crate::db::{Database, File};
 use crate::error::{CortexError, Result};
 use crate::export::{
-    PathValidator, RakeChunk, RakeChunkMetadata, RakeExportConfig, RakeExportMetadata, RakeExportPackage,
+    changed_since, check_cancelled, ChunkStrategy, LineEnding, PathValidator, RakeChunk, RakeChunkMetadata, RakeExportConfig,
+    RakeExportManifest, RakeExportMetadata, RakeExportPackage, RakeExportPart, RakeExportProgress,
 };
+use crate::text::split_sentences;
 use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 /// Exports indexed content in Rake-compatible format
 pub struct RakeExporter {
     db: Database,
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl RakeExporter {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self { db, cancel: None }
+    }
+
+    /// Stop with `CortexError::Cancelled` once `cancel` is set, checked
+    /// between files
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
     }
 
     /// Export to Rake-compatible JSON format
-    pub async fn export(&self, config: &RakeExportConfig) -> Result<RakeExportPackage> {
+    ///
+    /// `progress`, if given, is called after each file is converted.
+    pub async fn export(
+        &self,
+        config: &RakeExportConfig,
+        progress: Option<&(dyn Fn(RakeExportProgress) + Send + Sync)>,
+    ) -> Result<RakeExportPackage> {
         let _conn = self.db.get_connection();
 
         // Get files to export
         let files = self.get_files_for_export(config)?;
+        let total_files = files.len();
 
         // Convert files to chunks
         let mut chunks = Vec::new();
-        for file in &files {
+        for (index, file) in files.iter().enumerate() {
+            check_cancelled(self.cancel.as_deref())?;
+
             let file_chunks = self.convert_file_to_chunks(file, config).await?;
             chunks.extend(file_chunks);
+
+            if let Some(progress) = progress {
+                progress(RakeExportProgress {
+                    files_processed: index + 1,
+                    total_files,
+                });
+            }
         }
 
         // Build metadata
@@ -56,35 +86,47 @@ impl RakeExporter {
         })
     }
 
-    /// Write export package to JSON file
-    pub async fn export_to_file(&self, config: &RakeExportConfig) -> Result<String> {
+    /// Write export package to JSON file, reporting progress as in [`export`](Self::export)
+    ///
+    /// Returns the paths written: the output file, or with
+    /// `max_output_bytes` exceeded, its numbered parts followed by their
+    /// manifest (see [`split_package`]). Files are written to temporary
+    /// names beside the output and renamed into place, so a cancelled or
+    /// failed export leaves no partial output.
+    pub async fn export_to_file(
+        &self,
+        config: &RakeExportConfig,
+        progress: Option<&(dyn Fn(RakeExportProgress) + Send + Sync)>,
+    ) -> Result<Vec<String>> {
         // Validate and sanitize output path (security)
         let validated_path = PathValidator::validate_export_path(&config.output_path)?;
 
-        let package = self.export(config).await?;
+        let package = self.export(config, progress).await?;
+        let json = to_json(&package, config.line_ending)?;
 
-        // Serialize to JSON
-        let json = serde_json::to_string_pretty(&package).map_err(|e| CortexError::Internal {
-            message: format!("Failed to serialize export package: {}", e),
-        })?;
+        let files = match config.max_output_bytes {
+            Some(limit) if json.len() as u64 > limit => {
+                split_package(package, limit, &validated_path, config.line_ending)?
+            }
+            _ => vec![(validated_path, json)],
+        };
 
-        // Write to file (using validated path)
-        fs::write(&validated_path, json).map_err(|e| CortexError::Internal {
-            message: format!("Failed to write export file: {}", e),
-        })?;
+        write_all_or_nothing(&files)?;
 
-        Ok(validated_path.to_string_lossy().to_string())
+        Ok(files
+            .into_iter()
+            .map(|(path, _)| path.to_string_lossy().to_string())
+            .collect())
     }
 
     /// Get files to export based on configuration
-    fn get_files_for_export(&self, _config: &RakeExportConfig) -> Result<Vec<File>> {
+    fn get_files_for_export(&self, config: &RakeExportConfig) -> Result<Vec<File>> {
         let conn = self.db.get_connection();
 
         // For now, get all files (collection support will be added in Phase 3)
-        // In incremental mode, we would filter by last sync time
         let files = crate::db::operations::list_files(conn, 10000, 0)?;
 
-        Ok(files)
+        Ok(changed_since(files, config.changed_since))
     }
 
     /// Convert a single file to Rake chunks
@@ -93,25 +135,7 @@ impl RakeExporter {
         file: &File,
         config: &RakeExportConfig,
     ) -> Result<Vec<RakeChunk>> {
-        let conn = self.db.get_connection();
-
-        // Get file content
-        let content = crate::db::operations::get_file_content(conn, file.id)?;
-
-        let text_content = match content {
-            Some(fc) => fc.text_content,
-            None => None,
-        };
-
-        if text_content.is_none() {
-            // Skip files without content
-            return Ok(Vec::new());
-        }
-
-        let text = text_content.unwrap();
-
-        // Chunk the text (Rake uses ~500 token chunks)
-        let chunks = self.chunk_text(&text, 500);
+        let chunks = self.file_chunk_texts(file, config)?;
 
         let mut rake_chunks = Vec::new();
 
@@ -152,29 +176,44 @@ impl RakeExporter {
         Ok(rake_chunks)
     }
 
-    /// Chunk text into segments (simple word-based chunking)
-    fn chunk_text(&self, text: &str, target_tokens: usize) -> Vec<String> {
-        let words: Vec<&str> = text.split_whitespace().collect();
-        let mut chunks = Vec::new();
-
-        // Approximate: 1 token ≈ 0.75 words
-        let words_per_chunk = (target_tokens as f32 * 0.75) as usize;
+    /// The texts of a file's chunks under `config`; none for a file without
+    /// content
+    fn file_chunk_texts(&self, file: &File, config: &RakeExportConfig) -> Result<Vec<String>> {
+        let conn = self.db.get_connection();
 
-        let mut current_chunk = Vec::new();
+        // Get file content
+        let content = crate::db::operations::get_file_content(conn, file.id)?;
 
-        for word in words {
-            current_chunk.push(word);
+        let content = match content {
+            Some(fc) => fc,
+            // Skip files without content
+            None => return Ok(Vec::new()),
+        };
 
-            if current_chunk.len() >= words_per_chunk {
-                chunks.push(current_chunk.join(" "));
-                current_chunk.clear();
-            }
+        if config.summaries_only {
+            // One chunk per file holding just its summary
+            return Ok(crate::export::summary_or_generate(&content).into_iter().collect());
         }
 
-        // Add remaining words
-        if !current_chunk.is_empty() {
-            chunks.push(current_chunk.join(" "));
-        }
+        let text = match content.text_content {
+            Some(text) => text,
+            None => return Ok(Vec::new()),
+        };
+
+        // Chunk the text (Rake uses ~500 token chunks)
+        Ok(self.chunk_text(&text, 500, config.chunk_strategy))
+    }
+
+    /// Chunk text into segments of roughly `target_tokens` using `strategy`
+    fn chunk_text(&self, text: &str, target_tokens: usize, strategy: ChunkStrategy) -> Vec<String> {
+        // Approximate: 1 token ≈ 0.75 words
+        let words_per_chunk = ((target_tokens as f32 * 0.75) as usize).max(1);
+
+        let mut chunks = match strategy {
+            ChunkStrategy::FixedWords => chunk_fixed_words(text, words_per_chunk),
+            ChunkStrategy::Sentences => chunk_sentences(text, words_per_chunk),
+            ChunkStrategy::Paragraphs => chunk_paragraphs(text, words_per_chunk),
+        };
 
         // Return at least one chunk
         if chunks.is_empty() {
@@ -188,7 +227,7 @@ impl RakeExporter {
     async fn get_chunk_embedding(&self, file_id: i64) -> Result<Option<Vec<f32>>> {
         let conn = self.db.get_connection();
 
-        match crate::db::operations::get_embedding(conn, file_id)? {
+        match crate::db::embeddings::get_embedding(conn, file_id)? {
             Some(emb) => Ok(Some(emb.embedding)),
             None => Ok(None),
         }
@@ -196,26 +235,20 @@ impl RakeExporter {
 
     /// Estimate token count for text
     fn estimate_tokens(&self, text: &str) -> u32 {
-        // Rough estimate: 1 token ≈ 4 characters
-        (text.len() / 4) as u32
+        crate::export::estimate_tokens(text) as u32
     }
 
     /// Get export preview (before actual export)
+    ///
+    /// Files are chunked as the export would chunk them, so the chunk count
+    /// follows `config.chunk_strategy`.
     pub fn preview_sync(&self, config: &RakeExportConfig) -> Result<RakeExportMetadata> {
         let files = self.get_files_for_export(config)?;
-        let conn = self.db.get_connection();
 
-        // Estimate total chunks (assuming ~500 tokens per chunk, ~3 chars per word)
-        let total_chunks: usize = files
-            .iter()
-            .filter_map(|f| {
-                crate::db::operations::get_file_content(conn, f.id)
-                    .ok()
-                    .flatten()
-                    .and_then(|fc| fc.word_count)
-                    .map(|wc| ((wc as f32 * 0.75) / 375.0).ceil() as usize) // ~375 words per chunk
-            })
-            .sum();
+        let mut total_chunks = 0;
+        for file in &files {
+            total_chunks += self.file_chunk_texts(file, config)?.len();
+        }
 
         Ok(RakeExportMetadata {
             cortex_version: env!("CARGO_PKG_VERSION").to_string(),
@@ -234,6 +267,585 @@ impl RakeExporter {
     }
 }
 
-// Tests removed: chunk_text and estimate_tokens are simple utility functions
-// that don't require database access. More comprehensive integration tests
-// should be added for the full export workflow.
+/// Pretty JSON for `value` with `line_ending` breaks
+fn to_json<T: Serialize>(value: &T, line_ending: LineEnding) -> Result<String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| CortexError::Internal {
+        message: format!("Failed to serialize export package: {}", e),
+    })?;
+
+    // Pretty JSON escapes newlines inside strings, so only the layout
+    // breaks are converted
+    Ok(line_ending.apply(&json))
+}
+
+/// `path` with `suffix` inserted before its extension, e.g.
+/// `export.json` -> `export.part1.json`
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
+
+/// Split `package` into parts of at most `limit` bytes each, returning the
+/// part files (`<name>.part1.json`, ...) followed by a
+/// [`RakeExportManifest`] (`<name>.manifest.json`), all beside `path`
+///
+/// Parts are whole packages holding consecutive chunks, so each parses on
+/// its own. Fails if a single chunk can't fit in a part.
+fn split_package(
+    package: RakeExportPackage,
+    limit: u64,
+    path: &Path,
+    line_ending: LineEnding,
+) -> Result<Vec<(PathBuf, String)>> {
+    let RakeExportPackage { version, source, tenant_id, export_timestamp, metadata, chunks } = package;
+    let total_chunks = chunks.len();
+
+    let build = |chunks: Vec<RakeChunk>| RakeExportPackage {
+        version: version.clone(),
+        source: source.clone(),
+        tenant_id: tenant_id.clone(),
+        export_timestamp,
+        metadata: RakeExportMetadata {
+            total_chunks: chunks.len(),
+            ..metadata.clone()
+        },
+        chunks,
+    };
+
+    // A part's size is its envelope plus each chunk as laid out inside it;
+    // the extra byte per chunk covers the separating comma
+    let envelope = to_json(&build(Vec::new()), line_ending)?.len() as u64;
+    let mut parts: Vec<Vec<RakeChunk>> = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = envelope;
+
+    for chunk in chunks {
+        let chunk_json = to_json(&chunk, line_ending)?;
+        let chunk_bytes = chunk_json.len() as u64 + chunk_json.lines().count() as u64 * 4 + 8;
+
+        if envelope + chunk_bytes > limit {
+            return Err(CortexError::Internal {
+                message: format!(
+                    "Chunk {} needs about {} bytes, more than the {} byte output limit",
+                    chunk.id,
+                    envelope + chunk_bytes,
+                    limit
+                ),
+            });
+        }
+
+        if !current.is_empty() && current_bytes + chunk_bytes > limit {
+            parts.push(std::mem::take(&mut current));
+            current_bytes = envelope;
+        }
+        current.push(chunk);
+        current_bytes += chunk_bytes;
+    }
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(current);
+    }
+
+    let mut files = Vec::with_capacity(parts.len() + 1);
+    let mut manifest_parts = Vec::with_capacity(parts.len());
+    for (index, chunks) in parts.into_iter().enumerate() {
+        let part_path = sibling_path(path, &format!("part{}", index + 1));
+        let chunk_count = chunks.len();
+        let json = to_json(&build(chunks), line_ending)?;
+        debug_assert!(json.len() as u64 <= limit, "part exceeds the output limit");
+
+        manifest_parts.push(RakeExportPart {
+            file_name: part_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            chunks: chunk_count,
+            bytes: json.len() as u64,
+        });
+        files.push((part_path, json));
+    }
+
+    let manifest = RakeExportManifest {
+        version: version.clone(),
+        tenant_id: tenant_id.clone(),
+        export_timestamp,
+        total_files: metadata.total_files,
+        total_chunks,
+        parts: manifest_parts,
+    };
+    files.push((sibling_path(path, "manifest"), to_json(&manifest, line_ending)?));
+
+    Ok(files)
+}
+
+/// Write every file under a temporary name, then rename them all into place
+///
+/// Files being replaced are set aside first. If a write or rename fails,
+/// the renames done so far are undone and the set-aside files put back, so
+/// either every file is updated or none is.
+fn write_all_or_nothing(files: &[(PathBuf, String)]) -> Result<()> {
+    let with_suffix = |path: &Path, suffix: &str| {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        path.with_file_name(name)
+    };
+    let partial_paths: Vec<PathBuf> = files.iter().map(|(path, _)| with_suffix(path, ".partial")).collect();
+    let backup_paths: Vec<PathBuf> = files.iter().map(|(path, _)| with_suffix(path, ".previous")).collect();
+
+    let written = files
+        .iter()
+        .zip(&partial_paths)
+        .try_for_each(|((_, contents), partial)| fs::write(partial, contents));
+
+    // Targets renamed into place, or about to be, and whether each
+    // replaced a file
+    let mut placed: Vec<(usize, bool)> = Vec::new();
+    let result = written.and_then(|_| {
+        files.iter().enumerate().try_for_each(|(i, (path, _))| {
+            let replaces = path.is_file();
+            if replaces {
+                fs::rename(path, &backup_paths[i])?;
+            }
+            placed.push((i, replaces));
+            fs::rename(&partial_paths[i], path)
+        })
+    });
+
+    if let Err(e) = result {
+        for (i, replaced) in placed.into_iter().rev() {
+            let path = &files[i].0;
+            // Missing if it was the rename that failed
+            let _ = fs::remove_file(path);
+            if replaced {
+                if let Err(e) = fs::rename(&backup_paths[i], path) {
+                    log::warn!("Failed to restore {}: {}", path.display(), e);
+                }
+            }
+        }
+        for partial in &partial_paths {
+            let _ = fs::remove_file(partial);
+        }
+        return Err(CortexError::Internal {
+            message: format!("Failed to write export file: {}", e),
+        });
+    }
+
+    for (i, replaced) in placed {
+        if replaced {
+            let _ = fs::remove_file(&backup_paths[i]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Consecutive runs of `words_per_chunk` words
+fn chunk_fixed_words(text: &str, words_per_chunk: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    words
+        .chunks(words_per_chunk)
+        .map(|chunk| chunk.join(" "))
+        .collect()
+}
+
+/// Whole sentences grouped until adding another would exceed
+/// `words_per_chunk`; a single longer sentence becomes its own chunk
+fn chunk_sentences(text: &str, words_per_chunk: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_words = 0;
+
+    for sentence in split_sentences(text) {
+        let sentence = sentence.split_whitespace().collect::<Vec<_>>().join(" ");
+        let words = sentence.split(' ').count();
+
+        if !current.is_empty() && current_words + words > words_per_chunk {
+            chunks.push(current.join(" "));
+            current.clear();
+            current_words = 0;
+        }
+
+        current.push(sentence);
+        current_words += words;
+    }
+
+    if !current.is_empty() {
+        chunks.push(current.join(" "));
+    }
+
+    chunks
+}
+
+/// One chunk per blank-line-separated paragraph, splitting oversized
+/// paragraphs by words
+fn chunk_paragraphs(text: &str, words_per_chunk: usize) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line.trim_end());
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join("\n"));
+    }
+
+    paragraphs
+        .into_iter()
+        .flat_map(|paragraph| {
+            if paragraph.split_whitespace().count() > words_per_chunk {
+                chunk_fixed_words(&paragraph, words_per_chunk)
+            } else {
+                vec![paragraph]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_file, upsert_file_content};
+    use crate::export::{LineEnding, RakeExportManifest, RakeExportMode};
+
+    fn config(summaries_only: bool) -> RakeExportConfig {
+        RakeExportConfig {
+            collection_id: None,
+            tenant_id: "tenant".to_string(),
+            output_path: String::new(),
+            include_embeddings: false,
+            export_mode: RakeExportMode::Full,
+            summaries_only,
+            chunk_strategy: ChunkStrategy::default(),
+            line_ending: LineEnding::default(),
+            max_output_bytes: None,
+            changed_since: None,
+        }
+    }
+
+    #[test]
+    fn test_crlf_line_endings_in_written_package() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let id = insert_file(conn, "/proj/notes.md", "notes.md", "md", 30,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, id, Some("first line\nsecond line"), None).unwrap();
+
+        let exporter = RakeExporter::new(db);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let written = |name: &str, line_ending: LineEnding| {
+            let config = RakeExportConfig {
+                output_path: temp_dir.path().join(name).to_string_lossy().into_owned(),
+                line_ending,
+                ..config(false)
+            };
+            let paths = tokio_test::block_on(exporter.export_to_file(&config, None)).unwrap();
+            assert_eq!(paths.len(), 1);
+            std::fs::read_to_string(&paths[0]).unwrap()
+        };
+
+        let crlf = written("crlf.json", LineEnding::CrLf);
+        assert!(crlf.contains("\r\n"));
+        assert!(!crlf.replace("\r\n", "").contains('\n'));
+
+        let lf = written("lf.json", LineEnding::Lf);
+        assert!(lf.contains('\n'));
+        assert!(!lf.contains('\r'));
+
+        // Still valid JSON with the same content
+        let package: RakeExportPackage = serde_json::from_str(&crlf).unwrap();
+        assert_eq!(package.chunks[0].content, "first line second line");
+    }
+
+    #[test]
+    fn test_crlf_line_endings_in_written_bundle() {
+        use crate::export::{BundleBuilder, ExportConfig};
+
+        // The bundle's builders each read through a clone of the database
+        let db = tokio_test::block_on(Database::new_in_memory()).unwrap();
+        let conn = db.get_connection();
+        let id = insert_file(conn, "/proj/src/main.rs", "main.rs", "rs", 30,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, id, Some("fn main() {\n    run();\n}"), None).unwrap();
+
+        let bundler = BundleBuilder::new(db);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let written = |name: &str, line_ending: LineEnding| {
+            let output = temp_dir.path().join(name);
+            let config = ExportConfig {
+                output_path: output.to_string_lossy().into_owned(),
+                line_ending,
+                ..ExportConfig::default()
+            };
+            tokio_test::block_on(bundler.create_bundle(&config)).unwrap();
+
+            let mut files = vec![
+                output.join("CONTEXT.md"),
+                output.join("STARTER_PROMPT.md"),
+                output.join("README.md"),
+                output.join(".claude").join("settings.json"),
+            ];
+            files.extend(std::fs::read_dir(output.join("prompts")).unwrap().map(|entry| entry.unwrap().path()));
+            files.into_iter().map(|path| std::fs::read_to_string(path).unwrap()).collect::<Vec<_>>()
+        };
+
+        for text in written("crlf", LineEnding::CrLf) {
+            assert!(text.contains("\r\n"));
+            assert!(!text.replace("\r\n", "").contains('\n'));
+        }
+        for text in written("lf", LineEnding::Lf) {
+            assert!(text.contains('\n'));
+            assert!(!text.contains('\r'));
+        }
+    }
+
+    #[test]
+    fn test_summaries_only_export_is_smaller() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        let body = format!("Billing module\n\n{}", "invoice line item totals ".repeat(1000));
+        let billing_id = insert_file(conn, "/proj/billing.md", "billing.md", "md", body.len() as i64,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, billing_id, Some(&body), Some("Handles customer billing")).unwrap();
+
+        // No stored summary, so one is generated from the text
+        let notes_id = insert_file(conn, "/proj/notes.txt", "notes.txt", "txt", 60,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(conn, notes_id, Some("Release checklist\nstep one\nstep two"), None).unwrap();
+
+        let exporter = RakeExporter::new(db);
+        let full = tokio_test::block_on(exporter.export(&config(false), None)).unwrap();
+        let summaries = tokio_test::block_on(exporter.export(&config(true), None)).unwrap();
+
+        let total = |package: &RakeExportPackage| -> usize {
+            package.chunks.iter().map(|c| c.content.len()).sum()
+        };
+        assert!(total(&summaries) * 10 < total(&full));
+        assert_eq!(summaries.chunks.len(), 2);
+
+        let contents: Vec<&str> = summaries.chunks.iter().map(|c| c.content.as_str()).collect();
+        assert!(contents.contains(&"Handles customer billing"));
+        assert!(contents.contains(&"Release checklist"));
+        assert!(contents.iter().all(|c| !c.contains("invoice line item")));
+    }
+
+    #[test]
+    fn test_progress_reported_per_file() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let id = insert_file(conn, &format!("/proj/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+            upsert_file_content(conn, id, Some("some exported text"), None).unwrap();
+        }
+
+        let seen = std::sync::Mutex::new(Vec::new());
+        let record = |p: RakeExportProgress| seen.lock().unwrap().push(p.files_processed);
+
+        let exporter = RakeExporter::new(db);
+        let package = tokio_test::block_on(exporter.export(&config(false), Some(&record))).unwrap();
+
+        assert_eq!(package.metadata.total_files, 3);
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_cancel_stops_between_files_without_output() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let id = insert_file(conn, &format!("/proj/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+            upsert_file_content(conn, id, Some("some exported text"), None).unwrap();
+        }
+
+        // Cancel as soon as the first file is converted
+        let cancel = Arc::new(AtomicBool::new(false));
+        let seen = std::sync::Mutex::new(Vec::new());
+        let record = |p: RakeExportProgress| {
+            seen.lock().unwrap().push(p.files_processed);
+            cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+        };
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = RakeExportConfig {
+            output_path: temp_dir.path().join("package.json").to_string_lossy().into_owned(),
+            ..config(false)
+        };
+        let exporter = RakeExporter::new(db).with_cancel_flag(cancel.clone());
+        let result = tokio_test::block_on(exporter.export_to_file(&config, Some(&record)));
+
+        assert!(matches!(result, Err(CortexError::Cancelled { .. })));
+        assert_eq!(*seen.lock().unwrap(), vec![1]);
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_export_split_into_parts_under_the_limit() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.get_connection();
+
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"] {
+            let id = insert_file(conn, &format!("/proj/{}", name), name, "txt", 10,
+                "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+            upsert_file_content(conn, id, Some(&format!("{} ", name).repeat(800)), None).unwrap();
+        }
+
+        let exporter = RakeExporter::new(db);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output = temp_dir.path().join("export.json");
+        let config = RakeExportConfig {
+            output_path: output.to_string_lossy().into_owned(),
+            max_output_bytes: Some(8_000),
+            line_ending: LineEnding::CrLf,
+            ..config(false)
+        };
+        let paths = tokio_test::block_on(exporter.export_to_file(&config, None)).unwrap();
+
+        // Parts first, then the manifest; the unsplit name is never written
+        assert!(paths.len() > 2);
+        assert!(!output.exists());
+        assert!(paths[0].ends_with("export.part1.json"));
+        assert!(paths[1].ends_with("export.part2.json"));
+        let manifest_path = paths.last().unwrap();
+        assert!(manifest_path.ends_with("export.manifest.json"));
+
+        let manifest: RakeExportManifest =
+            serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.parts.len(), paths.len() - 1);
+        assert_eq!(manifest.total_chunks, 15);
+
+        let mut ids = Vec::new();
+        for (path, part) in paths.iter().zip(&manifest.parts) {
+            let json = std::fs::read_to_string(path).unwrap();
+            assert!(json.len() as u64 <= 8_000);
+            assert_eq!(json.len() as u64, part.bytes);
+
+            let package: RakeExportPackage = serde_json::from_str(&json).unwrap();
+            assert_eq!(package.chunks.len(), part.chunks);
+            assert_eq!(package.metadata.total_chunks, part.chunks);
+            ids.extend(package.chunks.into_iter().map(|c| c.id));
+        }
+
+        let whole = tokio_test::block_on(exporter.export(&config, None)).unwrap();
+        let expected: Vec<String> = whole.chunks.into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, expected);
+
+        // A limit no chunk fits under fails without writing anything
+        let tiny_dir = tempfile::TempDir::new().unwrap();
+        let config = RakeExportConfig {
+            output_path: tiny_dir.path().join("export.json").to_string_lossy().into_owned(),
+            max_output_bytes: Some(500),
+            ..config
+        };
+        assert!(tokio_test::block_on(exporter.export_to_file(&config, None)).is_err());
+        assert_eq!(std::fs::read_dir(tiny_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_failed_rename_restores_replaced_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let part = temp_dir.path().join("export.part1.json");
+        std::fs::write(&part, "previous part").unwrap();
+        // A non-empty directory can't be replaced by a file
+        let manifest = temp_dir.path().join("export.manifest.json");
+        std::fs::create_dir(&manifest).unwrap();
+        std::fs::write(manifest.join("keep"), "").unwrap();
+
+        let files = vec![(part.clone(), "new part".to_string()), (manifest.clone(), "{}".to_string())];
+        assert!(write_all_or_nothing(&files).is_err());
+
+        assert_eq!(std::fs::read_to_string(&part).unwrap(), "previous part");
+        assert!(manifest.join("keep").exists());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+
+        // With nothing in the way, every file is replaced and no backups stay
+        std::fs::remove_dir_all(&manifest).unwrap();
+        write_all_or_nothing(&files).unwrap();
+        assert_eq!(std::fs::read_to_string(&part).unwrap(), "new part");
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+
+    const SAMPLE: &str = "Cortex indexes files. It runs locally!\n\
+                          Does it need a server? No.\n\
+                          \n\
+                          Second paragraph here.\n\
+                          \n\
+                          \n\
+                          Third paragraph, version 1.5 notes.";
+
+    #[test]
+    fn test_fixed_words_chunks() {
+        let chunks = chunk_fixed_words(SAMPLE, 4);
+        assert_eq!(chunks[0], "Cortex indexes files. It");
+        assert!(chunks.iter().all(|c| c.split(' ').count() <= 4));
+        assert_eq!(
+            chunks.iter().map(|c| c.split(' ').count()).sum::<usize>(),
+            SAMPLE.split_whitespace().count()
+        );
+    }
+
+    #[test]
+    fn test_sentence_chunks_keep_sentences_whole() {
+        let chunks = chunk_sentences(SAMPLE, 7);
+        assert_eq!(
+            chunks,
+            vec![
+                "Cortex indexes files. It runs locally!",
+                "Does it need a server? No.",
+                "Second paragraph here.",
+                "Third paragraph, version 1.5 notes.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paragraph_chunks_split_on_blank_lines() {
+        let chunks = chunk_paragraphs(SAMPLE, 100);
+        assert_eq!(
+            chunks,
+            vec![
+                "Cortex indexes files. It runs locally!\nDoes it need a server? No.",
+                "Second paragraph here.",
+                "Third paragraph, version 1.5 notes.",
+            ]
+        );
+
+        // Oversized paragraphs fall back to word splitting
+        let small = chunk_paragraphs(SAMPLE, 5);
+        assert_eq!(small[0], "Cortex indexes files. It runs");
+        assert!(small.contains(&"Second paragraph here.".to_string()));
+    }
+
+    #[test]
+    fn test_chunk_strategy_selected_from_config() {
+        let exporter = RakeExporter::new(Database::open_in_memory().unwrap());
+
+        assert_eq!(exporter.chunk_text(SAMPLE, 500, ChunkStrategy::FixedWords).len(), 1);
+        assert_eq!(exporter.chunk_text(SAMPLE, 500, ChunkStrategy::Paragraphs).len(), 3);
+        assert_eq!(exporter.chunk_text("", 500, ChunkStrategy::Sentences), vec![""]);
+
+        // The preview counts the chunks the chosen strategy makes
+        let db = Database::open_in_memory().unwrap();
+        let id = insert_file(db.get_connection(), "/proj/notes.txt", "notes.txt", "txt", 10,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/proj").unwrap();
+        upsert_file_content(db.get_connection(), id, Some(SAMPLE), None).unwrap();
+        let exporter = RakeExporter::new(db);
+        let preview = |chunk_strategy| {
+            exporter.preview_sync(&RakeExportConfig { chunk_strategy, ..config(false) }).unwrap().total_chunks
+        };
+        assert_eq!(preview(ChunkStrategy::FixedWords), 1);
+        assert_eq!(preview(ChunkStrategy::Paragraphs), 3);
+    }
+}
@@ -0,0 +1,344 @@
+use std::path::Path;
+
+/// Languages whose comments can be told apart from their code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeLanguage {
+    /// `//` and nestable `/* */` comments, raw strings and lifetimes
+    Rust,
+    /// C, C++ and Java: `//` and `/* */` comments
+    CFamily,
+    /// JavaScript and TypeScript, including template literals
+    JavaScript,
+    /// `#` comments; docstrings are string literals and count as code
+    Python,
+}
+
+impl CodeLanguage {
+    /// The language of `path`, judging by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|s| s.to_lowercase())?;
+
+        match extension.as_str() {
+            "rs" => Some(Self::Rust),
+            "c" | "cpp" | "h" | "hpp" | "java" => Some(Self::CFamily),
+            "js" | "jsx" | "mjs" | "ts" | "tsx" => Some(Self::JavaScript),
+            "py" => Some(Self::Python),
+            _ => None,
+        }
+    }
+}
+
+/// A source file's text split into code and comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeSplit {
+    /// The source with comments removed; line breaks are kept
+    pub code: String,
+    /// Each comment, markers included, one per line
+    pub comments: String,
+}
+
+/// Separate the comments in `source` from its code
+///
+/// This lexes rather than parses: it only tracks string and character
+/// literals so that comment markers inside them are ignored, which is enough
+/// for well-formed source.
+pub fn split_comments(source: &str, language: CodeLanguage) -> CodeSplit {
+    let chars: Vec<char> = source.chars().collect();
+    let mut split = CodeSplit::default();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let end = if let Some(end) = comment_end(&chars, i, language) {
+            let comment: String = chars[i..end].iter().collect();
+            split.comments.push_str(comment.trim_end());
+            split.comments.push('\n');
+
+            // Keep the tokens on either side of a block comment apart
+            if chars.get(end - 1) == Some(&'/') {
+                split.code.push(' ');
+            }
+            end
+        } else {
+            let end = literal_end(&chars, i, language).unwrap_or(i + 1);
+            split.code.extend(&chars[i..end]);
+            end
+        };
+
+        i = end;
+    }
+
+    split
+}
+
+/// The documentation block at the top of `source`, markers removed
+///
+/// That is a Rust `//!` or `/*! */` module doc, a `/** */` doc comment in C,
+/// Java and JavaScript, or a Python module docstring. A shebang, blank lines
+/// and plain comments such as license headers may come before it; anything
+/// else means the file has no module doc.
+pub fn module_doc(source: &str, language: CodeLanguage) -> Option<String> {
+    let mut rest = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if rest.starts_with("#!") && !rest.starts_with("#![") {
+        rest = rest.split_once('\n').map_or("", |(_, after)| after);
+    }
+
+    loop {
+        rest = rest.trim_start();
+
+        let doc = match language {
+            CodeLanguage::Rust if rest.starts_with("//!") => {
+                let lines: Vec<&str> = rest
+                    .lines()
+                    .map(str::trim_start)
+                    .take_while(|line| line.starts_with("//!"))
+                    .map(|line| line[3..].strip_prefix(' ').unwrap_or(&line[3..]))
+                    .collect();
+                Some(lines.join("\n"))
+            }
+            CodeLanguage::Rust if rest.starts_with("/*!") => Some(block_doc(&rest[3..])),
+            CodeLanguage::CFamily | CodeLanguage::JavaScript
+                if rest.starts_with("/**") && !rest.starts_with("/**/") =>
+            {
+                Some(block_doc(&rest[3..]))
+            }
+            CodeLanguage::Python => docstring(rest),
+            _ => None,
+        };
+        if let Some(doc) = doc {
+            let doc = doc.trim();
+            return (!doc.is_empty()).then(|| doc.to_string());
+        }
+
+        // Skip a plain comment
+        let line_comment = if language == CodeLanguage::Python { "#" } else { "//" };
+        rest = if rest.starts_with(line_comment) {
+            rest.split_once('\n').map_or("", |(_, after)| after)
+        } else if language != CodeLanguage::Python && rest.starts_with("/*") {
+            rest[2..].split_once("*/").map_or("", |(_, after)| after)
+        } else {
+            return None;
+        };
+    }
+}
+
+/// Text of a block comment whose opening marker was just consumed, without
+/// the `*` that commonly starts each line
+fn block_doc(body: &str) -> String {
+    let body = body.split_once("*/").map_or(body, |(doc, _)| doc);
+    body.lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The text of a triple-quoted string at the start of `source`, if there is
+/// one, with each line's indentation removed
+fn docstring(source: &str) -> Option<String> {
+    let unprefixed = source.trim_start_matches(['r', 'R', 'u', 'U']);
+    if source.len() - unprefixed.len() > 1 {
+        return None;
+    }
+
+    let quote = ["\"\"\"", "'''"].into_iter().find(|quote| unprefixed.starts_with(quote))?;
+    let body = &unprefixed[3..];
+    let body = body.find(quote).map_or(body, |end| &body[..end]);
+
+    Some(body.lines().map(str::trim).collect::<Vec<_>>().join("\n"))
+}
+
+fn starts_with(chars: &[char], i: usize, pattern: &str) -> bool {
+    pattern.chars().enumerate().all(|(k, p)| chars.get(i + k) == Some(&p))
+}
+
+fn is_ident(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// End (exclusive) of the comment starting at `i`, if one does
+///
+/// Line comments end before their newline; an unterminated block comment
+/// runs to the end of the source.
+fn comment_end(chars: &[char], i: usize, language: CodeLanguage) -> Option<usize> {
+    let line_comment = match language {
+        CodeLanguage::Python => chars[i] == '#',
+        _ => starts_with(chars, i, "//"),
+    };
+
+    if line_comment {
+        let end = chars[i..].iter().position(|&ch| ch == '\n').map_or(chars.len(), |p| i + p);
+        return Some(end);
+    }
+
+    if language == CodeLanguage::Python || !starts_with(chars, i, "/*") {
+        return None;
+    }
+
+    let nested = language == CodeLanguage::Rust;
+    let mut depth = 0;
+    let mut j = i;
+    while j < chars.len() {
+        if starts_with(chars, j, "/*") && (nested || depth == 0) {
+            depth += 1;
+            j += 2;
+        } else if starts_with(chars, j, "*/") {
+            depth -= 1;
+            j += 2;
+            if depth == 0 {
+                return Some(j);
+            }
+        } else {
+            j += 1;
+        }
+    }
+
+    Some(chars.len())
+}
+
+/// End (exclusive) of the string or character literal starting at `i`, if
+/// one does
+fn literal_end(chars: &[char], i: usize, language: CodeLanguage) -> Option<usize> {
+    let ch = chars[i];
+
+    match language {
+        CodeLanguage::Python => {
+            if starts_with(chars, i, "\"\"\"") || starts_with(chars, i, "'''") {
+                let quote: String = chars[i..i + 3].iter().collect();
+                let end = (i + 3..chars.len())
+                    .find(|&j| chars[j - 1] != '\\' && starts_with(chars, j, &quote))
+                    .map_or(chars.len(), |j| j + 3);
+                return Some(end);
+            }
+            matches!(ch, '"' | '\'').then(|| quoted_end(chars, i, ch, false))
+        }
+        CodeLanguage::JavaScript => {
+            matches!(ch, '"' | '\'' | '`').then(|| quoted_end(chars, i, ch, ch == '`'))
+        }
+        CodeLanguage::CFamily => match ch {
+            '"' => Some(quoted_end(chars, i, '"', true)),
+            '\'' => Some(quoted_end(chars, i, '\'', false)),
+            _ => None,
+        },
+        CodeLanguage::Rust => match ch {
+            '"' => Some(quoted_end(chars, i, '"', true)),
+            'r' => raw_string_end(chars, i),
+            // A character literal, unless this is a lifetime like `'a`
+            '\'' if chars.get(i + 1) == Some(&'\\') || chars.get(i + 2) == Some(&'\'') => {
+                Some(quoted_end(chars, i, '\'', false))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// End of a literal opened by `quote` at `i`, honoring backslash escapes
+///
+/// Unless `multiline`, an unterminated literal ends at the line break.
+fn quoted_end(chars: &[char], i: usize, quote: char, multiline: bool) -> usize {
+    let mut j = i + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '\\' => j += 2,
+            '\n' if !multiline => return j,
+            ch if ch == quote => return j + 1,
+            _ => j += 1,
+        }
+    }
+    chars.len()
+}
+
+/// End of a Rust raw string (`r"…"`, `r#"…"#`, `br"…"`) starting at the `r`
+fn raw_string_end(chars: &[char], i: usize) -> Option<usize> {
+    // `r` must start a token, or follow the `b` that does
+    let token_start = |k: usize| k == 0 || !is_ident(chars[k - 1]);
+    if !(token_start(i) || (i >= 1 && chars[i - 1] == 'b' && token_start(i - 1))) {
+        return None;
+    }
+
+    let hashes = chars[i + 1..].iter().take_while(|&&ch| ch == '#').count();
+    if chars.get(i + 1 + hashes) != Some(&'"') {
+        return None;
+    }
+
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let body = i + 2 + hashes;
+    let end = (body..chars.len())
+        .find(|&j| starts_with(chars, j, &closing))
+        .map_or(chars.len(), |j| j + closing.len());
+
+    Some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_comments_and_literals() {
+        let source = r####"
+/// Parses the config
+fn parse(input: &str) -> Option<&'static str> {
+    // TODO handle tabs
+    let url = "https://example.com"; /* inline /* nested */ note */
+    let raw = r#"not // a comment"#;
+    let quote = '"';
+    None
+}
+"####;
+
+        let split = split_comments(source, CodeLanguage::Rust);
+
+        assert_eq!(
+            split.comments,
+            "/// Parses the config\n// TODO handle tabs\n/* inline /* nested */ note */\n"
+        );
+        assert!(split.code.contains("\"https://example.com\""));
+        assert!(split.code.contains(r##"r#"not // a comment"#"##));
+        assert!(split.code.contains("let quote = '\"';"));
+        assert!(split.code.contains("&'static str"));
+        assert!(!split.code.contains("TODO") && !split.code.contains("nested"));
+    }
+
+    #[test]
+    fn test_python_and_javascript_comments() {
+        let python = "def area(r):\n    \"\"\"Circle # area\"\"\"\n    return 3.14 * r  # TODO use math.pi\n";
+        let split = split_comments(python, CodeLanguage::Python);
+        assert_eq!(split.comments, "# TODO use math.pi\n");
+        assert!(split.code.contains("\"\"\"Circle # area\"\"\""));
+
+        let js = "const path = `/api//v1`; // TODO version\nconst re = '/* not */'; /* real */\n";
+        let split = split_comments(js, CodeLanguage::JavaScript);
+        assert_eq!(split.comments, "// TODO version\n/* real */\n");
+        assert!(split.code.contains("`/api//v1`") && split.code.contains("'/* not */'"));
+
+        assert_eq!(CodeLanguage::from_path(Path::new("App.TSX")), Some(CodeLanguage::JavaScript));
+        assert_eq!(CodeLanguage::from_path(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn test_module_doc_after_license_header() {
+        let rust = "// Copyright 2025 Example Corp\n// SPDX-License-Identifier: MIT\n\n\
+            //! Rate limiting for the API gateway\n//!\n//! Token buckets per client.\nuse std::time::Duration;\n";
+        assert_eq!(
+            module_doc(rust, CodeLanguage::Rust).as_deref(),
+            Some("Rate limiting for the API gateway\n\nToken buckets per client.")
+        );
+
+        let js = "#!/usr/bin/env node\n/* eslint-disable */\n/**\n * CLI entry point.\n * @author Dana\n */\nmain();\n";
+        assert_eq!(module_doc(js, CodeLanguage::JavaScript).as_deref(), Some("CLI entry point.\n@author Dana"));
+
+        let python = "#!/usr/bin/env python3\n# -*- coding: utf-8 -*-\nr'''\n    Resize images in bulk.\n'''\nimport sys\n";
+        assert_eq!(module_doc(python, CodeLanguage::Python).as_deref(), Some("Resize images in bulk."));
+
+        // Docs further down, and item docs, don't count
+        assert_eq!(module_doc("use std::fmt;\n//! Late\n", CodeLanguage::Rust), None);
+        assert_eq!(module_doc("/// Parses input\nfn parse() {}\n", CodeLanguage::Rust), None);
+        assert_eq!(module_doc("import os\n\"\"\"Not a docstring\"\"\"\n", CodeLanguage::Python), None);
+        assert_eq!(module_doc("/** */\nint x;\n", CodeLanguage::CFamily), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::error::Result;
+use crate::indexer::extractors::{ExtractedContent, Extractor, TextExtractor};
+use std::path::Path;
+
+/// Extractor for comma- and tab-separated tables
+pub struct CsvExtractor;
+
+impl CsvExtractor {
+    /// Extract the cell text of a CSV or TSV file
+    /// One line per row with cells separated by spaces; the header row
+    /// becomes the summary
+    pub fn extract(path: &Path) -> Result<ExtractedContent> {
+        // Read with the text extractor's encoding detection
+        let raw = TextExtractor::extract(path)?;
+        let rows = Self::parse_rows(&raw.text, Self::delimiter(path));
+
+        let text = rows
+            .iter()
+            .map(|row| Self::join_cells(row, " "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut content = ExtractedContent::new(text);
+        content.summary = rows
+            .iter()
+            .map(|row| Self::join_cells(row, ", "))
+            .find(|header| !header.is_empty())
+            .and_then(|header| ExtractedContent::generate_summary(&header));
+        content.warnings = raw.warnings;
+
+        Ok(content)
+    }
+
+    /// Tab for `.tsv` files, comma otherwise
+    fn delimiter(path: &Path) -> char {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") => '\t',
+            _ => ',',
+        }
+    }
+
+    /// The non-blank cells of a row, trimmed and joined with `separator`
+    fn join_cells(row: &[String], separator: &str) -> String {
+        row.iter()
+            .map(|cell| cell.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|cell| !cell.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Split delimited text into rows of cells
+    ///
+    /// Follows RFC 4180: a quoted cell may contain the delimiter, line breaks
+    /// and `""` for a literal quote. Quotes inside an unquoted cell are kept
+    /// as written.
+    fn parse_rows(text: &str, delimiter: char) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut cell = String::new();
+        let mut in_quotes = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        cell.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = false,
+                    _ => cell.push(c),
+                }
+            } else if c == '"' && cell.is_empty() {
+                in_quotes = true;
+            } else if c == delimiter {
+                row.push(std::mem::take(&mut cell));
+            } else if c == '\n' || c == '\r' {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            } else {
+                cell.push(c);
+            }
+        }
+
+        // Last row without a trailing line break
+        if !cell.is_empty() || !row.is_empty() {
+            row.push(cell);
+            rows.push(row);
+        }
+
+        rows
+    }
+}
+
+impl Extractor for CsvExtractor {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn label(&self) -> &str {
+        "CSV and TSV tables"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "tsv"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        CsvExtractor::extract(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extract_csv_with_quoted_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("products.csv");
+        std::fs::write(&path, "sku,name,notes\r\n\
+            A-1,\"Widget, large\",\"Says \"\"hello\"\"\"\r\n\
+            B-2,Gadget,\"Two\nlines\"\r\n\
+            ,,\r\n").unwrap();
+
+        let result = CsvExtractor::extract(&path).unwrap();
+
+        assert_eq!(result.text, "sku name notes\nA-1 Widget, large Says \"hello\"\nB-2 Gadget Two lines");
+        for cell in ["A-1", "Widget, large", "Says \"hello\"", "Gadget", "Two lines"] {
+            assert!(result.text.contains(cell), "missing {}", cell);
+        }
+        assert_eq!(result.word_count, 12);
+        assert_eq!(result.summary.as_deref(), Some("sku, name, notes"));
+    }
+
+    #[test]
+    fn test_extract_tsv_splits_on_tabs() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("terms.TSV");
+        std::fs::write(&path, "term\tdefinition\nlatency\ttime to first byte, in ms").unwrap();
+
+        let result = CsvExtractor::extract(&path).unwrap();
+
+        assert_eq!(result.text, "term definition\nlatency time to first byte, in ms");
+        assert_eq!(result.summary.as_deref(), Some("term, definition"));
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = CsvExtractor::parse_rows("a,\"b,c\"\n5\" disk,\"\"\n", ',');
+        assert_eq!(rows, vec![vec!["a", "b,c"], vec!["5\" disk", ""]]);
+        assert!(CsvExtractor::parse_rows("", ',').is_empty());
+    }
+}
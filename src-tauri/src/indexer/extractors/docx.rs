@@ -1,5 +1,5 @@
 use crate::error::{CortexError, Result};
-use crate::indexer::extractors::ExtractedContent;
+use crate::indexer::extractors::{ExtractedContent, Extractor};
 use std::path::Path;
 
 /// Extractor for DOCX files
@@ -57,6 +57,28 @@ impl DocxExtractor {
     }
 }
 
+impl Extractor for DocxExtractor {
+    fn name(&self) -> &str {
+        "docx"
+    }
+
+    fn label(&self) -> &str {
+        "Word (.docx)"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["docx"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        DocxExtractor::extract(path)
+    }
+
+    fn text_fallback(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,300 @@
+use crate::error::Result;
+use crate::indexer::extractors::{ExtractedContent, Extractor, TextExtractor};
+use std::path::Path;
+
+/// Elements whose content is never visible text
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "head"];
+
+/// Elements that start a new line of text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr",
+    "li", "main", "nav", "ol", "p", "pre", "section", "table", "td", "th", "title", "tr", "ul",
+];
+
+/// Extractor for HTML files
+pub struct HtmlExtractor;
+
+impl HtmlExtractor {
+    /// Extract the visible text of an HTML file
+    /// Drops tags, comments and script/style content, one line per block
+    pub fn extract(path: &Path) -> Result<ExtractedContent> {
+        // Read with the text extractor's encoding detection
+        let raw = TextExtractor::extract(path)?;
+
+        let mut content = ExtractedContent::new(Self::html_to_text(&raw.text));
+        content.warnings = raw.warnings;
+        content.add_links(Self::link_targets(&raw.text));
+
+        Ok(content)
+    }
+
+    /// Convert HTML to plain text with whitespace collapsed
+    fn html_to_text(html: &str) -> String {
+        let mut output = String::new();
+        let mut rest = html;
+
+        // Line breaks in the source are just whitespace; only blocks start
+        // new lines
+        let push_text = |output: &mut String, text: &str| {
+            output.extend(text.chars().map(|c| if c == '\n' || c == '\r' { ' ' } else { c }));
+        };
+
+        while let Some(start) = rest.find(['<', '&']) {
+            push_text(&mut output, &rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with('&') {
+                let (decoded, len) = Self::decode_entity(rest);
+                output.push_str(&decoded);
+                rest = &rest[len..];
+            } else if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?') {
+                let end = Self::tag_end(rest);
+                let tag = rest[1..end].trim_end_matches('>');
+                rest = &rest[end..];
+
+                let closing = tag.starts_with('/');
+                let name: String = tag
+                    .trim_start_matches('/')
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+
+                if !closing && HIDDEN_ELEMENTS.contains(&name.as_str()) && !tag.ends_with('/') {
+                    rest = Self::skip_element(rest, &name);
+                } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    output.push('\n');
+                }
+            } else {
+                // A lone `<`, e.g. in `a < b`
+                output.push('<');
+                rest = &rest[1..];
+            }
+        }
+        push_text(&mut output, rest);
+
+        output
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The `href`s of the `<a>` and `<link>` tags in `html`, entities decoded
+    fn link_targets(html: &str) -> Vec<String> {
+        let mut targets = Vec::new();
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start..];
+            let end = Self::tag_end(rest);
+            let tag = &rest[1..end];
+            rest = &rest[end..];
+
+            let name: String = tag
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if name == "a" || name == "link" {
+                targets.extend(Self::attribute(&tag[name.len()..], "href"));
+            }
+        }
+
+        targets
+    }
+
+    /// Decoded value of the `name` attribute in a tag's attribute text
+    fn attribute(attributes: &str, name: &str) -> Option<String> {
+        let mut rest = attributes.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+
+        while !rest.is_empty() && !rest.starts_with('>') {
+            let key_end = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>').unwrap_or(rest.len());
+            let key = &rest[..key_end];
+            rest = rest[key_end..].trim_start();
+
+            let mut value = None;
+            if let Some(after) = rest.strip_prefix('=') {
+                let after = after.trim_start();
+                let (raw, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let inner = &after[1..];
+                        let close = inner.find(quote).unwrap_or(inner.len());
+                        (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let close = after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                        (&after[..close], &after[close..])
+                    }
+                };
+                value = Some(raw);
+                rest = remaining;
+            }
+
+            if key.eq_ignore_ascii_case(name) {
+                return value.map(Self::decode_entities);
+            }
+            rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        }
+
+        None
+    }
+
+    /// `text` with its character references decoded
+    fn decode_entities(text: &str) -> String {
+        let mut output = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('&') {
+            output.push_str(&rest[..start]);
+            let (decoded, len) = Self::decode_entity(&rest[start..]);
+            output.push_str(&decoded);
+            rest = &rest[start + len..];
+        }
+        output.push_str(rest);
+
+        output
+    }
+
+    /// Byte length of the tag at the start of `html`, up to and including
+    /// its `>`, skipping any `>` inside quoted attribute values
+    fn tag_end(html: &str) -> usize {
+        let mut quote = None;
+        for (i, c) in html.char_indices() {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), _) if c == q => quote = None,
+                (None, '>') => return i + 1,
+                _ => {}
+            }
+        }
+        html.len()
+    }
+
+    /// The text after the closing tag of the `name` element `html` is inside
+    fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+        let closing = format!("</{}", name);
+        match html.to_ascii_lowercase().find(&closing) {
+            Some(start) => {
+                let after = &html[start..];
+                &after[Self::tag_end(after)..]
+            }
+            None => "",
+        }
+    }
+
+    /// The text a character reference at the start of `html` stands for,
+    /// and its byte length; unknown references are kept as written
+    fn decode_entity(html: &str) -> (String, usize) {
+        let Some(end) = html.find(';').filter(|&end| end <= 10) else {
+            return ("&".to_string(), 1);
+        };
+
+        let entity = &html[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity.strip_prefix('#').and_then(|number| {
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => number.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }),
+        };
+
+        match decoded {
+            Some(c) => (c.to_string(), end + 1),
+            None => ("&".to_string(), 1),
+        }
+    }
+}
+
+impl Extractor for HtmlExtractor {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn label(&self) -> &str {
+        "HTML"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["html", "htm"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        HtmlExtractor::extract(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_extract_html_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"<html><head><title>Ignored</title></head>\
+            <body><p>Hello <b>bold</b> world</p></body></html>").unwrap();
+
+        let result = HtmlExtractor::extract(file.path()).unwrap();
+
+        assert_eq!(result.text, "Hello bold world");
+        assert_eq!(result.word_count, 3);
+    }
+
+    #[test]
+    fn test_html_to_text_strips_tags() {
+        let text = HtmlExtractor::html_to_text("<div class=\"a > b\"><b>bold</b></div>");
+        assert_eq!(text, "bold");
+    }
+
+    #[test]
+    fn test_html_to_text_drops_script_and_style() {
+        let html = "<p>Before</p>\
+            <script type=\"text/javascript\">if (a < b) { alert('hidden'); }</script>\
+            <STYLE>.hidden { color: red; }</STYLE>\
+            <!-- a <p>commented</p> out block -->\
+            <p>After</p>";
+        let text = HtmlExtractor::html_to_text(html);
+
+        assert_eq!(text, "Before\nAfter");
+    }
+
+    #[test]
+    fn test_html_to_text_collapses_whitespace() {
+        let html = "<ul>\n  <li>First\n     item</li>\n  <li>Second</li>\n</ul>";
+        let text = HtmlExtractor::html_to_text(html);
+
+        assert_eq!(text, "First item\nSecond");
+    }
+
+    #[test]
+    fn test_html_link_targets() {
+        let html = "<p>See <a class=x href=\"https://arxiv.org/abs/1?a=1&amp;b=2\">the paper</a>, \
+            <A HREF='https://example.com/'>home</A>, <a href=/local>here</a> \
+            and <a name=top>.</a></p>";
+
+        assert_eq!(
+            HtmlExtractor::link_targets(html),
+            vec!["https://arxiv.org/abs/1?a=1&b=2", "https://example.com/", "/local"]
+        );
+    }
+
+    #[test]
+    fn test_html_to_text_decodes_entities() {
+        let text = HtmlExtractor::html_to_text("<p>Fish &amp; chips &lt;3 &#233;&#x41; a < b &copy; AT&T</p>");
+        assert_eq!(text, "Fish & chips <3 éA a < b &copy; AT&T");
+    }
+}
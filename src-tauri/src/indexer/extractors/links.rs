@@ -0,0 +1,111 @@
+//! URLs referenced by extracted documents
+//!
+//! Extractors that know where their links are (Markdown link targets, HTML
+//! `href`s, PDF link annotations) report them directly; [`find_urls`] picks
+//! up the bare URLs written in any text.
+
+/// Schemes recognised as links
+const SCHEMES: &[&str] = &["https://", "http://"];
+
+/// Characters that end a URL written in running text
+fn ends_url(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`' | '{' | '}' | '|' | '\\' | '^')
+}
+
+/// The `http(s)://` URLs written in `text`, in order of appearance
+///
+/// Trailing sentence punctuation is not part of the URL, and neither is a
+/// closing bracket without a matching opening one, so `(see https://a.org/x).`
+/// yields `https://a.org/x`.
+pub fn find_urls(text: &str) -> Vec<String> {
+    let lower = text.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut from = 0;
+
+    while let Some((start, scheme)) = SCHEMES
+        .iter()
+        .filter_map(|scheme| lower[from..].find(scheme).map(|i| (from + i, scheme.len())))
+        .min()
+    {
+        let end = text[start..].find(ends_url).map_or(text.len(), |len| start + len);
+        let url = trim_url(&text[start..end]);
+        if link_domain(url).is_some() {
+            urls.push(url.to_string());
+        }
+        from = (start + scheme).max(end);
+    }
+
+    urls
+}
+
+/// `url` without punctuation that belongs to the surrounding sentence
+fn trim_url(mut url: &str) -> &str {
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*']);
+        let trimmed = match trimmed.chars().last() {
+            Some(close @ (')' | ']')) => {
+                let open = if close == ')' { '(' } else { '[' };
+                let opens = trimmed.matches(open).count();
+                let closes = trimmed.matches(close).count();
+                if closes > opens {
+                    &trimmed[..trimmed.len() - 1]
+                } else {
+                    trimmed
+                }
+            }
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Lowercase host name of an `http(s)://` URL, or `None` for other links
+/// (relative paths, `mailto:` and so on)
+///
+/// `https://user@Export.arXiv.org:443/abs` gives `export.arxiv.org`.
+pub fn link_domain(url: &str) -> Option<String> {
+    let lower = url.to_ascii_lowercase();
+    let rest = SCHEMES.iter().find_map(|scheme| lower.strip_prefix(scheme))?;
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().trim_end_matches('.');
+
+    let valid = !host.is_empty()
+        && host.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-');
+    valid.then(|| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_urls_in_text() {
+        let text = "See https://arxiv.org/abs/2301.00001, and (http://example.com/a_(b)).\n\
+                    Also HTTPS://Docs.rs/serde? Not ftp://files.example.com or https:// alone.";
+
+        assert_eq!(
+            find_urls(text),
+            vec![
+                "https://arxiv.org/abs/2301.00001",
+                "http://example.com/a_(b)",
+                "HTTPS://Docs.rs/serde",
+            ]
+        );
+        assert!(find_urls("no links here").is_empty());
+    }
+
+    #[test]
+    fn test_link_domain() {
+        assert_eq!(link_domain("https://arxiv.org/abs/1").as_deref(), Some("arxiv.org"));
+        assert_eq!(link_domain("https://user@Export.arXiv.org:443/abs").as_deref(), Some("export.arxiv.org"));
+        assert_eq!(link_domain("http://example.com?q=1").as_deref(), Some("example.com"));
+        assert_eq!(link_domain("docs/setup.md"), None);
+        assert_eq!(link_domain("mailto:someone@example.com"), None);
+        assert_eq!(link_domain("https://"), None);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{CortexError, Result};
-use crate::indexer::extractors::ExtractedContent;
+use crate::indexer::extractors::{ExtractedContent, Extractor};
 use pulldown_cmark::{Event, Parser, Tag};
 use std::fs;
 use std::path::Path;
@@ -20,7 +20,20 @@ impl MarkdownExtractor {
         // Parse markdown and convert to plain text
         let text = Self::markdown_to_text(&markdown);
 
-        Ok(ExtractedContent::new(text))
+        let mut content = ExtractedContent::new(text);
+        content.add_links(Self::link_targets(&markdown));
+
+        Ok(content)
+    }
+
+    /// Destinations of the links and images in a markdown document
+    fn link_targets(markdown: &str) -> Vec<String> {
+        Parser::new(markdown)
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link(_, url, _) | Tag::Image(_, url, _)) => Some(url.to_string()),
+                _ => None,
+            })
+            .collect()
     }
 
     /// Convert markdown to plain text
@@ -90,6 +103,24 @@ impl MarkdownExtractor {
     }
 }
 
+impl Extractor for MarkdownExtractor {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn label(&self) -> &str {
+        "Markdown"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["md"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        MarkdownExtractor::extract(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,6 +190,23 @@ mod tests {
         // URL might or might not be included depending on parser
     }
 
+    #[test]
+    fn test_extract_markdown_links() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"Read [the paper](https://arxiv.org/abs/2301.00001) and \
+            [setup](docs/setup.md).\n\n![chart](https://example.com/chart.png)\n\n\
+            Mirror: https://export.arxiv.org/abs/2301.00001").unwrap();
+
+        let result = MarkdownExtractor::extract(file.path()).unwrap();
+
+        // Relative links aren't collected, and bare URLs are left to
+        // `ContentExtractor`, which finds them in every format
+        assert_eq!(
+            result.links,
+            vec!["https://arxiv.org/abs/2301.00001", "https://example.com/chart.png"]
+        );
+    }
+
     #[test]
     fn test_extract_empty_markdown() {
         let file = NamedTempFile::new().unwrap();
@@ -1,16 +1,46 @@
 // Content extraction module
 mod text;
 mod markdown;
+mod html;
+mod csv;
 mod docx;
 mod pdf;
+mod opendocument;
+mod code;
+mod links;
 
 pub use text::TextExtractor;
 pub use markdown::MarkdownExtractor;
+pub use html::HtmlExtractor;
+pub use csv::CsvExtractor;
 pub use docx::DocxExtractor;
 pub use pdf::PdfExtractor;
+pub use opendocument::OpenDocumentExtractor;
+pub use code::{module_doc, split_comments, CodeLanguage, CodeSplit};
+pub use links::{find_urls, link_domain};
 
 use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Sample paths kept per warning kind in a [`WarningSummary`]
+pub const WARNING_SAMPLE_PATHS: usize = 5;
+
+/// Version of the processing [`ContentExtractor`] applies on top of every
+/// extractor (link detection, module-doc summaries); part of each
+/// [`ExtractorRegistry::content_key`]
+pub const EXTRACTION_VERSION: u32 = 1;
+
+/// [`ExtractedContent::metadata`] key for the document's own title
+pub const METADATA_TITLE: &str = "title";
+
+/// [`ExtractedContent::metadata`] key for the document's author
+pub const METADATA_AUTHOR: &str = "author";
+
+/// [`ExtractedContent::metadata`] key for the number of pages
+pub const METADATA_PAGE_COUNT: &str = "page_count";
 
 /// Result of content extraction
 #[derive(Debug, Clone)]
@@ -25,7 +55,85 @@ pub struct ExtractedContent {
     pub summary: Option<String>,
 
     /// Any warnings encountered during extraction
-    pub warnings: Vec<String>,
+    pub warnings: Vec<ExtractionWarning>,
+
+    /// `http(s)` URLs the document references, each once, in order of
+    /// appearance
+    pub links: Vec<String>,
+
+    /// Document properties the format records, e.g. [`METADATA_TITLE`];
+    /// empty for formats without any
+    pub metadata: HashMap<String, String>,
+}
+
+/// What an extraction warning is about, for counting them across a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// Invalid byte sequences were replaced while decoding text
+    LossyDecoding,
+    /// The specialized extractor failed and the raw file was read as text
+    TextFallback,
+    Other,
+}
+
+/// A problem that didn't stop extraction but may have degraded the text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractionWarning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+/// Warnings of one kind across an indexing run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarningKindCount {
+    pub kind: WarningKind,
+    /// Files with at least one warning of this kind
+    pub files: usize,
+    /// Up to [`WARNING_SAMPLE_PATHS`] of those files, first seen first
+    pub sample_paths: Vec<String>,
+}
+
+/// Extraction warnings across an indexing run, counted by kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarningSummary {
+    /// Files with at least one warning
+    pub files_with_warnings: usize,
+    /// One entry per kind seen, in the order first seen
+    pub kinds: Vec<WarningKindCount>,
+}
+
+impl WarningSummary {
+    /// Count the `warnings` raised while extracting `path`
+    pub fn record(&mut self, path: &Path, warnings: &[ExtractionWarning]) {
+        if warnings.is_empty() {
+            return;
+        }
+        self.files_with_warnings += 1;
+
+        let mut kinds: Vec<WarningKind> = Vec::new();
+        for warning in warnings {
+            if !kinds.contains(&warning.kind) {
+                kinds.push(warning.kind);
+            }
+        }
+
+        for kind in kinds {
+            let index = match self.kinds.iter().position(|k| k.kind == kind) {
+                Some(index) => index,
+                None => {
+                    self.kinds.push(WarningKindCount { kind, files: 0, sample_paths: Vec::new() });
+                    self.kinds.len() - 1
+                }
+            };
+
+            let count = &mut self.kinds[index];
+            count.files += 1;
+            if count.sample_paths.len() < WARNING_SAMPLE_PATHS {
+                count.sample_paths.push(path.display().to_string());
+            }
+        }
+    }
 }
 
 impl ExtractedContent {
@@ -38,15 +146,33 @@ impl ExtractedContent {
             word_count,
             summary,
             warnings: Vec::new(),
+            links: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
-    pub fn with_warning(mut self, warning: String) -> Self {
-        self.warnings.push(warning);
+    pub fn with_warning(mut self, kind: WarningKind, message: String) -> Self {
+        self.warnings.push(ExtractionWarning { kind, message });
         self
     }
 
-    fn generate_summary(text: &str) -> Option<String> {
+    /// Add `urls` to [`links`](Self::links), skipping ones already there
+    /// and anything that isn't an `http(s)` URL
+    pub fn add_links<I, S>(&mut self, urls: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for url in urls {
+            let url = url.into();
+            if link_domain(&url).is_some() && !self.links.contains(&url) {
+                self.links.push(url);
+            }
+        }
+    }
+
+    /// Short summary of `text`: its first non-empty line, capped at 200 chars
+    pub fn generate_summary(text: &str) -> Option<String> {
         if text.is_empty() {
             return None;
         }
@@ -66,24 +192,248 @@ impl ExtractedContent {
     }
 }
 
+/// Options for [`ContentExtractor::extract_with_options`]
+///
+/// The index's [`CortexConfig`](crate::db::CortexConfig) holds the ones
+/// indexing starts from, which an indexing profile can override.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractOptions {
+    /// When the DOCX or PDF extractor fails, salvage whatever readable text
+    /// the raw bytes contain instead of failing the file
+    pub text_fallback: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            text_fallback: true,
+        }
+    }
+}
+
+/// Turns files of some extensions into text
+///
+/// Implementations are registered with [`ContentExtractor::register`] (or an
+/// [`ExtractorRegistry`]) and picked by file extension.
+pub trait Extractor: Send + Sync {
+    /// Short stable name, e.g. `pdf`
+    fn name(&self) -> &str;
+
+    /// Human-readable description of the formats handled
+    fn label(&self) -> &str;
+
+    /// Extensions handled, lowercase and without the dot
+    fn extensions(&self) -> &[&str];
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent>;
+
+    /// Bumped whenever the extractor's output changes, so content it
+    /// extracted earlier is no longer reused for unchanged files
+    fn version(&self) -> u32 {
+        1
+    }
+
+    /// Whether a failed file may still be indexed from the readable text in
+    /// its raw bytes (see [`ExtractOptions::text_fallback`])
+    fn text_fallback(&self) -> bool {
+        false
+    }
+}
+
+/// An extractor and the file extensions the scanner hands to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupportedFormat {
+    /// Extractor name, as in [`ContentExtractor::extractor_name`]
+    pub extractor: String,
+    pub label: String,
+    /// Lowercase, without the dot
+    pub extensions: Vec<String>,
+}
+
+/// Extractors by the extensions they handle, with the text extractor for
+/// everything else
+pub struct ExtractorRegistry {
+    /// In registration order, the fallback first
+    extractors: Vec<Arc<dyn Extractor>>,
+    by_extension: HashMap<String, Arc<dyn Extractor>>,
+    fallback: Arc<dyn Extractor>,
+}
+
+impl ExtractorRegistry {
+    /// Only the text fallback, for building a custom set
+    pub fn text_only() -> Self {
+        let fallback: Arc<dyn Extractor> = Arc::new(TextExtractor);
+        Self {
+            extractors: vec![fallback.clone()],
+            by_extension: HashMap::new(),
+            fallback,
+        }
+    }
+
+    /// The built-in extractors
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::text_only();
+        registry.register(MarkdownExtractor);
+        registry.register(HtmlExtractor);
+        registry.register(CsvExtractor);
+        registry.register(DocxExtractor);
+        registry.register(PdfExtractor);
+        registry.register(OpenDocumentExtractor);
+        registry
+    }
+
+    /// Handle `extractor`'s extensions with it, taking them over from any
+    /// extractor registered earlier
+    pub fn register(&mut self, extractor: impl Extractor + 'static) {
+        let extractor: Arc<dyn Extractor> = Arc::new(extractor);
+        for extension in extractor.extensions() {
+            self.by_extension.insert(extension.to_lowercase(), extractor.clone());
+        }
+        self.extractors.push(extractor);
+    }
+
+    /// The extractor for `path`'s extension
+    pub fn for_path(&self, path: &Path) -> Arc<dyn Extractor> {
+        let extension = path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|s| s.to_lowercase())
+            .unwrap_or_default();
+
+        self.by_extension.get(&extension).unwrap_or(&self.fallback).clone()
+    }
+
+    /// Identifies the extraction `path` would get: its extractor's name and
+    /// version, and [`EXTRACTION_VERSION`]
+    ///
+    /// Stored with extracted content; content stored under another key is
+    /// stale and must be extracted again.
+    pub fn content_key(&self, path: &Path) -> String {
+        let extractor = self.for_path(path);
+        format!("{}@{}.{}", extractor.name(), extractor.version(), EXTRACTION_VERSION)
+    }
+
+    /// Extract content from a file with the extractor for its extension
+    pub fn extract_with_options(&self, path: &Path, options: ExtractOptions) -> Result<ExtractedContent> {
+        ContentExtractor::extract_with(self.for_path(path).as_ref(), path, options)
+    }
+
+    /// Each extractor with the indexed extensions it handles
+    ///
+    /// The text extractor is listed with every extension the scanner
+    /// indexes by default that no other extractor claims.
+    pub fn supported_formats(&self) -> Vec<SupportedFormat> {
+        self.extractors
+            .iter()
+            .filter(|extractor| {
+                // Skip extractors whose extensions were all taken over
+                Arc::ptr_eq(extractor, &self.fallback)
+                    || self.by_extension.values().any(|handler| Arc::ptr_eq(handler, extractor))
+            })
+            .map(|extractor| {
+                let extensions = crate::indexer::scanner::SUPPORTED_EXTENSIONS
+                    .iter()
+                    .filter(|ext| Arc::ptr_eq(&self.for_path(Path::new(&format!("file.{}", ext))), extractor))
+                    .map(|ext| ext.to_string())
+                    .collect();
+
+                SupportedFormat {
+                    extractor: extractor.name().to_string(),
+                    label: extractor.label().to_string(),
+                    extensions,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// The registry behind [`ContentExtractor`]
+fn registry() -> &'static RwLock<ExtractorRegistry> {
+    static REGISTRY: OnceLock<RwLock<ExtractorRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(ExtractorRegistry::with_builtins()))
+}
+
 /// Main extractor that dispatches to appropriate sub-extractor
 pub struct ContentExtractor;
 
 impl ContentExtractor {
     /// Extract content from a file based on its extension
     pub fn extract(path: &Path) -> Result<ExtractedContent> {
-        let extension = path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|s| s.to_lowercase())
-            .unwrap_or_default();
+        Self::extract_with_options(path, ExtractOptions::default())
+    }
+
+    /// Extract content from a file based on its extension, with `options`
+    pub fn extract_with_options(path: &Path, options: ExtractOptions) -> Result<ExtractedContent> {
+        // Extract without holding the lock
+        let extractor = registry().read().unwrap().for_path(path);
+        Self::extract_with(extractor.as_ref(), path, options)
+    }
+
+    /// Add an extractor for every later extraction, e.g. from a plugin
+    ///
+    /// The scanner only hands it files whose extensions it indexes.
+    pub fn register(extractor: impl Extractor + 'static) {
+        registry().write().unwrap().register(extractor);
+    }
+
+    /// Name of the extractor that handles `path`, for diagnostics
+    pub fn extractor_name(path: &Path) -> String {
+        registry().read().unwrap().for_path(path).name().to_string()
+    }
+
+    /// [`ExtractorRegistry::content_key`] for `path`
+    pub fn content_key(path: &Path) -> String {
+        registry().read().unwrap().content_key(path)
+    }
+
+    /// Each extractor with the indexed extensions it handles
+    pub fn supported_formats() -> Vec<SupportedFormat> {
+        registry().read().unwrap().supported_formats()
+    }
 
-        match extension.as_str() {
-            "txt" => TextExtractor::extract(path),
-            "md" => MarkdownExtractor::extract(path),
-            "docx" => DocxExtractor::extract(path),
-            "pdf" => PdfExtractor::extract(path),
-            _ => TextExtractor::extract(path), // Fallback to text
+    fn extract_with(extractor: &dyn Extractor, path: &Path, options: ExtractOptions) -> Result<ExtractedContent> {
+        let mut content = match extractor.extract(path) {
+            Err(e) if options.text_fallback && extractor.text_fallback() => {
+                Self::salvage_text(path).ok_or(e)
+            }
+            result => result,
+        }?;
+
+        // Bare URLs in the text, whatever the format
+        let urls = find_urls(&content.text);
+        content.add_links(urls);
+
+        // A source file's module docs describe it better than its first line
+        let doc = CodeLanguage::from_path(path).and_then(|language| module_doc(&content.text, language));
+        if let Some(doc) = doc {
+            content.summary = ExtractedContent::generate_summary(&doc);
         }
+
+        Ok(content)
+    }
+
+    /// Read a file the specialized extractor rejected as plain text
+    ///
+    /// Returns `None` when nothing readable is found, e.g. because the bytes
+    /// are compressed or otherwise binary.
+    fn salvage_text(path: &Path) -> Option<ExtractedContent> {
+        let content = TextExtractor::extract(path).ok()?;
+
+        if content.text.trim().is_empty() || TextExtractor::looks_binary(&content.text) {
+            return None;
+        }
+
+        log::warn!("Indexed {} from salvaged plain text", path.display());
+        Some(content.with_warning(
+            WarningKind::TextFallback,
+            "Specialized extraction failed; indexed readable text from the raw file".to_string(),
+        ))
     }
 }
 
@@ -108,12 +458,142 @@ mod tests {
         assert!(summary.len() <= 203); // 200 + "..."
     }
 
+    #[test]
+    fn test_supported_formats_match_dispatch() {
+        let formats = ContentExtractor::supported_formats();
+
+        let extractor_for = |ext: &str| {
+            formats
+                .iter()
+                .find(|f| f.extensions.iter().any(|e| e == ext))
+                .map(|f| f.extractor.as_str())
+        };
+        assert_eq!(extractor_for("txt"), Some("text"));
+        assert_eq!(extractor_for("md"), Some("markdown"));
+        assert_eq!(extractor_for("htm"), Some("html"));
+        assert_eq!(extractor_for("tsv"), Some("csv"));
+        assert_eq!(extractor_for("pdf"), Some("pdf"));
+        assert_eq!(extractor_for("docx"), Some("docx"));
+        assert_eq!(extractor_for("rs"), Some("text"));
+
+        // Every registered extractor is listed, each extension once, and
+        // each under the extractor `extract` dispatches it to
+        assert_eq!(formats.len(), ExtractorRegistry::with_builtins().extractors.len());
+        let mut seen = std::collections::HashSet::new();
+        for format in &formats {
+            assert!(!format.extensions.is_empty(), "{} has no extensions", format.extractor);
+            assert!(!format.label.is_empty());
+            for ext in &format.extensions {
+                assert!(seen.insert(ext.clone()), "{} listed twice", ext);
+                let path = format!("file.{}", ext.to_uppercase());
+                assert_eq!(ContentExtractor::extractor_name(Path::new(&path)), format.extractor);
+            }
+        }
+    }
+
+    struct ShoutExtractor;
+
+    impl Extractor for ShoutExtractor {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn label(&self) -> &str {
+            "Shouted notes"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["shout", "md"]
+        }
+
+        fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+            let text = std::fs::read_to_string(path)?;
+            Ok(ExtractedContent::new(text.to_uppercase()))
+        }
+    }
+
+    #[test]
+    fn test_registered_extractor_is_dispatched_by_extension() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let notes = temp_dir.path().join("meeting.SHOUT");
+        std::fs::write(&notes, "ship it friday").unwrap();
+        let plain = temp_dir.path().join("meeting.txt");
+        std::fs::write(&plain, "ship it friday").unwrap();
+
+        // Unknown extensions fall back to text until someone claims them
+        let mut registry = ExtractorRegistry::with_builtins();
+        assert_eq!(registry.for_path(&notes).name(), "text");
+
+        registry.register(ShoutExtractor);
+        assert_eq!(registry.for_path(&notes).name(), "shout");
+        assert_eq!(registry.content_key(&notes), format!("shout@1.{}", EXTRACTION_VERSION));
+        let options = ExtractOptions::default();
+        assert_eq!(registry.extract_with_options(&notes, options).unwrap().text, "SHIP IT FRIDAY");
+        assert_eq!(registry.extract_with_options(&plain, options).unwrap().text, "ship it friday");
+
+        // It took over Markdown, which no longer handles anything
+        assert_eq!(registry.for_path(Path::new("a.md")).name(), "shout");
+        let formats = registry.supported_formats();
+        assert!(formats.iter().all(|f| f.extractor != "markdown"));
+        let shout = formats.iter().find(|f| f.extractor == "shout").unwrap();
+        assert_eq!(shout.extensions, vec!["md".to_string()]);
+    }
+
+    #[test]
+    fn test_broken_docx_falls_back_to_text() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        // Not a zip archive, but the text is readable
+        let readable = temp_dir.path().join("draft.docx");
+        std::fs::write(&readable, "Quarterly budget draft\nRevenue up in the north region").unwrap();
+
+        let content = ContentExtractor::extract(&readable).unwrap();
+        assert!(content.text.contains("Quarterly budget draft"));
+        assert_eq!(content.warnings.len(), 1);
+
+        let strict = ExtractOptions { text_fallback: false };
+        assert!(ContentExtractor::extract_with_options(&readable, strict).is_err());
+
+        // Binary garbage is still rejected
+        let garbage = temp_dir.path().join("corrupt.docx");
+        std::fs::write(&garbage, b"PK\x03\x04\x00\x00\xff\xfe\x00\x01").unwrap();
+        assert!(ContentExtractor::extract(&garbage).is_err());
+    }
+
+    #[test]
+    fn test_module_doc_becomes_code_summary() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let rust = temp_dir.path().join("limiter.rs");
+        std::fs::write(&rust, "// SPDX-License-Identifier: MIT\n\
+            //! Token-bucket rate limiting for the API gateway\n\
+            //!\n//! Buckets are kept per client.\n\
+            use std::time::Duration;\n").unwrap();
+        let content = ContentExtractor::extract(&rust).unwrap();
+        assert_eq!(content.summary.as_deref(), Some("Token-bucket rate limiting for the API gateway"));
+        // The text itself is unchanged
+        assert!(content.text.starts_with("// SPDX-License-Identifier: MIT"));
+
+        let python = temp_dir.path().join("resize.py");
+        std::fs::write(&python, "#!/usr/bin/env python3\n\"\"\"Resize product images in bulk.\n\n\
+            Usage: resize.py DIR\n\"\"\"\nimport sys\n").unwrap();
+        let content = ContentExtractor::extract(&python).unwrap();
+        assert_eq!(content.summary.as_deref(), Some("Resize product images in bulk."));
+
+        // Without module docs the first line is kept
+        let plain = temp_dir.path().join("main.py");
+        std::fs::write(&plain, "import sys\nprint(sys.argv)\n").unwrap();
+        let content = ContentExtractor::extract(&plain).unwrap();
+        assert_eq!(content.summary.as_deref(), Some("import sys"));
+    }
+
     #[test]
     fn test_with_warning() {
         let content = ExtractedContent::new("test".to_string())
-            .with_warning("Encoding issue".to_string());
+            .with_warning(WarningKind::LossyDecoding, "Encoding issue".to_string());
 
         assert_eq!(content.warnings.len(), 1);
-        assert_eq!(content.warnings[0], "Encoding issue");
+        assert_eq!(content.warnings[0].kind, WarningKind::LossyDecoding);
+        assert_eq!(content.warnings[0].message, "Encoding issue");
     }
 }
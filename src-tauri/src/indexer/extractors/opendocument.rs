@@ -0,0 +1,240 @@
+use crate::error::{CortexError, Result};
+use crate::indexer::extractors::{ExtractedContent, Extractor};
+use std::io::Read;
+use std::path::Path;
+
+/// Extractor for OpenDocument text and spreadsheet files (.odt, .ods)
+///
+/// Both are zip containers whose document body lives in `content.xml`.
+pub struct OpenDocumentExtractor;
+
+impl OpenDocumentExtractor {
+    /// Extract text content from an OpenDocument file
+    ///
+    /// Fails with `ExtractionFailed` if the file can't be read or isn't a
+    /// valid OpenDocument archive.
+    pub fn extract(path: &Path) -> Result<ExtractedContent> {
+        let content = Self::try_extract(path);
+        if let Err(e) = &content {
+            log::warn!("OpenDocument extraction failed: {}", e);
+        }
+        content
+    }
+
+    fn try_extract(path: &Path) -> Result<ExtractedContent> {
+        let failed = |error: String| CortexError::ExtractionFailed {
+            path: path.to_string_lossy().to_string(),
+            error,
+        };
+
+        let file = std::fs::File::open(path).map_err(|e| failed(format!("Failed to read file: {}", e)))?;
+
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| failed(format!("Not a valid OpenDocument archive: {}", e)))?;
+
+        let mut xml = String::new();
+        archive
+            .by_name("content.xml")
+            .map_err(|e| failed(format!("Missing content.xml: {}", e)))?
+            .read_to_string(&mut xml)
+            .map_err(|e| failed(format!("Failed to read content.xml: {}", e)))?;
+
+        Ok(ExtractedContent::new(Self::xml_to_text(&xml)))
+    }
+
+    /// Strip tags from `content.xml`, keeping paragraph and cell boundaries
+    ///
+    /// Only text inside paragraphs and headings is kept, so indentation
+    /// between elements is dropped. Paragraphs, headings and table rows end a
+    /// line; table and spreadsheet cells are separated by tabs.
+    fn xml_to_text(xml: &str) -> String {
+        let mut text = String::new();
+        let mut rest = xml;
+        let mut paragraph_depth = 0usize;
+        let mut in_cell = false;
+
+        while let Some(start) = rest.find('<') {
+            if paragraph_depth > 0 {
+                text.push_str(&decode_entities(&rest[..start]));
+            }
+
+            let Some(end) = rest[start..].find('>') else {
+                break;
+            };
+            let tag = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+
+            let name = tag
+                .trim_end_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or("");
+
+            let self_closing = tag.ends_with('/');
+
+            match name {
+                "text:p" | "text:h" if !self_closing => paragraph_depth += 1,
+                "/text:p" | "/text:h" => {
+                    paragraph_depth = paragraph_depth.saturating_sub(1);
+                    // Paragraphs within a cell stay on the row's line
+                    text.push(if in_cell { ' ' } else { '\n' });
+                }
+                "text:p" | "text:h" if !in_cell => text.push('\n'),
+                "table:table-cell" if !self_closing => in_cell = true,
+                "/table:table-cell" => {
+                    in_cell = false;
+                    text.truncate(text.trim_end_matches(' ').len());
+                    text.push('\t');
+                }
+                "table:table-cell" => text.push('\t'),
+                "/table:table-row" | "text:line-break" => text.push('\n'),
+                "text:tab" => text.push('\t'),
+                "text:s" => text.push(' '),
+                _ => {}
+            }
+        }
+
+        // Drop the trailing tab of each row and surrounding blank lines
+        text.lines()
+            .map(|line| line.trim_end_matches('\t'))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+}
+
+/// Decode the predefined XML entities and numeric character references
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+
+        match replacement {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+impl Extractor for OpenDocumentExtractor {
+    fn name(&self) -> &str {
+        "opendocument"
+    }
+
+    fn label(&self) -> &str {
+        "OpenDocument (.odt, .ods)"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["odt", "ods"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        OpenDocumentExtractor::extract(path)
+    }
+
+    fn text_fallback(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_odf(path: &Path, content_xml: &str) {
+        let file = std::fs::File::create(path).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::FileOptions::default();
+
+        zip.start_file("mimetype", options).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.text").unwrap();
+        zip.start_file("content.xml", options).unwrap();
+        zip.write_all(content_xml.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_nonexistent_odt() {
+        let result = OpenDocumentExtractor::extract(Path::new("/nonexistent/file.odt"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_extract_minimal_odt_and_ods() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let odt = temp_dir.path().join("notes.odt");
+        write_odf(&odt, r#"<?xml version="1.0" encoding="UTF-8"?>
+            <office:document-content><office:body><office:text>
+            <text:h text:outline-level="1">Meeting notes</text:h>
+            <text:p>Budget &amp; timeline<text:s/>agreed.</text:p>
+            <text:p>Next<text:tab/>steps</text:p>
+            </office:text></office:body></office:document-content>"#);
+
+        let content = OpenDocumentExtractor::extract(&odt).unwrap();
+        assert_eq!(content.text, "Meeting notes\nBudget & timeline agreed.\nNext\tsteps");
+        assert_eq!(content.summary.as_deref(), Some("Meeting notes"));
+
+        let ods = temp_dir.path().join("sheet.ods");
+        write_odf(&ods, r#"<office:spreadsheet><table:table table:name="Sheet1">
+            <table:table-row><table:table-cell><text:p>Item</text:p></table:table-cell><table:table-cell><text:p>Cost</text:p></table:table-cell></table:table-row>
+            <table:table-row><table:table-cell><text:p>Paper</text:p></table:table-cell><table:table-cell><text:p>&#36;4</text:p></table:table-cell></table:table-row>
+            </table:table></office:spreadsheet>"#);
+
+        let content = OpenDocumentExtractor::extract(&ods).unwrap();
+        assert_eq!(content.text, "Item\tCost\nPaper\t$4");
+    }
+
+    #[test]
+    fn test_malformed_odt_fails() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("broken.odt");
+        std::fs::write(&path, b"not a zip archive").unwrap();
+
+        match OpenDocumentExtractor::extract(&path) {
+            // The cause is reported once, not wrapped in a second failure
+            Err(CortexError::ExtractionFailed { error, .. }) => {
+                assert!(error.starts_with("Not a valid OpenDocument archive"), "{}", error);
+            }
+            other => panic!("expected ExtractionFailed, got {:?}", other.map(|c| c.text)),
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crate::error::{CortexError, Result};
-use crate::indexer::extractors::ExtractedContent;
+use crate::indexer::extractors::{
+    ExtractedContent, Extractor, METADATA_AUTHOR, METADATA_PAGE_COUNT, METADATA_TITLE,
+};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Extractor for PDF files
@@ -23,16 +26,84 @@ impl PdfExtractor {
     }
 
     fn try_extract(path: &Path) -> Result<ExtractedContent> {
-        // Extract text from PDF
-        let text = pdf_extract::extract_text(path).map_err(|e| CortexError::ExtractionFailed {
+        let parse_error = |e: &dyn std::fmt::Display| CortexError::ExtractionFailed {
             path: path.to_string_lossy().to_string(),
             error: format!("Failed to parse PDF: {}", e),
-        })?;
+        };
+
+        // Load once for both the text and the link annotations
+        let mut doc = pdf_extract::Document::load(path).map_err(|e| parse_error(&e))?;
+        if doc.is_encrypted() {
+            // Only documents with an empty user password can be read
+            doc.decrypt("").map_err(|e| parse_error(&e))?;
+        }
+
+        // Extract text from PDF
+        let mut text = String::new();
+        pdf_extract::output_doc(&doc, &mut pdf_extract::PlainTextOutput::new(&mut text))
+            .map_err(|e| parse_error(&e))?;
 
         // Clean up extracted text (PDFs often have extra whitespace)
         let cleaned = Self::clean_pdf_text(&text);
 
-        Ok(ExtractedContent::new(cleaned))
+        let mut content = ExtractedContent::new(cleaned);
+        content.add_links(Self::annotation_links(&doc));
+        content.metadata = Self::document_metadata(&doc);
+
+        Ok(content)
+    }
+
+    /// Title and author from the document information dictionary, and the
+    /// page count
+    fn document_metadata(doc: &pdf_extract::Document) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        metadata.insert(METADATA_PAGE_COUNT.to_string(), doc.get_pages().len().to_string());
+
+        let info = doc.trailer.get_deref(b"Info", doc).and_then(|info| info.as_dict());
+        if let Ok(info) = info {
+            for (key, name) in [(METADATA_TITLE, b"Title".as_slice()), (METADATA_AUTHOR, b"Author")] {
+                let value = info.get_deref(name, doc).and_then(|value| value.as_str());
+                if let Ok(value) = value {
+                    let value = Self::decode_text_string(value);
+                    let value = value.trim();
+                    if !value.is_empty() {
+                        metadata.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+        }
+
+        metadata
+    }
+
+    /// Decode a PDF text string: UTF-16BE or UTF-8 with a byte order mark,
+    /// otherwise PDFDocEncoding, read as Latin-1 (they differ only in rarely
+    /// used punctuation)
+    fn decode_text_string(bytes: &[u8]) -> String {
+        if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            String::from_utf8_lossy(utf8).into_owned()
+        } else {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+    }
+
+    /// Targets of the URI link annotations on every page
+    fn annotation_links(doc: &pdf_extract::Document) -> Vec<String> {
+        doc.get_pages()
+            .into_values()
+            .flat_map(|page_id| doc.get_page_annotations(page_id).unwrap_or_default())
+            .filter_map(|annotation| {
+                let action = annotation.get_deref(b"A", doc).and_then(|a| a.as_dict()).ok()?;
+                let uri = action.get_deref(b"URI", doc).and_then(|uri| uri.as_str()).ok()?;
+                Some(String::from_utf8_lossy(uri).into_owned())
+            })
+            .collect()
     }
 
     /// Clean up PDF text extraction artifacts
@@ -45,6 +116,28 @@ impl PdfExtractor {
     }
 }
 
+impl Extractor for PdfExtractor {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn label(&self) -> &str {
+        "PDF documents"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pdf"]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        PdfExtractor::extract(path)
+    }
+
+    fn text_fallback(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,6 +164,70 @@ mod tests {
         assert_eq!(cleaned, "Content\nMore content");
     }
 
-    // Note: Testing PDF extraction requires actual PDF files
-    // In a real project, you would include test fixtures
+    #[test]
+    fn test_decode_text_string() {
+        assert_eq!(PdfExtractor::decode_text_string(b"Caf\xe9 notes"), "Café notes");
+        assert_eq!(PdfExtractor::decode_text_string(b"\xFE\xFF\x00H\x00i\x20\x14"), "Hi—");
+        assert_eq!(PdfExtractor::decode_text_string("\u{feff}Ünïcode".as_bytes()), "Ünïcode");
+    }
+
+    /// A one-page PDF saying "Hello PDF", with an information dictionary
+    fn write_pdf(path: &Path, info: Option<pdf_extract::Dictionary>) {
+        use pdf_extract::{dictionary, Document, Object, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let content = b"BT /F1 24 Tf 72 720 Td (Hello PDF) Tj ET".to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        if let Some(info) = info {
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", info_id);
+        }
+
+        doc.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_extract_pdf_metadata() {
+        use pdf_extract::{dictionary, Object, StringFormat};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("paper.pdf");
+        write_pdf(&path, Some(dictionary! {
+            "Title" => Object::String(b"Attention Is All You Need".to_vec(), StringFormat::Literal),
+            "Author" => Object::String(b"  ".to_vec(), StringFormat::Literal),
+        }));
+
+        let result = PdfExtractor::extract(&path).unwrap();
+
+        assert!(result.text.contains("Hello PDF"));
+        assert_eq!(result.metadata.get(METADATA_TITLE).map(String::as_str), Some("Attention Is All You Need"));
+        assert_eq!(result.metadata.get(METADATA_PAGE_COUNT).map(String::as_str), Some("1"));
+        // Blank fields are left out
+        assert!(!result.metadata.contains_key(METADATA_AUTHOR));
+
+        let untitled = temp_dir.path().join("untitled.pdf");
+        write_pdf(&untitled, None);
+        let result = PdfExtractor::extract(&untitled).unwrap();
+        assert_eq!(result.metadata.len(), 1);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::error::{CortexError, Result};
-use crate::indexer::extractors::ExtractedContent;
+use crate::indexer::extractors::{ExtractedContent, Extractor, WarningKind};
 use encoding_rs::Encoding;
 use std::fs;
 use std::path::Path;
@@ -23,7 +23,7 @@ impl TextExtractor {
 
         // Add warning if we had to fallback to lossy conversion
         if had_errors {
-            content = content.with_warning(format!(
+            content = content.with_warning(WarningKind::LossyDecoding, format!(
                 "File decoded as {} with replacement characters for invalid sequences",
                 encoding_used.name()
             ));
@@ -32,6 +32,27 @@ impl TextExtractor {
         Ok(content)
     }
 
+    /// Whether decoded text looks like binary data rather than readable text
+    ///
+    /// True when it contains NUL characters or when more than a tenth of its
+    /// characters are control characters or decoding replacements.
+    pub fn looks_binary(text: &str) -> bool {
+        let mut total = 0usize;
+        let mut unreadable = 0usize;
+
+        for c in text.chars() {
+            if c == '\0' {
+                return true;
+            }
+            total += 1;
+            if c == char::REPLACEMENT_CHARACTER || (c.is_control() && !c.is_whitespace()) {
+                unreadable += 1;
+            }
+        }
+
+        unreadable * 10 > total
+    }
+
     /// Detect encoding and decode bytes to string
     fn decode_with_detection(bytes: &[u8]) -> (String, &'static Encoding, bool) {
         // Check for BOM first
@@ -53,6 +74,25 @@ impl TextExtractor {
     }
 }
 
+impl Extractor for TextExtractor {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn label(&self) -> &str {
+        "Plain text and source code"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        // Also the fallback for every extension without its own extractor
+        &[]
+    }
+
+    fn extract(&self, path: &Path) -> Result<ExtractedContent> {
+        TextExtractor::extract(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +139,14 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_looks_binary() {
+        assert!(!TextExtractor::looks_binary("Plain text\twith tabs\nand lines"));
+        assert!(!TextExtractor::looks_binary(""));
+        assert!(TextExtractor::looks_binary("PK\u{3}\u{4}\0\0header"));
+        assert!(TextExtractor::looks_binary(&"\u{FFFD}\u{1}ab".repeat(10)));
+    }
+
     #[test]
     fn test_encoding_detection() {
         // Test UTF-8
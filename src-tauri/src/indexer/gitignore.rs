@@ -0,0 +1,245 @@
+//! `.gitignore` matching for the scanner
+//!
+//! Follows git's rules closely enough for indexing: each `.gitignore`
+//! applies to the directory it is in and everything below, deeper files
+//! override shallower ones, the last matching pattern wins and `!`
+//! re-includes. An ignored directory is never entered, so nothing inside it
+//! can be re-included, as in git. Only `.gitignore` files inside the scanned
+//! root are read.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-directory ignore file
+pub const GITIGNORE_FILE: &str = ".gitignore";
+
+/// One pattern line of an ignore file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnoreRule {
+    glob: Vec<char>,
+    /// `!pattern`: re-include what earlier patterns excluded
+    negated: bool,
+    /// `pattern/`: only matches directories
+    dir_only: bool,
+    /// Contains a `/`, so it matches the path from the ignore file's
+    /// directory rather than just the name
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /// Parse one line, or `None` for blanks and comments
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!'])).unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            glob: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule matches `relative`, a `/`-separated path from the
+    /// ignore file's directory
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let target = if self.anchored {
+            relative
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        let target: Vec<char> = target.chars().collect();
+        glob_match(&self.glob, &target)
+    }
+}
+
+/// Match `text` against a gitignore glob
+///
+/// `*` and `?` stay within one path segment, `**` crosses segments (and
+/// `**/` may match no directories at all), and `[...]` is a character class.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|skip| glob_match(rest, &text[skip..]))
+        }
+        Some('*') => {
+            let rest = &glob[1..];
+            for skip in 0..=text.len() {
+                if glob_match(rest, &text[skip..]) {
+                    return true;
+                }
+                if text.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => text.first().is_some_and(|&c| c != '/') && glob_match(&glob[1..], &text[1..]),
+        Some('[') => match (class_match(&glob[1..], text.first().copied()), text.first()) {
+            (Some((true, len)), Some(_)) => glob_match(&glob[1 + len..], &text[1..]),
+            (Some(_), _) => false,
+            // No closing `]`: a literal `[`
+            (None, Some('[')) => glob_match(&glob[1..], &text[1..]),
+            (None, _) => false,
+        },
+        Some('\\') if glob.len() > 1 => text.first() == Some(&glob[1]) && glob_match(&glob[2..], &text[1..]),
+        Some(&c) => text.first() == Some(&c) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+/// Whether `c` is in the class starting just after a `[`, and the class's
+/// length including its `]`; `None` if the class is never closed
+fn class_match(class: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let (negated, start) = match class.first() {
+        Some('!' | '^') => (true, 1),
+        _ => (false, 0),
+    };
+
+    let mut matched = false;
+    let mut i = start;
+    while i < class.len() {
+        if class[i] == ']' && i > start {
+            let matched = c.is_some_and(|c| c != '/') && matched != negated;
+            return Some((matched, i + 1));
+        }
+        if class.get(i + 1) == Some(&'-') && class.get(i + 2).is_some_and(|&end| end != ']') {
+            matched |= c.is_some_and(|c| class[i] <= c && c <= class[i + 2]);
+            i += 3;
+        } else {
+            matched |= c == Some(class[i]);
+            i += 1;
+        }
+    }
+    None
+}
+
+fn parse_rules(text: &str) -> Vec<IgnoreRule> {
+    text.lines().filter_map(IgnoreRule::parse).collect()
+}
+
+/// Decides which entries of one scan are ignored
+///
+/// Ignore files are read once per directory and cached for the scan.
+pub struct IgnoreMatcher {
+    root: PathBuf,
+    respect_gitignore: bool,
+    /// Ad-hoc patterns, relative to the root and applied after every
+    /// ignore file
+    extra: Vec<IgnoreRule>,
+    gitignores: HashMap<PathBuf, Vec<IgnoreRule>>,
+}
+
+impl IgnoreMatcher {
+    pub fn new<S: AsRef<str>>(root: &Path, respect_gitignore: bool, patterns: &[S]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            respect_gitignore,
+            extra: patterns.iter().filter_map(|p| IgnoreRule::parse(p.as_ref())).collect(),
+            gitignores: HashMap::new(),
+        }
+    }
+
+    /// Whether `path`, somewhere under the root, is ignored
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let segments: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if segments.is_empty() {
+            return false;
+        }
+
+        let mut ignored = false;
+        let mut apply = |rules: &[IgnoreRule], relative: &str| {
+            for rule in rules {
+                if rule.matches(relative, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        };
+
+        if self.respect_gitignore {
+            // Ignore files from the root down to the entry's own directory
+            let mut dir = self.root.clone();
+            for depth in 0..segments.len() {
+                if depth > 0 {
+                    dir.push(&segments[depth - 1]);
+                }
+                let rules = self.gitignores.entry(dir.clone()).or_insert_with(|| {
+                    std::fs::read_to_string(dir.join(GITIGNORE_FILE))
+                        .map(|text| parse_rules(&text))
+                        .unwrap_or_default()
+                });
+                apply(rules, &segments[depth..].join("/"));
+            }
+        }
+        apply(&self.extra, &segments.join("/"));
+
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+        IgnoreRule::parse(pattern).unwrap().matches(path, is_dir)
+    }
+
+    #[test]
+    fn test_gitignore_patterns() {
+        // Unanchored patterns match the name at any depth
+        assert!(matches("*.log", "logs/today/app.log", false));
+        assert!(!matches("*.log", "app.log.txt", false));
+        assert!(matches("generated", "src/generated", true));
+
+        // A slash anchors to the ignore file's directory
+        assert!(matches("/build.txt", "build.txt", false));
+        assert!(!matches("/build.txt", "sub/build.txt", false));
+        assert!(matches("docs/*.md", "docs/a.md", false));
+        assert!(!matches("docs/*.md", "docs/sub/a.md", false));
+
+        // `**` crosses directories, possibly none
+        assert!(matches("docs/**/a.md", "docs/a.md", false));
+        assert!(matches("docs/**/a.md", "docs/x/y/a.md", false));
+        assert!(matches("**/cache", "deep/down/cache", true));
+
+        // Directory-only patterns, classes and escapes
+        assert!(!matches("out/", "out", false));
+        assert!(matches("out/", "out", true));
+        assert!(matches("file[0-9].txt", "file7.txt", false));
+        assert!(!matches("file[!0-9].txt", "file7.txt", false));
+        assert!(matches("\\#notes", "#notes", false));
+        assert!(IgnoreRule::parse("# comment").is_none());
+        assert!(IgnoreRule::parse("   ").is_none());
+    }
+}
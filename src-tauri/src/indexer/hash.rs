@@ -0,0 +1,48 @@
+use crate::error::{CortexError, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Compute the blake3 hash of a file's bytes as a lowercase hex string
+///
+/// Streams the file so large documents are not loaded into memory at once.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let file = File::open(path).map_err(|e| CortexError::Internal {
+        message: format!("Failed to open {} for hashing: {}", path.display(), e),
+    })?;
+
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut BufReader::new(file), &mut hasher).map_err(|e| CortexError::Internal {
+        message: format!("Failed to hash {}: {}", path.display(), e),
+    })?;
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_identical_content_same_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a.txt");
+        let b = temp_dir.path().join("b.md");
+        let c = temp_dir.path().join("c.txt");
+        std::fs::write(&a, "same bytes").unwrap();
+        std::fs::write(&b, "same bytes").unwrap();
+        std::fs::write(&c, "other bytes").unwrap();
+
+        let hash_a = hash_file(&a).unwrap();
+        assert_eq!(hash_a.len(), 64);
+        assert_eq!(hash_a, hash_file(&b).unwrap());
+        assert_ne!(hash_a, hash_file(&c).unwrap());
+    }
+
+    #[test]
+    fn test_missing_file_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(hash_file(&temp_dir.path().join("missing.txt")).is_err());
+    }
+}
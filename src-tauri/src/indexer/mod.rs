@@ -3,8 +3,13 @@ pub mod scanner;
 pub mod types;
 pub mod watcher;
 pub mod extractors;
+pub mod hash;
+pub mod profiles;
+pub mod gitignore;
 
 pub use scanner::*;
 pub use types::*;
 pub use watcher::*;
 pub use extractors::*;
+pub use hash::*;
+pub use profiles::*;
@@ -0,0 +1,425 @@
+//! Indexing profiles
+//!
+//! A profile is a named set of scanner, extractor and embedding settings, so
+//! a code repository and a documents folder can be indexed in one run with
+//! different extensions, size limits and extraction. Profiles are stored in
+//! the index's [`CortexConfig`](crate::db::CortexConfig); an [`IndexTarget`]
+//! names the one to use for its root. The roots indexed under a profile are
+//! recorded too, so files indexed or embedded later keep its settings.
+
+use crate::db::{get_config, get_profile_roots};
+use crate::error::{CortexError, Result};
+use crate::indexer::ExtractOptions;
+use crate::indexer::scanner::FileScanner;
+use crate::paths::{normalize_root, path_key};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Named indexing settings; unset fields keep the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexingProfile {
+    pub name: String,
+    /// Extensions to index instead of the built-in list; `*` allows any
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+    /// Largest file to index, in bytes
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Index dot-prefixed files and directories
+    #[serde(default)]
+    pub index_hidden: Option<bool>,
+    /// Salvage readable text from DOCX and PDF files their extractor can't
+    /// read (see [`ExtractOptions::text_fallback`])
+    #[serde(default)]
+    pub text_fallback: Option<bool>,
+    /// Generate embeddings for the root's files when embedding in bulk
+    #[serde(default)]
+    pub embed: Option<bool>,
+}
+
+impl IndexingProfile {
+    /// `scanner` with this profile's settings
+    pub fn apply(&self, mut scanner: FileScanner) -> FileScanner {
+        if let Some(extensions) = &self.extensions {
+            scanner = scanner.with_extensions(extensions);
+        }
+        if let Some(size) = self.max_file_size {
+            scanner = scanner.with_max_file_size(size);
+        }
+        if let Some(index_hidden) = self.index_hidden {
+            scanner = scanner.with_index_hidden(index_hidden);
+        }
+        scanner
+    }
+
+    /// `options` with this profile's extractor settings
+    pub fn extract_options(&self, mut options: ExtractOptions) -> ExtractOptions {
+        if let Some(text_fallback) = self.text_fallback {
+            options.text_fallback = text_fallback;
+        }
+        options
+    }
+
+    /// Whether bulk embedding covers the root's files
+    pub fn embeds(&self) -> bool {
+        self.embed.unwrap_or(true)
+    }
+}
+
+/// A directory to index, optionally under a named profile
+///
+/// Deserializes from a bare path string too, which uses the default
+/// settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "IndexTargetInput")]
+pub struct IndexTarget {
+    pub path: String,
+    pub profile: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexTargetInput {
+    Path(String),
+    Target { path: String, profile: Option<String> },
+}
+
+impl From<IndexTargetInput> for IndexTarget {
+    fn from(input: IndexTargetInput) -> Self {
+        match input {
+            IndexTargetInput::Path(path) => Self { path, profile: None },
+            IndexTargetInput::Target { path, profile } => Self { path, profile },
+        }
+    }
+}
+
+/// A root to scan and the profile it's scanned with
+#[derive(Debug, Clone)]
+pub struct PlannedRoot {
+    pub path: PathBuf,
+    pub profile: IndexingProfile,
+    /// Roots inside this one that have a profile of their own, and are left
+    /// to their own scan
+    pub nested: Vec<PathBuf>,
+}
+
+impl PlannedRoot {
+    /// `scanner` with this root's profile, skipping its nested roots
+    pub fn scanner(&self, scanner: FileScanner) -> FileScanner {
+        let nested: Vec<String> = self
+            .nested
+            .iter()
+            .filter_map(|nested| nested.strip_prefix(&self.path).ok())
+            .map(|relative| {
+                let segments: Vec<String> = relative
+                    .components()
+                    .map(|segment| escape_glob(&segment.as_os_str().to_string_lossy()))
+                    .collect();
+                format!("/{}/", segments.join("/"))
+            })
+            .collect();
+        self.profile.apply(scanner).with_additional_ignore_patterns(&nested)
+    }
+}
+
+/// `text` as a gitignore pattern matching only itself
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Roots to scan, each with the profile of the target it came from
+#[derive(Debug, Default)]
+pub struct PlannedRoots {
+    pub roots: Vec<PlannedRoot>,
+    /// Inputs that couldn't be resolved, with the reason
+    pub unresolved: Vec<(String, std::io::Error)>,
+}
+
+/// Resolve `targets` against `profiles` and collapse overlapping roots
+///
+/// Each root keeps the profile of the target it came from. A root inside
+/// another is dropped when both use the same profile, as
+/// [`normalize_roots`](crate::paths::normalize_roots) does; with a different
+/// profile the most specific root wins, and the outer root's scan skips it.
+/// Fails if a target names a profile that doesn't exist.
+pub fn plan_roots(targets: &[IndexTarget], profiles: &[IndexingProfile]) -> Result<PlannedRoots> {
+    let mut resolved = Vec::with_capacity(targets.len());
+    for target in targets {
+        let profile = match &target.profile {
+            Some(name) => profiles
+                .iter()
+                .find(|profile| &profile.name == name)
+                .cloned()
+                .ok_or_else(|| CortexError::InvalidInput {
+                    message: format!("Unknown indexing profile: {}", name),
+                })?,
+            None => IndexingProfile::default(),
+        };
+        resolved.push((target, profile));
+    }
+
+    let mut planned = PlannedRoots::default();
+    let mut candidates = Vec::with_capacity(resolved.len());
+    for (target, profile) in resolved {
+        match normalize_root(&target.path) {
+            Ok(path) => candidates.push((key(&path), PlannedRoot { path, profile, nested: Vec::new() })),
+            Err(e) => planned.unresolved.push((target.path.clone(), e)),
+        }
+    }
+
+    // Outermost first, so each root meets the roots containing it; the sort
+    // is stable, so of exact duplicates the first is kept
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by_key(|&i| candidates[i].0.components().count());
+
+    let mut kept: Vec<usize> = Vec::new();
+    for i in order {
+        let root_key = &candidates[i].0;
+        let innermost = kept
+            .iter()
+            .copied()
+            .filter(|&j| root_key.starts_with(&candidates[j].0))
+            .max_by_key(|&j| candidates[j].0.components().count());
+
+        match innermost {
+            Some(j) if candidates[j].0 == *root_key || candidates[j].1.profile.name == candidates[i].1.profile.name => {
+                log::info!(
+                    "Skipping root {} already covered by another root",
+                    candidates[i].1.path.display()
+                );
+            }
+            Some(j) => {
+                let nested = candidates[i].1.path.clone();
+                candidates[j].1.nested.push(nested);
+                kept.push(i);
+            }
+            None => kept.push(i),
+        }
+    }
+
+    kept.sort_unstable();
+    let mut candidates: Vec<Option<PlannedRoot>> = candidates.into_iter().map(|(_, root)| Some(root)).collect();
+    planned.roots = kept.into_iter().filter_map(|i| candidates[i].take()).collect();
+    Ok(planned)
+}
+
+/// Key comparing roots the way the index compares paths
+fn key(path: &Path) -> PathBuf {
+    PathBuf::from(path_key(&path.to_string_lossy()))
+}
+
+/// A root last indexed under a named profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileRoot {
+    pub path: PathBuf,
+    pub profile: String,
+}
+
+/// Finds the profile a file was indexed under, and the settings it gets
+#[derive(Debug, Default)]
+pub struct ProfileRoots {
+    roots: Vec<(PathBuf, IndexingProfile)>,
+    /// Extractor options of files no profile overrides
+    base: ExtractOptions,
+}
+
+impl ProfileRoots {
+    /// The profiles of a planned run, over the index's `base` extractor
+    /// options
+    pub fn from_planned(planned: &[PlannedRoot], base: ExtractOptions) -> Self {
+        Self {
+            roots: planned.iter().map(|root| (key(&root.path), root.profile.clone())).collect(),
+            base,
+        }
+    }
+
+    /// The profiles recorded in the index, over its configured extractor
+    /// options; a root whose profile has since been deleted uses those
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let config = get_config(conn)?;
+        let roots = get_profile_roots(conn)?
+            .into_iter()
+            .filter_map(|root| {
+                let profile = config.indexing_profiles.iter().find(|profile| profile.name == root.profile)?;
+                Some((key(&root.path), profile.clone()))
+            })
+            .collect();
+        Ok(Self { roots, base: config.extraction })
+    }
+
+    /// Profile of the most specific root containing `path`, if any
+    pub fn profile_for(&self, path: &Path) -> Option<&IndexingProfile> {
+        let path = key(path);
+        self.roots
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, profile)| profile)
+    }
+
+    /// Extractor options for `path`: the base ones, with whatever its
+    /// profile overrides
+    pub fn extract_options(&self, path: &Path) -> ExtractOptions {
+        match self.profile_for(path) {
+            Some(profile) => profile.extract_options(self.base),
+            None => self.base,
+        }
+    }
+
+    /// Whether bulk embedding covers `path`
+    pub fn embeds(&self, path: &Path) -> bool {
+        self.profile_for(path).is_none_or(IndexingProfile::embeds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roots_scan_with_their_own_profiles() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let code = temp_dir.path().join("repo");
+        let docs = temp_dir.path().join("docs");
+        std::fs::create_dir_all(&code).unwrap();
+        std::fs::create_dir_all(&docs).unwrap();
+
+        for dir in [&code, &docs] {
+            std::fs::write(dir.join("small.rs"), "fn main() {}").unwrap();
+            std::fs::write(dir.join("big.rs"), "x".repeat(5_000)).unwrap();
+            std::fs::write(dir.join("small.md"), "# Notes").unwrap();
+            std::fs::write(dir.join("big.md"), "x".repeat(5_000)).unwrap();
+        }
+
+        let profiles = vec![
+            IndexingProfile {
+                name: "code".to_string(),
+                extensions: Some(vec!["rs".to_string()]),
+                max_file_size: Some(1_000),
+                ..Default::default()
+            },
+            IndexingProfile {
+                name: "docs".to_string(),
+                extensions: Some(vec!["md".to_string()]),
+                max_file_size: Some(100_000),
+                ..Default::default()
+            },
+        ];
+
+        // Targets arrive as JSON, bare paths included
+        let targets: Vec<IndexTarget> = serde_json::from_value(serde_json::json!([
+            { "path": code.to_string_lossy(), "profile": "code" },
+            { "path": docs.to_string_lossy(), "profile": "docs" },
+        ]))
+        .unwrap();
+
+        let planned = plan_roots(&targets, &profiles).unwrap();
+        assert_eq!(planned.roots.len(), 2);
+
+        let mut found: Vec<String> = Vec::new();
+        for root in &planned.roots {
+            let scanner = root.scanner(FileScanner::new().with_progress_count(false));
+            for job in scanner.scan_directory(&root.path).unwrap() {
+                let dir = root.path.file_name().unwrap().to_string_lossy();
+                found.push(format!("{}/{}", dir, job.path.file_name().unwrap().to_string_lossy()));
+            }
+        }
+        found.sort();
+        assert_eq!(found, vec!["docs/big.md", "docs/small.md", "repo/small.rs"]);
+
+        // Bare paths use the defaults; unknown profiles are refused
+        let bare: Vec<IndexTarget> = serde_json::from_value(serde_json::json!([code.to_string_lossy()])).unwrap();
+        assert_eq!(bare[0].profile, None);
+        assert_eq!(plan_roots(&bare, &profiles).unwrap().roots[0].profile, IndexingProfile::default());
+
+        let unknown = vec![IndexTarget { path: code.to_string_lossy().into_owned(), profile: Some("media".to_string()) }];
+        assert!(plan_roots(&unknown, &profiles).is_err());
+    }
+
+    #[test]
+    fn test_nested_root_keeps_its_own_profile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let docs = repo.join("docs [draft]");
+        let notes = docs.join("notes");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(repo.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(docs.join("guide.md"), "# Guide").unwrap();
+        std::fs::write(docs.join("lib.rs"), "pub fn f() {}").unwrap();
+        std::fs::write(notes.join("todo.md"), "- ship").unwrap();
+
+        let profiles = vec![
+            IndexingProfile {
+                name: "code".to_string(),
+                extensions: Some(vec!["rs".to_string()]),
+                ..Default::default()
+            },
+            IndexingProfile {
+                name: "docs".to_string(),
+                extensions: Some(vec!["md".to_string()]),
+                text_fallback: Some(false),
+                embed: Some(false),
+                ..Default::default()
+            },
+        ];
+        let target = |path: &Path, profile: &str| IndexTarget {
+            path: path.to_string_lossy().into_owned(),
+            profile: Some(profile.to_string()),
+        };
+
+        // Same-profile nesting still collapses; the docs root survives
+        // inside the code root either way round
+        let planned =
+            plan_roots(&[target(&notes, "docs"), target(&repo, "code"), target(&docs, "docs")], &profiles).unwrap();
+        let names: Vec<&str> = planned.roots.iter().map(|root| root.profile.name.as_str()).collect();
+        assert_eq!(names, vec!["code", "docs"]);
+
+        let mut found: Vec<String> = Vec::new();
+        for root in &planned.roots {
+            let scanner = root.scanner(FileScanner::new().with_progress_count(false));
+            for job in scanner.scan_directory(&root.path).unwrap() {
+                let relative = job.path.strip_prefix(&planned.roots[0].path).unwrap();
+                found.push(format!("{}:{}", root.profile.name, relative.to_string_lossy().replace('\\', "/")));
+            }
+        }
+        found.sort();
+        assert_eq!(found, vec!["code:main.rs", "docs:docs [draft]/guide.md", "docs:docs [draft]/notes/todo.md"]);
+
+        // Files later indexed or embedded get the most specific root's settings
+        let roots = ProfileRoots::from_planned(&planned.roots, ExtractOptions::default());
+        let root = &planned.roots[1].path;
+        assert!(!roots.embeds(&root.join("notes").join("todo.md")));
+        assert!(roots.embeds(&planned.roots[0].path.join("main.rs")));
+        assert!(!roots.extract_options(&root.join("guide.md")).text_fallback);
+        assert!(roots.extract_options(&planned.roots[0].path.join("main.rs")).text_fallback);
+
+        // ...as recorded in the index, until the outer root is re-indexed
+        // without them
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::create_tables(&conn).unwrap();
+        let config = crate::db::CortexConfig {
+            indexing_profiles: profiles.clone(),
+            extraction: ExtractOptions { text_fallback: false },
+            ..Default::default()
+        };
+        crate::db::set_config(&conn, &config).unwrap();
+        crate::db::record_profile_roots(&conn, &planned.roots).unwrap();
+        let roots = ProfileRoots::load(&conn).unwrap();
+        assert!(!roots.embeds(&root.join("guide.md")));
+        // The configured options apply where no profile overrides them
+        assert!(!roots.extract_options(&planned.roots[0].path.join("main.rs")).text_fallback);
+        assert!(!roots.extract_options(&temp_dir.path().join("elsewhere.pdf")).text_fallback);
+
+        let unprofiled = plan_roots(&[IndexTarget { path: repo.to_string_lossy().into_owned(), profile: None }], &profiles).unwrap();
+        crate::db::record_profile_roots(&conn, &unprofiled.roots).unwrap();
+        assert!(crate::db::get_profile_roots(&conn).unwrap().is_empty());
+    }
+}
@@ -1,15 +1,27 @@
 use crate::error::{CortexError, Result};
+use crate::indexer::gitignore::IgnoreMatcher;
 use crate::indexer::types::{IndexJob, ScanProgress};
-use std::collections::BinaryHeap;
+use crate::paths::{strip_verbatim_prefix, to_extended_length};
+use std::collections::{BinaryHeap, HashSet};
 use std::path::Path;
 use std::sync::{Arc, RwLock};
 use walkdir::{DirEntry, WalkDir};
 
 /// Supported file types for indexing
-const SUPPORTED_EXTENSIONS: &[&str] = &[
-    "txt", "md", "pdf", "docx", "doc", "rtf",
+pub(crate) const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "txt", "md", "pdf", "docx", "doc", "rtf", "odt", "ods",
     "rs", "js", "ts", "py", "java", "c", "cpp", "h", "hpp",
-    "json", "yaml", "yml", "toml", "xml", "html", "css",
+    "json", "yaml", "yml", "toml", "xml", "html", "htm", "css", "csv", "tsv",
+];
+
+/// Binary and media types that are never indexed, whatever the configured
+/// extensions allow
+const DENIED_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tiff", "psd",
+    "mp3", "wav", "flac", "ogg", "m4a", "mp4", "mkv", "mov", "avi", "webm",
+    "zip", "gz", "tar", "7z", "rar", "bz2", "xz", "iso", "dmg",
+    "exe", "dll", "so", "dylib", "a", "o", "obj", "lib", "bin", "class", "jar",
+    "wasm", "pyc", "db", "sqlite", "ttf", "otf", "woff", "woff2",
 ];
 
 /// File scanner for recursive directory traversal
@@ -22,6 +34,21 @@ pub struct FileScanner {
 
     /// Whether to follow symlinks
     follow_symlinks: bool,
+
+    /// Whether to index dot-prefixed files and directories
+    index_hidden: bool,
+
+    /// Whether to count files up front so progress has a known total
+    count_total: bool,
+
+    /// Extensions to index (lowercase, without the dot); `*` allows any
+    extensions: Vec<String>,
+
+    /// Whether to skip what `.gitignore` files inside the root exclude
+    respect_gitignore: bool,
+
+    /// Extra gitignore-style patterns, relative to the scanned root
+    ignore_patterns: Vec<String>,
 }
 
 impl FileScanner {
@@ -30,6 +57,11 @@ impl FileScanner {
             progress: Arc::new(RwLock::new(ScanProgress::new())),
             max_file_size: 100_000_000, // 100MB
             follow_symlinks: false,
+            index_hidden: false,
+            count_total: true,
+            extensions: SUPPORTED_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            respect_gitignore: true,
+            ignore_patterns: Vec::new(),
         }
     }
 
@@ -45,6 +77,71 @@ impl FileScanner {
         self
     }
 
+    /// Set whether to index hidden (dot-prefixed) files and directories
+    ///
+    /// Ignored directories such as `.git` stay excluded either way.
+    pub fn with_index_hidden(mut self, index_hidden: bool) -> Self {
+        self.index_hidden = index_hidden;
+        self
+    }
+
+    /// Set whether to count files before collecting them
+    ///
+    /// The count is a full extra traversal that only serves to give progress
+    /// a known total. Without it, `total_files` stays 0 and `current_file`
+    /// still counts up as files are found (indeterminate progress).
+    pub fn with_progress_count(mut self, count_total: bool) -> Self {
+        self.count_total = count_total;
+        self
+    }
+
+    /// Set the extensions to index, replacing the built-in list
+    ///
+    /// `*` allows any extension. Binary and media types stay excluded
+    /// either way.
+    pub fn with_extensions<S: AsRef<str>>(mut self, extensions: &[S]) -> Self {
+        self.extensions = extensions.iter().map(|e| normalize_extension(e.as_ref())).collect();
+        self
+    }
+
+    /// Index these extensions as well as the current ones, e.g. `go` or
+    /// `sql` on top of the built-in list
+    pub fn with_additional_extensions<S: AsRef<str>>(mut self, extensions: &[S]) -> Self {
+        for extension in extensions {
+            let extension = normalize_extension(extension.as_ref());
+            if !self.extensions.contains(&extension) {
+                self.extensions.push(extension);
+            }
+        }
+        self
+    }
+
+    /// Set whether to skip files and directories excluded by `.gitignore`
+    /// files inside the scanned root (default: true)
+    ///
+    /// Each file applies to its own directory and below, as in git.
+    pub fn with_respect_gitignore(mut self, respect_gitignore: bool) -> Self {
+        self.respect_gitignore = respect_gitignore;
+        self
+    }
+
+    /// Also skip paths matching these gitignore-style patterns, relative to
+    /// the scanned root
+    ///
+    /// They apply after any `.gitignore` files, and whether or not those are
+    /// respected.
+    pub fn with_ignore_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.ignore_patterns = patterns;
+        self
+    }
+
+    /// Skip paths matching these patterns as well as the current ones (see
+    /// [`with_ignore_patterns`](Self::with_ignore_patterns))
+    pub fn with_additional_ignore_patterns<S: AsRef<str>>(mut self, patterns: &[S]) -> Self {
+        self.ignore_patterns.extend(patterns.iter().map(|pattern| pattern.as_ref().to_string()));
+        self
+    }
+
     /// Get current progress
     pub fn get_progress(&self) -> ScanProgress {
         self.progress.read().unwrap().clone()
@@ -67,31 +164,38 @@ impl FileScanner {
         log::info!("Starting directory scan: {}", root_path.display());
 
         // First pass: count total files
-        let total = self.count_files(root_path);
-        {
+        if self.count_total {
+            let total = self.count_files(root_path);
             let mut progress = self.progress.write().unwrap();
             progress.total_files = total;
         }
 
         // Second pass: collect files
         let mut jobs = Vec::new();
-
-        // Clone root_path for closure
-        let root_path_clone = root_path.to_path_buf();
-        let walker = WalkDir::new(root_path)
+        // Symlinks resolve to their targets, so one file can be reached twice
+        let mut seen_paths = HashSet::new();
+
+        // Walk from the extended-length root so nested paths past MAX_PATH
+        // stay reachable on Windows (no-op elsewhere)
+        let walk_root = to_extended_length(root_path);
+        let root_path_clone = walk_root.clone();
+        let mut ignore = self.ignore_matcher(&walk_root);
+        let walker = WalkDir::new(&walk_root)
             .follow_links(self.follow_symlinks)
             .into_iter()
-            .filter_entry(move |e| self.should_visit(e, &root_path_clone));
+            .filter_entry(move |e| self.should_visit(e, &root_path_clone, &mut ignore));
 
         for entry in walker {
             match entry {
                 Ok(entry) => {
                     if let Some(job) = self.process_entry(&entry)? {
-                        jobs.push(job);
+                        if seen_paths.insert(job.path.clone()) {
+                            jobs.push(job);
+                        }
 
                         // Update progress
                         let mut progress = self.progress.write().unwrap();
-                        progress.update_current(entry.path().to_path_buf());
+                        progress.update_current(strip_verbatim_prefix(entry.path()));
                     }
                 }
                 Err(e) => {
@@ -114,19 +218,26 @@ impl FileScanner {
 
     /// Count total files in directory
     fn count_files(&self, root_path: &Path) -> usize {
-        let root_path_clone = root_path.to_path_buf();
-        WalkDir::new(root_path)
+        let walk_root = to_extended_length(root_path);
+        let root_path_clone = walk_root.clone();
+        let mut ignore = self.ignore_matcher(&walk_root);
+        WalkDir::new(&walk_root)
             .follow_links(self.follow_symlinks)
             .into_iter()
-            .filter_entry(move |e| self.should_visit(e, &root_path_clone))
+            .filter_entry(move |e| self.should_visit(e, &root_path_clone, &mut ignore))
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
             .count()
     }
 
+    /// Ignore rules for one walk of `walk_root`
+    fn ignore_matcher(&self, walk_root: &Path) -> IgnoreMatcher {
+        IgnoreMatcher::new(walk_root, self.respect_gitignore, &self.ignore_patterns)
+    }
+
     /// Check if we should visit this directory entry
     /// Note: root_path itself is always visited, even if it starts with '.'
-    fn should_visit(&self, entry: &DirEntry, root_path: &Path) -> bool {
+    fn should_visit(&self, entry: &DirEntry, root_path: &Path, ignore: &mut IgnoreMatcher) -> bool {
         let path = entry.path();
 
         // Always visit the root directory itself
@@ -136,8 +247,8 @@ impl FileScanner {
 
         let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
-        // Skip hidden files and directories (but not the root)
-        if file_name.starts_with('.') && file_name != "." {
+        // Skip hidden files and directories (but not the root) unless enabled
+        if !self.index_hidden && file_name.starts_with('.') && file_name != "." {
             return false;
         }
 
@@ -147,7 +258,7 @@ impl FileScanner {
             return false;
         }
 
-        true
+        !ignore.is_ignored(path, entry.file_type().is_dir())
     }
 
     /// Process a directory entry and create an IndexJob if applicable
@@ -164,12 +275,23 @@ impl FileScanner {
             return Ok(None);
         }
 
-        // Get file metadata
-        let metadata = match entry.metadata() {
-            Ok(m) => m,
-            Err(e) => {
-                log::warn!("Failed to get metadata for {}: {}", path.display(), e);
-                return Ok(None);
+        // For a followed symlink, use the target's metadata and real path so
+        // size, mtime and the stored path describe the linked file
+        let (path, metadata) = if entry.path_is_symlink() {
+            match (std::fs::canonicalize(path), std::fs::metadata(path)) {
+                (Ok(real_path), Ok(m)) => (real_path, m),
+                (Err(e), _) | (_, Err(e)) => {
+                    log::warn!("Failed to resolve symlink {}: {}", path.display(), e);
+                    return Ok(None);
+                }
+            }
+        } else {
+            match entry.metadata() {
+                Ok(m) => (path.to_path_buf(), m),
+                Err(e) => {
+                    log::warn!("Failed to get metadata for {}: {}", path.display(), e);
+                    return Ok(None);
+                }
             }
         };
 
@@ -183,21 +305,38 @@ impl FileScanner {
 
         let modified = metadata.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
 
-        Ok(Some(IndexJob::new(path.to_path_buf(), size, modified)))
+        // Store the ordinary form so paths match what users and the DB expect
+        Ok(Some(IndexJob::new(strip_verbatim_prefix(&path), size, modified)))
     }
 
-    /// Check if file has a supported extension
+    /// Check if file has a supported extension that isn't denied
     fn is_supported_file(&self, path: &Path) -> bool {
         path.extension()
             .and_then(|ext| ext.to_str())
             .map(|ext| {
                 // Use case-insensitive comparison without creating temporary String
-                SUPPORTED_EXTENSIONS.iter().any(|&e| e.eq_ignore_ascii_case(ext))
+                !is_denied_extension(ext)
+                    && self.extensions.iter().any(|e| e == "*" || e.eq_ignore_ascii_case(ext))
             })
             .unwrap_or(false)
     }
 }
 
+/// Whether the default scanner would index `path`, judging by its extension
+pub fn is_supported_path(path: &Path) -> bool {
+    FileScanner::new().is_supported_file(path)
+}
+
+/// `ext` lowercase and without a leading dot
+fn normalize_extension(ext: &str) -> String {
+    ext.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Whether `ext` is a binary or media type that is never indexed
+fn is_denied_extension(ext: &str) -> bool {
+    DENIED_EXTENSIONS.iter().any(|&e| e.eq_ignore_ascii_case(ext))
+}
+
 impl Default for FileScanner {
     fn default() -> Self {
         Self::new()
@@ -430,4 +569,196 @@ mod tests {
         // Should only find the small file
         assert_eq!(jobs.len(), 1);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scanner_uses_symlink_target_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("root");
+        let elsewhere = temp_dir.path().join("elsewhere");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&elsewhere).unwrap();
+
+        let target = create_test_file(&elsewhere, "target.txt", &"x".repeat(1234));
+        std::os::unix::fs::symlink(&target, root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&target, root.join("second_link.txt")).unwrap();
+
+        let scanner = FileScanner::new().with_follow_symlinks(true);
+        let jobs = scanner.scan_directory(&root).unwrap();
+
+        // Both links resolve to the same real file
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].size, 1234);
+        assert_eq!(jobs[0].path, fs::canonicalize(&target).unwrap());
+    }
+
+    #[test]
+    fn test_scanner_index_hidden() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+
+        let config_dir = dir_path.join(".config");
+        let git_dir = dir_path.join(".git");
+        fs::create_dir(&config_dir).unwrap();
+        fs::create_dir(&git_dir).unwrap();
+
+        create_test_file(dir_path, "visible.txt", "content");
+        create_test_file(dir_path, ".hidden.md", "content");
+        create_test_file(&config_dir, "settings.toml", "content");
+        create_test_file(&git_dir, "notes.txt", "content");
+
+        // Default: hidden entries are skipped in both passes
+        let scanner = FileScanner::new();
+        let jobs = scanner.scan_directory(dir_path).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(scanner.get_progress().total_files, 1);
+
+        let scanner = FileScanner::new().with_index_hidden(true);
+        let jobs = scanner.scan_directory(dir_path).unwrap();
+        let mut names: Vec<_> = jobs
+            .iter()
+            .map(|j| j.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        // .git stays ignored
+        assert_eq!(names, vec![".hidden.md", "settings.toml", "visible.txt"]);
+        assert_eq!(scanner.get_progress().total_files, 3);
+    }
+
+    #[test]
+    fn test_scanner_without_progress_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+        let nested = dir_path.join("nested");
+        fs::create_dir(&nested).unwrap();
+
+        create_test_file(dir_path, "test1.txt", "content 1");
+        create_test_file(dir_path, "test2.md", "content 2");
+        create_test_file(&nested, "test3.rs", "content 3");
+
+        let scanner = FileScanner::new().with_progress_count(false);
+        let jobs = scanner.scan_directory(dir_path).unwrap();
+        assert_eq!(jobs.len(), 3);
+
+        // No known total, but scanned files are still counted
+        let progress = scanner.get_progress();
+        assert_eq!(progress.total_files, 0);
+        assert_eq!(progress.current_file, 3);
+        assert_eq!(progress.percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_media_extensions_rejected_regardless_of_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+
+        create_test_file(dir_path, "notes.txt", "text");
+        create_test_file(dir_path, "build.log", "text");
+        for name in ["photo.png", "clip.MP4", "bundle.zip", "tool.exe", "libfoo.so", "foo.dll"] {
+            create_test_file(dir_path, name, "binary");
+        }
+
+        // Wildcard picks up any extension except the denied ones
+        let jobs = FileScanner::new().with_extensions(&["*"]).scan_directory(dir_path).unwrap();
+        let mut names: Vec<String> = jobs
+            .iter()
+            .map(|j| j.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["build.log", "notes.txt"]);
+
+        // Listing a media type explicitly doesn't allow it either
+        let jobs = FileScanner::new()
+            .with_extensions(&["txt", ".png", "mp4", "zip"])
+            .scan_directory(dir_path)
+            .unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].path.ends_with("notes.txt"));
+
+        let scanner = FileScanner::new().with_extensions(&["*"]);
+        for name in ["a.png", "a.JPG", "a.mp4", "a.zip", "a.exe", "a.so", "a.dll"] {
+            assert!(!scanner.is_supported_file(Path::new(name)), "{} should be denied", name);
+        }
+    }
+
+    #[test]
+    fn test_scanner_additional_extensions() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+
+        create_test_file(dir_path, "main.go", "package main");
+        create_test_file(dir_path, "schema.SQL", "create table t (id int);");
+        create_test_file(dir_path, "notes.txt", "text");
+
+        assert_eq!(FileScanner::new().scan_directory(dir_path).unwrap().len(), 1);
+
+        let scanner = FileScanner::new().with_additional_extensions(&["go", ".Sql", "txt"]);
+        let mut names: Vec<String> = scanner
+            .scan_directory(dir_path)
+            .unwrap()
+            .iter()
+            .map(|j| j.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["main.go", "notes.txt", "schema.SQL"]);
+
+        // Stored normalized and only once
+        assert_eq!(scanner.extensions.iter().filter(|e| *e == "txt").count(), 1);
+        assert!(scanner.extensions.contains(&"sql".to_string()));
+    }
+
+    fn scanned_names(scanner: FileScanner, root: &Path) -> Vec<String> {
+        let mut names: Vec<String> = scanner
+            .scan_directory(root)
+            .unwrap()
+            .iter()
+            .map(|j| j.path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_scanner_respects_nested_gitignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let app = root.join("app");
+        let vendor = app.join("vendor");
+        fs::create_dir_all(&vendor).unwrap();
+
+        create_test_file(root, ".gitignore", "*.md\n/secrets.txt\n");
+        create_test_file(root, "notes.txt", "keep");
+        create_test_file(root, "secrets.txt", "skip");
+        create_test_file(root, "debug.md", "skip");
+        create_test_file(&app, ".gitignore", "vendor/\n!keep.md\ngenerated.rs\n");
+        create_test_file(&app, "main.rs", "keep");
+        create_test_file(&app, "generated.rs", "skip");
+        create_test_file(&app, "secrets.txt", "anchored to the root, so kept");
+        create_test_file(&app, "keep.md", "re-included");
+        create_test_file(&vendor, "lib.rs", "skip");
+
+        let expected = vec!["app/keep.md", "app/main.rs", "app/secrets.txt", "notes.txt"];
+        assert_eq!(scanned_names(FileScanner::new(), root), expected);
+
+        // The counting pass skips the same files
+        let scanner = FileScanner::new();
+        scanner.scan_directory(root).unwrap();
+        assert_eq!(scanner.get_progress().total_files, expected.len());
+
+        let all = scanned_names(FileScanner::new().with_respect_gitignore(false), root);
+        assert_eq!(all.len(), 8);
+
+        // Ad-hoc patterns apply with or without .gitignore files
+        let patterns = vec!["app/*.rs".to_string(), "notes.txt".to_string()];
+        assert_eq!(
+            scanned_names(FileScanner::new().with_ignore_patterns(patterns.clone()), root),
+            vec!["app/keep.md", "app/secrets.txt"]
+        );
+        let without_gitignore = FileScanner::new().with_respect_gitignore(false).with_ignore_patterns(patterns);
+        assert_eq!(
+            scanned_names(without_gitignore, root),
+            vec!["app/keep.md", "app/secrets.txt", "app/vendor/lib.rs", "debug.md", "secrets.txt"]
+        );
+    }
 }
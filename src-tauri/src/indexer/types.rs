@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// Priority levels for indexing files
@@ -69,6 +70,7 @@ impl IndexJob {
 /// Progress information for indexing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanProgress {
+    /// 0 when the total is unknown (the scan skipped its counting pass)
     pub total_files: usize,
     pub current_file: usize,
 }
@@ -105,6 +107,68 @@ impl Default for ScanProgress {
     }
 }
 
+/// Indexing throughput used to turn a scan into a time estimate
+///
+/// Time is modelled as a fixed per-file cost plus a per-byte extraction cost.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IndexThroughput {
+    pub files_per_sec: f64,
+    pub mb_per_sec: f64,
+}
+
+impl IndexThroughput {
+    /// Calibrated against typical small-file indexing runs (~50 files/sec)
+    pub const DEFAULT: Self = Self {
+        files_per_sec: 50.0,
+        mb_per_sec: 10.0,
+    };
+
+    pub fn estimate_secs(&self, file_count: usize, total_bytes: u64) -> f64 {
+        let per_file = file_count as f64 / self.files_per_sec;
+        let per_byte = total_bytes as f64 / 1_000_000.0 / self.mb_per_sec;
+        per_file + per_byte
+    }
+}
+
+impl Default for IndexThroughput {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Estimated cost of indexing, from a metadata-only scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEstimate {
+    pub file_count: usize,
+    pub total_bytes: u64,
+    /// Files per lowercase extension
+    pub file_types: HashMap<String, usize>,
+    pub estimated_duration_secs: f64,
+}
+
+impl IndexEstimate {
+    pub fn from_jobs(jobs: &[IndexJob], throughput: IndexThroughput) -> Self {
+        let total_bytes = jobs.iter().map(|job| job.size).sum();
+
+        let mut file_types = HashMap::new();
+        for job in jobs {
+            let extension = job
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            *file_types.entry(extension).or_insert(0) += 1;
+        }
+
+        Self {
+            file_count: jobs.len(),
+            total_bytes,
+            file_types,
+            estimated_duration_secs: throughput.estimate_secs(jobs.len(), total_bytes),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,4 +197,33 @@ mod tests {
         progress.current_file = 50;
         assert_eq!(progress.percentage(), 50.0);
     }
+
+    #[test]
+    fn test_estimate_scales_with_file_count() {
+        let throughput = IndexThroughput {
+            files_per_sec: 10.0,
+            mb_per_sec: 1.0,
+        };
+        let jobs = |count: usize| -> Vec<IndexJob> {
+            (0..count)
+                .map(|i| {
+                    let name = if i % 2 == 0 { format!("f{}.md", i) } else { format!("f{}.RS", i) };
+                    IndexJob::new(PathBuf::from(name), 500_000, std::time::SystemTime::UNIX_EPOCH)
+                })
+                .collect()
+        };
+
+        // 10 files: 1s of per-file cost + 5MB at 1MB/s
+        let small = IndexEstimate::from_jobs(&jobs(10), throughput);
+        assert_eq!(small.file_count, 10);
+        assert_eq!(small.total_bytes, 5_000_000);
+        assert!((small.estimated_duration_secs - 6.0).abs() < 1e-9);
+        assert_eq!(small.file_types.get("md"), Some(&5));
+        assert_eq!(small.file_types.get("rs"), Some(&5));
+
+        let large = IndexEstimate::from_jobs(&jobs(100), throughput);
+        assert!((large.estimated_duration_secs - 10.0 * small.estimated_duration_secs).abs() < 1e-9);
+
+        assert_eq!(IndexEstimate::from_jobs(&[], throughput).estimated_duration_secs, 0.0);
+    }
 }
@@ -1,33 +1,114 @@
 use crate::error::{CortexError, Result};
 use crate::indexer::types::IndexJob;
 use crossbeam_channel::{bounded, Receiver, Sender};
+use notify::event::{ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long a removed path must stay missing before it counts as deleted
+pub const DEFAULT_DELETE_GRACE: Duration = Duration::from_secs(2);
+
+/// How often a [`WatchLoop`] checks for removals past their grace period
+/// when no change arrives
+pub const WATCH_TICK: Duration = Duration::from_millis(250);
+
+/// Removed paths waiting out the grace period
+///
+/// Editors save atomically by renaming a temp file over the original, so a
+/// file can vanish and reappear within milliseconds. A removal is only
+/// confirmed once the path is still missing after the grace period.
+#[derive(Debug)]
+pub struct PendingDeletes {
+    grace: Duration,
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl PendingDeletes {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Start (or restart) the grace period for a path removed at `at`
+    pub fn schedule(&mut self, path: &Path, at: Instant) {
+        self.pending.insert(path.to_path_buf(), at);
+    }
+
+    /// Drop the pending removal of a path that has reappeared
+    pub fn cancel(&mut self, path: &Path) {
+        self.pending.remove(path);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Take the paths whose grace period has passed by `now` and that are
+    /// still missing; paths that came back are dropped
+    pub fn take_confirmed(&mut self, now: Instant) -> Vec<PathBuf> {
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, &at)| now.saturating_duration_since(at) >= self.grace)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut confirmed = Vec::new();
+        for path in due {
+            self.pending.remove(&path);
+            if !path.exists() {
+                confirmed.push(path);
+            }
+        }
+
+        confirmed.sort();
+        confirmed
+    }
+}
 
 /// Filesystem watcher that detects file changes
 pub struct FileWatcher {
     _watcher: RecommendedWatcher,
     receiver: Receiver<IndexJob>,
+    pending_deletes: Arc<Mutex<PendingDeletes>>,
 }
 
 impl FileWatcher {
     /// Create a new filesystem watcher for the given paths
     pub fn new(paths: Vec<PathBuf>) -> Result<Self> {
+        Self::with_delete_grace(paths, DEFAULT_DELETE_GRACE)
+    }
+
+    /// Create a watcher that confirms removals only after `grace` (see
+    /// [`PendingDeletes`])
+    pub fn with_delete_grace(paths: Vec<PathBuf>, grace: Duration) -> Result<Self> {
         let (tx, rx) = bounded(1000);
+        let pending_deletes = Arc::new(Mutex::new(PendingDeletes::new(grace)));
 
-        let watcher = Self::create_watcher(tx, paths)?;
+        let watcher = Self::create_watcher(tx, Arc::clone(&pending_deletes), paths)?;
 
         Ok(Self {
             _watcher: watcher,
             receiver: rx,
+            pending_deletes,
         })
     }
 
     /// Create the notify watcher
     fn create_watcher(
         tx: Sender<IndexJob>,
+        pending_deletes: Arc<Mutex<PendingDeletes>>,
         paths: Vec<PathBuf>,
     ) -> Result<RecommendedWatcher> {
         let tx = Arc::new(tx);
@@ -36,6 +117,8 @@ impl FileWatcher {
         let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
             match res {
                 Ok(event) => {
+                    Self::track_removal(&event, &mut pending_deletes.lock().unwrap());
+
                     if let Some(job) = Self::process_event(&event) {
                         if let Err(e) = tx_clone.send(job) {
                             log::error!("Failed to send watch event: {}", e);
@@ -66,17 +149,18 @@ impl FileWatcher {
     /// Process a filesystem event and create an IndexJob if relevant
     fn process_event(event: &Event) -> Option<IndexJob> {
         match event.kind {
-            // File created or modified
-            EventKind::Create(_) | EventKind::Modify(_) => {
-                if let Some(path) = event.paths.first() {
+            // Renamed or moved: index the destination, which indexing
+            // recognizes by its hash as the same file
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                if let Some(path) = event.paths.last() {
                     if path.is_file() {
                         return Self::create_index_job(path);
                     }
                 }
             }
-            // We might also want to handle renames
-            EventKind::Modify(notify::event::ModifyKind::Name(_)) => {
-                if let Some(path) = event.paths.last() {
+            // File created or modified
+            EventKind::Create(_) | EventKind::Modify(_) => {
+                if let Some(path) = event.paths.first() {
                     if path.is_file() {
                         return Self::create_index_job(path);
                     }
@@ -88,6 +172,36 @@ impl FileWatcher {
         None
     }
 
+    /// Schedule removed paths for deletion and cancel those that reappear
+    fn track_removal(event: &Event, pending: &mut PendingDeletes) {
+        let now = Instant::now();
+
+        match event.kind {
+            EventKind::Remove(_) => {
+                for path in &event.paths {
+                    pending.schedule(path, now);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(path) = event.paths.first() {
+                    pending.schedule(path, now);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let (Some(from), Some(to)) = (event.paths.first(), event.paths.last()) {
+                    pending.schedule(from, now);
+                    pending.cancel(to);
+                }
+            }
+            EventKind::Create(_) | EventKind::Modify(_) => {
+                for path in &event.paths {
+                    pending.cancel(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
     /// Create an IndexJob from a file path
     fn create_index_job(path: &Path) -> Option<IndexJob> {
         let metadata = path.metadata().ok()?;
@@ -118,6 +232,68 @@ impl FileWatcher {
     pub fn recv_timeout(&self, timeout: Duration) -> Option<IndexJob> {
         self.receiver.recv_timeout(timeout).ok()
     }
+
+    /// Take the removed paths that stayed missing for the whole grace
+    /// period, ready to be soft-deleted from the index
+    pub fn take_removed(&self) -> Vec<PathBuf> {
+        self.pending_deletes.lock().unwrap().take_confirmed(Instant::now())
+    }
+}
+
+/// A [`FileWatcher`] drained on its own thread until stopped or dropped
+///
+/// Changed files are handed to `on_changed` as they arrive. Every
+/// [`WATCH_TICK`] at most, paths that stayed missing through the grace
+/// period are handed to `on_removed`.
+pub struct WatchLoop {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchLoop {
+    pub fn spawn<C, R>(watcher: FileWatcher, mut on_changed: C, mut on_removed: R) -> Self
+    where
+        C: FnMut(IndexJob) + Send + 'static,
+        R: FnMut(Vec<PathBuf>) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+
+        let thread = std::thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                if let Some(job) = watcher.recv_timeout(WATCH_TICK) {
+                    on_changed(job);
+                }
+
+                let removed = watcher.take_removed();
+                if !removed.is_empty() {
+                    on_removed(removed);
+                }
+            }
+        });
+
+        Self { stop, thread: Some(thread) }
+    }
+
+    /// Stop watching, waiting for the current change to be handled
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("Watch loop panicked");
+            }
+        }
+    }
+}
+
+impl Drop for WatchLoop {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
 #[cfg(test)]
@@ -197,4 +373,36 @@ mod tests {
         let result = watcher.try_recv();
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_pending_delete_cancelled_by_quick_recreate() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("draft.txt");
+        fs::write(&path, "v1").unwrap();
+
+        let grace = Duration::from_secs(2);
+        let start = Instant::now();
+        let mut pending = PendingDeletes::new(grace);
+
+        // Atomic save: removed, then written back moments later
+        fs::remove_file(&path).unwrap();
+        pending.schedule(&path, start);
+        fs::write(&path, "v2").unwrap();
+        pending.cancel(&path);
+        assert!(pending.take_confirmed(start + grace).is_empty());
+
+        // A recreate whose event was missed is caught by the final check
+        fs::remove_file(&path).unwrap();
+        pending.schedule(&path, start);
+        fs::write(&path, "v3").unwrap();
+        assert!(pending.take_confirmed(start + grace).is_empty());
+        assert!(pending.is_empty());
+
+        // Still missing after the grace period: confirmed once
+        fs::remove_file(&path).unwrap();
+        pending.schedule(&path, start);
+        assert!(pending.take_confirmed(start + grace / 2).is_empty());
+        assert_eq!(pending.take_confirmed(start + grace), vec![path.clone()]);
+        assert!(pending.take_confirmed(start + grace * 2).is_empty());
+    }
 }
@@ -5,5 +5,7 @@ pub mod db;
 pub mod error;
 pub mod export;
 pub mod indexer;
+pub mod paths;
 pub mod search;
 pub mod state;
+pub mod text;
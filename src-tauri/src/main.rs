@@ -19,22 +19,93 @@ async fn main() {
         .invoke_handler(tauri::generate_handler![
             commands::indexing::start_indexing,
             commands::indexing::stop_indexing,
+            commands::indexing::estimate_indexing,
             commands::indexing::get_index_status,
+            commands::indexing::reindex_by_type,
+            commands::indexing::preview_extraction,
+            commands::indexing::get_supported_formats,
+            commands::indexing::index_newly_eligible,
+            commands::indexing::backfill_hashes,
+            commands::indexing::get_files_missing_content,
+            commands::indexing::get_files_missing_embeddings,
+            commands::indexing::find_orphans,
+            commands::indexing::cleanup_orphans,
+            commands::indexing::repair_index,
+            commands::indexing::check_integrity,
+            commands::indexing::delete_files,
+            commands::indexing::purge_files,
+            commands::indexing::cancel_purge,
+            commands::indexing::export_index,
+            commands::indexing::import_index,
+            commands::watch::start_watching,
+            commands::watch::stop_watching,
             commands::search::search_files,
+            commands::search::refine_search,
+            commands::search::multi_search,
+            commands::search::search_paths,
+            commands::search::search_grouped_by_collection,
+            commands::search::get_highlight_ranges,
             commands::search::get_file_detail,
+            commands::search::list_indexed_files,
+            commands::search::restore_file,
             commands::search::get_search_stats,
+            commands::search::get_content_stats,
+            commands::search::recompute_stats,
+            commands::search::get_db_health,
+            commands::search::get_db_recovery,
+            commands::search::get_config,
+            commands::search::set_config,
+            commands::search::get_content_storage_stats,
+            commands::search::get_file_tree,
+            commands::search::get_largest_files,
+            commands::search::get_largest_files_by_type,
+            commands::search::search_by_link_domain,
+            commands::tags::add_tags_to_files,
+            commands::tags::remove_file_tag,
+            commands::tags::get_file_tags,
+            commands::tags::list_tags,
+            commands::tags::pin_file,
+            commands::tags::unpin_file,
+            commands::tags::get_pinned_files,
+            commands::bookmarks::add_bookmark,
+            commands::bookmarks::remove_bookmark,
+            commands::bookmarks::list_bookmarks,
+            commands::collections::create_collection,
+            commands::collections::delete_collection,
+            commands::collections::list_collections,
+            commands::collections::add_files_to_collection,
+            commands::collections::remove_files_from_collection,
             // AI Commands (Phase 2)
             commands::ai_commands::get_embedding_status,
+            commands::ai_commands::embedding_diagnostics,
+            commands::ai_commands::export_similarity_matrix,
+            commands::ai_commands::warmup_embeddings,
+            commands::ai_commands::count_tokens,
+            commands::ai_commands::benchmark_embeddings,
+            commands::ai_commands::list_downloaded_models,
+            commands::ai_commands::remove_model,
+            commands::ai_commands::gc_models,
             commands::ai_commands::generate_embeddings,
             commands::ai_commands::generate_all_embeddings,
+            commands::ai_commands::reembed_all,
+            commands::ai_commands::cancel_reembed,
             commands::ai_commands::semantic_search,
+            commands::ai_commands::compare_files,
             commands::ai_commands::find_similar_files,
+            commands::ai_commands::regenerate_embedding,
+            commands::ai_commands::get_file_keywords,
+            commands::ai_commands::suggest_related_searches,
             // Export Commands (Phase 3)
             commands::export::export_vscode_context,
             commands::export::export_rake_package,
+            commands::export::cancel_export,
             commands::export::get_export_preview,
             commands::export::get_rake_export_preview,
+            commands::export::validate_rake_config,
             commands::export::list_prompt_templates,
+            commands::export::render_prompt_template,
+            commands::export::create_prompt_template,
+            commands::export::delete_prompt_template,
             commands::export::get_export_stats,
         ])
         .setup(|app| {
@@ -88,12 +159,50 @@ async fn main() {
                     Ok(app_state) => {
                         info!("App state initialized successfully");
 
+                        let embedding_service = app_state.embedding_service.clone();
+                        let embedding_db = app_state.db.clone();
+                        let recovery = app_state.db.lock().unwrap().recovery().cloned();
+
+                        // A corrupt database was replaced; the UI offers a re-index
+                        if let Some(recovery) = &recovery {
+                            if let Some(splash) = &splash_window {
+                                let _ = splash.emit(
+                                    "splash-status",
+                                    "Database was damaged and has been reset. Please re-index.",
+                                );
+                            }
+                            let _ = handle.emit("db:recovered", recovery);
+                            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                        }
+
                         // Store app state
                         handle.manage(app_state);
 
+                        // Re-scan configured roots in the background; the
+                        // window doesn't wait for it
+                        let auto_index_handle = handle.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let state = auto_index_handle.state::<AppState>();
+                            match commands::indexing::start_auto_index(auto_index_handle.clone(), &state).await {
+                                Ok(true) => info!("Started auto-index of configured roots"),
+                                Ok(false) => {}
+                                Err(e) => log::warn!("Auto-index on startup failed: {}", e),
+                            }
+                        });
+
                         if let Some(splash) = &splash_window {
                             let _ = splash.emit("splash-status", "Checking AI models...");
                         }
+
+                        // Load the embedding model in the background so the first
+                        // AI command doesn't pay for it; never downloads at startup
+                        if cortex_lib::ai::is_model_downloaded().unwrap_or(false) {
+                            tauri::async_runtime::spawn_blocking(move || {
+                                if let Err(e) = commands::ai_commands::warmup_embedding_service(&embedding_service, &embedding_db) {
+                                    log::warn!("Embedding model warm-up failed: {}", e);
+                                }
+                            });
+                        }
                         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
                         if let Some(splash) = &splash_window {
@@ -0,0 +1,318 @@
+//! Platform path helpers
+//!
+//! Windows limits ordinary paths to MAX_PATH (260) characters unless they use
+//! the extended-length `\\?\` form, and `canonicalize` always returns that
+//! verbatim form (`\\?\C:\...` or `\\?\UNC\server\share\...`). These helpers
+//! convert between the two so comparisons and file access behave the same on
+//! every platform. On other platforms they return the path unchanged.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Classic Windows path length limit
+pub const MAX_PATH: usize = 260;
+
+/// Strip a verbatim prefix so the path compares equal to its ordinary form
+///
+/// `\\?\C:\Users\me` becomes `C:\Users\me` and `\\?\UNC\server\share\dir`
+/// becomes `\\server\share\dir`. Other paths are returned unchanged.
+#[cfg(windows)]
+pub fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return path.to_path_buf(),
+    };
+
+    let mut stripped = match prefix.kind() {
+        Prefix::VerbatimDisk(drive) => format!("{}:", drive as char).into(),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = std::ffi::OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            unc
+        }
+        _ => return path.to_path_buf(),
+    };
+
+    for component in components {
+        match component {
+            Component::Normal(part) => {
+                stripped.push(r"\");
+                stripped.push(part);
+            }
+            Component::RootDir | Component::CurDir => {}
+            // Verbatim paths are taken literally, so ".." here is a real name
+            // we cannot express in ordinary form
+            _ => return path.to_path_buf(),
+        }
+    }
+
+    if path.components().count() == 2 {
+        // Bare root, e.g. `\\?\C:\`
+        stripped.push(r"\");
+    }
+
+    PathBuf::from(stripped)
+}
+
+#[cfg(not(windows))]
+pub fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Convert an absolute path to extended-length form (`\\?\C:\...` or
+/// `\\?\UNC\server\share\...`) so it can exceed MAX_PATH
+///
+/// Relative paths, already-verbatim paths and paths containing `..` are
+/// returned unchanged.
+#[cfg(windows)]
+pub fn to_extended_length(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return path.to_path_buf(),
+    };
+
+    let mut extended = match prefix.kind() {
+        Prefix::Disk(drive) => format!(r"\\?\{}:", drive as char).into(),
+        Prefix::UNC(server, share) => {
+            let mut unc = std::ffi::OsString::from(r"\\?\UNC\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            unc
+        }
+        _ => return path.to_path_buf(),
+    };
+
+    if !path.has_root() {
+        // Drive-relative (`C:foo`), can't be made verbatim
+        return path.to_path_buf();
+    }
+
+    // Verbatim paths skip normalization, so rebuild with backslashes only
+    for component in components {
+        match component {
+            Component::Normal(part) => {
+                extended.push(r"\");
+                extended.push(part);
+            }
+            Component::RootDir | Component::CurDir => {}
+            _ => return path.to_path_buf(),
+        }
+    }
+
+    if path.components().count() == 2 {
+        extended.push(r"\");
+    }
+
+    PathBuf::from(extended)
+}
+
+#[cfg(not(windows))]
+pub fn to_extended_length(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Use extended-length form only when the path is too long for ordinary APIs
+pub fn to_long_path(path: &Path) -> PathBuf {
+    if path.as_os_str().len() < MAX_PATH {
+        return path.to_path_buf();
+    }
+
+    to_extended_length(path)
+}
+
+/// Whether the platform's default filesystems ignore case in paths (APFS
+/// and HFS+ on macOS, NTFS on Windows)
+pub const CASE_INSENSITIVE_PATHS: bool = cfg!(any(target_os = "macos", windows));
+
+/// Key identifying a path in the index
+///
+/// Case variants of a path name the same file where the filesystem ignores
+/// case, so there the key is lowercased; elsewhere it is the path itself.
+pub fn path_key(path: &str) -> String {
+    if CASE_INSENSITIVE_PATHS {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Expand a leading `~` to the home directory
+///
+/// Only `~` on its own or followed by a separator is expanded; `~user` is
+/// left as-is.
+pub fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(path),
+    };
+
+    match dirs::home_dir() {
+        Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Canonical form of an indexing root: `~` expanded, `.`/`..`, trailing
+/// separators and symlinks resolved
+///
+/// Fails if the path doesn't exist.
+pub fn normalize_root(path: &str) -> std::io::Result<PathBuf> {
+    let canonical = std::fs::canonicalize(expand_home(path))?;
+    Ok(strip_verbatim_prefix(&canonical))
+}
+
+/// Canonical form of an indexed file's path, as a scan of its normalized
+/// root would store it
+///
+/// The directory is resolved as [`normalize_root`] resolves roots while the
+/// name is kept, so a symlinked file stays itself. Where the directory no
+/// longer exists, `.` and `..` components are resolved by name instead.
+pub fn canonical_file_path(path: &Path) -> PathBuf {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return path.to_path_buf();
+    };
+
+    match std::fs::canonicalize(dir) {
+        Ok(dir) => strip_verbatim_prefix(&dir).join(name),
+        Err(_) => {
+            let mut normalized = PathBuf::new();
+            for component in path.components() {
+                match component {
+                    Component::CurDir => {}
+                    Component::ParentDir => {
+                        normalized.pop();
+                    }
+                    component => normalized.push(component),
+                }
+            }
+            normalized
+        }
+    }
+}
+
+/// Indexing roots after normalization, with roots inside another root
+/// dropped
+#[derive(Debug, Default)]
+pub struct NormalizedRoots {
+    /// Distinct roots, none containing another, in input order
+    pub roots: Vec<PathBuf>,
+    /// Inputs that couldn't be resolved, with the reason
+    pub unresolved: Vec<(String, std::io::Error)>,
+}
+
+/// Normalize `paths` with [`normalize_root`] and collapse overlapping ones,
+/// so no directory is scanned twice
+pub fn normalize_roots<S: AsRef<str>>(paths: &[S]) -> NormalizedRoots {
+    let mut normalized = NormalizedRoots::default();
+    let mut resolved = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        match normalize_root(path.as_ref()) {
+            Ok(root) => resolved.push(root),
+            Err(e) => normalized.unresolved.push((path.as_ref().to_string(), e)),
+        }
+    }
+
+    let key = |root: &Path| PathBuf::from(path_key(&root.to_string_lossy()));
+    for (i, root) in resolved.iter().enumerate() {
+        let root_key = key(root);
+        // A root is dropped if another contains it; of exact duplicates the
+        // first is kept
+        let covered = resolved.iter().enumerate().any(|(j, other)| {
+            let other_key = key(other);
+            root_key.starts_with(&other_key) && (root_key != other_key || j < i)
+        });
+
+        if covered {
+            log::info!("Skipping root {} already covered by another root", root.display());
+        } else {
+            normalized.roots.push(root.clone());
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_paths_unchanged() {
+        let path = Path::new("some/relative/file.txt");
+        assert_eq!(to_long_path(path), path);
+        assert_eq!(strip_verbatim_prefix(path), path);
+    }
+
+    #[test]
+    fn test_overlapping_roots_collapse() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let parent = temp_dir.path().join("docs");
+        let child = parent.join("notes");
+        let sibling = temp_dir.path().join("other");
+        std::fs::create_dir_all(&child).unwrap();
+        std::fs::create_dir_all(&sibling).unwrap();
+
+        let as_str = |p: &Path| p.to_string_lossy().into_owned();
+        let normalized = normalize_roots(&[
+            as_str(&child),
+            format!("{}/", as_str(&parent)),
+            as_str(&parent.join(".")),
+            as_str(&child.join("..")),
+            as_str(&sibling),
+            as_str(&temp_dir.path().join("missing")),
+        ]);
+
+        let parent = normalize_root(&as_str(&parent)).unwrap();
+        let sibling = normalize_root(&as_str(&sibling)).unwrap();
+        assert_eq!(normalized.roots, vec![parent, sibling]);
+        assert_eq!(normalized.unresolved.len(), 1);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/docs"), home.join("docs"));
+        assert_eq!(expand_home("~user/docs"), PathBuf::from("~user/docs"));
+        assert_eq!(expand_home("/tmp/~"), PathBuf::from("/tmp/~"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_strip_verbatim_disk_and_unc() {
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\C:\Users\me\export")),
+            PathBuf::from(r"C:\Users\me\export")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\UNC\server\share\dir")),
+            PathBuf::from(r"\\server\share\dir")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"C:\plain")),
+            PathBuf::from(r"C:\plain")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_path_gets_extended_prefix() {
+        let long = PathBuf::from(r"C:\").join("a".repeat(300)).join("file.txt");
+        let extended = to_long_path(&long);
+
+        assert!(extended.to_string_lossy().starts_with(r"\\?\C:\"));
+        assert_eq!(strip_verbatim_prefix(&extended), long);
+
+        let unc = PathBuf::from(r"\\server\share").join("b".repeat(300));
+        assert!(to_long_path(&unc).to_string_lossy().starts_with(r"\\?\UNC\server\share\"));
+    }
+}
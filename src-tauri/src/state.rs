@@ -1,29 +1,53 @@
-use crate::db::Database;
+use crate::ai::{EmbeddingService, LazyService};
+use crate::db::{get_config, Database, DatabasePool};
 use crate::error::Result;
-use crate::indexer::ScanProgress;
+use crate::indexer::{ScanProgress, WatchLoop};
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
 /// Global application state shared across Tauri commands
 pub struct AppState {
     pub db: Arc<Mutex<Database>>,
+    /// Writes queued to a dedicated write connection, and pooled reads;
+    /// neither contends for `db`'s mutex
+    pub db_pool: Arc<DatabasePool>,
     pub indexing_active: Arc<RwLock<bool>>,
     pub indexing_progress: Arc<RwLock<Option<ScanProgress>>>,
     pub indexing_errors: Arc<RwLock<Vec<String>>>,
     /// Used to signal the indexing task to stop
     pub stop_indexing: Arc<RwLock<bool>>,
+    /// Used to signal a running re-embed to stop
+    pub stop_embedding: Arc<AtomicBool>,
+    /// Used to signal a running batched purge to stop
+    pub stop_purge: Arc<AtomicBool>,
+    /// Used to signal running exports to stop
+    pub stop_export: Arc<AtomicBool>,
+    /// Embedding model, loaded on first use (or at startup) and then reused
+    pub embedding_service: Arc<LazyService<EmbeddingService>>,
+    /// Watch started by `start_watching`, stopped when replaced or dropped
+    pub watcher: Arc<Mutex<Option<WatchLoop>>>,
 }
 
 impl AppState {
     pub async fn new() -> Result<Self> {
         let db = Database::new().await?;
+        let pool_config = get_config(db.get_connection())?.database_pool;
+        let db_pool = DatabasePool::new(&db, pool_config)?;
+        let db = Arc::new(Mutex::new(db));
 
         Ok(Self {
-            db: Arc::new(Mutex::new(db)),
+            db,
+            db_pool: Arc::new(db_pool),
             indexing_active: Arc::new(RwLock::new(false)),
             indexing_progress: Arc::new(RwLock::new(None)),
             indexing_errors: Arc::new(RwLock::new(Vec::new())),
             stop_indexing: Arc::new(RwLock::new(false)),
+            stop_embedding: Arc::new(AtomicBool::new(false)),
+            stop_purge: Arc::new(AtomicBool::new(false)),
+            stop_export: Arc::new(AtomicBool::new(false)),
+            embedding_service: Arc::new(LazyService::new()),
+            watcher: Arc::new(Mutex::new(None)),
         })
     }
 
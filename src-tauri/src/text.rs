@@ -0,0 +1,48 @@
+//! Plain-text helpers shared by search, AI and export
+
+/// Sentences of `text`, split after `.`, `!` or `?` followed by whitespace
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let at_boundary = matches!(c, '.' | '!' | '?')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+
+        if at_boundary {
+            let end = i + c.len_utf8();
+            sentences.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim());
+
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        let text = "Cortex indexes files. It runs locally!\n\
+                    Does it need a server? No.\n\
+                    \n\
+                    Third paragraph, version 1.5 notes.";
+
+        assert_eq!(
+            split_sentences(text),
+            vec![
+                "Cortex indexes files.",
+                "It runs locally!",
+                "Does it need a server?",
+                "No.",
+                "Third paragraph, version 1.5 notes.",
+            ]
+        );
+        assert!(split_sentences("  \n ").is_empty());
+    }
+}
@@ -57,5 +57,20 @@ async fn test_schema_creation() -> Result<()> {
 
     assert!(table_exists, "files_fts virtual table should exist");
 
+    // Check that the embeddings table exists and migrations are recorded
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='file_embeddings'",
+        [],
+        |row| {
+            let count: i32 = row.get(0)?;
+            Ok(count > 0)
+        },
+    )?;
+
+    assert!(table_exists, "file_embeddings table should exist");
+
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    assert_eq!(version, cortex_lib::db::SCHEMA_VERSION);
+
     Ok(())
 }
@@ -0,0 +1,122 @@
+use cortex_lib::db::{
+    add_bookmark, add_files_to_collection, add_tags_to_files, count_embeddings, create_collection,
+    create_prompt_template, create_tables, export_index_archive, get_db_stats, get_file_by_path,
+    get_file_tags, import_index_archive, insert_file, list_collections, list_prompt_templates,
+    pin_file, search_files_fts, set_file_links, upsert_embedding, upsert_file_content,
+    IndexArchive, INDEX_ARCHIVE_VERSION, EMBEDDING_DIM,
+};
+use cortex_lib::error::Result;
+use rusqlite::Connection;
+
+fn open_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+    create_tables(&conn).unwrap();
+    conn
+}
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn test_index_archive_round_trip() -> Result<()> {
+    let source = open_db();
+
+    let mut ids = Vec::new();
+    for (name, text) in [
+        ("rust.md", "Ownership and borrowing in Rust"),
+        ("garden.txt", "Planting tomatoes in spring"),
+        ("notes.txt", "Meeting notes about the Rust migration"),
+    ] {
+        let id = insert_file(&source, &format!("/docs/{}", name), name, "md", 100,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/docs")?;
+        upsert_file_content(&source, id, Some(text), Some("summary"))?;
+        ids.push(id);
+    }
+    add_tags_to_files(&source, &ids[..2], &["work".to_string()])?;
+    pin_file(&source, ids[2])?;
+    let mut vector = vec![0.0f32; EMBEDDING_DIM];
+    vector[0] = 1.0;
+    upsert_embedding(&source, ids[0], &vector, "all-MiniLM-L6-v2")?;
+    set_file_links(&source, ids[0], &["https://doc.rust-lang.org/book/".to_string()])?;
+    add_bookmark(&source, ids[1])?;
+    let collection = create_collection(&source, "Garden")?;
+    add_files_to_collection(&source, collection.id, &ids[1..])?;
+    create_collection(&source, "Empty")?;
+    create_prompt_template(&source, "Summarize", "Short summary", "📝", "Summarize {{topic}}", &[])?;
+
+    let mut archive = Vec::new();
+    let exported = export_index_archive(&source, &mut archive)?;
+    assert_eq!(exported.files, 3);
+    assert_eq!(exported.tags, 2);
+    assert_eq!(exported.embeddings, 1);
+    assert_eq!(exported.links, 1);
+    assert_eq!(exported.bookmarks, 1);
+    assert_eq!(exported.collections, 2);
+    assert_eq!(exported.prompt_templates, 1);
+
+    // The archive is plain, versioned JSON
+    let parsed: IndexArchive = serde_json::from_slice(&archive).unwrap();
+    assert_eq!(parsed.format_version, INDEX_ARCHIVE_VERSION);
+
+    let target = open_db();
+    let imported = import_index_archive(&target, archive.as_slice())?;
+    assert_eq!(imported, exported);
+
+    for table in [
+        "files", "file_content", "file_tags", "file_embeddings", "files_fts", "code_fts",
+        "file_links", "bookmarks", "collections", "collection_files", "prompt_templates",
+    ] {
+        assert_eq!(count(&target, table), count(&source, table), "{} rows", table);
+    }
+    assert_eq!(get_db_stats(&target)?, get_db_stats(&source)?);
+    assert_eq!(count_embeddings(&target)?, 1);
+
+    let results = search_files_fts(&target, "rust", 10)?;
+    assert_eq!(results.len(), 2);
+    // Pinned file ranks first
+    assert_eq!(results[0].path, "/docs/notes.txt");
+
+    let garden = get_file_by_path(&target, "/docs/garden.txt")?.unwrap();
+    assert_eq!(get_file_tags(&target, garden.id)?, vec!["work".to_string()]);
+    let collections = list_collections(&target)?;
+    assert_eq!(collections.iter().map(|c| (c.name.as_str(), c.file_count)).collect::<Vec<_>>(),
+        vec![("Empty", 0), ("Garden", 2)]);
+    assert_eq!(list_prompt_templates(&target)?[0].body, "Summarize {{topic}}");
+
+    // Importing over an existing index is refused
+    assert!(import_index_archive(&target, archive.as_slice()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_import_indexes_code_and_comments() -> Result<()> {
+    let source = open_db();
+    let id = insert_file(&source, "/src/main.rs", "main.rs", "rs", 40,
+        "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/src")?;
+    upsert_file_content(&source, id, Some("// quokka entry point\nfn main() {}\n"), None)?;
+
+    let mut archive = Vec::new();
+    export_index_archive(&source, &mut archive)?;
+    let target = open_db();
+    import_index_archive(&target, archive.as_slice())?;
+
+    let comments: String =
+        target.query_row("SELECT comments FROM code_fts", [], |row| row.get(0)).unwrap();
+    assert!(comments.contains("quokka"));
+
+    Ok(())
+}
+
+#[test]
+fn test_newer_archive_format_rejected() {
+    let archive = format!(
+        r#"{{"format_version": {}, "schema_version": 1, "exported_at": "", "files": []}}"#,
+        INDEX_ARCHIVE_VERSION + 1
+    );
+
+    assert!(import_index_archive(&open_db(), archive.as_bytes()).is_err());
+}
@@ -0,0 +1,135 @@
+use cortex_lib::db::{
+    create_tables, get_config, insert_file, repair_index, search_files_fts, set_code_split, set_config,
+    upsert_embedding, upsert_file_content, CortexConfig, RepairReport, EMBEDDING_DIM,
+};
+use cortex_lib::indexer::{split_comments, CodeLanguage};
+use cortex_lib::error::Result;
+use rusqlite::Connection;
+
+fn open_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+    create_tables(&conn).unwrap();
+    conn
+}
+
+fn count(conn: &Connection, table: &str) -> usize {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0))
+        .unwrap() as usize
+}
+
+fn found(conn: &Connection, query: &str) -> Vec<i64> {
+    let mut ids: Vec<i64> = search_files_fts(conn, query, 10).unwrap().iter().map(|r| r.file_id).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_repair_restores_search_after_desync() -> Result<()> {
+    let conn = open_db();
+
+    let mut ids = Vec::new();
+    for (name, text) in [
+        ("otters.md", "River otters hold hands while sleeping"),
+        ("badgers.md", "Honey badgers raid beehives"),
+        ("herons.md", "Grey herons nest in colonies"),
+    ] {
+        let id = insert_file(&conn, &format!("/notes/{}", name), name, "md", 100,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/notes")?;
+        upsert_file_content(&conn, id, Some(text), None)?;
+        ids.push(id);
+    }
+    let (otters, badgers, herons) = (ids[0], ids[1], ids[2]);
+    upsert_embedding(&conn, otters, &vec![0.1f32; EMBEDDING_DIM], "test-model")?;
+
+    // A healthy index needs nothing
+    let report = repair_index(&conn)?;
+    assert!(!report.repaired_anything());
+    assert_eq!((report.content_rows, report.fts_rows), (3, 3));
+
+    // Desynchronize: lose one FTS row, leave another with outdated text,
+    // and strand rows whose file is gone
+    conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+    conn.execute("DELETE FROM files_fts WHERE rowid = ?1", [otters]).unwrap();
+    conn.execute("UPDATE files_fts SET content = 'stale text' WHERE rowid = ?1", [badgers]).unwrap();
+    conn.execute("INSERT INTO files_fts (rowid, filename, content) VALUES (900, 'gone.md', 'ghost otters')", [])
+        .unwrap();
+    conn.execute("INSERT INTO file_content (file_id, text_content) VALUES (901, 'orphaned herons')", [])
+        .unwrap();
+    conn.execute(
+        "INSERT INTO file_embeddings (file_id, embedding, model_version, created_at)
+         VALUES (902, x'00', 'test-model', '2025-11-29T00:00:00Z')",
+        [],
+    ).unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+
+    // The otters file dropped out of search, the badgers file matches old text
+    assert!(found(&conn, "sleeping").is_empty());
+    assert!(found(&conn, "beehives").is_empty());
+    assert_eq!(found(&conn, "stale"), vec![badgers]);
+
+    let report = repair_index(&conn)?;
+    assert_eq!(report, RepairReport {
+        content_rows: 4,
+        fts_rows: 3,
+        rebuilt_fts_index: false,
+        orphaned_content: 1,
+        orphaned_fts: 1,
+        orphaned_embeddings: 1,
+        stale_fts: 1,
+        missing_fts: 1,
+        ..RepairReport::default()
+    });
+
+    // Search is back to one row per file, with current text
+    assert_eq!(found(&conn, "sleeping"), vec![otters]);
+    assert_eq!(found(&conn, "beehives"), vec![badgers]);
+    assert_eq!(found(&conn, "colonies"), vec![herons]);
+    assert!(found(&conn, "stale").is_empty());
+    assert_eq!(count(&conn, "files_fts"), count(&conn, "file_content"));
+    assert_eq!(count(&conn, "file_embeddings"), 1);
+
+    assert!(!repair_index(&conn)?.repaired_anything());
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_checks_compressed_text_and_code_index() -> Result<()> {
+    let conn = open_db();
+    set_config(&conn, &CortexConfig { compress_content: true, ..get_config(&conn)? })?;
+
+    let add = |name: &str, text: &str| -> Result<i64> {
+        let id = insert_file(&conn, &format!("/src/{}", name), name, "rs", 100,
+            "2025-11-29T00:00:00Z", "2025-11-29T00:00:00Z", None, "/src")?;
+        upsert_file_content(&conn, id, Some(text), None)?;
+        Ok(id)
+    };
+    let lib = add("lib.rs", "// wallaby parser\nfn parse() {}\n")?;
+    let main = add("main.rs", "// numbat entry\nfn main() {}\n")?;
+    let notes = add("notes.txt", "plain dingo notes")?;
+    set_code_split(&conn, lib, Some(&split_comments("// wallaby parser\nfn parse() {}\n", CodeLanguage::Rust)))?;
+
+    // The compressed text is indexed under old words, main.rs lost its code
+    // row, and the notes got one they shouldn't have
+    conn.execute("UPDATE files_fts SET content = 'stale text' WHERE rowid = ?1", [lib]).unwrap();
+    conn.execute("INSERT INTO code_fts (rowid, code, comments) VALUES (?1, 'dingo', '')", [notes]).unwrap();
+    conn.execute("INSERT INTO code_fts (rowid, code, comments) VALUES (999, 'ghost', '')", []).unwrap();
+
+    let report = repair_index(&conn)?;
+    assert_eq!(report.stale_fts, 1);
+    assert_eq!(report.orphaned_code_fts, 1);
+    assert_eq!(report.stale_code_fts, 2);
+
+    assert_eq!(found(&conn, "wallaby"), vec![lib]);
+    assert!(found(&conn, "stale").is_empty());
+    let comments: String = conn
+        .query_row("SELECT comments FROM code_fts WHERE rowid = ?1", [main], |row| row.get(0))
+        .unwrap();
+    assert!(comments.contains("numbat"));
+    assert_eq!(count(&conn, "code_fts"), 2);
+
+    assert!(!repair_index(&conn)?.repaired_anything());
+
+    Ok(())
+}
@@ -28,6 +28,19 @@ export interface IndexCompleteEvent {
   failed_files: number;
   duration_seconds: number;
   errors: string[];
+  warnings: WarningSummary;
+}
+
+export type WarningKind = 'lossy_decoding' | 'text_fallback' | 'other';
+
+/** Extraction warnings across an indexing run, counted by kind */
+export interface WarningSummary {
+  files_with_warnings: number;
+  kinds: {
+    kind: WarningKind;
+    files: number;
+    sample_paths: string[]; // at most 5
+  }[];
 }
 
 export interface IndexErrorEvent {
@@ -35,6 +48,13 @@ export interface IndexErrorEvent {
   error_message: string;
 }
 
+export interface IndexEstimate {
+  file_count: number;
+  total_bytes: number;
+  file_types: Record<string, number>; // extension -> file count
+  estimated_duration_secs: number;
+}
+
 // Search Types
 
 export interface SearchFilters {
@@ -43,12 +63,49 @@ export interface SearchFilters {
   max_size?: number;
   date_from?: string;  // ISO 8601
   date_to?: string;    // ISO 8601
+  tags?: string[];
+  match_all_tags?: boolean; // require every tag instead of any
+  search_scope?: SearchScope;
+}
+
+/** Match source files' code or comments alone; 'all' also covers non-source files */
+export type SearchScope = 'all' | 'code_only' | 'comments_only';
+
+export interface SearchOptions {
+  limit?: number | null; // max 1000, default 50
+  offset?: number | null;
+  include_deleted?: boolean;
+  verify_exists?: boolean; // soft-delete results missing on disk
+  case_sensitive?: boolean;
+  max_per_type?: number | null;
 }
 
 export interface SearchResults {
   results: SearchResult[];
   total: number;
   query_time_ms: number;
+  warnings: string[]; // e.g. only part of the matches were checked
+}
+
+export interface Collection {
+  id: number;
+  name: string;
+  created_at: string;
+  file_count: number;
+}
+
+/** search_grouped_by_collection */
+export interface GroupedSearchResults {
+  groups: CollectionGroup[];
+  total: number; // distinct files; one in several collections counts once
+  query_time_ms: number;
+}
+
+export interface CollectionGroup {
+  collection_id: number | null; // null for the "Uncollected" bucket
+  name: string;
+  results: SearchResult[];
+  count: number;
 }
 
 export interface SearchResult {
@@ -57,6 +114,12 @@ export interface SearchResult {
   filename: string;
   snippet: string;  // HTML with <mark> tags
   score: number;
+  is_deleted: boolean;
+  duplicate_paths: string[]; // other live files with identical content
+}
+
+export interface MultiSearchResult extends SearchResult {
+  matched_queries: string[];
 }
 
 export interface FileDetail {
@@ -71,6 +134,7 @@ export interface FileDetail {
   full_content: string | null;
   word_count: number | null;
   summary: string | null;
+  metadata: Record<string, string>; // e.g. a PDF's title, author and page_count
 }
 
 export interface SearchStats {
@@ -79,6 +143,101 @@ export interface SearchStats {
   total_size_bytes: number;
 }
 
+export interface ContentStats {
+  /** Live files with extracted text; duplicates share their owner's */
+  files_with_content: number;
+  total_words: number;
+  average_words: number;
+  min_words: number;
+  median_words: number;
+  max_words: number;
+}
+
+export interface DatabaseHealth {
+  mmap_size: number; // bytes, 0 = memory-mapped I/O disabled
+  journal_mode: string;
+  schema_version: number;
+}
+
+/** Settings stored in the index itself */
+export interface CortexConfig {
+  compress_content: boolean; // gzip extracted text in the database
+  indexing_profiles: IndexingProfile[];
+  auto_index_on_startup: boolean; // incremental re-index of auto_index_roots on launch
+  auto_index_roots: IndexTarget[];
+  embedding_chunks?: ChunkConfig | null; // set by reembed_all; model defaults when unset
+  database_pool?: WriterConfig; // takes effect on the next launch
+  extraction?: ExtractOptions; // every root's; indexing profiles can override
+}
+
+export interface ExtractOptions {
+  text_fallback: boolean; // salvage text from unreadable DOCX/PDF (default true)
+}
+
+export interface WriterConfig {
+  queue_capacity: number; // writes waiting for the writer thread
+  read_connections: number; // idle read connections kept open
+}
+
+export interface IndexingProfile {
+  name: string;
+  extensions?: string[] | null; // replaces the built-in list; '*' allows any
+  max_file_size?: number | null; // bytes
+  index_hidden?: boolean | null;
+  text_fallback?: boolean | null; // salvage text from unreadable DOCX/PDF
+  embed?: boolean | null; // include in bulk embedding (default true)
+}
+
+// start_indexing also accepts bare path strings
+export interface IndexTarget {
+  path: string;
+  profile?: string | null;
+}
+
+export interface ContentStorageStats {
+  rows: number;
+  compressed_rows: number;
+  text_bytes: number;   // extracted text, uncompressed
+  stored_bytes: number; // what the database actually holds
+  saved_bytes: number;
+}
+
+/** Set when a corrupt database was replaced with an empty one at startup */
+export interface DatabaseRecovery {
+  backup_path: string; // where the corrupt file was moved
+  reason: string;
+  needs_reindex: boolean;
+}
+
+export interface FileTreeNode {
+  name: string;
+  path: string;
+  is_dir: boolean;
+  file_id: number | null;
+  size: number;
+  file_count: number;
+  children: FileTreeNode[];
+}
+
+export interface IndexedFile {
+  id: number;
+  path: string;
+  filename: string;
+  file_type: string;
+  size: number;
+  created_at: string;  // ISO 8601
+  modified_at: string; // ISO 8601
+  last_indexed: string;
+  hash: string | null;
+  root_path: string;
+  is_deleted: boolean;
+}
+
+export interface LinkedFile {
+  file: IndexedFile;
+  urls: string[]; // links to the searched domain, in document order
+}
+
 // File type definitions for UI
 
 export const FILE_TYPES = {
@@ -150,16 +309,166 @@ export interface EmbeddingStatus {
   total_files: number;
   files_with_embeddings: number;
   files_without_embeddings: number;
+  files_skipped: number;
   model_downloaded: boolean;
   model_version: string;
 }
 
+export interface DownloadedModel {
+  name: string;
+  size_bytes: number;
+  active: boolean; // the model in use; never removed
+  downloaded: boolean; // downloaded by Cortex; gc_models only removes these
+}
+
+export interface TokenCount {
+  token_count: number; // includes [CLS] and [SEP]
+  tokens: string[] | null; // only with include_tokens
+}
+
+export interface EmbeddingBenchmark {
+  files_embedded: number;
+  chunks_embedded: number;
+  elapsed_ms: number; // embedding only, excluding model load
+  files_per_second: number;
+  ms_per_file: number;
+}
+
+export interface EmbeddingDiagnostics {
+  total_embeddings: number;
+  sampled: number;
+  wrong_dimension: number;
+  degenerate: number; // zero or non-finite vectors
+  mean_pairwise_similarity: number | null;
+  mean_nearest_neighbor_similarity: number | null;
+  median_nearest_neighbor_similarity: number | null;
+  near_duplicate_fraction: number | null;
+}
+
+/** Matched span in a file's full content, in UTF-16 code units like JS string indexes (end exclusive) */
+export interface HighlightRange {
+  start: number;
+  end: number;
+}
+
+export interface SupportedFormat {
+  extractor: string; // e.g. 'markdown', 'pdf'
+  label: string; // e.g. 'Word (.docx)'
+  extensions: string[]; // lowercase, without the dot
+}
+
+export interface ExtractionPreview {
+  path: string;
+  extractor: string; // e.g. 'markdown', 'pdf'
+  file_type: string;
+  size: number;
+  modified_at: string | null;
+  text: string;
+  word_count: number;
+  char_count: number;
+  summary: string | null;
+  warnings: string[];
+}
+
+export interface IndexArchiveSummary {
+  files: number;
+  contents: number;
+  tags: number;
+  embeddings: number;
+  links: number;
+  bookmarks: number;
+  collections: number;
+  prompt_templates: number;
+}
+
+export interface PurgeProgressEvent {
+  total_files: number;
+  processed_files: number;
+}
+
+export interface PurgeSummary {
+  total_files: number;
+  deleted_files: number;
+  processed_files: number;
+  cancelled: boolean;
+}
+
+export interface RepairReport {
+  content_rows: number;
+  fts_rows: number; // before repair; differs from content_rows when out of step
+  rebuilt_fts_index: boolean;
+  orphaned_content: number;
+  orphaned_fts: number;
+  orphaned_embeddings: number;
+  stale_fts: number;
+  missing_fts: number;
+  rebuilt_code_fts_index: boolean;
+  orphaned_code_fts: number;
+  stale_code_fts: number; // source files whose code/comments were split again
+}
+
+export type EmbeddingStrategy = 'first_chunk' | 'mean_of_chunks';
+
+/** What is embedded per file; the summary sources are faster but coarser */
+export type EmbeddingSource = 'full_text' | 'summary' | 'title_plus_summary';
+
+export interface ChunkConfig {
+  chunk_tokens: number; // 1-512
+  strategy: EmbeddingStrategy;
+  max_embed_chars?: number | null;
+  source?: EmbeddingSource;
+}
+
+export interface ReembedProgressEvent {
+  total_files: number;
+  processed_files: number;
+}
+
+export interface ReembedSummary {
+  total_files: number;
+  embedded_files: number;
+  chunks_embedded: number;
+  skipped_files: number;
+  cancelled: boolean;
+}
+
+export interface SimilarityMatrixExport {
+  output_path: string;
+  file_ids: number[]; // row/column order
+  missing_embeddings: number[];
+}
+
+export interface FileComparison {
+  file_id_a: number;
+  file_id_b: number;
+  similarity: number; // cosine similarity of the two embeddings
+  model_version_a: string;
+  model_version_b: string;
+}
+
 export interface SemanticSearchResult {
   file_id: number;
   path: string;
   filename: string;
   file_type: string;
   similarity_score: number;
+  /** The sentence most similar to the query, for the top results */
+  snippet: string | null;
+  duplicate_paths: string[]; // other live files with identical content
+}
+
+export interface SemanticSearchResponse {
+  results: SemanticSearchResult[];
+  /** Embedded files compared against the query */
+  candidates_scored: number;
+  /** Files at or above the threshold, which may exceed `limit` */
+  passed_threshold: number;
+  /** Best similarity of any scored file, above the threshold or not */
+  max_score: number | null;
+  /** Embeddings whose file is gone; cleanup_orphans or repair_index removes them */
+  orphaned_embeddings: number[];
+  limit: number;
+  threshold: number;
 }
 
 export interface SemanticSearchFilters {
@@ -175,3 +484,13 @@ export interface SimilarFilesParams {
   threshold?: number;
   [key: string]: unknown;
 }
+
+// Error Types
+
+/** Serialized CortexError; branch on `code`, not the English `message` */
+export interface CortexError {
+  type: string;
+  code: string;  // e.g. "E_INVALID_QUERY"
+  message: string;
+  data?: Record<string, string>;
+}
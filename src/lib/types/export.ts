@@ -17,6 +17,23 @@ export interface ExportStats {
   total_size_bytes: number;
   files_with_embeddings: number;
   prompts_generated: number;
+  estimated_tokens: number;
+  token_budget: number | null;
+  /** Paths whose contents went into CONTEXT.md's "File Contents" */
+  included_files: string[];
+  /** Paths left out by the file limit, the token budget or missing content */
+  omitted_files: string[];
+}
+
+export interface ContextOptions {
+  include_file_tree: boolean;
+  include_file_summaries: boolean;
+  include_full_content: boolean;
+  include_stats: boolean;
+  use_code_fences: boolean;
+  max_files_with_full_content: number;
+  /** Estimated token budget for CONTEXT.md; only file contents are trimmed */
+  max_tokens?: number | null;
 }
 
 export interface ExportPreview {
@@ -47,6 +64,38 @@ export interface RakeExportMetadata {
   export_mode: string;
 }
 
+export type ChunkStrategy = 'fixed_words' | 'sentences' | 'paragraphs';
+
+/** A problem found by validate_rake_config; none means the export can run */
+export interface RakeConfigProblem {
+  field: string; // e.g. 'tenant_id', 'export_mode', 'output_path'
+  message: string;
+}
+
+/** Line endings of exported files; 'platform' is CRLF on Windows, LF elsewhere */
+export type LineEnding = 'lf' | 'crlf' | 'platform';
+
+export interface RakeExportProgress {
+  files_processed: number;
+  total_files: number;
+}
+
+/** Written beside an export split by max_output_bytes; each part is a full package */
+export interface RakeExportManifest {
+  version: string;
+  tenant_id: string;
+  export_timestamp: string;
+  total_files: number;
+  total_chunks: number;
+  parts: RakeExportPart[];
+}
+
+export interface RakeExportPart {
+  file_name: string; // relative to the manifest
+  chunks: number;
+  bytes: number;
+}
+
 export interface PromptTemplateInfo {
   id: string;
   name: string;
@@ -54,4 +103,13 @@ export interface PromptTemplateInfo {
   description: string;
   icon: string;
   category: string;
+  variables: PromptVariable[];
+}
+
+export interface PromptVariable {
+  key: string;
+  label: string;
+  placeholder: string;
+  required: boolean;
+  default: string | null;
 }